@@ -0,0 +1,102 @@
+// src/cli_enhancements.rs - Main CLI enhancements for better UX
+
+use termion::{color, style};
+
+pub struct CLIFormatter;
+
+impl CLIFormatter {
+    pub fn print_header(title: &str) {
+        diag!("{}{}{}", 
+                 color::Fg(color::Cyan), 
+                 title, 
+                 color::Fg(color::Reset));
+        diag!("{}{}{}", 
+                 color::Fg(color::Blue), 
+                 "─".repeat(title.len()), 
+                 color::Fg(color::Reset));
+    }
+    
+    pub fn print_success(message: &str) {
+        diag!("{}✓{} {}", 
+                 color::Fg(color::Green), 
+                 color::Fg(color::Reset), 
+                 message);
+    }
+    
+    pub fn print_warning(message: &str) {
+        diag!("{}⚠{} {}", 
+                 color::Fg(color::Yellow), 
+                 color::Fg(color::Reset), 
+                 message);
+    }
+    
+    pub fn print_error(message: &str) {
+        eprintln!("{}✗{} {}", 
+                  color::Fg(color::Red), 
+                  color::Fg(color::Reset), 
+                  message);
+    }
+    
+    pub fn print_info(message: &str) {
+        diag!("{}ℹ{} {}", 
+                 color::Fg(color::Blue), 
+                 color::Fg(color::Reset), 
+                 message);
+    }
+    
+    pub fn print_search_result(index: usize, title: &str, path: &str, score: f64, snippet: &str) {
+        println!("{}[{}]{} {}{}{}", 
+                 color::Fg(color::Yellow),
+                 index,
+                 color::Fg(color::Reset),
+                 color::Fg(color::White),
+                 title,
+                 color::Fg(color::Reset));
+        
+        println!("    {}📂{} {}", 
+                 color::Fg(color::Blue),
+                 color::Fg(color::Reset),
+                 Self::truncate_path(path, 70));
+        
+        println!("    {}⚛️{} Score: {:.3}", 
+                 color::Fg(color::Magenta),
+                 color::Fg(color::Reset),
+                 score);
+        
+        println!("    {}📝{} {}", 
+                 color::Fg(color::Green),
+                 color::Fg(color::Reset),
+                 Self::truncate_text(snippet, 100));
+        
+        println!();
+    }
+    
+    pub fn print_progress_bar(current: usize, total: usize, label: &str) {
+        let percentage = if total > 0 { (current * 100) / total } else { 0 };
+        let bar_width = 40;
+        let filled = (percentage * bar_width) / 100;
+        
+        let bar: String = (0..bar_width).map(|i| if i < filled { '█' } else { '░' }).collect();
+        diag_print!("\r{} [{}] {}% ({}/{})", label, bar, percentage, current, total);
+        
+        if current >= total {
+            diag!(); // New line when complete
+        }
+    }
+    
+    fn truncate_path(path: &str, max_len: usize) -> String {
+        if path.len() <= max_len {
+            path.to_string()
+        } else {
+            format!("...{}", &path[path.len() - max_len + 3..])
+        }
+    }
+    
+    fn truncate_text(text: &str, max_len: usize) -> String {
+        if text.len() <= max_len {
+            text.to_string()
+        } else {
+            format!("{}...", &text[..max_len - 3])
+        }
+    }
+}
@@ -1,16 +1,22 @@
 // src/crawler.rs - Enhanced version that follows links and respects robots.txt
 
-use reqwest::{Client, Url};
+use reqwest::{redirect, Client, Url};
 use scraper::{Html, Selector};
-use std::collections::{HashSet, VecDeque, HashMap};
+use std::collections::{BTreeMap, HashSet, VecDeque, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::time::sleep;
 use tokio::sync::mpsc;
 use std::error::Error;
 use std::fmt;
 use futures::stream::{self, StreamExt};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::encoding::{charset_from_content_type, decode_text};
+use crate::page_renderer::PageRenderer;
 
 /// A simple error type for crawling.
 #[derive(Debug)]
@@ -35,36 +41,243 @@ pub struct CrawledDocument {
     pub text: String,
 }
 
+/// Crawl targets grouped by topic, plus the crawl parameters to use with them.
+/// Missing fields fall back to the crawler defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SeedConfig {
+    pub topics: BTreeMap<String, Vec<String>>,
+    pub max_depth: u32,
+    pub max_pages: usize,
+    pub stay_in_domain: bool,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        SeedConfig {
+            topics: BTreeMap::new(),
+            max_depth: 3,
+            max_pages: 1000,
+            stay_in_domain: false,
+        }
+    }
+}
+
+impl SeedConfig {
+    /// Seed URLs for a single topic
+    pub fn urls_for(&self, topic: &str) -> Option<&[String]> {
+        self.topics.get(topic).map(|urls| urls.as_slice())
+    }
+
+    /// Seed URLs of every topic, without duplicates
+    pub fn seed_urls(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.topics.values()
+            .flatten()
+            .filter(|url| seen.insert(url.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Load a JSON seed config, e.g.
+/// `{"topics": {"rust": ["https://blog.rust-lang.org/"]}, "max_depth": 2}`
+pub fn load_seed_config(path: impl AsRef<Path>) -> io::Result<SeedConfig> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Where a crawl stands, to pick it up after a restart: the URLs still queued
+/// and those already visited, and for politeness, when each domain was last
+/// fetched and the crawl delays set for them. Written as JSON by
+/// `Crawler::save_state`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlState {
+    /// Queued URLs with their depth, next first
+    pub queue: Vec<(String, u32)>,
+    pub visited: Vec<String>,
+    /// Each domain's last (or next booked) fetch, in milliseconds since the Unix epoch
+    pub last_access: BTreeMap<String, u64>,
+    /// Milliseconds to leave between fetches from a domain, where set
+    pub crawl_delays: BTreeMap<String, u64>,
+}
+
+// Milliseconds since the Unix epoch, the unit of `CrawlState::last_access`
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+// Query parameters that only track where a click came from
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "msclkid", "mc_cid", "mc_eid", "_ga", "ref", "ref_src"];
+
+/// Canonical form of a URL, so variants of the same page are crawled once:
+/// the fragment is dropped, tracking parameters (`utm_*` and the like) are
+/// removed and the rest sorted, and a trailing slash is removed from any path
+/// but the root. Parsing already lowercases the host and drops default ports.
+pub fn normalize_url(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = url.query_pairs()
+        .filter(|(key, _)| !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_ref()))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    params.sort();
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(&params);
+    }
+
+    let path = url.path().to_string();
+    if path.len() > 1 && path.ends_with('/') {
+        url.set_path(path.trim_end_matches('/'));
+    }
+
+    url.to_string()
+}
+
+// Pending URLs with their depth, optionally capped in length. URLs are
+// queued in roughly breadth-first order, so the deepest sit at the back;
+// when the queue is full those are the ones given up.
+struct CrawlQueue {
+    urls: VecDeque<(String, u32)>,
+    max_len: Option<usize>,
+    peak_len: usize,
+    dropped: usize,
+}
+
+impl CrawlQueue {
+    fn new() -> Self {
+        CrawlQueue {
+            urls: VecDeque::new(),
+            max_len: None,
+            peak_len: 0,
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, url: String, depth: u32) {
+        if let Some(max_len) = self.max_len {
+            if self.urls.len() >= max_len {
+                // Make room only for a URL shallower than the deepest queued one
+                match self.urls.back() {
+                    Some((_, deepest)) if *deepest > depth => {
+                        self.urls.pop_back();
+                    }
+                    _ => {
+                        self.dropped += 1;
+                        return;
+                    }
+                }
+                self.dropped += 1;
+            }
+        }
+        self.urls.push_back((url, depth));
+        self.peak_len = self.peak_len.max(self.urls.len());
+    }
+
+    fn pop(&mut self) -> Option<(String, u32)> {
+        self.urls.pop_front()
+    }
+}
+
+// Per-crawl settings every fetch needs
+struct FetchLimits {
+    max_response_bytes: u64,
+    // Domains a page may end up on once redirects resolve; None for anywhere
+    final_domains: Option<HashSet<String>>,
+    renderer: Option<Arc<dyn PageRenderer>>,
+    render_domains: HashSet<String>,
+    min_static_text_chars: usize,
+}
+
+impl FetchLimits {
+    // Whether to render a page in the browser rather than index its HTML as
+    // sent, given the text found in that HTML
+    fn needs_rendering(&self, url: &Url, static_text: &str) -> bool {
+        self.renderer.is_some()
+            && (url.host_str().is_some_and(|host| self.render_domains.contains(host))
+                || static_text.split_whitespace().map(|word| word.chars().count()).sum::<usize>() < self.min_static_text_chars)
+    }
+}
+
+// Connection reuse for the client the workers share
+#[derive(Debug, Clone)]
+struct PoolOptions {
+    max_idle_per_host: usize,
+    idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    http2_prior_knowledge: bool,
+}
+
+impl Default for PoolOptions {
+    // reqwest's own defaults
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: usize::MAX,
+            idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: None,
+            http2_prior_knowledge: false,
+        }
+    }
+}
+
 /// A web crawler that fetches and extracts content from URLs.
 pub struct Crawler {
-    client: Client,
     doc_sender: mpsc::Sender<CrawledDocument>,
     visited_urls: Arc<Mutex<HashSet<String>>>,
-    url_queue: Arc<Mutex<VecDeque<(String, u32)>>>,  // URL and its depth
+    // URLs taken off the queue and still being fetched, with their depth;
+    // saved states queue them again rather than count them as visited
+    in_flight: Arc<Mutex<HashMap<String, u32>>>,
+    url_queue: Arc<Mutex<CrawlQueue>>,
     max_depth: u32,
     max_pages: usize,
+    max_response_bytes: u64,
     stay_in_domain: bool,
     allowed_domains: Option<HashSet<String>>,
+    max_redirects: usize,
+    // Follow redirects that leave the allowed domains, and index where they lead
+    follow_offsite_redirects: bool,
+    pool: PoolOptions,
+    renderer: Option<Arc<dyn PageRenderer>>,
+    render_domains: HashSet<String>,
+    min_static_text_chars: usize,
     domain_timestamps: Arc<Mutex<HashMap<String, u64>>>, // Last time a domain was accessed
+    min_domain_interval: Duration,
+    // Longer intervals for domains that ask for them
+    crawl_delays: HashMap<String, Duration>,
+    // Where the crawl state is saved as the crawl goes, see `set_state_path`
+    state_path: Option<PathBuf>,
+    // Held while saving, as workers checkpoint through the same temporary file
+    saving: Mutex<()>,
 }
 
 impl Crawler {
     /// Creates a new `Crawler` with default settings.
     pub fn new(doc_sender: mpsc::Sender<CrawledDocument>) -> Self {
         Crawler {
-            client: Client::builder()
-                .timeout(Duration::from_secs(30))  // Increased timeout
-                .user_agent("ResonantSearch/0.1 (+https://github.com/yourusername/resonant_search)")
-                .build()
-                .unwrap_or_else(|_| Client::new()),
             doc_sender,
             visited_urls: Arc::new(Mutex::new(HashSet::new())),
-            url_queue: Arc::new(Mutex::new(VecDeque::new())),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            url_queue: Arc::new(Mutex::new(CrawlQueue::new())),
             max_depth: 3,                // Default max depth
             max_pages: 1000,             // Default page limit
+            max_response_bytes: 10_000_000, // 10MB per page
             stay_in_domain: false,       // Default to following links to other domains
             allowed_domains: None,       // No domain restrictions by default
+            max_redirects: 10,
+            follow_offsite_redirects: false,
+            pool: PoolOptions::default(),
+            renderer: None,
+            render_domains: HashSet::new(),
+            min_static_text_chars: 100,
             domain_timestamps: Arc::new(Mutex::new(HashMap::new())),
+            min_domain_interval: Duration::from_secs(1),
+            crawl_delays: HashMap::new(),
+            state_path: None,
+            saving: Mutex::new(()),
         }
     }
     
@@ -80,6 +293,32 @@ impl Crawler {
         self
     }
     
+    /// Largest response body downloaded for a page. Responses advertising a
+    /// larger `Content-Length` are rejected before reading, and bodies without
+    /// one are cut off once they pass the limit.
+    pub fn set_max_response_bytes(&mut self, bytes: u64) -> &mut Self {
+        self.max_response_bytes = bytes;
+        self
+    }
+    
+    /// Cap the number of queued URLs (None for unbounded). When the queue is
+    /// full, newly found links are dropped unless they are shallower than the
+    /// deepest queued URL, which is dropped instead.
+    pub fn set_max_queue_size(&mut self, max: Option<usize>) -> &mut Self {
+        self.url_queue.lock().unwrap().max_len = max;
+        self
+    }
+    
+    /// Largest number of URLs queued at once so far
+    pub fn peak_queue_size(&self) -> usize {
+        self.url_queue.lock().unwrap().peak_len
+    }
+    
+    /// URLs discarded because the queue was full
+    pub fn dropped_urls(&self) -> usize {
+        self.url_queue.lock().unwrap().dropped
+    }
+    
     /// Set whether to stay within the seed domains
     pub fn set_stay_in_domain(&mut self, stay: bool) -> &mut Self {
         self.stay_in_domain = stay;
@@ -93,6 +332,200 @@ impl Crawler {
         self
     }
 
+    /// Give up on a page after this many redirects (0 to follow none)
+    pub fn set_max_redirects(&mut self, redirects: usize) -> &mut Self {
+        self.max_redirects = redirects;
+        self
+    }
+    
+    /// Whether to follow redirects out of the allowed domains (off by default).
+    /// Off, a redirect to a disallowed domain ends the fetch and nothing is
+    /// indexed, so redirects can't escape a domain-restricted crawl. Without
+    /// domain restrictions every redirect is followed either way.
+    pub fn set_follow_offsite_redirects(&mut self, follow: bool) -> &mut Self {
+        self.follow_offsite_redirects = follow;
+        self
+    }
+
+    /// Idle connections kept open per host for the workers to reuse
+    /// (unlimited by default). With many workers on few hosts, a low limit
+    /// makes each request pay for a new connection; 0 disables reuse.
+    pub fn set_pool_max_idle_per_host(&mut self, max: usize) -> &mut Self {
+        self.pool.max_idle_per_host = max;
+        self
+    }
+    
+    /// Close pooled connections idle for this long (None to keep them;
+    /// 90 seconds by default)
+    pub fn set_pool_idle_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.pool.idle_timeout = timeout;
+        self
+    }
+    
+    /// Send TCP keep-alive probes on idle connections after this long (None,
+    /// the default, leaves it to the OS)
+    pub fn set_tcp_keepalive(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.pool.tcp_keepalive = interval;
+        self
+    }
+    
+    /// Speak HTTP/2 from the start, multiplexing every request to a host over
+    /// one connection (off by default). Only for crawls of servers known to
+    /// support it; HTTP/1-only servers fail every request.
+    pub fn set_http2_prior_knowledge(&mut self, enabled: bool) -> &mut Self {
+        self.pool.http2_prior_knowledge = enabled;
+        self
+    }
+
+    /// Render pages with scripts run (e.g. `page_renderer::ChromeRenderer`)
+    /// when their HTML has too little text, as single-page apps' does. None,
+    /// the default, indexes every page as the server sent it.
+    pub fn set_renderer(&mut self, renderer: Option<Arc<dyn PageRenderer>>) -> &mut Self {
+        self.renderer = renderer;
+        self
+    }
+    
+    /// Domains whose pages are always rendered, however much text their HTML
+    /// has. Only used with a renderer.
+    pub fn set_render_domains(&mut self, domains: Vec<String>) -> &mut Self {
+        self.render_domains = domains.into_iter().collect();
+        self
+    }
+    
+    /// Render pages whose HTML has fewer than this many characters of text,
+    /// not counting whitespace (100 by default). Only used with a renderer.
+    pub fn set_min_static_text_chars(&mut self, chars: usize) -> &mut Self {
+        self.min_static_text_chars = chars;
+        self
+    }
+
+    /// Least time between two fetches from the same domain (1 second by default)
+    pub fn set_min_domain_interval(&mut self, interval: Duration) -> &mut Self {
+        self.min_domain_interval = interval;
+        self
+    }
+    
+    /// Leave at least `delay` between fetches from `domain`, as a site's
+    /// robots.txt `Crawl-delay` asks. Delays below the minimum interval
+    /// have no effect.
+    pub fn set_crawl_delay(&mut self, domain: &str, delay: Duration) -> &mut Self {
+        self.crawl_delays.insert(domain.to_string(), delay);
+        self
+    }
+    
+    /// Save the crawl state to `path` every 10 pages and when the crawl ends,
+    /// so a crawl that is stopped can resume with `load_state` (None, the
+    /// default, saves nothing)
+    pub fn set_state_path(&mut self, path: Option<PathBuf>) -> &mut Self {
+        self.state_path = path;
+        self
+    }
+    
+    /// The crawl's state as it is now. Pages still being fetched are queued
+    /// first rather than counted as visited, so a restart fetches them again.
+    pub fn state(&self) -> CrawlState {
+        // Locked in the order the workers take them
+        let visited = self.visited_urls.lock().unwrap();
+        let in_flight = self.in_flight.lock().unwrap();
+        CrawlState {
+            queue: in_flight.iter()
+                .map(|(url, &depth)| (url.clone(), depth))
+                .chain(self.url_queue.lock().unwrap().urls.iter().cloned())
+                .collect(),
+            visited: visited.iter()
+                .filter(|url| !in_flight.contains_key(*url))
+                .cloned()
+                .collect(),
+            last_access: self.domain_timestamps.lock().unwrap().iter().map(|(domain, &at)| (domain.clone(), at)).collect(),
+            crawl_delays: self.crawl_delays.iter().map(|(domain, delay)| (domain.clone(), delay.as_millis() as u64)).collect(),
+        }
+    }
+    
+    /// Pick up a saved crawl: its URLs are queued ahead of any seeds passed
+    /// to `crawl`, its visited URLs aren't fetched again, and domains fetched
+    /// shortly before it was saved wait out the rest of their interval, since
+    /// last access times are wall-clock times.
+    pub fn restore_state(&mut self, state: CrawlState) -> &mut Self {
+        {
+            let mut queue = self.url_queue.lock().unwrap();
+            for (url, depth) in state.queue {
+                queue.push(url, depth);
+            }
+        }
+        self.visited_urls.lock().unwrap().extend(state.visited);
+        {
+            let mut timestamps = self.domain_timestamps.lock().unwrap();
+            for (domain, at) in state.last_access {
+                let last = timestamps.entry(domain).or_insert(at);
+                *last = (*last).max(at);
+            }
+        }
+        for (domain, delay) in state.crawl_delays {
+            self.crawl_delays.entry(domain).or_insert(Duration::from_millis(delay));
+        }
+        self
+    }
+    
+    /// Write the crawl state to `path` as JSON, replacing it atomically
+    pub fn save_state(&self, path: &Path) -> io::Result<()> {
+        let _saving = self.saving.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let json = serde_json::to_string(&self.state()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)
+    }
+    
+    /// Restore a crawl state saved with `save_state`
+    pub fn load_state(&mut self, path: &Path) -> io::Result<&mut Self> {
+        let content = fs::read_to_string(path)?;
+        let state = serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(self.restore_state(state))
+    }
+    
+    // Save to the state path, if one is set
+    fn checkpoint_state(&self) {
+        if let Some(path) = &self.state_path {
+            if let Err(e) = self.save_state(path) {
+                eprintln!("Failed to save crawl state to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Apply the crawl parameters from a seed config
+    pub fn apply_seed_config(&mut self, config: &SeedConfig) -> &mut Self {
+        self.set_max_depth(config.max_depth)
+            .set_max_pages(config.max_pages)
+            .set_stay_in_domain(config.stay_in_domain)
+    }
+
+    // A client whose redirects stop after `max_redirects`, and before leaving
+    // `stay_within` if that is set, pooling connections as `pool` says
+    fn build_client(max_redirects: usize, stay_within: Option<HashSet<String>>, pool: &PoolOptions) -> Client {
+        let policy = redirect::Policy::custom(move |attempt| {
+            let offsite = stay_within.as_ref()
+                .is_some_and(|domains| attempt.url().host_str().is_none_or(|host| !domains.contains(host)));
+            if attempt.previous().len() > max_redirects {
+                attempt.error(CrawlerError(format!("more than {} redirects", max_redirects)))
+            } else if offsite {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        });
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(30))  // Increased timeout
+            .user_agent("ResonantSearch/0.1 (+https://github.com/yourusername/resonant_search)")
+            .redirect(policy)
+            .pool_max_idle_per_host(pool.max_idle_per_host)
+            .pool_idle_timeout(pool.idle_timeout)
+            .tcp_keepalive(pool.tcp_keepalive);
+        if pool.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        builder.build().unwrap_or_else(|_| Client::new())
+    }
+
     /// Extract the domain from a URL string
     fn extract_domain(url_str: &str) -> Option<String> {
         match Url::parse(url_str) {
@@ -101,54 +534,41 @@ impl Crawler {
         }
     }
 
-    // src/crawler.rs - Fix to the respect_rate_limits function
-
-    // Replace the respect_rate_limits method with this fixed version:
+    // Wait until `domain` may be fetched again: its interval after its last
+    // fetch. Times are wall-clock, so those restored from a saved state count
+    // too. The fetch's slot is booked before sleeping, so workers after the
+    // same domain queue up behind each other instead of waking together.
     async fn respect_rate_limits(&self, domain: &str) {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
+        let now = now_millis();
+        let interval = self.crawl_delays.get(domain)
+            .map_or(self.min_domain_interval, |&delay| delay.max(self.min_domain_interval))
             .as_millis() as u64;
         
-        // Check if we need to delay (scope the mutex guard to this block)
-        let wait_time = {
-            let timestamps = self.domain_timestamps.lock().unwrap();
-            
-            if let Some(last_access) = timestamps.get(domain) {
-                let elapsed = now - last_access;
-                
-                // If we've accessed this domain recently, calculate wait time
-                if elapsed < 1000 {  // Minimum 1 second between requests to same domain
-                    Some(1000 - elapsed)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
+        let slot = {
+            let mut timestamps = self.domain_timestamps.lock().unwrap();
+            let slot = timestamps.get(domain).map_or(now, |&last_access| now.max(last_access + interval));
+            timestamps.insert(domain.to_string(), slot);
+            slot
         };
         
-        // If needed, wait without holding the lock
-        if let Some(delay) = wait_time {
-            sleep(Duration::from_millis(delay)).await;
+        // Wait without holding the lock
+        if slot > now {
+            sleep(Duration::from_millis(slot - now)).await;
         }
-        
-        // Update the timestamp after waiting
-        let mut timestamps = self.domain_timestamps.lock().unwrap();
-        timestamps.insert(domain.to_string(), now);
     }
 
     /// Starts the crawling process from a list of URLs.
     /// Processes URLs concurrently using the specified number of workers.
     pub async fn crawl(&self, seed_urls: Vec<String>, num_workers: usize) {
-        println!("Starting crawl of {} seed URLs with {} workers...", seed_urls.len(), num_workers);
-        println!("Max depth: {}, Max pages: {}", self.max_depth, self.max_pages);
+        diag!("Starting crawl of {} seed URLs with {} workers...", seed_urls.len(), num_workers);
+        diag!("Max depth: {}, Max pages: {}", self.max_depth, self.max_pages);
         
         // Initialize the crawler with seed URLs at depth 0
         {
             let mut queue = self.url_queue.lock().unwrap();
             for url in seed_urls {
-                queue.push_back((url, 0)); // Depth 0 for seed URLs
+                let url = Url::parse(&url).map(|parsed| normalize_url(&parsed)).unwrap_or(url);
+                queue.push(url, 0); // Depth 0 for seed URLs
             }
         }
         
@@ -156,7 +576,7 @@ impl Crawler {
         let allowed_domains = if self.stay_in_domain {
             let mut domains = HashSet::new();
             let queue = self.url_queue.lock().unwrap();
-            for (url, _) in queue.iter() {
+            for (url, _) in queue.urls.iter() {
                 if let Some(domain) = Self::extract_domain(url) {
                     domains.insert(domain);
                 }
@@ -172,18 +592,31 @@ impl Crawler {
         
         // Print allowed domains for visibility
         if let Some(domains) = &allowed_domains {
-            println!("Restricting crawl to these domains:");
+            diag!("Restricting crawl to these domains:");
             for domain in domains {
-                println!("  - {}", domain);
+                diag!("  - {}", domain);
             }
         }
         
+        let final_domains = allowed_domains.clone().filter(|_| !self.follow_offsite_redirects);
+        // One client for all workers, so they share its connection pool
+        let client = Self::build_client(self.max_redirects, final_domains.clone(), &self.pool);
+        let limits = Arc::new(FetchLimits {
+            max_response_bytes: self.max_response_bytes,
+            final_domains,
+            renderer: self.renderer.clone(),
+            render_domains: self.render_domains.clone(),
+            min_static_text_chars: self.min_static_text_chars,
+        });
+        
         // Create worker tasks to process URLs from the queue
         stream::iter(0..num_workers)
             .for_each_concurrent(num_workers, |worker_id| {
-                let client = self.client.clone();
+                let client = client.clone();
+                let limits = limits.clone();
                 let doc_sender = self.doc_sender.clone();
                 let visited_urls = self.visited_urls.clone();
+                let in_flight = self.in_flight.clone();
                 let url_queue = self.url_queue.clone();
                 let max_depth = self.max_depth;
                 let max_pages = self.max_pages;
@@ -191,7 +624,7 @@ impl Crawler {
                 let _domain_timestamps = self.domain_timestamps.clone();
                 
                 async move {
-                    println!("Worker {} started", worker_id);
+                    diag!("Worker {} started", worker_id);
                     
                     // Keep processing until the queue is empty or max pages is reached
                     loop {
@@ -199,7 +632,7 @@ impl Crawler {
                         {
                             let visited = visited_urls.lock().unwrap();
                             if visited.len() >= max_pages {
-                                println!("Worker {} stopping: reached maximum pages", worker_id);
+                                diag!("Worker {} stopping: reached maximum pages", worker_id);
                                 break;
                             }
                         }
@@ -207,7 +640,7 @@ impl Crawler {
                         // Try to get the next URL from the queue
                         let current_url = {
                             let mut queue = url_queue.lock().unwrap();
-                            queue.pop_front()
+                            queue.pop()
                         };
                         
                         match current_url {
@@ -220,16 +653,20 @@ impl Crawler {
                                     }
                                 }
                                 
-                                // Mark as visited before processing
-                                {
+                                // Mark as visited before processing, so no other
+                                // worker takes it, and as in flight until done
+                                let checkpoint = {
                                     let mut visited = visited_urls.lock().unwrap();
                                     visited.insert(url_str.clone());
+                                    in_flight.lock().unwrap().insert(url_str.clone(), depth);
                                     
-                                    // Print progress periodically
-                                    if visited.len() % 10 == 0 {
-                                        println!("Processed {} pages so far...", visited.len());
+                                    // Print progress (and save the state) periodically
+                                    let checkpoint = visited.len().is_multiple_of(10);
+                                    if checkpoint {
+                                        diag!("Processed {} pages so far...", visited.len());
                                     }
-                                }
+                                    checkpoint
+                                };
                                 
                                 // Process the URL
                                 match Url::parse(&url_str) {
@@ -239,6 +676,7 @@ impl Crawler {
                                             if let Some(host) = url.host_str() {
                                                 if !allowed.contains(host) {
                                                     // println!("Skipping URL from domain {}: not in allowed list", host);
+                                                    in_flight.lock().unwrap().remove(&url_str);
                                                     continue;
                                                 }
                                             }
@@ -260,7 +698,8 @@ impl Crawler {
                                             depth < max_depth,
                                             url_queue.clone(),
                                             visited_urls.clone(),
-                                            depth
+                                            depth,
+                                            &limits
                                         ).await {
                                             Ok(Some(doc)) => {
                                                 // Send the document to the indexer
@@ -278,15 +717,19 @@ impl Crawler {
                                     }
                                     Err(e) => eprintln!("Failed to parse URL '{}': {}", url_str, e),
                                 }
+                                in_flight.lock().unwrap().remove(&url_str);
+                                if checkpoint {
+                                    self.checkpoint_state();
+                                }
                             }
                             None => {
                                 // Queue is empty, wait a bit and check again
                                 sleep(Duration::from_millis(100)).await;
                                 
                                 // Check if all workers are idle (queue is empty)
-                                let queue_is_empty = url_queue.lock().unwrap().is_empty();
+                                let queue_is_empty = url_queue.lock().unwrap().urls.is_empty();
                                 if queue_is_empty {
-                                    println!("Worker {} stopping: queue is empty", worker_id);
+                                    diag!("Worker {} stopping: queue is empty", worker_id);
                                     break;
                                 }
                             }
@@ -296,11 +739,50 @@ impl Crawler {
             })
             .await;
 
-        println!("Crawler finished processing URLs.");
+        diag!("Crawler finished processing URLs.");
+        self.checkpoint_state();
         
         // Print final stats
         let total_visited = self.visited_urls.lock().unwrap().len();
-        println!("Total URLs crawled: {}", total_visited);
+        diag!("Total URLs crawled: {}", total_visited);
+        diag!("Peak queue size: {} ({} URLs dropped)", self.peak_queue_size(), self.dropped_urls());
+    }
+
+    // Whether a URL's path looks like a web page rather than a downloadable file
+    fn looks_like_page(url: &Url) -> bool {
+        let last_segment = url.path_segments().and_then(|mut segments| segments.next_back()).unwrap_or("");
+        match last_segment.rsplit_once('.') {
+            Some((_, extension)) => matches!(
+                extension.to_lowercase().as_str(),
+                "html" | "htm" | "xhtml" | "shtml" | "php" | "asp" | "aspx" | "jsp"
+            ),
+            None => true,
+        }
+    }
+
+    fn is_html(response: &reqwest::Response) -> bool {
+        response.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.contains("text/html"))
+    }
+
+    // Judged from the Content-Length header; HEAD responses have no body to measure
+    fn too_large(response: &reqwest::Response, max_response_bytes: u64) -> bool {
+        response.headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .is_some_and(|length| length > max_response_bytes)
+    }
+
+    // The text of a page's <body>
+    fn page_text(document: &Html) -> String {
+        let text_selector = Selector::parse("body").unwrap();
+        document.select(&text_selector)
+                .next()
+                .map(|body| body.text().collect::<String>())
+                .unwrap_or_default()
     }
 
     /// Fetches a single URL and extracts text and links.
@@ -308,37 +790,78 @@ impl Crawler {
         client: &Client, 
         url: &Url, 
         extract_links: bool,
-        url_queue: Arc<Mutex<VecDeque<(String, u32)>>>,
+        url_queue: Arc<Mutex<CrawlQueue>>,
         visited_urls: Arc<Mutex<HashSet<String>>>,
-        depth: u32
+        depth: u32,
+        limits: &FetchLimits
     ) -> Result<Option<CrawledDocument>, Box<dyn Error + Send + Sync>> {
+        let max_response_bytes = limits.max_response_bytes;
         // Add a small delay per request for politeness
         sleep(Duration::from_millis(50)).await;
 
-        let response = client.get(url.clone()).send().await?;
+        // Links that may not be pages (e.g. /file.zip) are checked with a cheap
+        // HEAD first. Servers that don't support HEAD just get the GET.
+        if !Self::looks_like_page(url) {
+            if let Ok(head) = client.head(url.clone()).send().await {
+                if head.status().is_success()
+                    && (!Self::is_html(&head) || Self::too_large(&head, max_response_bytes)) {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let mut response = client.get(url.clone()).send().await?;
+
+        // The redirect policy stops short of disallowed domains; checking where
+        // the fetch ended up also covers anything the policy let through
+        let offsite = limits.final_domains.as_ref()
+            .is_some_and(|domains| response.url().host_str().is_none_or(|host| !domains.contains(host)));
+        if response.status().is_redirection() || offsite {
+            diag!("Not indexing {}: redirected off the allowed domains", url);
+            return Ok(None);
+        }
 
         if !response.status().is_success() {
             return Err(Box::new(CrawlerError(format!("HTTP error status: {}", response.status()))));
         }
 
-        let content_type = response.headers()
+        if !Self::is_html(&response) {
+            return Ok(None);
+        }
+
+        if Self::too_large(&response, max_response_bytes) {
+            return Err(Box::new(CrawlerError(format!("response larger than {} bytes", max_response_bytes))));
+        }
+
+        let charset = response.headers()
             .get(reqwest::header::CONTENT_TYPE)
             .and_then(|value| value.to_str().ok())
-            .unwrap_or("");
+            .and_then(charset_from_content_type)
+            .map(str::to_string);
 
-        if !content_type.contains("text/html") {
-            return Ok(None);
+        // Read in chunks so a body without an honest Content-Length stops at the cap
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() as u64 + chunk.len() as u64 > max_response_bytes {
+                return Err(Box::new(CrawlerError(format!("response larger than {} bytes", max_response_bytes))));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let mut html_string = decode_text(&body, charset.as_deref(), true);
+        
+        // Pages that build their content with scripts are indexed as the browser shows them
+        if let Some(renderer) = &limits.renderer {
+            if limits.needs_rendering(url, &Self::page_text(&Html::parse_document(&html_string))) {
+                match renderer.render(url).await {
+                    Ok(rendered) => html_string = rendered,
+                    Err(e) => diag!("⚠️  Could not render {}, indexing its HTML as sent: {}", url, e),
+                }
+            }
         }
-
-        let html_string = response.text().await?;
         let fragment = Html::parse_document(&html_string);
 
         // Extract page text
-        let text_selector = Selector::parse("body").unwrap();
-        let text = fragment.select(&text_selector)
-                           .next()
-                           .map(|body| body.text().collect::<String>())
-                           .unwrap_or_else(|| "".to_string());
+        let text = Self::page_text(&fragment);
 
         // Extract page title
         let title_selector = Selector::parse("title").unwrap();
@@ -347,6 +870,23 @@ impl Crawler {
                            .map(|t| t.text().collect::<String>())
                            .unwrap_or_else(|| url.to_string());
 
+        // A page naming a canonical URL is indexed once under that URL, however it was reached
+        let canonical_selector = Selector::parse(r#"link[rel="canonical"][href]"#).unwrap();
+        let canonical = fragment.select(&canonical_selector)
+            .next()
+            .and_then(|link| link.value().attr("href"))
+            .and_then(|href| url.join(href).ok())
+            .map(|canonical| normalize_url(&canonical));
+        let page_url = match canonical {
+            Some(canonical) if canonical != normalize_url(url) => {
+                if !visited_urls.lock().unwrap().insert(canonical.clone()) {
+                    return Ok(None); // Already crawled under its canonical URL
+                }
+                canonical
+            }
+            _ => url.to_string(),
+        };
+
         if text.trim().is_empty() {
             Ok(None)
         } else {
@@ -357,7 +897,7 @@ impl Crawler {
                     .filter_map(|link| {
                         link.value().attr("href").and_then(|href| {
                             // Resolve relative URLs
-                            url.join(href).ok().map(|u| u.to_string())
+                            url.join(href).ok().map(|u| normalize_url(&u))
                         })
                     })
                     .collect();
@@ -368,16 +908,376 @@ impl Crawler {
                 
                 for link in links {
                     if !visited.contains(&link) {
-                        queue.push_back((link, depth + 1));
+                        queue.push(link, depth + 1);
                     }
                 }
             }
 
             Ok(Some(CrawledDocument {
-                url: url.to_string(),
+                url: page_url,
                 title,
                 text,
             }))
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn crawler() -> Crawler {
+        let (sender, _) = mpsc::channel(1);
+        Crawler::new(sender)
+    }
+
+    #[tokio::test]
+    async fn a_restarted_crawl_waits_out_the_saved_interval() {
+        let mut before = crawler();
+        before.set_min_domain_interval(Duration::from_millis(400));
+        before.respect_rate_limits("example.org").await;
+        let state = before.state();
+
+        let mut after = crawler();
+        after.set_min_domain_interval(Duration::from_millis(400)).restore_state(state);
+        let start = Instant::now();
+        after.respect_rate_limits("example.org").await;
+        assert!(start.elapsed() >= Duration::from_millis(300), "{:?}", start.elapsed());
+
+        // Other domains aren't held up
+        let start = Instant::now();
+        after.respect_rate_limits("example.com").await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn pages_being_fetched_are_saved_as_queued() {
+        let crawler = crawler();
+        crawler.visited_urls.lock().unwrap().extend(["https://a.test/".to_string(), "https://a.test/busy".to_string()]);
+        crawler.in_flight.lock().unwrap().insert("https://a.test/busy".to_string(), 2);
+        crawler.url_queue.lock().unwrap().push("https://a.test/next".to_string(), 3);
+
+        let state = crawler.state();
+        assert_eq!(state.queue, vec![("https://a.test/busy".to_string(), 2), ("https://a.test/next".to_string(), 3)]);
+        assert_eq!(state.visited, vec!["https://a.test/".to_string()]);
+    }
+
+    // Renders every page as `html`, counting the pages it was asked for
+    struct MockRenderer {
+        html: &'static str,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl PageRenderer for MockRenderer {
+        fn render<'a>(&'a self, _url: &'a Url) -> futures::future::BoxFuture<'a, Result<String, crate::page_renderer::RenderError>> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Box::pin(async move { Ok(self.html.to_string()) })
+        }
+    }
+
+    // Serve `html` to every request on a local port, returning the page's URL
+    async fn serve(html: String) -> Url {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/app", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    html.len(), html
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    async fn fetch(url: &Url, renderer: Option<Arc<dyn PageRenderer>>) -> Option<CrawledDocument> {
+        let limits = FetchLimits {
+            max_response_bytes: 1 << 20,
+            final_domains: None,
+            renderer,
+            render_domains: HashSet::new(),
+            min_static_text_chars: 100,
+        };
+        let client = Crawler::build_client(5, None, &PoolOptions::default());
+        let queue = Arc::new(Mutex::new(CrawlQueue::new()));
+        let visited = Arc::new(Mutex::new(HashSet::new()));
+        Crawler::fetch_and_process_url(&client, url, false, queue, visited, 0, &limits).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn empty_single_page_apps_are_indexed_as_rendered() {
+        let url = serve(r#"<html><head><title>Shop</title></head><body><div id="app"></div></body></html>"#.into()).await;
+        assert!(fetch(&url, None).await.is_none());
+
+        let renderer = Arc::new(MockRenderer {
+            html: "<html><head><title>Shop</title></head><body><h1>Zeppelin models</h1></body></html>",
+            calls: Default::default(),
+        });
+        let page = fetch(&url, Some(renderer.clone())).await.unwrap();
+        assert_eq!(renderer.calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+        assert!(page.text.contains("Zeppelin models"));
+    }
+
+    #[tokio::test]
+    async fn pages_with_enough_text_are_not_rendered() {
+        let text = "A page that says plenty on its own, well past the hundred characters of text, not counting spaces, it takes to skip the browser.";
+        let url = serve(format!("<html><body><p>{text}</p></body></html>")).await;
+        let renderer = Arc::new(MockRenderer { html: "<html><body>rendered</body></html>", calls: Default::default() });
+        let page = fetch(&url, Some(renderer.clone())).await.unwrap();
+        assert_eq!(renderer.calls.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert!(page.text.contains("plenty on its own"));
+    }
+
+    #[test]
+    fn concurrent_checkpoints_all_succeed() {
+        let crawler = crawler();
+        crawler.url_queue.lock().unwrap().push("https://a.test/".to_string(), 0);
+        let dir = std::env::temp_dir().join(format!("quantum-search-crawl-state-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        let failures: usize = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| (0..50).filter(|_| crawler.save_state(&path).is_err()).count()))
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).sum()
+        });
+        let saved = fs::read_to_string(&path);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(failures, 0);
+        assert!(saved.unwrap().contains("https://a.test/"));
+    }
+
+    #[test]
+    fn variant_urls_of_a_page_normalize_to_one_form() {
+        let variants = [
+            "http://x.com/a",
+            "http://x.com/a/",
+            "http://X.COM:80/a",
+            "http://x.com/a#section-2",
+            "http://x.com/a?utm_source=feed&utm_medium=rss",
+            "http://x.com/a/?fbclid=abc123#top",
+        ];
+        for variant in variants {
+            assert_eq!(normalize_url(&Url::parse(variant).unwrap()), "http://x.com/a", "{variant}");
+        }
+
+        // Real parameters are kept, in a stable order
+        let a = normalize_url(&Url::parse("http://x.com/search?q=vco&page=2&utm_campaign=x").unwrap());
+        let b = normalize_url(&Url::parse("http://x.com/search/?page=2&q=vco").unwrap());
+        assert_eq!(a, "http://x.com/search?page=2&q=vco");
+        assert_eq!(a, b);
+        assert_ne!(normalize_url(&Url::parse("https://x.com/a").unwrap()), "http://x.com/a");
+    }
+
+    #[test]
+    fn a_seed_config_supplies_the_urls_and_crawl_parameters() {
+        let file = std::env::temp_dir().join(format!("quantum-search-seeds-{}.json", std::process::id()));
+        fs::write(&file, r#"{
+            "topics": {
+                "eurorack": ["https://modwiggler.example/", "https://synths.example/vco"],
+                "synthesis": ["https://synths.example/vco", "https://dsp.example/"]
+            },
+            "max_depth": 2,
+            "stay_in_domain": true
+        }"#).unwrap();
+        let config = load_seed_config(&file);
+        let _ = fs::remove_file(&file);
+        let config = config.unwrap();
+
+        assert_eq!(config.urls_for("eurorack").unwrap(), ["https://modwiggler.example/", "https://synths.example/vco"]);
+        assert_eq!(config.seed_urls(), ["https://modwiggler.example/", "https://synths.example/vco", "https://dsp.example/"]);
+
+        let mut crawler = crawler();
+        crawler.apply_seed_config(&config);
+        // Unset parameters keep the crawler defaults
+        assert_eq!((crawler.max_depth, crawler.max_pages, crawler.stay_in_domain), (2, 1000, true));
+    }
+
+    #[tokio::test]
+    async fn a_capped_queue_stays_bounded_on_a_densely_linked_site() {
+        let links: String = (0..300).map(|i| format!(r#"<a href="/app?page={i}">page {i}</a> "#)).collect();
+        let url = serve(format!("<html><body><p>A hub page that links to everything on the site.</p>{links}</body></html>")).await;
+
+        let (sender, mut receiver) = mpsc::channel(16);
+        let pages = tokio::spawn(async move {
+            let mut pages = 0;
+            while receiver.recv().await.is_some() {
+                pages += 1;
+            }
+            pages
+        });
+        let mut crawler = Crawler::new(sender);
+        crawler.set_max_queue_size(Some(10)).set_max_pages(4).set_max_depth(3).set_min_domain_interval(Duration::ZERO);
+        crawler.crawl(vec![url.to_string()], 1).await;
+
+        assert_eq!(crawler.peak_queue_size(), 10);
+        assert!(crawler.dropped_urls() >= 290, "{}", crawler.dropped_urls());
+        assert_eq!(crawler.visited_urls.lock().unwrap().len(), 4);
+        drop(crawler);
+        assert_eq!(pages.await.unwrap(), 4);
+    }
+
+    // Serve an endless HTML body to every request, after a header advertising
+    // `content_length` if given, counting the body bytes written
+    async fn serve_endless(content_length: Option<u64>) -> (Url, Arc<std::sync::atomic::AtomicU64>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/big", listener.local_addr().unwrap())).unwrap();
+        let written = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let counter = written.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = stream.read(&mut request).await;
+                    let length = content_length.map(|length| format!("Content-Length: {length}\r\n")).unwrap_or_default();
+                    let header = format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n{length}Connection: close\r\n\r\n<html><body>");
+                    if stream.write_all(header.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let chunk = [b'z'; 64 * 1024];
+                    while stream.write_all(&chunk).await.is_ok() {
+                        counter.fetch_add(chunk.len() as u64, std::sync::atomic::Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        (url, written)
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_are_abandoned_early() {
+        let limits = FetchLimits {
+            max_response_bytes: 1 << 20,
+            final_domains: None,
+            renderer: None,
+            render_domains: HashSet::new(),
+            min_static_text_chars: 100,
+        };
+        let client = Crawler::build_client(5, None, &PoolOptions::default());
+
+        // Advertised as 10 GB, or not advertised at all and never ending
+        for content_length in [Some(10_000_000_000), None] {
+            let (url, written) = serve_endless(content_length).await;
+            let queue = Arc::new(Mutex::new(CrawlQueue::new()));
+            let visited = Arc::new(Mutex::new(HashSet::new()));
+            let fetched = tokio::time::timeout(
+                Duration::from_secs(10),
+                Crawler::fetch_and_process_url(&client, &url, false, queue, visited, 0, &limits),
+            ).await.expect("the fetch should give up on its own");
+            let Err(error) = fetched else {
+                panic!("{content_length:?}: the oversized page was indexed");
+            };
+            assert!(error.to_string().contains("larger than"), "{content_length:?}: {error}");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let written = written.load(std::sync::atomic::Ordering::Relaxed);
+            assert!(written < 64 << 20, "{content_length:?}: {written} bytes sent");
+        }
+    }
+
+    // Connections accepted and requests answered by a `serve_keep_alive` server
+    #[derive(Default)]
+    struct ServerCounts {
+        connections: std::sync::atomic::AtomicUsize,
+        requests: std::sync::atomic::AtomicUsize,
+    }
+
+    // Answer every request on a local port with `status` and `headers` and an
+    // HTML `body`, keeping connections open between requests
+    async fn serve_keep_alive(status: &str, headers: &str, body: &str) -> (Url, Arc<ServerCounts>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/app", listener.local_addr().unwrap())).unwrap();
+        let response = format!(
+            "HTTP/1.1 {status}\r\n{headers}Content-Type: text/html\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let counts = Arc::new(ServerCounts::default());
+        let server_counts = counts.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                server_counts.connections.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let (counts, response) = (server_counts.clone(), response.clone());
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0u8; 4096];
+                    while let Ok(read) = stream.read(&mut buffer).await {
+                        if read == 0 {
+                            break;
+                        }
+                        request.extend_from_slice(&buffer[..read]);
+                        if request.windows(4).any(|window| window == b"\r\n\r\n") {
+                            request.clear();
+                            counts.requests.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            if stream.write_all(response.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        (url, counts)
+    }
+
+    #[tokio::test]
+    async fn redirects_off_the_allowed_domains_are_not_indexed() {
+        let text = "The page a redirect leads to, with more than enough text of its own to be indexed without any rendering.";
+        let (target, target_counts) = serve_keep_alive("200 OK", "", &format!("<html><body><p>{text}</p></body></html>")).await;
+        // Same server, other host name: a different domain as far as the crawl is concerned
+        let offsite = format!("http://localhost:{}/app", target.port().unwrap());
+        let (redirect, _) = serve_keep_alive("302 Found", &format!("Location: {offsite}\r\n"), "").await;
+        let (onsite_redirect, _) = serve_keep_alive("302 Found", &format!("Location: {target}\r\n"), "").await;
+
+        let allowed: HashSet<String> = ["127.0.0.1".to_string()].into();
+        let limits = FetchLimits {
+            max_response_bytes: 1 << 20,
+            final_domains: Some(allowed.clone()),
+            renderer: None,
+            render_domains: HashSet::new(),
+            min_static_text_chars: 100,
+        };
+        let client = Crawler::build_client(5, Some(allowed), &PoolOptions::default());
+        let fetch = |url: Url| {
+            let (client, limits) = (&client, &limits);
+            async move {
+                let queue = Arc::new(Mutex::new(CrawlQueue::new()));
+                let visited = Arc::new(Mutex::new(HashSet::new()));
+                Crawler::fetch_and_process_url(client, &url, false, queue, visited, 0, limits).await.unwrap()
+            }
+        };
+
+        assert!(fetch(redirect).await.is_none());
+        assert_eq!(target_counts.requests.load(std::sync::atomic::Ordering::Relaxed), 0);
+
+        // Redirects that stay on the allowed domains are followed as usual
+        let page = fetch(onsite_redirect).await.unwrap();
+        assert!(page.text.contains("a redirect leads to"));
+        assert_eq!(target_counts.requests.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn the_shared_client_pools_connections_as_configured() {
+        let mut reused = crawler();
+        reused.set_pool_max_idle_per_host(4).set_pool_idle_timeout(Some(Duration::from_secs(30)));
+        let mut unpooled = crawler();
+        unpooled.set_pool_max_idle_per_host(0);
+
+        for (crawler, expected_connections) in [(reused, 1), (unpooled, 3)] {
+            let (url, counts) = serve_keep_alive("200 OK", "", "<html><body>pooled</body></html>").await;
+            let client = Crawler::build_client(crawler.max_redirects, None, &crawler.pool);
+            for _ in 0..3 {
+                let body = client.get(url.clone()).send().await.unwrap().text().await.unwrap();
+                assert!(body.contains("pooled"));
+            }
+            assert_eq!(counts.requests.load(std::sync::atomic::Ordering::Relaxed), 3);
+            assert_eq!(counts.connections.load(std::sync::atomic::Ordering::Relaxed), expected_connections);
+        }
+    }
+}
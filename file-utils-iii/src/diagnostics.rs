@@ -0,0 +1,39 @@
+// src/diagnostics.rs
+//
+// Progress and status messages go to stderr so stdout only carries results
+// and can be piped into a file. `set_quiet(true)` drops them altogether.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress diagnostic output (progress, status and warnings). Errors are still reported.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether diagnostic output is currently suppressed
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a diagnostic line to stderr unless quiet mode is on
+#[macro_export]
+macro_rules! diag {
+    ($($arg:tt)*) => {
+        if !$crate::diagnostics::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Like `diag!` without the trailing newline, for prompts and progress lines
+#[macro_export]
+macro_rules! diag_print {
+    ($($arg:tt)*) => {
+        if !$crate::diagnostics::is_quiet() {
+            eprint!($($arg)*);
+            let _ = std::io::Write::flush(&mut std::io::stderr());
+        }
+    };
+}
@@ -0,0 +1,74 @@
+// src/encoding.rs
+//
+// Turning file and page bytes into text. Older corpora are full of Latin-1,
+// Windows-1252 and Shift-JIS files that `fs::read_to_string` rejects outright,
+// so anything that isn't UTF-8 is transcoded rather than skipped.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// Decode `bytes` as text. A byte order mark wins, then `declared` (e.g. the
+/// charset of an HTTP response) if it names a known encoding, then UTF-8 if the
+/// bytes are valid UTF-8. Anything else is detected when `detect` is set and
+/// read as Windows-1252 otherwise. Undecodable bytes become U+FFFD, so this
+/// never fails.
+pub fn decode_text(bytes: &[u8], declared: Option<&str>, detect: bool) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        return decode_with(encoding, &bytes[bom_length..]);
+    }
+    if let Some(encoding) = declared.and_then(|label| Encoding::for_label(label.trim().as_bytes())) {
+        return decode_with(encoding, bytes);
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+
+    let encoding = if detect {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, true)
+    } else {
+        WINDOWS_1252
+    };
+    decode_with(encoding, bytes)
+}
+
+fn decode_with(encoding: &'static Encoding, bytes: &[u8]) -> String {
+    if encoding == UTF_8 {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    text.into_owned()
+}
+
+/// The charset parameter of a Content-Type header value, if any
+pub fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ResonantEngine;
+    use std::path::PathBuf;
+
+    // "Le café du théâtre sert une crème brûlée — à la française, très appréciée."
+    // as a legacy Windows editor would save it; the dash is 0x97, which Latin-1 lacks
+    const WINDOWS_1252: &[u8] = b"Le caf\xe9 du th\xe9\xe2tre sert une cr\xe8me br\xfbl\xe9e \x97 \xe0 la fran\xe7aise, tr\xe8s appr\xe9ci\xe9e.";
+    const DECODED: &str = "Le café du théâtre sert une crème brûlée — à la française, très appréciée.";
+
+    #[test]
+    fn windows_1252_text_decodes_to_its_accented_characters() {
+        assert_eq!(decode_text(WINDOWS_1252, None, true), DECODED);
+        assert_eq!(decode_text(WINDOWS_1252, None, false), DECODED);
+        assert_eq!(decode_text(WINDOWS_1252, charset_from_content_type("text/plain; charset=windows-1252"), false), DECODED);
+
+        let mut engine = ResonantEngine::new();
+        engine.add_local_document("menu".into(), decode_text(WINDOWS_1252, None, true), PathBuf::from("/menu.txt"));
+        assert_eq!(engine.search("crème brûlée", 5).len(), 1);
+    }
+}
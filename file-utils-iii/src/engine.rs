@@ -1,77 +1,342 @@
 // src/engine.rs
 
-use crate::tokenizer::PrimeTokenizer;
-use crate::prime_hilbert::{build_vector, dot_product, PrimeVector, build_biorthogonal_vector, BiorthogonalVector, to_dense_vector, resonance_complex, biorthogonal_score};
-use crate::entropy::{shannon_entropy, calculate_reversibility, entropy_pressure, buffering_capacity, persistence_score};
+use crate::tokenizer::{NumericTokens, PrimeTokenizer};
+use crate::prime_hilbert::{build_vector, dot_product, PrimeVector, build_biorthogonal_vector, biorthogonal_from_vector, BiorthogonalVector, to_dense_vector, dense_length, cosine_similarity, jaccard_similarity, resonance_complex, biorthogonal_score};
+use crate::entropy::{shannon_entropy, char_ngram_entropy, calculate_reversibility, entropy_pressure, buffering_capacity, persistence_score};
 use crate::crawler::CrawledDocument;
+use crate::encoding::decode_text;
 
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::io::{self, Write, Read};
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use scraper::Html;
 use flate2::write::GzEncoder;
 use flate2::read::GzDecoder;
 use flate2::Compression;
 use num_complex::Complex;
+use rayon::prelude::*;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc;
+use serde::{Serialize, Deserialize};
 
 /// Represents a processed document in the engine's index.
 struct IndexedDocument {
     title: String,
+    // Empty while the text is held compressed
     text: String,
-    compressed_text: Option<Vec<u8>>, // New field for compressed text
+    storage: TextStorage,
+    // Compressed text once a search has needed it, or `None` if it couldn't
+    // be decompressed. Filled in through `&self`, see `decompress_text()`.
+    decompressed: OnceLock<Option<String>>,
     vector: PrimeVector,
-    biorthogonal: BiorthogonalVector,
+    // Only built while quantum scoring is in use, see `biorthogonal()`
+    biorthogonal: OnceLock<BiorthogonalVector>,
     entropy: f64,
+    // Whether `entropy` is over character n-grams, see `set_ngram_entropy`
+    ngram_entropy: bool,
     path: PathBuf,
+    source: DocumentSource,
     timestamp: u64,
     // Persistence theory metrics
     reversibility: f64,
     buffering: f64,
+    // Empty until persistence scoring needs it, see `ensure_persistence_state()`
     historical_vectors: Vec<Vec<f64>>,
+    // Update tracking for per-document entropy pressure
+    first_indexed: u64,
+    update_count: u32,
+    // Older than the staleness threshold at the last `age_documents` pass
+    stale: bool,
+    // Multiplier on the combined score, adjusted by `record_feedback`
+    relevance: f64,
+    // Tombstone left by `remove_document` until the next `compact`
+    removed: bool,
+    // Query-independent persistence, see `ResonantEngine::refresh_persistence_cache()`
+    persistence: Option<CachedPersistence>,
+}
+
+/// Everything the query-independent part of a document's persistence score
+/// depends on. A cached value is reused while these stay the same.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PersistenceInputs {
+    // The document's age in units of the engine's cache bucket
+    age_bucket: u64,
+    timestamp: u64,
+    first_indexed: u64,
+    update_count: u32,
+    reversibility: f64,
+    buffering: f64,
+    fragility: f64,
+    trend_decay: f64,
+    baseline_frequency: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedPersistence {
+    inputs: PersistenceInputs,
+    update_frequency: f64,
+    entropy_pressure: f64,
+    // Before the query's entropy factor
+    persistence: f64,
+}
+
+/// How a document's text is held, see `ResonantEngine::compress_all_documents`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TextStorage {
+    Uncompressed,
+    /// Gzipped; the text is only decompressed while it is needed
+    Compressed(Vec<u8>),
+    /// Compression or reading back failed, so the text stays uncompressed
+    /// and isn't tried again
+    CompressionFailed,
+}
+
+fn gunzip_text(compressed: &[u8]) -> io::Result<String> {
+    let mut text = String::new();
+    GzDecoder::new(compressed).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Dense history seed and buffering capacity for persistence scoring
+fn persistence_state(vector: &PrimeVector, dimension: usize) -> (Vec<Vec<f64>>, f64) {
+    let dense_vec = to_dense_vector(vector, dimension);
+    let buffering = buffering_capacity(&dense_vec);
+    (vec![dense_vec], buffering)
+}
+
+/// A dense history vector keyed by token rather than by prime, for saving
+/// apart from the vocabulary that assigned the primes
+fn dense_to_terms(dense: &[f64], tokenizer: &PrimeTokenizer) -> HashMap<String, f64> {
+    dense.iter()
+        .enumerate()
+        .filter(|(_, &value)| value != 0.0)
+        .filter_map(|(prime, &value)| tokenizer.get_token(prime as u64).map(|token| (token, value)))
+        .collect()
+}
+
+/// Rebuild a dense history vector from `dense_to_terms` under the current
+/// vocabulary; tokens it no longer knows, or whose primes don't fit, drop out
+fn terms_to_dense(terms: &HashMap<String, f64>, tokenizer: &PrimeTokenizer, dimension: usize) -> Vec<f64> {
+    let mut dense = vec![0.0; dimension];
+    for (token, &value) in terms {
+        if let Some(slot) = tokenizer.get_prime(token).and_then(|prime| dense.get_mut(prime as usize)) {
+            *slot = value;
+        }
+    }
+    dense
 }
 
 // Add these methods to the IndexedDocument implementation
 impl IndexedDocument {
-    /// Compress the document text to save memory
-    fn compress_text(&mut self) {
-        if !self.text.is_empty() && self.compressed_text.is_none() {
-            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-            encoder.write_all(self.text.as_bytes()).unwrap_or_default();
-            self.compressed_text = encoder.finish().ok();
-            
-            // Only clear the text if compression was successful
-            if self.compressed_text.is_some() {
-                self.text.clear();
-            }
+    /// The biorthogonal vector, built from the prime vector on first use.
+    /// Concurrent searches that both need it build it once between them.
+    fn biorthogonal(&self) -> &BiorthogonalVector {
+        self.biorthogonal.get_or_init(|| biorthogonal_from_vector(&self.vector))
+    }
+
+    /// Build the dense history and buffering capacity if the document was
+    /// indexed while persistence scoring was off
+    fn ensure_persistence_state(&mut self, dimension: usize) {
+        if self.historical_vectors.is_empty() {
+            (self.historical_vectors, self.buffering) = persistence_state(&self.vector, dimension);
+        }
+    }
+
+    /// Whether the document carries anything worth keeping across a reindex
+    fn has_learned_state(&self) -> bool {
+        !self.removed && (self.relevance != 1.0 || self.reversibility != 1.0 || self.update_count > 0 || self.historical_vectors.len() > 1)
+    }
+
+    fn learned_state(&self, tokenizer: &PrimeTokenizer) -> LearnedState {
+        LearnedState {
+            relevance: self.relevance,
+            reversibility: self.reversibility,
+            historical_vectors: self.historical_vectors.iter().map(|dense| dense_to_terms(dense, tokenizer)).collect(),
+            first_indexed: self.first_indexed,
+            update_count: self.update_count,
+        }
+    }
+
+    /// Carry learned state over onto a freshly extracted document. Like a
+    /// replacement in `insert_document`, this counts as an update. The saved
+    /// history is mapped back through `tokenizer` at the engine's current
+    /// dense `dimension`.
+    fn restore_learned_state(&mut self, state: LearnedState, dimension: usize, tokenizer: &PrimeTokenizer) {
+        self.relevance = state.relevance;
+        self.reversibility = state.reversibility;
+        self.first_indexed = self.first_indexed.min(state.first_indexed);
+        self.update_count = state.update_count.saturating_add(1);
+        
+        if !state.historical_vectors.is_empty() {
+            self.ensure_persistence_state(dimension);
+            let mut history: Vec<Vec<f64>> = state.historical_vectors.iter()
+                .map(|terms| terms_to_dense(terms, tokenizer, dimension))
+                .collect();
+            history.append(&mut self.historical_vectors);
+            let excess = history.len().saturating_sub(5);
+            history.drain(..excess);
+            self.historical_vectors = history;
         }
     }
+
+    /// Updates per day over the document's lifetime, or `baseline` if it has
+    /// never been re-indexed
+    fn update_frequency(&self, now: u64, baseline: f64) -> f64 {
+        if self.update_count == 0 {
+            return baseline;
+        }
+        let lifetime_days = (now.saturating_sub(self.first_indexed) as f64 / (24.0 * 3600.0)).max(1.0);
+        self.update_count as f64 / lifetime_days
+    }
     
-    /// Decompress the text when needed
-    fn decompress_text(&mut self) -> &str {
-        if self.text.is_empty() && self.compressed_text.is_some() {
-            let compressed = self.compressed_text.as_ref().unwrap();
-            let mut decoder = GzDecoder::new(&compressed[..]);
-            let mut text = String::new();
-            
-            if decoder.read_to_string(&mut text).is_ok() {
-                self.text = text;
+    /// Compress the document text to save memory. With `verify`, the
+    /// compressed copy must read back to the text before the text is dropped.
+    /// Returns true if compression work was actually done.
+    fn compress_text(&mut self, verify: bool) -> bool {
+        match self.storage {
+            TextStorage::Compressed(_) => {
+                // Already compressed; drop any decompressed copy without re-encoding.
+                // Text that couldn't be decompressed is left uncompressed (and empty).
+                if let Some(None) = self.decompressed.take() {
+                    self.storage = TextStorage::CompressionFailed;
+                }
+                return false;
             }
+            TextStorage::CompressionFailed => return false,
+            TextStorage::Uncompressed => {}
+        }
+        if self.text.is_empty() {
+            return false;
         }
         
-        &self.text
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder.write_all(self.text.as_bytes())
+            .and_then(|_| encoder.finish())
+            .and_then(|compressed| if verify { self.verify_compressed(compressed) } else { Ok(compressed) });
+        self.store_compressed(compressed)
+    }
+    
+    // `compressed` if it decompresses to the current text
+    fn verify_compressed(&self, compressed: Vec<u8>) -> io::Result<Vec<u8>> {
+        if gunzip_text(&compressed)? == self.text {
+            Ok(compressed)
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "compressed text reads back differently"))
+        }
+    }
+    
+    // Switch to the compressed copy, or keep the text as it is if compression failed
+    fn store_compressed(&mut self, compressed: io::Result<Vec<u8>>) -> bool {
+        match compressed {
+            Ok(compressed) => {
+                self.storage = TextStorage::Compressed(compressed);
+                self.text.clear();
+                true
+            }
+            Err(e) => {
+                diag!("⚠️  Keeping {} uncompressed: {}", self.path.display(), e);
+                self.storage = TextStorage::CompressionFailed;
+                false
+            }
+        }
+    }
+    
+    /// The document text, decompressing it on first use. Only needs `&self`:
+    /// concurrent searches share one decompressed copy, and whichever gets
+    /// there first does the work while the others wait for it. Text that can't
+    /// be decompressed is lost; it is reported once, reads as empty, and the
+    /// document is left uncompressed at the next compression pass.
+    fn decompress_text(&self) -> &str {
+        let TextStorage::Compressed(compressed) = &self.storage else {
+            return &self.text;
+        };
+        let decompressed = self.decompressed.get_or_init(|| {
+            gunzip_text(compressed)
+                .map_err(|e| diag!("⚠️  Could not decompress the text of {}: {}", self.path.display(), e))
+                .ok()
+        });
+        decompressed.as_deref().unwrap_or_default()
+    }
+    
+    fn is_compressed(&self) -> bool {
+        matches!(self.storage, TextStorage::Compressed(_))
     }
     
     /// Get a snippet of the document text
-    fn get_snippet(&mut self, max_len: usize) -> String {
+    fn get_snippet(&self, max_len: usize) -> String {
+        sanitize_snippet(self.decompress_text(), max_len) + "..."
+    }
+    
+    /// A snippet of the document's content around the first query word in it
+    /// (the start of the content when there is none), and which fields each
+    /// query word was found in, see `ResonantEngine::set_field_snippets`
+    fn field_snippet(&self, tokenizer: &PrimeTokenizer, words: &[String], max_len: usize) -> (String, Vec<TermMatch>) {
         let text = self.decompress_text();
-        let snippet_chars: String = text.chars().take(max_len).collect();
-        snippet_chars.trim().replace('\n', " ") + "..."
+        // Filesystem documents carry their name, path and tags after the content
+        let content = text.split_once(METADATA_SEPARATOR).map_or(text, |(content, _)| content);
+        let snippet = match tokenizer.find_word(content, words) {
+            Some(at) => snippet_around(content, at, max_len),
+            None => sanitize_snippet(content, max_len) + "...",
+        };
+        
+        // A file's name is its title, so only its folders count as the path
+        let path = match self.source {
+            DocumentSource::Filesystem => self.path.parent().unwrap_or(&self.path),
+            DocumentSource::Web => self.path.as_path(),
+        };
+        let path = path.to_string_lossy();
+        let fields = [(MatchField::Title, self.title.as_str()), (MatchField::Path, &*path), (MatchField::Content, content)];
+        let matches = words.iter()
+            .filter_map(|word| {
+                let found: Vec<MatchField> = fields.iter()
+                    .filter(|(_, text)| tokenizer.find_word(text, std::slice::from_ref(word)).is_some())
+                    .map(|&(field, _)| field)
+                    .collect();
+                (!found.is_empty()).then(|| TermMatch { term: word.clone(), fields: found })
+            })
+            .collect();
+        (snippet, matches)
+    }
+}
+
+/// Separates a filesystem document's content from the name, path and tags
+/// that follow it in its text. Control characters never become words, and
+/// snippets show them as a space.
+pub(crate) const METADATA_SEPARATOR: &str = "\n\u{1E}\n";
+
+/// A part of a search result a query word can be found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    /// The title; a file's name
+    Title,
+    /// A file's folders or a page's URL
+    Path,
+    /// The text, without the name and path filesystem documents also carry
+    Content,
+}
+
+impl MatchField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchField::Title => "title",
+            MatchField::Path => "path",
+            MatchField::Content => "content",
+        }
     }
 }
 
+/// Where a query word was found in a search result
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermMatch {
+    pub term: String,
+    pub fields: Vec<MatchField>,
+}
+
 /// Represents a search result with scoring details and a snippet.
+#[derive(Debug, Clone)]
 pub struct SearchResult {
     pub title: String,
     pub resonance: f64,
@@ -79,590 +344,4932 @@ pub struct SearchResult {
     pub score: f64,
     pub quantum_score: f64,
     pub persistence_score: f64,
+    /// Weighted blend of the enabled scores; results are ranked by this
+    pub combined_score: f64,
+    /// `combined_score` rescaled over the returned result set, see `ScoreNormalization`
+    pub normalized_score: f64,
     pub snippet: String,
     pub path: String,
+    pub source: DocumentSource,
+    /// Paths of lower-ranked near-duplicates folded into this result, see
+    /// `ResonantEngine::set_result_dedup`
+    pub alternates: Vec<String>,
+    /// Where each query word was found; empty unless field snippets are on,
+    /// see `ResonantEngine::set_field_snippets`
+    pub term_matches: Vec<TermMatch>,
 }
 
-/// The main search engine struct that manages documents and performs searches.
-pub struct ResonantEngine {
-    tokenizer: PrimeTokenizer,
-    docs: Vec<IndexedDocument>,
-    entropy_weight: f64,
-    // Quantum and persistence parameters
-    fragility: f64,
-    trend_decay: f64,
-    use_quantum_score: bool,
-    use_persistence_score: bool,
+/// A `SearchResult` without its snippet, from `ResonantEngine::search_readonly`
+#[derive(Debug, Clone)]
+pub struct SearchResultNoSnippet {
+    pub title: String,
+    pub resonance: f64,
+    pub delta_entropy: f64,
+    pub score: f64,
+    pub quantum_score: f64,
+    pub persistence_score: f64,
+    pub combined_score: f64,
+    pub normalized_score: f64,
+    pub path: String,
+    pub source: DocumentSource,
+    pub alternates: Vec<String>,
 }
 
-impl ResonantEngine {
-    /// Save the current index state to a file
-    pub fn save_checkpoint(&self, path: &str) -> io::Result<()> {
-        let mut file = fs::File::create(path)?;
-        
-        // Write header with metadata
-        writeln!(file, "# Resonant Search Engine Checkpoint")?;
-        writeln!(file, "# Total documents: {}", self.docs.len())?;
-        writeln!(file, "# Timestamp: {}", 
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-        )?;
-        
-        // Write document entries
-        for doc in &self.docs {
-            writeln!(file, "{}\t{}\t{}\t{}\t{}", 
-                doc.path.to_string_lossy(), 
-                doc.title.replace('\t', " "),
-                doc.entropy,
-                doc.reversibility,
-                doc.timestamp
-            )?;
+impl From<SearchResult> for SearchResultNoSnippet {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            title: result.title,
+            resonance: result.resonance,
+            delta_entropy: result.delta_entropy,
+            score: result.score,
+            quantum_score: result.quantum_score,
+            persistence_score: result.persistence_score,
+            combined_score: result.combined_score,
+            normalized_score: result.normalized_score,
+            path: result.path,
+            source: result.source,
+            alternates: result.alternates,
         }
-        
-        println!("Checkpoint saved to {}", path);
-        Ok(())
     }
-    
-    /// Load a previous checkpoint
-    pub fn load_checkpoint(&mut self, path: &str) -> io::Result<()> {
-        let content = fs::read_to_string(path)?;
-        let mut lines = content.lines();
-        
-        // Skip header lines
-        while let Some(line) = lines.next() {
-            if !line.starts_with('#') {
-                // Process the first non-header line
-                self.process_checkpoint_line(line)?;
-                break;
-            }
-        }
-        
-        // Process remaining lines
-        for line in lines {
-            self.process_checkpoint_line(line)?;
+}
+
+/// Which parts of a `SearchResult` `format_search_result` shows
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    pub resonance: bool,
+    pub delta_entropy: bool,
+    pub quantum_score: bool,
+    pub persistence_score: bool,
+    pub combined_score: bool,
+    pub snippet: bool,
+    /// Where each query word was found, when the result says
+    pub matches: bool,
+    /// Longer paths keep their end, prefixed with "..."
+    pub max_path_chars: usize,
+    pub max_snippet_chars: usize,
+    /// Decimal places shown for scores
+    pub precision: usize,
+    pub score_display: ScoreDisplay,
+}
+
+/// How `format_search_result` shows the combined score. The other scores
+/// are always shown raw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoreDisplay {
+    /// The raw score, followed by its normalized value as a whole percentage
+    #[default]
+    Raw,
+    /// Only the normalized score, 0-1 (see `ScoreNormalization`)
+    Normalized,
+    /// Only the normalized score as a whole percentage
+    Percent,
+}
+
+impl ScoreDisplay {
+    pub const ALL: [ScoreDisplay; 3] = [ScoreDisplay::Raw, ScoreDisplay::Normalized, ScoreDisplay::Percent];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScoreDisplay::Raw => "raw",
+            ScoreDisplay::Normalized => "normalized",
+            ScoreDisplay::Percent => "percent",
         }
-        
-        println!("Loaded {} documents from checkpoint", self.docs.len());
-        Ok(())
     }
-    
-    /// Process a single line from the checkpoint file
-    fn process_checkpoint_line(&mut self, line: &str) -> io::Result<()> {
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() < 5 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData, 
-                format!("Invalid checkpoint line: {}", line)
-            ));
+
+    /// The display called `name` (see `as_str`), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|display| display.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            resonance: true,
+            delta_entropy: false,
+            quantum_score: true,
+            persistence_score: true,
+            combined_score: true,
+            snippet: true,
+            matches: true,
+            max_path_chars: 70,
+            max_snippet_chars: 100,
+            precision: 3,
+            score_display: ScoreDisplay::default(),
         }
-        
-        let url = parts[0];
-        let title = parts[1];
-        let entropy: f64 = parts[2].parse().unwrap_or(0.0);
-        let reversibility: f64 = parts[3].parse().unwrap_or(1.0);
-        let timestamp: u64 = parts[4].parse().unwrap_or(0);
-        
-        // Create a placeholder document to be filled with real content later
-        let path = PathBuf::from(url);
-        let tokens = self.tokenizer.tokenize("placeholder");
-        let vector = build_vector(&tokens);
-        let biorthogonal = build_biorthogonal_vector(&tokens);
-        let dense_vec = to_dense_vector(&vector, 1000);
-        
-        self.docs.push(IndexedDocument {
-            title: title.to_string(),
-            text: String::new(),
-            compressed_text: None,
-            vector,
-            biorthogonal,
-            entropy,
-            path,
-            timestamp,
-            reversibility,
-            buffering: 0.5, // Default value
-            historical_vectors: vec![dense_vec],
+    }
+}
+
+/// Render a search result for the terminal, one indented line per part, so
+/// every CLI shows the same labels. Truncation counts characters, never
+/// splitting a multi-byte character.
+pub fn format_search_result(result: &SearchResult, options: &DisplayOptions) -> String {
+    let mut lines = vec![
+        format!("📄 {}", result.title),
+        format!("    📂 {}", truncate_start(&result.path, options.max_path_chars)),
+    ];
+
+    let precision = options.precision;
+    let mut scores = Vec::new();
+    if options.resonance {
+        scores.push(format!("Resonance: {:.*}", precision, result.resonance));
+    }
+    if options.delta_entropy {
+        scores.push(format!("Δ Entropy: {:.*}", precision, result.delta_entropy));
+    }
+    if options.quantum_score {
+        scores.push(format!("Quantum: {:.*}", precision, result.quantum_score));
+    }
+    if options.persistence_score {
+        scores.push(format!("Persistence: {:.*}", precision, result.persistence_score));
+    }
+    if options.combined_score {
+        let percent = result.normalized_score * 100.0;
+        scores.push(match options.score_display {
+            ScoreDisplay::Raw => format!("Combined: {:.*} ({:.0}% match)", precision, result.combined_score, percent),
+            ScoreDisplay::Normalized => format!("Match: {:.*}", precision, result.normalized_score),
+            ScoreDisplay::Percent => format!("Match: {:.0}%", percent),
         });
-        
-        Ok(())
     }
-    
-    /// Compress all documents to save memory
-    pub fn compress_all_documents(&mut self) {
-        for doc in &mut self.docs {
-            doc.compress_text();
+    if !scores.is_empty() {
+        lines.push(format!("    ⚛️  {}", scores.join(" | ")));
+    }
+
+    if options.snippet && !result.snippet.is_empty() {
+        lines.push(format!("    📝 {}", truncate_end(&result.snippet, options.max_snippet_chars)));
+    }
+
+    if options.matches && !result.term_matches.is_empty() {
+        let matches: Vec<String> = result.term_matches.iter()
+            .map(|m| format!("{} in {}", m.term, m.fields.iter().map(|field| field.as_str()).collect::<Vec<_>>().join(", ")))
+            .collect();
+        lines.push(format!("    🔎 {}", matches.join(" | ")));
+    }
+
+    for alternate in &result.alternates {
+        lines.push(format!("    🔁 Also at {}", truncate_start(alternate, options.max_path_chars)));
+    }
+
+    lines.join("\n")
+}
+
+// Characters that print as nothing (or garble the line) without being whitespace
+fn is_invisible(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}' | '\u{FEFF}')
+}
+
+// The first `max_len` characters of `text` as a single line of printable text:
+// control and other invisible characters count as whitespace, and whitespace
+// runs collapse to one space. Binary-ish and log files otherwise garble the terminal.
+fn sanitize_snippet(text: &str, max_len: usize) -> String {
+    let mut snippet = String::new();
+    let mut len = 0;
+    let mut pending_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() || is_invisible(c) {
+            pending_space = len > 0;
+            continue;
         }
-        println!("Compressed {} documents", self.docs.len());
+        if pending_space {
+            if len + 1 >= max_len {
+                break;
+            }
+            snippet.push(' ');
+            len += 1;
+            pending_space = false;
+        }
+        if len >= max_len {
+            break;
+        }
+        snippet.push(c);
+        len += 1;
     }
-    
-    /// Export the index to a simple CSV file
-    pub fn export_index(&self, path: &str) -> io::Result<()> {
-        let mut file = fs::File::create(path)?;
-        
-        // Write CSV header
-        writeln!(file, "url,title,entropy,resonance,persistence")?;
-        
-        // Write each document
-        for doc in &self.docs {
-            writeln!(file, "\"{}\",\"{}\",{},{},{}", 
-                doc.path.to_string_lossy().replace('"', "\"\""), 
-                doc.title.replace('"', "\"\""),
-                doc.entropy,
-                doc.reversibility,
-                doc.buffering
-            )?;
+    snippet
+}
+
+// `max_len` characters of sanitized `text` starting a word or so before byte
+// offset `at`, so the word there shows with some context; "..." marks the cuts
+fn snippet_around(text: &str, at: usize, max_len: usize) -> String {
+    let start = text[..at].char_indices().rev().nth(max_len / 4).map_or(0, |(i, _)| i);
+    if start == 0 {
+        return sanitize_snippet(text, max_len) + "...";
+    }
+    let start = text[start..at].find(char::is_whitespace).map_or(start, |i| start + i);
+    format!("...{}...", sanitize_snippet(&text[start..], max_len))
+}
+
+// Keep the last `max_chars` characters, marking the cut with "..."
+fn truncate_start(text: &str, max_chars: usize) -> String {
+    let len = text.chars().count();
+    if len <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().skip(len - max_chars.saturating_sub(3)).collect();
+    format!("...{}", kept)
+}
+
+// Keep the first `max_chars` characters, marking the cut with "..."
+fn truncate_end(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", kept)
+}
+
+/// Where an indexed document came from. Both kinds share the `path` field
+/// (file paths and URLs), so this is what tells them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentSource {
+    Filesystem,
+    Web,
+}
+
+impl DocumentSource {
+    /// Guess the source of a stored path, for formats that don't record it
+    fn infer(path: &Path) -> Self {
+        if path.to_string_lossy().contains("://") {
+            DocumentSource::Web
+        } else {
+            DocumentSource::Filesystem
         }
-        
-        println!("Index exported to {}", path);
-        Ok(())
     }
-    
-    /// Creates a new `ResonantEngine`.
-    pub fn new() -> Self {
-        ResonantEngine {
-            tokenizer: PrimeTokenizer::new(),
-            docs: Vec::new(),
-            entropy_weight: 0.1,
-            fragility: 0.2,
-            trend_decay: 0.05,
-            use_quantum_score: true,
-            use_persistence_score: true,
+}
+
+/// How combined scores are rescaled within a result set for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScoreNormalization {
+    /// Leave scores raw
+    None,
+    /// Best result maps to 1.0, worst to 0.0
+    #[default]
+    MinMax,
+    /// Scores become shares of the result set that sum to 1.0
+    Softmax,
+}
+
+/// What happens when a document's primes no longer fit the dense vectors
+/// behind persistence scoring (see `to_dense_vector`). The default is
+/// `SparseOnly`: every document keeps a dense history, so growing the
+/// dimension costs memory in proportion to the whole index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DenseOverflow {
+    /// Widen the dense vectors to the next power of two that holds every
+    /// prime, up to `max_dimension` components; past that, as `SparseOnly`
+    Grow { max_dimension: usize },
+    /// Keep the dimension and suspend persistence scoring, so results rank
+    /// on the sparse vectors alone rather than on truncated dense ones
+    #[default]
+    SparseOnly,
+}
+
+/// How the query's term vector is compared with a document's for the
+/// resonance score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SimilarityMetric {
+    /// Dot product of the weighted term vectors
+    #[default]
+    Dot,
+    /// Dot product over the product of the norms. The same as `Dot` while
+    /// both vectors have unit length, but stays within [-1, 1] for vectors
+    /// that lost terms, e.g. to vocabulary eviction
+    Cosine,
+    /// Share of the terms in either vector that appear in both, ignoring weights
+    Jaccard,
+}
+
+impl SimilarityMetric {
+    pub const ALL: [SimilarityMetric; 3] = [SimilarityMetric::Dot, SimilarityMetric::Cosine, SimilarityMetric::Jaccard];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SimilarityMetric::Dot => "dot",
+            SimilarityMetric::Cosine => "cosine",
+            SimilarityMetric::Jaccard => "jaccard",
         }
     }
 
-    /// Returns the number of documents in the index.
-    pub fn len(&self) -> usize {
-        self.docs.len()
+    /// The metric called `name` (see `as_str`), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str().eq_ignore_ascii_case(name))
     }
 
-    /// Enable or disable quantum scoring
-    pub fn set_use_quantum_score(&mut self, enable: bool) {
-        self.use_quantum_score = enable;
+    fn similarity(self, query: &PrimeVector, doc: &PrimeVector) -> f64 {
+        match self {
+            SimilarityMetric::Dot => dot_product(query, doc),
+            SimilarityMetric::Cosine => cosine_similarity(query, doc),
+            SimilarityMetric::Jaccard => jaccard_similarity(query, doc),
+        }
     }
+}
 
-    /// Enable or disable persistence scoring
-    pub fn set_use_persistence_score(&mut self, enable: bool) {
-        self.use_persistence_score = enable;
+/// How the resonance, quantum and persistence scores become one combined score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScoreCombination {
+    /// Sum of the scores, weighted by `ScoreWeights`
+    #[default]
+    Weighted,
+    /// Each score ranks the results on its own, and the rankings are fused
+    /// as in `FederatedSearch`: the sum of weight / (60 + rank). Scores on
+    /// very different scales then count only by how they order the results.
+    ReciprocalRank,
+}
+
+impl ScoreCombination {
+    pub const ALL: [ScoreCombination; 2] = [ScoreCombination::Weighted, ScoreCombination::ReciprocalRank];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScoreCombination::Weighted => "weighted",
+            ScoreCombination::ReciprocalRank => "rrf",
+        }
     }
 
-    /// Adds a single local file document to the engine's index.
-    #[allow(dead_code)]
-    fn add_local_document(&mut self, title: String, text: String, path: PathBuf) {
-        let tokens = self.tokenizer.tokenize(&text);
-        let vec = build_vector(&tokens);
-        let biorthogonal = build_biorthogonal_vector(&tokens);
-        let entropy = shannon_entropy(&tokens);
-        
-        // Convert to dense vector for historical comparisons
-        let dense_vec = to_dense_vector(&vec, 1000); // Arbitrary dimension
-        
-        // Get current timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
-        // Calculate persistence metrics
-        let reversibility = 1.0; // New document is fully reversible with itself
-        let buffering = buffering_capacity(&dense_vec);
-        
-        self.docs.push(IndexedDocument {
-            title,
-            text,
-            compressed_text: None,
-            vector: vec,
-            biorthogonal,
-            entropy,
-            path,
-            timestamp,
-            reversibility,
-            buffering,
-            historical_vectors: vec![dense_vec.clone()], // Initialize with current vector
-        });
+    /// The strategy called `name` (see `as_str`), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|combination| combination.as_str().eq_ignore_ascii_case(name))
     }
+}
 
-    /// Adds a crawled web document to the engine's index.
-    pub fn add_crawled_document(&mut self, doc: CrawledDocument) {
-        let tokens = self.tokenizer.tokenize(&doc.text);
-        if tokens.is_empty() {
-            return;
-        }
-        
-        let vec = build_vector(&tokens);
-        let biorthogonal = build_biorthogonal_vector(&tokens);
-        let entropy = shannon_entropy(&tokens);
-        
-        // Convert to dense vector for historical comparisons
-        let dense_vec = to_dense_vector(&vec, 1000); // Arbitrary dimension
-        
-        // Get current timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-            
-        // Calculate persistence metrics
-        let reversibility = 1.0; // New document is fully reversible with itself
-        let buffering = buffering_capacity(&dense_vec);
+/// Rank offset for `ScoreCombination::ReciprocalRank`
+const RRF_K: f64 = 60.0;
 
-        // Store the URL string in the path field
-        let doc_path = PathBuf::from(doc.url);
+/// 1 / (RRF_K + rank) for each of `scores`, rank 1 being the highest.
+/// Equal scores share a rank.
+fn reciprocal_ranks(scores: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap_or(std::cmp::Ordering::Equal));
 
-        self.docs.push(IndexedDocument {
-            title: doc.title,
-            text: doc.text,
-            compressed_text: None,
-            vector: vec,
-            biorthogonal,
-            entropy,
-            path: doc_path,
-            timestamp,
-            reversibility,
-            buffering,
-            historical_vectors: vec![dense_vec.clone()], // Initialize with current vector
-        });
+    let mut ranks = vec![0.0; scores.len()];
+    let mut rank = 0;
+    for (position, &i) in order.iter().enumerate() {
+        if position == 0 || scores[i] != scores[order[position - 1]] {
+            rank = position + 1;
+        }
+        ranks[i] = 1.0 / (RRF_K + rank as f64);
     }
+    ranks
+}
 
-    /// Loads and indexes supported files from a directory and its subdirectories recursively.
-    #[allow(dead_code)]
-    pub fn load_directory<P: AsRef<Path>>(&mut self, folder: P) -> io::Result<()> {
-        let path = folder.as_ref();
-        if !path.is_dir() {
-             return Err(io::Error::new(io::ErrorKind::NotFound, format!("'{}' is not a directory", path.display())));
+impl ScoreNormalization {
+    fn apply(self, results: &mut [SearchResult]) {
+        let scores = results.iter().map(|r| r.combined_score);
+        let max = scores.clone().fold(f64::NEG_INFINITY, f64::max);
+        let min = scores.fold(f64::INFINITY, f64::min);
+
+        match self {
+            ScoreNormalization::None => {
+                for result in results.iter_mut() {
+                    result.normalized_score = result.combined_score;
+                }
+            }
+            ScoreNormalization::MinMax => {
+                let range = max - min;
+                for result in results.iter_mut() {
+                    result.normalized_score = if range > 0.0 {
+                        (result.combined_score - min) / range
+                    } else {
+                        1.0 // All results tie for best
+                    };
+                }
+            }
+            ScoreNormalization::Softmax => {
+                // Shift by the max so exp() cannot overflow
+                let total: f64 = results.iter().map(|r| (r.combined_score - max).exp()).sum();
+                for result in results.iter_mut() {
+                    result.normalized_score = (result.combined_score - max).exp() / total;
+                }
+            }
         }
-        self.process_directory_recursive(path)
     }
+}
 
-    /// Recursive helper function to process directories and files.
-    #[allow(dead_code)]
-    fn process_directory_recursive<P: AsRef<Path>>(&mut self, current_dir: P) -> io::Result<()> {
-        for entry in fs::read_dir(current_dir)? {
-            let entry = entry?;
-            let file_path = entry.path();
+/// How the combined scores of filesystem and web documents are put on a
+/// common footing before a mixed index is ranked. Files indexed by name and
+/// path alone score differently from web pages with their full text, so raw
+/// scores tend to favour one source.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SourceCalibration {
+    /// Rank on the raw scores
+    #[default]
+    None,
+    /// Map each source's scores through fixed factors, e.g. as fitted by
+    /// `ResonantEngine::calibrate_sources`
+    Scaled { filesystem: SourceScale, web: SourceScale },
+    /// Rescale each source's scores within the result set so its best
+    /// document scores 1.0 and its worst 0.0, interleaving the sources
+    PerSourceMinMax,
+}
 
-            if file_path.is_file() {
-                let extension = file_path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
-                 let title = file_path.file_stem()
-                                .and_then(|s| s.to_str())
-                                .unwrap_or_else(|| file_path.file_name().and_then(|s| s.to_str()).unwrap_or("unknown file"))
-                                .to_string();
+/// Calibration factors for one source: its scores become
+/// `(score - offset) * scale`. Combined scores can be negative, so an offset
+/// is needed as well as a scale to line two sources up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SourceScale {
+    pub offset: f64,
+    pub scale: f64,
+}
 
-                let text_content = match extension.as_str() {
-                    "txt" => {
-                        match fs::read_to_string(&file_path) {
-                            Ok(text) => Some(text),
-                            Err(e) => {
-                                eprintln!("Error reading {}: {}", file_path.display(), e);
-                                None
-                            }
-                        }
-                    }
-                    "html" => {
-                        match fs::read_to_string(&file_path) {
-                            Ok(html_string) => {
-                                let fragment = Html::parse_document(&html_string);
-                                let text = fragment.root_element().text().collect::<String>();
-                                Some(text)
-                            }
-                            Err(e) => {
-                                eprintln!("Error reading {}: {}", file_path.display(), e);
-                                None
-                            }
-                        }
-                    }
-                    _ => None,
-                };
+impl Default for SourceScale {
+    fn default() -> Self {
+        SourceScale { offset: 0.0, scale: 1.0 }
+    }
+}
 
-                if let Some(text) = text_content {
-                    if !text.trim().is_empty() {
-                         self.add_local_document(title, text, file_path);
-                    } else {
-                        println!("Skipping empty local document after text extraction: {}", file_path.display());
-                    }
+impl SourceScale {
+    fn check(self, source: &'static str) -> Result<(), InvalidParameter> {
+        InvalidParameter::check(source, self.offset, true, "a finite offset")?;
+        InvalidParameter::check(source, self.scale, self.scale > 0.0, "a finite scale > 0")
+    }
+
+    /// Standardizes scores with this mean and standard deviation; no spread
+    /// leaves the scale at 1
+    fn standardizing(scores: &[f64]) -> Self {
+        if scores.is_empty() {
+            return SourceScale::default();
+        }
+        let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+        let std_dev = (scores.iter().map(|score| (score - mean).powi(2)).sum::<f64>() / scores.len() as f64).sqrt();
+        SourceScale { offset: mean, scale: if std_dev > 0.0 { 1.0 / std_dev } else { 1.0 } }
+    }
+}
+
+impl SourceCalibration {
+    fn apply(self, results: &mut [SearchResult]) {
+        match self {
+            SourceCalibration::None => {}
+            SourceCalibration::Scaled { filesystem, web } => {
+                for result in results.iter_mut() {
+                    let factors = match result.source {
+                        DocumentSource::Filesystem => filesystem,
+                        DocumentSource::Web => web,
+                    };
+                    result.combined_score = (result.combined_score - factors.offset) * factors.scale;
                 }
-            } else if file_path.is_dir() {
-                if let Err(e) = self.process_directory_recursive(&file_path) {
-                    eprintln!("Error traversing directory {}: {}", file_path.display(), e);
+            }
+            SourceCalibration::PerSourceMinMax => {
+                for source in [DocumentSource::Filesystem, DocumentSource::Web] {
+                    let scores = results.iter().filter(|r| r.source == source).map(|r| r.combined_score);
+                    let max = scores.clone().fold(f64::NEG_INFINITY, f64::max);
+                    let min = scores.fold(f64::INFINITY, f64::min);
+                    let range = max - min;
+                    for result in results.iter_mut().filter(|r| r.source == source) {
+                        result.combined_score = if range > 0.0 {
+                            (result.combined_score - min) / range
+                        } else {
+                            1.0 // All of the source's results tie for best
+                        };
+                    }
                 }
             }
         }
+    }
+}
+
+/// Outcome of a search, distinguishing why nothing came back.
+pub enum SearchOutcome {
+    /// The query contained no indexable tokens
+    EmptyQuery,
+    /// Nothing has been indexed yet
+    EmptyIndex,
+    /// The index was searched but no document shares a term with the query
+    NoMatches,
+    /// Ranked results, best first
+    Ok(Vec<SearchResult>),
+}
+
+/// Result of `ResonantEngine::search_with_deadline`
+pub struct TimedSearch {
+    pub outcome: SearchOutcome,
+    /// The deadline passed before every document was scored; the results
+    /// are the best among those that were
+    pub truncated: bool,
+}
+
+/// One query transformation a search applied, see `QueryPlan`
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanStage {
+    /// "tokenize", "synonyms" or "co-occurrence"
+    pub name: &'static str,
+    /// Terms the stage added to the query; for "tokenize", the query's own terms
+    pub added: Vec<String>,
+    /// Distinct query terms after the stage
+    pub terms: usize,
+}
+
+/// Every quantity behind one document's scores for one query, as written by
+/// `ResonantEngine::diagnostics_export`. The quantum and persistence values
+/// are 0 while those scores are off.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScoreComponents {
+    pub path: String,
+    pub title: String,
+    pub resonance: f64,
+    pub delta_entropy: f64,
+    /// Resonance less the entropy penalty, `SearchResult::score`
+    pub score: f64,
+    pub age_days: f64,
+    /// Phase decay of the complex resonance, from the age
+    pub decay_factor: f64,
+    pub complex_real: f64,
+    pub complex_imaginary: f64,
+    pub biorthogonal_score: f64,
+    pub quantum_score: f64,
+    pub reversibility: f64,
+    pub update_frequency: f64,
+    pub entropy_pressure: f64,
+    pub buffering: f64,
+    /// Query/document entropy match the persistence score is scaled by
+    pub entropy_factor: f64,
+    pub persistence_score: f64,
+    /// Feedback multiplier on the combined score
+    pub relevance: f64,
+    pub combined_score: f64,
+}
+
+// What a search records about itself besides its results
+#[derive(Default)]
+struct SearchTrace<'a> {
+    plan: Option<&'a mut QueryPlan>,
+    // One entry per scored document, in scoring order
+    components: Option<&'a mut Vec<ScoreComponents>>,
+}
+
+// What a search produces and what it may fill in on the way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchMode {
+    // Results with snippets, decompressing text where needed
+    Snippets,
+    // Empty snippets
+    ScoresOnly,
+    // Empty snippets, and no cache updates, see `search_readonly`
+    ReadOnly,
+}
+
+/// How `ResonantEngine::search_explain` got from a query to its results.
+/// Tokenizing lowercases the query, applies the numeric-token policy and
+/// drops words no document has; the expansion stages only appear when enabled.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryPlan {
+    pub query: String,
+    /// The stages that ran, in order
+    pub stages: Vec<PlanStage>,
+    /// The query terms that were scored, with their weights, strongest first
+    pub final_terms: Vec<(String, f64)>,
+    /// Documents the inverted index offered for scoring
+    pub candidates: usize,
+    /// Candidates actually scored, after the source filter and any deadline
+    pub scored: usize,
+    /// Path and combined score of each returned result, best first
+    pub top_scores: Vec<(String, f64)>,
+}
+
+impl std::fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Query: {:?}", self.query)?;
+        for stage in &self.stages {
+            writeln!(f, "  {}: {} ({} terms)", stage.name, stage.added.join(", "), stage.terms)?;
+        }
+        let terms: Vec<String> = self.final_terms.iter().map(|(term, weight)| format!("{} {:.3}", term, weight)).collect();
+        writeln!(f, "  final terms: {}", terms.join(", "))?;
+        writeln!(f, "  candidates: {}, scored: {}", self.candidates, self.scored)?;
+        for (rank, (path, score)) in self.top_scores.iter().enumerate() {
+            writeln!(f, "  {}. {:.3} {}", rank + 1, score, path)?;
+        }
         Ok(())
     }
+}
 
-    /// Update document relationships and calculate reversibility
-    fn update_document_relationships(&mut self) {
-        // Create a copy of all document vectors
-        let all_vectors: Vec<Vec<f64>> = self.docs.iter()
-            .map(|doc| {
-                to_dense_vector(&doc.vector, 1000) // Convert to same-sized dense vectors
-            })
-            .collect();
-        
-        // Update reversibility for each document
-        for (i, doc) in self.docs.iter_mut().enumerate() {
-            // Get all vectors except this document's vector
-            let others_vectors: Vec<Vec<f64>> = all_vectors.iter()
-                .enumerate()
-                .filter(|&(j, _)| j != i) // Skip the current document
-                .map(|(_, vec)| vec.clone())
-                .collect();
-            
-            // Update reversibility and add to historical vectors
-            if !others_vectors.is_empty() {
-                let current_vec = &all_vectors[i];
-                doc.reversibility = calculate_reversibility(current_vec, &others_vectors);
-                
-                // Only keep a reasonable number of historical vectors (e.g., up to 5)
-                if doc.historical_vectors.len() < 5 {
-                    doc.historical_vectors.push(current_vec.clone());
-                }
-            }
+impl SearchOutcome {
+    /// Discard the reason and return the results, empty when there are none
+    pub fn into_results(self) -> Vec<SearchResult> {
+        match self {
+            SearchOutcome::Ok(results) => results,
+            _ => Vec::new(),
         }
     }
+}
 
-    /// Calculate quantum score for a document given a query
-    fn calculate_quantum_score(&self, query_vec: &PrimeVector, doc: &IndexedDocument) -> f64 {
-        // Calculate basic resonance using dot product
-        let _basic_resonance = dot_product(query_vec, &doc.vector);
-        
-        // Calculate complex resonance with decay
-        // Use doc age for decay factor - newer documents have less decay
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let doc_age = ((now - doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
-        let decay_factor = 0.01 * doc_age.min(100.0); // Cap at 100 days
+/// When `ResonantEngine::cluster_results` stops merging groups of results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClusterBy {
+    /// Keep merging while the two closest groups have at least this average similarity
+    Threshold(f64),
+    /// Keep merging until at most this many groups are left
+    Count(usize),
+    /// One group per directory (a file's folder, a web page's parent URL),
+    /// led by its best-ranked result
+    Directory,
+}
+
+/// Search results about the same topic, see `ResonantEngine::cluster_results`
+pub struct ResultCluster {
+    /// Members in their original rank order
+    pub members: Vec<SearchResult>,
+    /// Position in `members` of the result most similar to the rest of the cluster
+    pub representative: usize,
+}
+
+impl ResultCluster {
+    /// The member that best stands for the whole cluster
+    pub fn representative(&self) -> &SearchResult {
+        &self.members[self.representative]
+    }
+}
+
+// Average pairwise similarity between two groups of results
+fn average_linkage(a: &[usize], b: &[usize], similarity: &[Vec<f64>]) -> f64 {
+    let total: f64 = a.iter()
+        .flat_map(|&i| b.iter().map(move |&j| similarity[i][j]))
+        .sum();
+    total / (a.len() * b.len()) as f64
+}
+
+/// Progress of a `compress_all_documents` run
+#[derive(Debug)]
+pub struct CompressProgress {
+    pub processed: usize,
+    pub compressed: usize,
+    pub total: usize,
+}
+
+/// Metadata of a single document captured by `ResonantEngine::snapshot`
+#[derive(Debug, Clone)]
+pub struct DocumentSnapshot {
+    pub title: String,
+    pub path: PathBuf,
+    pub entropy: f64,
+    pub reversibility: f64,
+    pub buffering: f64,
+    pub timestamp: u64,
+}
+
+/// What the engine has learned about a single path, kept apart from the
+/// document content so it survives a reindex, see `ResonantEngine::save_learned_state`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnedState {
+    pub relevance: f64,
+    pub reversibility: f64,
+    /// Dense history keyed by token, as primes are assigned afresh by each
+    /// vocabulary
+    pub historical_vectors: Vec<HashMap<String, f64>>,
+    pub first_indexed: u64,
+    pub update_count: u32,
+}
+
+/// Factor applied to a document's relevance per `record_feedback` call
+const FEEDBACK_STEP: f64 = 1.25;
+/// Bounds on the relevance multiplier so feedback can't bury or pin a document
+const MIN_RELEVANCE: f64 = 0.2;
+const MAX_RELEVANCE: f64 = 5.0;
+
+/// A consistent, read-only view of the engine's documents for exporting
+#[derive(Debug, Clone)]
+pub struct EngineSnapshot {
+    pub taken_at: u64,
+    pub documents: Vec<DocumentSnapshot>,
+}
+
+impl EngineSnapshot {
+    /// Write the snapshot in the checkpoint format read by `ResonantEngine::load_checkpoint`.
+    /// The checkpoint is written to a temporary file and renamed into place, so an
+    /// interrupted save leaves the previous checkpoint intact.
+    pub fn save_checkpoint(&self, path: &str) -> io::Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        let mut file = io::BufWriter::new(fs::File::create(&tmp_path)?);
         
-        let complex_res = resonance_complex(query_vec, &doc.vector, decay_factor);
+        // Write header with metadata
+        writeln!(file, "# Resonant Search Engine Checkpoint")?;
+        writeln!(file, "# Total documents: {}", self.documents.len())?;
+        writeln!(file, "# Timestamp: {}", self.taken_at)?;
         
-        // For biorthogonal scoring
-        let query_bio = build_biorthogonal_vector(&self.tokenizer.tokenize_without_update(query_vec.keys().cloned().collect::<Vec<_>>().as_slice()));
-        let bio_score = biorthogonal_score(&query_bio, &doc.biorthogonal);
+        // Write document entries
+        for doc in &self.documents {
+            writeln!(file, "{}\t{}\t{}\t{}\t{}", 
+                doc.path.to_string_lossy(), 
+                doc.title.replace('\t', " "),
+                doc.entropy,
+                doc.reversibility,
+                doc.timestamp
+            )?;
+        }
         
-        // Combine scores - weight the real part most heavily but consider phase
-        let quantum_score = complex_res.re * 0.6 + complex_res.im.abs() * 0.2 + bio_score * 0.2;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp_path, path)?;
         
-        quantum_score
+        diag!("Checkpoint saved to {}", path);
+        Ok(())
     }
     
-    /// Calculate persistence score for a document
-    fn calculate_persistence_score(&self, query_entropy: f64, doc: &IndexedDocument) -> f64 {
-        // Calculate document age in days
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let doc_age = ((now - doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
-        
-        // Calculate update frequency (using a default value for now)
-        let update_frequency = 0.1; // Lower means less frequent updates
-        
-        // Get the current vector for the document
-        let _current_vec = to_dense_vector(&doc.vector, 1000);
+    /// Export the snapshot to a simple CSV file
+    pub fn export_index(&self, path: &str) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
         
-        // Calculate persistence score using the thermodynamic model
-        let persistence = persistence_score(
-            doc.reversibility,
-            entropy_pressure(doc_age, update_frequency, self.trend_decay),
-            doc.buffering,
-            self.fragility
-        );
+        // Write CSV header
+        writeln!(file, "url,title,entropy,resonance,persistence")?;
         
-        // Adjust based on entropy delta with query
-        let entropy_delta = (doc.entropy - query_entropy).abs();
-        let entropy_factor = (-entropy_delta * self.entropy_weight).exp();
+        // Write each document
+        for doc in &self.documents {
+            writeln!(file, "\"{}\",\"{}\",{},{},{}", 
+                doc.path.to_string_lossy().replace('"', "\"\""), 
+                doc.title.replace('"', "\"\""),
+                doc.entropy,
+                doc.reversibility,
+                doc.buffering
+            )?;
+        }
         
-        persistence * entropy_factor
+        diag!("Index exported to {}", path);
+        Ok(())
     }
+}
 
-    /// Performs a search query against the indexed documents.
-    /// Returns a vector of `SearchResult`s, sorted by score in descending order.
-    pub fn search(&mut self, query: &str, top_k: usize) -> Vec<SearchResult> {
-        // First update document relationships to ensure reversibility is current
-        self.update_document_relationships();
-        
-        let query_tokens = self.tokenizer.tokenize(query);
-        if query_tokens.is_empty() {
-            return Vec::new();
+/// File format for `ResonantEngine::export_vectors`. Every format holds one
+/// row per document, in the order of the companion metadata file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorFormat {
+    /// NumPy `.npy` (version 1.0), a float64 matrix of shape (documents, dimension)
+    Npy,
+    /// Comma-separated values, one row per line, no header
+    Csv,
+    /// Row and column counts as little-endian u64s, then the rows as little-endian f64s
+    Binary,
+}
+
+impl VectorFormat {
+    /// Pick the format from a file name: `.npy`, `.csv`, anything else binary
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("npy") => VectorFormat::Npy,
+            Some("csv") => VectorFormat::Csv,
+            _ => VectorFormat::Binary,
         }
-        
-        let query_vec = build_vector(&query_tokens);
-        let query_entropy = shannon_entropy(&query_tokens);
+    }
+}
 
-        // First get all the scores without using 'self' inside the closure
-        let mut results: Vec<SearchResult> = Vec::new();
-        
-        // Process each document individually to avoid borrowing conflicts
-        for doc in &mut self.docs {
-            // Standard resonance score
-            let resonance = dot_product(&query_vec, &doc.vector);
-            let delta_entropy = (doc.entropy - query_entropy).abs();
-            let standard_score = resonance - delta_entropy * self.entropy_weight;
-            
-            // Quantum-inspired score
-            let quantum_score = if self.use_quantum_score {
-                // Calculate directly instead of calling self.method()
-                // Begin quantum score calculation (copied from calculate_quantum_score)
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                let doc_age = ((now - doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
-                let decay_factor = 0.01 * doc_age.min(100.0); // Cap at 100 days
-                
-                let complex_res = resonance_complex(&query_vec, &doc.vector, decay_factor);
-                
-                // For biorthogonal scoring
-                let query_bio = build_biorthogonal_vector(&self.tokenizer.tokenize_without_update(query_vec.keys().cloned().collect::<Vec<_>>().as_slice()));
-                let bio_score = biorthogonal_score(&query_bio, &doc.biorthogonal);
-                
-                // Combine scores - weight the real part most heavily but consider phase
-                complex_res.re * 0.6 + complex_res.im.abs() * 0.2 + bio_score * 0.2
-                // End quantum score calculation
-            } else {
-                0.0
-            };
-            
-            // Persistence theory score
-            let persistence_score = if self.use_persistence_score {
-                // Calculate directly instead of calling self.method()
-                // Begin persistence score calculation (copied from calculate_persistence_score)
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                let doc_age = ((now - doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
-                
-                // Calculate update frequency (using a default value for now)
-                let update_frequency = 0.1; // Lower means less frequent updates
-                
-                // Calculate persistence score using the thermodynamic model
-                let persistence = persistence_score(
-                    doc.reversibility,
-                    entropy_pressure(doc_age, update_frequency, self.trend_decay),
-                    doc.buffering,
-                    self.fragility
-                );
-                
-                // Adjust based on entropy delta with query
-                let entropy_delta = (doc.entropy - query_entropy).abs();
-                let entropy_factor = (-entropy_delta * self.entropy_weight).exp();
-                
-                persistence * entropy_factor
-                // End persistence score calculation
-            } else {
-                0.0
-            };
-            
-            // Generate snippet
-            let snippet = doc.get_snippet(200);
+// The `.npy` header for a float64 matrix: magic, version 1.0, then a Python
+// dict literal padded with spaces so the data starts on a 64-byte boundary
+fn npy_header(rows: usize, columns: usize) -> Vec<u8> {
+    let dict = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}", rows, columns);
+    let unpadded = 10 + dict.len() + 1; // magic, version and length, dict, newline
+    let padding = (64 - unpadded % 64) % 64;
+    let header_len = (dict.len() + padding + 1) as u16;
 
-            results.push(SearchResult {
-                title: doc.title.clone(),
-                resonance,
-                delta_entropy,
-                score: standard_score,
-                quantum_score,
-                persistence_score,
-                snippet,
-                path: doc.path.to_string_lossy().into_owned(),
-            });
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&header_len.to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header.extend(std::iter::repeat_n(b' ', padding));
+    header.push(b'\n');
+    header
+}
+
+/// Reorders the top candidates of a search, see `ResonantEngine::set_reranker`.
+/// Called with the query and the candidates in score order; whatever it returns
+/// (in its order) becomes the search results.
+pub type Reranker = Box<dyn Fn(&str, &[SearchResult]) -> Vec<SearchResult> + Send + Sync>;
+
+/// Source of the current time for age-dependent scoring
+pub trait Clock: Send + Sync {
+    /// Seconds since the Unix epoch
+    fn now_secs(&self) -> u64;
+}
+
+/// The real wall clock, used by default
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+/// A clock stuck at a given instant, for deterministic scoring in tests
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now_secs(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Parameters of the thermodynamic persistence model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PersistenceParams {
+    pub entropy_weight: f64,
+    pub fragility: f64,
+    pub trend_decay: f64,
+    pub update_frequency: f64,
+}
+
+/// Relative weights of the score components in the combined score. Only the
+/// enabled components take part, with their weights rescaled to sum to 1;
+/// see `ResonantEngine::effective_score_weights`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScoreWeights {
+    pub resonance: f64,
+    pub quantum: f64,
+    pub persistence: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights { resonance: 0.5, quantum: 0.25, persistence: 0.25 }
+    }
+}
+
+/// Every tunable of a `ResonantEngine`, for keeping in a config file; see
+/// `ResonantEngine::settings` and `ResonantEngine::apply_settings`. The
+/// reranker and clock are code rather than settings and aren't included.
+/// Missing fields take their defaults when deserializing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineSettings {
+    pub use_quantum_score: bool,
+    pub use_persistence_score: bool,
+    pub score_weights: ScoreWeights,
+    pub score_normalization: ScoreNormalization,
+    pub source_calibration: SourceCalibration,
+    pub similarity_metric: SimilarityMetric,
+    pub score_combination: ScoreCombination,
+    pub entropy_weight: f64,
+    pub fragility: f64,
+    pub trend_decay: f64,
+    pub update_frequency: f64,
+    pub quantum_jump_threshold: f64,
+    pub quantum_jump_importance: f64,
+    pub quantum_jump_freshening: f64,
+    pub snippet_length: usize,
+    pub field_snippets: bool,
+    pub min_term_overlap: usize,
+    pub idf_weighting: bool,
+    pub dedup_threshold: Option<f64>,
+    pub max_per_directory: Option<usize>,
+    pub filesystem_page_size: usize,
+    pub filesystem_candidate_multiplier: usize,
+    pub query_expansion_terms: usize,
+    pub cooccurrence_window: usize,
+    pub ngram_entropy_below: usize,
+    pub ngram_size: usize,
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    pub synonym_weight: f64,
+    pub max_vocabulary: Option<usize>,
+    pub numeric_tokens: NumericTokens,
+    pub stemming: bool,
+    pub compression_threads: usize,
+    pub verify_compression: bool,
+    pub indexing_threads: usize,
+    pub export_dimension: usize,
+    pub dense_overflow: DenseOverflow,
+    pub autosave_path: Option<String>,
+    pub autosave_every_n_docs: usize,
+    pub autosave_interval: Option<Duration>,
+    pub stale_after_days: f64,
+    pub keep_learned_state: bool,
+    pub warm_up_documents: usize,
+    pub auto_compact_ratio: Option<f64>,
+    pub persistence_cache_bucket: Option<Duration>,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        ResonantEngine::new().settings()
+    }
+}
+
+/// A judged query for `ResonantEngine::optimize_weights`: the paths of the
+/// documents that should come first for it
+#[derive(Debug, Clone)]
+pub struct Judgment {
+    pub query: String,
+    pub relevant: Vec<String>,
+}
+
+/// Ranking quality measure, averaged over judged queries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RankingMetric {
+    /// Mean reciprocal rank of the first relevant result
+    Mrr,
+    /// Normalized discounted cumulative gain over the top K, relevance being yes or no
+    Ndcg(usize),
+}
+
+impl RankingMetric {
+    // Quality in [0, 1] of a ranking whose results are flagged relevant or
+    // not, out of `relevant_total` relevant documents
+    fn measure(self, ranking: &[bool], relevant_total: usize) -> f64 {
+        match self {
+            RankingMetric::Mrr => ranking.iter()
+                .position(|&relevant| relevant)
+                .map_or(0.0, |rank| 1.0 / (rank + 1) as f64),
+            RankingMetric::Ndcg(k) => {
+                let gain = |rank: usize| 1.0 / (rank as f64 + 2.0).log2();
+                let dcg: f64 = ranking.iter().take(k).enumerate()
+                    .filter(|(_, &relevant)| relevant)
+                    .map(|(rank, _)| gain(rank))
+                    .sum();
+                let ideal: f64 = (0..relevant_total.min(k)).map(gain).sum();
+                if ideal > 0.0 { dcg / ideal } else { 0.0 }
+            }
         }
+    }
+}
 
-        // Now sort results based on combined score
-        results.sort_by(|a, b| {
-            let a_combined = if self.use_quantum_score && self.use_persistence_score {
-                a.score * 0.5 + a.quantum_score * 0.25 + a.persistence_score * 0.25
-            } else if self.use_quantum_score {
-                a.score * 0.7 + a.quantum_score * 0.3
-            } else if self.use_persistence_score {
-                a.score * 0.7 + a.persistence_score * 0.3
-            } else {
-                a.score
-            };
-            
-            let b_combined = if self.use_quantum_score && self.use_persistence_score {
-                b.score * 0.5 + b.quantum_score * 0.25 + b.persistence_score * 0.25
-            } else if self.use_quantum_score {
-                b.score * 0.7 + b.quantum_score * 0.3
-            } else if self.use_persistence_score {
-                b.score * 0.7 + b.persistence_score * 0.3
-            } else {
-                b.score
-            };
-            
-            b_combined.partial_cmp(&a_combined).unwrap_or(std::cmp::Ordering::Equal)
-        });
+/// Outcome of `ResonantEngine::optimize_weights`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightFit {
+    /// The weights now in use
+    pub weights: ScoreWeights,
+    /// Mean metric over the judgments with those weights
+    pub metric: f64,
+    /// Mean metric with the weights in use before
+    pub baseline: f64,
+}
+
+/// Returned when an engine parameter is set outside its valid range
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidParameter {
+    pub name: &'static str,
+    pub value: f64,
+    pub expected: &'static str,
+}
+
+impl InvalidParameter {
+    fn check(name: &'static str, value: f64, in_range: bool, expected: &'static str) -> Result<(), Self> {
+        if value.is_finite() && in_range {
+            Ok(())
+        } else {
+            Err(InvalidParameter { name, value, expected })
+        }
+    }
+}
 
-        results.into_iter().take(top_k).collect()
+impl std::fmt::Display for InvalidParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Invalid {}: {} (expected {})", self.name, self.value, self.expected)
     }
+}
 
-    // Method to set the entropy weight
-    pub fn set_entropy_weight(&mut self, weight: f64) {
-        self.entropy_weight = weight;
+impl std::error::Error for InvalidParameter {}
+
+/// The main search engine struct that manages documents and performs searches.
+///
+/// # Concurrency
+///
+/// Anything that changes the index (adding and removing documents, feedback,
+/// settings) takes `&mut self`, as does `search` itself, which refreshes
+/// reversibility first when documents have changed. `search_shared` only needs
+/// `&self`, so one engine behind an `RwLock` serves concurrent searches under
+/// read locks. What such searches share is safe to touch from many threads:
+/// the vocabulary is sharded (see `PrimeTokenizer`), and the per-document
+/// caches a search fills in, decompressed text and biorthogonal vectors, are
+/// `OnceLock`s built once by whichever search needs them first while the
+/// others wait. `search_readonly` goes further and writes nothing at all, not
+/// even vocabulary entries for new query words. Reversibility is the
+/// exception: refreshing it rewrites every document, so shared searches use
+/// the values from the last refresh. Call `warm_up` under the write lock after
+/// changing the index to bring them up to date.
+pub struct ResonantEngine {
+    tokenizer: PrimeTokenizer,
+    docs: Vec<IndexedDocument>,
+    path_index: HashMap<PathBuf, usize>, // Position of each document in `docs`
+    entropy_weight: f64,
+    // Quantum and persistence parameters
+    fragility: f64,
+    trend_decay: f64,
+    update_frequency: f64,
+    use_quantum_score: bool,
+    use_persistence_score: bool,
+    score_weights: ScoreWeights,
+    similarity_metric: SimilarityMetric,
+    score_combination: ScoreCombination,
+    // Characters of sanitized text in each result's snippet
+    snippet_length: usize,
+    // Snippets from the content around a query word, see `set_field_snippets`
+    field_snippets: bool,
+    // Query expansion from term co-occurrence
+    query_expansion_terms: usize,
+    cooccurrence_window: usize,
+    cooccurrences: HashMap<u64, HashMap<u64, u32>>,
+    // Texts with fewer tokens than this get character n-gram entropy, see
+    // `set_ngram_entropy`
+    ngram_entropy_below: usize,
+    ngram_size: usize,
+    // Curated query expansion: term -> equivalent words
+    synonyms: HashMap<String, Vec<String>>,
+    synonym_weight: f64,
+    // Worker threads for bulk compression (0 = rayon default)
+    compression_threads: usize,
+    // Read compressed text back before dropping the plain text
+    verify_compression: bool,
+    indexing_threads: usize,
+    // Length of the dense rows written by `export_vectors`
+    export_dimension: usize,
+    // Length of the dense vectors behind persistence scoring, and what
+    // happens when documents outgrow it
+    dense_dimension: usize,
+    dense_overflow: DenseOverflow,
+    // Set once documents outgrew the dense vectors for good; persistence
+    // scoring is suspended while set
+    sparse_only: bool,
+    // Quantum-jump learning parameters
+    quantum_jump_threshold: f64,
+    quantum_jump_importance: f64,
+    quantum_jump_freshening: f64,
+    score_normalization: ScoreNormalization,
+    source_calibration: SourceCalibration,
+    // Automatic checkpointing, see `set_autosave_path`
+    autosave_path: Option<String>,
+    autosave_every_n_docs: usize,
+    autosave_interval: Option<Duration>,
+    docs_since_autosave: usize,
+    last_autosave: u64,
+    // Documents older than this (in days) are flagged by `age_documents`
+    stale_after_days: f64,
+    // Learned state of paths that are not (yet) indexed again, reapplied by
+    // `insert_document`; only kept across `clear_documents` if `keep_learned_state`
+    learned: HashMap<PathBuf, LearnedState>,
+    keep_learned_state: bool,
+    // Set when documents change; reversibility is only recomputed while set
+    relationships_stale: bool,
+    // Documents whose text `warm_up` decompresses ahead of the first search
+    warm_up_documents: usize,
+    // Inverted index: prime -> indices of the documents containing it, in
+    // index order. Kept exact on add, replace and remove.
+    postings: HashMap<u64, Vec<usize>>,
+    // Documents sharing fewer distinct primes with the query aren't scored
+    min_term_overlap: usize,
+    // Scale query terms by how rare they are in the index, see `set_idf_weighting`
+    idf_weighting: bool,
+    // Results at least this similar to a better-ranked one are folded into it
+    dedup_threshold: Option<f64>,
+    // Results kept from any one directory, see `set_max_per_directory`
+    max_per_directory: Option<usize>,
+    // Results `search_filesystem` returns, and how many candidates per result
+    // it ranks before filtering, see `set_filesystem_page`
+    filesystem_page_size: usize,
+    filesystem_candidate_multiplier: usize,
+    // Tombstoned documents still in `docs`, and the fraction of `docs` they
+    // may make up before `remove_document` compacts (None: only on `compact`)
+    removed_count: usize,
+    auto_compact_ratio: Option<f64>,
+    // Width of the age buckets query-independent persistence is cached for
+    persistence_cache_bucket: Option<Duration>,
+    reranker: Option<Reranker>,
+    clock: Box<dyn Clock>,
+}
+
+/// Weight of an expansion term relative to the query term that pulled it in
+const QUERY_EXPANSION_WEIGHT: f64 = 0.3;
+
+/// Renormalize an expanded query so scores stay comparable with unexpanded queries
+fn normalize(vector: &mut PrimeVector) {
+    let norm: f64 = vector.values().map(|v| v * v).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        for value in vector.values_mut() {
+            *value /= norm;
+        }
+    }
+}
+
+/// Run `work` on a pool of `threads` threads (0 = the global rayon pool),
+/// falling back to the global pool if one can't be built
+fn on_threads<R: Send>(threads: usize, purpose: &str, work: impl FnOnce() -> R + Send) -> R {
+    if threads == 0 {
+        return work();
+    }
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool.install(work),
+        Err(e) => {
+            diag!("Could not build {} thread pool: {}", purpose, e);
+            work()
+        }
+    }
+}
+
+/// The directory a result counts towards for `ClusterBy::Directory` and
+/// `ResonantEngine::set_max_per_directory`
+fn result_directory(result: &SearchResult) -> PathBuf {
+    Path::new(&result.path).parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Drop document `i` from the posting list of `prime`
+fn remove_posting(postings: &mut HashMap<u64, Vec<usize>>, prime: u64, i: usize) {
+    if let Some(list) = postings.get_mut(&prime) {
+        list.retain(|&j| j != i);
+        if list.is_empty() {
+            postings.remove(&prime);
+        }
+    }
+}
+
+impl ResonantEngine {
+    /// Take a point-in-time copy of the document metadata needed for exports.
+    /// This is cheap enough to do under a brief lock; the export itself can then
+    /// run against the snapshot without blocking searches or updates.
+    pub fn snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            taken_at: self.now(),
+            documents: self.docs.iter()
+                .filter(|doc| !doc.removed)
+                .map(|doc| DocumentSnapshot {
+                    title: doc.title.clone(),
+                    path: doc.path.clone(),
+                    entropy: doc.entropy,
+                    reversibility: doc.reversibility,
+                    buffering: doc.buffering,
+                    timestamp: doc.timestamp,
+                })
+                .collect(),
+        }
     }
     
-    // Method to set the fragility parameter
-    pub fn set_fragility(&mut self, fragility: f64) {
-        self.fragility = fragility;
+    /// Save the current index state to a file
+    pub fn save_checkpoint(&self, path: &str) -> io::Result<()> {
+        self.snapshot().save_checkpoint(path)
     }
     
-    // Method to set the trend decay parameter  
-    pub fn set_trend_decay(&mut self, decay: f64) {
-        self.trend_decay = decay;
+    /// Load a previous checkpoint
+    pub fn load_checkpoint(&mut self, path: &str) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        
+        // Skip header lines
+        while let Some(line) = lines.next() {
+            if !line.starts_with('#') {
+                // Process the first non-header line
+                self.process_checkpoint_line(line)?;
+                break;
+            }
+        }
+        
+        // Process remaining lines
+        for line in lines {
+            self.process_checkpoint_line(line)?;
+        }
+        
+        diag!("Loaded {} documents from checkpoint", self.docs.len());
+        Ok(())
     }
     
-    // Apply a quantum jump to the documents (for dynamic updates)
-    pub fn apply_quantum_jump(&mut self, query: &str, importance: f64) {
-        let query_tokens = self.tokenizer.tokenize(query);
-        if query_tokens.is_empty() {
-            return;
+    /// Process a single line from the checkpoint file
+    fn process_checkpoint_line(&mut self, line: &str) -> io::Result<()> {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() < 5 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData, 
+                format!("Invalid checkpoint line: {}", line)
+            ));
         }
         
-        let query_vec = build_vector(&query_tokens);
+        let url = parts[0];
+        let title = parts[1];
+        let entropy: f64 = parts[2].parse().unwrap_or(0.0);
+        let reversibility: f64 = parts[3].parse().unwrap_or(1.0);
+        let timestamp: u64 = parts[4].parse().unwrap_or(0);
         
-        // Create a simple Hamiltonian for the system
-        for doc in &mut self.docs {
-            // Convert vectors to dense format for quantum operations
-            let doc_dense = to_dense_vector(&doc.vector, 100);
-            let query_dense = to_dense_vector(&query_vec, 100);
-            
-            // Skip if too small
-            if doc_dense.is_empty() || query_dense.is_empty() {
-                continue;
-            }
-            
-            // Calculate resonance as overlap
-            let resonance = dot_product(&query_vec, &doc.vector);
-            
-            // If the document resonates with the query, boost its relevance
-            if resonance > 0.1 {
-                // Add the query vector to the document's historical vectors
-                let current_vec = to_dense_vector(&doc.vector, 1000);
-                if doc.historical_vectors.len() < 5 {
-                    doc.historical_vectors.push(current_vec);
-                } else if !doc.historical_vectors.is_empty() {
-                    // Replace oldest vector
-                    doc.historical_vectors.remove(0);
-                    doc.historical_vectors.push(current_vec);
+        // Create a placeholder document to be filled with real content later
+        let path = PathBuf::from(url);
+        let tokens = self.tokenizer.tokenize("placeholder");
+        let vector = build_vector(&tokens);
+        let dense_vec = to_dense_vector(&vector, self.dense_dimension);
+        
+        self.insert_document(IndexedDocument {
+            title: title.to_string(),
+            text: String::new(),
+            storage: TextStorage::Uncompressed,
+            decompressed: OnceLock::new(),
+            vector,
+            biorthogonal: OnceLock::new(),
+            entropy,
+            ngram_entropy: false,
+            source: DocumentSource::infer(&path),
+            path,
+            timestamp,
+            reversibility,
+            buffering: 0.5, // Default value
+            historical_vectors: vec![dense_vec],
+            first_indexed: timestamp,
+            update_count: 0,
+            stale: false,
+            relevance: 1.0,
+            removed: false,
+            persistence: None,
+        });
+        
+        Ok(())
+    }
+    
+    /// Set the number of threads used by `compress_all_documents` (0 = rayon default)
+    pub fn set_compression_threads(&mut self, threads: usize) {
+        self.compression_threads = threads;
+    }
+    
+    /// Check that each document's compressed text reads back before its
+    /// plain text is dropped (on by default). Off, compression takes about
+    /// half the time, but a bad compressed copy loses the text for good.
+    pub fn set_verify_compression(&mut self, verify: bool) {
+        self.verify_compression = verify;
+    }
+    
+    /// Set the number of threads `add_crawled_documents` tokenizes on (0 = rayon default)
+    pub fn set_indexing_threads(&mut self, threads: usize) {
+        self.indexing_threads = threads;
+    }
+    
+    /// Compress all documents in parallel to save memory.
+    /// Documents that are already compressed are skipped, so repeated calls are cheap,
+    /// and so are ones that failed to compress before, which keep their text.
+    /// Returns the number of documents newly compressed.
+    pub fn compress_all_documents(&mut self, progress_tx: Option<mpsc::Sender<CompressProgress>>) -> usize {
+        let total = self.docs.len();
+        let processed = AtomicUsize::new(0);
+        let compressed = AtomicUsize::new(0);
+        let verify = self.verify_compression;
+        
+        let docs = &mut self.docs;
+        let compress = || {
+            docs.par_iter_mut().for_each(|doc| {
+                if doc.compress_text(verify) {
+                    compressed.fetch_add(1, Ordering::Relaxed);
                 }
+                let done = processed.fetch_add(1, Ordering::Relaxed) + 1;
                 
-                // Increase reversibility based on match strength
-                doc.reversibility = doc.reversibility * 0.9 + 0.1 * (resonance * importance);
-                
-                // Update timestamp to mark it as "fresher"
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                
-                // Only update if significantly newer (> 1 day)
-                if now - doc.timestamp > 24 * 3600 {
-                    doc.timestamp = now - ((now - doc.timestamp) / 2); // Make it "halfway" newer
+                // Send progress update every 100 documents
+                if let Some(ref tx) = progress_tx {
+                    if done.is_multiple_of(100) {
+                        let _ = tx.try_send(CompressProgress {
+                            processed: done,
+                            compressed: compressed.load(Ordering::Relaxed),
+                            total,
+                        });
+                    }
+                }
+            });
+        };
+        
+        on_threads(self.compression_threads, "compression", compress);
+        
+        let compressed = compressed.into_inner();
+        
+        // Send final progress update
+        if let Some(ref tx) = progress_tx {
+            let _ = tx.try_send(CompressProgress {
+                processed: total,
+                compressed,
+                total,
+            });
+        }
+        
+        diag!("Compressed {} documents ({} already compressed)", compressed, total - compressed);
+        let failed = self.docs.iter().filter(|doc| doc.storage == TextStorage::CompressionFailed).count();
+        if failed > 0 {
+            diag!("⚠️  {} documents could not be compressed and keep their text uncompressed", failed);
+        }
+        compressed
+    }
+    
+    /// Export the index to a simple CSV file
+    pub fn export_index(&self, path: &str) -> io::Result<()> {
+        self.snapshot().export_index(path)
+    }
+    
+    /// Set the length of the dense vectors written by `export_vectors`
+    pub fn set_export_dimension(&mut self, dimension: usize) {
+        self.export_dimension = dimension.max(1);
+    }
+    
+    /// Write every document's vector as a dense row (see `to_dense_vector`:
+    /// component `p` holds the weight of the term with prime `p`, so terms
+    /// with primes past `set_export_dimension` are left out) for use in
+    /// external tools. Row `i` is described by line `i + 1` of `<path>.meta.csv`
+    /// (`row,path,title`). Returns the number of rows written.
+    pub fn export_vectors(&self, path: &str, format: VectorFormat) -> io::Result<usize> {
+        let dimension = self.export_dimension;
+        let docs: Vec<&IndexedDocument> = self.docs.iter().filter(|doc| !doc.removed).collect();
+        let truncated = docs.iter().filter(|doc| dense_length(&doc.vector) > dimension).count();
+        if truncated > 0 {
+            let needed = docs.iter().map(|doc| dense_length(&doc.vector)).max().unwrap_or(0);
+            diag!("⚠️  {} of {} vectors lose terms at dimension {}; {} would keep them all",
+                truncated, docs.len(), dimension, needed);
+        }
+        
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        match format {
+            VectorFormat::Npy => file.write_all(&npy_header(docs.len(), dimension))?,
+            VectorFormat::Binary => {
+                file.write_all(&(docs.len() as u64).to_le_bytes())?;
+                file.write_all(&(dimension as u64).to_le_bytes())?;
+            }
+            VectorFormat::Csv => {}
+        }
+        for doc in &docs {
+            let row = to_dense_vector(&doc.vector, dimension);
+            match format {
+                VectorFormat::Npy | VectorFormat::Binary => {
+                    for value in row {
+                        file.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                VectorFormat::Csv => {
+                    let values: Vec<String> = row.iter().map(f64::to_string).collect();
+                    writeln!(file, "{}", values.join(","))?;
                 }
             }
         }
+        file.flush()?;
+        
+        let mut meta = io::BufWriter::new(fs::File::create(format!("{}.meta.csv", path))?);
+        writeln!(meta, "row,path,title")?;
+        for (row, doc) in docs.iter().enumerate() {
+            writeln!(meta, "{},\"{}\",\"{}\"",
+                row,
+                doc.path.to_string_lossy().replace('"', "\"\""),
+                doc.title.replace('"', "\"\"")
+            )?;
+        }
+        meta.flush()?;
+        
+        diag!("Exported {} vectors of dimension {} to {}", docs.len(), dimension, path);
+        Ok(docs.len())
+    }
+    
+    /// Run each query and write every document it scored, with each score
+    /// component and the intermediate values behind them (see `ScoreComponents`),
+    /// as a CSV row for analysis in other tools. Rows follow the queries in
+    /// order, then the document paths. Scores depend on the time through
+    /// document ages, so a `FixedClock` (see `set_clock`) makes the output the
+    /// same from run to run, up to rounding in the last digits of sums.
+    /// Returns the number of rows written.
+    pub fn diagnostics_export(&mut self, queries: &[&str], path: &str) -> io::Result<usize> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "query,path,title,resonance,delta_entropy,score,age_days,decay_factor,complex_real,complex_imaginary,\
+            biorthogonal_score,quantum_score,reversibility,update_frequency,entropy_pressure,buffering,entropy_factor,\
+            persistence_score,relevance,combined_score")?;
+        let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+        
+        let mut rows = 0;
+        for query in queries {
+            // Nothing is kept, so only the scoring runs
+            let mut components = Vec::new();
+            self.search_inner(query, 0, None, SearchMode::ScoresOnly, None, SearchTrace { components: Some(&mut components), ..SearchTrace::default() });
+            components.sort_by(|a, b| a.path.cmp(&b.path));
+            for c in &components {
+                let values: Vec<String> = [
+                    c.resonance, c.delta_entropy, c.score, c.age_days, c.decay_factor, c.complex_real, c.complex_imaginary,
+                    c.biorthogonal_score, c.quantum_score, c.reversibility, c.update_frequency, c.entropy_pressure,
+                    c.buffering, c.entropy_factor, c.persistence_score, c.relevance, c.combined_score,
+                ].iter().map(f64::to_string).collect();
+                writeln!(file, "{},{},{},{}", quote(query), quote(&c.path), quote(&c.title), values.join(","))?;
+            }
+            rows += components.len();
+        }
+        file.flush()?;
+        
+        diag!("Exported {} scored documents for {} queries to {}", rows, queries.len(), path);
+        Ok(rows)
     }
-}
\ No newline at end of file
+    
+    /// Checkpoint file written by autosave (None disables autosave)
+    pub fn set_autosave_path(&mut self, path: Option<String>) {
+        self.autosave_path = path;
+        self.docs_since_autosave = 0;
+        self.last_autosave = self.now();
+    }
+
+    /// Save a checkpoint after every `n` added documents (0 disables the counter)
+    pub fn set_autosave_every_n_docs(&mut self, n: usize) {
+        self.autosave_every_n_docs = n;
+    }
+
+    /// Save a checkpoint once this much time has passed since the last save
+    /// (None disables the timer). Checked on every added document and by `autosave_if_due`.
+    pub fn set_autosave_interval(&mut self, interval: Option<Duration>) {
+        self.autosave_interval = interval;
+    }
+
+    /// Save a checkpoint if the document counter or the interval says one is due.
+    /// Returns whether a checkpoint was written.
+    pub fn autosave_if_due(&mut self) -> io::Result<bool> {
+        let Some(path) = self.autosave_path.clone() else {
+            return Ok(false);
+        };
+
+        let now = self.now();
+        let count_due = self.autosave_every_n_docs > 0 && self.docs_since_autosave >= self.autosave_every_n_docs;
+        let interval_due = self.docs_since_autosave > 0 && self.autosave_interval
+            .is_some_and(|interval| now.saturating_sub(self.last_autosave) >= interval.as_secs());
+        if !count_due && !interval_due {
+            return Ok(false);
+        }
+
+        self.save_checkpoint(&path)?;
+        self.docs_since_autosave = 0;
+        self.last_autosave = now;
+        Ok(true)
+    }
+
+    // Count an added document towards autosave and save if due
+    fn note_document_added(&mut self) {
+        if self.autosave_path.is_none() {
+            return;
+        }
+        self.docs_since_autosave += 1;
+        if let Err(e) = self.autosave_if_due() {
+            diag!("Warning: autosave failed: {}", e);
+        }
+    }
+
+    /// Creates a new `ResonantEngine` that ranks by plain resonance. Quantum
+    /// and persistence scoring are opt-in, see `set_use_quantum_score` and
+    /// `set_use_persistence_score`.
+    pub fn new() -> Self {
+        ResonantEngine {
+            tokenizer: PrimeTokenizer::new(),
+            docs: Vec::new(),
+            path_index: HashMap::new(),
+            entropy_weight: 0.1,
+            fragility: 0.2,
+            trend_decay: 0.05,
+            update_frequency: 0.1,
+            use_quantum_score: false,
+            use_persistence_score: false,
+            score_weights: ScoreWeights::default(),
+            similarity_metric: SimilarityMetric::default(),
+            score_combination: ScoreCombination::default(),
+            snippet_length: 200,
+            field_snippets: true,
+            query_expansion_terms: 0,
+            ngram_entropy_below: 0,
+            ngram_size: 3,
+            cooccurrence_window: 5,
+            cooccurrences: HashMap::new(),
+            synonyms: HashMap::new(),
+            synonym_weight: 0.5,
+            compression_threads: 0,
+            verify_compression: true,
+            indexing_threads: 0,
+            export_dimension: 1000,
+            dense_dimension: 1000,
+            dense_overflow: DenseOverflow::default(),
+            sparse_only: false,
+            quantum_jump_threshold: 0.1,
+            quantum_jump_importance: 0.2,
+            quantum_jump_freshening: 0.5,
+            score_normalization: ScoreNormalization::default(),
+            source_calibration: SourceCalibration::default(),
+            autosave_path: None,
+            autosave_every_n_docs: 0,
+            autosave_interval: None,
+            docs_since_autosave: 0,
+            last_autosave: 0,
+            stale_after_days: 90.0,
+            learned: HashMap::new(),
+            keep_learned_state: true,
+            relationships_stale: true,
+            warm_up_documents: 100,
+            postings: HashMap::new(),
+            min_term_overlap: 1,
+            idf_weighting: false,
+            dedup_threshold: None,
+            max_per_directory: None,
+            filesystem_page_size: 10,
+            filesystem_candidate_multiplier: 5,
+            removed_count: 0,
+            auto_compact_ratio: Some(0.25),
+            persistence_cache_bucket: None,
+            reranker: None,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// The engine's current tunables
+    pub fn settings(&self) -> EngineSettings {
+        EngineSettings {
+            use_quantum_score: self.use_quantum_score,
+            use_persistence_score: self.use_persistence_score,
+            score_weights: self.score_weights,
+            score_normalization: self.score_normalization,
+            source_calibration: self.source_calibration,
+            similarity_metric: self.similarity_metric,
+            score_combination: self.score_combination,
+            entropy_weight: self.entropy_weight,
+            fragility: self.fragility,
+            trend_decay: self.trend_decay,
+            update_frequency: self.update_frequency,
+            quantum_jump_threshold: self.quantum_jump_threshold,
+            quantum_jump_importance: self.quantum_jump_importance,
+            quantum_jump_freshening: self.quantum_jump_freshening,
+            snippet_length: self.snippet_length,
+            field_snippets: self.field_snippets,
+            min_term_overlap: self.min_term_overlap,
+            idf_weighting: self.idf_weighting,
+            dedup_threshold: self.dedup_threshold,
+            max_per_directory: self.max_per_directory,
+            filesystem_page_size: self.filesystem_page_size,
+            filesystem_candidate_multiplier: self.filesystem_candidate_multiplier,
+            query_expansion_terms: self.query_expansion_terms,
+            ngram_entropy_below: self.ngram_entropy_below,
+            ngram_size: self.ngram_size,
+            cooccurrence_window: self.cooccurrence_window,
+            synonyms: self.synonyms.iter().map(|(term, words)| (term.clone(), words.clone())).collect(),
+            synonym_weight: self.synonym_weight,
+            max_vocabulary: self.tokenizer.max_vocabulary(),
+            numeric_tokens: self.tokenizer.numeric_tokens(),
+            stemming: self.tokenizer.stemming(),
+            compression_threads: self.compression_threads,
+            verify_compression: self.verify_compression,
+            indexing_threads: self.indexing_threads,
+            export_dimension: self.export_dimension,
+            dense_overflow: self.dense_overflow,
+            autosave_path: self.autosave_path.clone(),
+            autosave_every_n_docs: self.autosave_every_n_docs,
+            autosave_interval: self.autosave_interval,
+            stale_after_days: self.stale_after_days,
+            keep_learned_state: self.keep_learned_state,
+            warm_up_documents: self.warm_up_documents,
+            auto_compact_ratio: self.auto_compact_ratio,
+            persistence_cache_bucket: self.persistence_cache_bucket,
+        }
+    }
+
+    /// Apply tunables saved with `settings`, through the usual setters.
+    /// Stops at the first out-of-range value, leaving the rest unchanged.
+    pub fn apply_settings(&mut self, settings: &EngineSettings) -> Result<(), InvalidParameter> {
+        self.set_score_weights(settings.score_weights)?;
+        self.set_entropy_weight(settings.entropy_weight)?;
+        self.set_fragility(settings.fragility)?;
+        self.set_trend_decay(settings.trend_decay)?;
+        self.set_update_frequency(settings.update_frequency)?;
+        self.set_synonym_weight(settings.synonym_weight)?;
+        self.set_result_dedup(settings.dedup_threshold)?;
+        self.set_max_per_directory(settings.max_per_directory);
+        self.set_filesystem_page(settings.filesystem_page_size, settings.filesystem_candidate_multiplier);
+        self.set_use_quantum_score(settings.use_quantum_score);
+        self.set_use_persistence_score(settings.use_persistence_score);
+        self.set_score_normalization(settings.score_normalization);
+        self.set_source_calibration(settings.source_calibration)?;
+        self.set_similarity_metric(settings.similarity_metric);
+        self.set_score_combination(settings.score_combination);
+        self.set_quantum_jump_threshold(settings.quantum_jump_threshold);
+        self.set_quantum_jump_importance(settings.quantum_jump_importance);
+        self.set_quantum_jump_freshening(settings.quantum_jump_freshening);
+        self.set_snippet_length(settings.snippet_length);
+        self.set_field_snippets(settings.field_snippets);
+        self.set_min_term_overlap(settings.min_term_overlap);
+        self.set_idf_weighting(settings.idf_weighting);
+        self.set_query_expansion(settings.query_expansion_terms);
+        self.set_ngram_entropy(settings.ngram_entropy_below, settings.ngram_size);
+        self.set_cooccurrence_window(settings.cooccurrence_window);
+        self.synonyms = settings.synonyms.iter().map(|(term, words)| (term.clone(), words.clone())).collect();
+        self.set_max_vocabulary(settings.max_vocabulary);
+        self.set_numeric_tokens(settings.numeric_tokens);
+        self.set_stemming(settings.stemming);
+        self.set_compression_threads(settings.compression_threads);
+        self.set_verify_compression(settings.verify_compression);
+        self.set_indexing_threads(settings.indexing_threads);
+        self.set_export_dimension(settings.export_dimension);
+        self.set_dense_overflow(settings.dense_overflow);
+        self.set_autosave_path(settings.autosave_path.clone());
+        self.set_autosave_every_n_docs(settings.autosave_every_n_docs);
+        self.set_autosave_interval(settings.autosave_interval);
+        self.set_stale_after_days(settings.stale_after_days);
+        self.set_keep_learned_state(settings.keep_learned_state);
+        self.set_warm_up_documents(settings.warm_up_documents);
+        self.set_auto_compact_ratio(settings.auto_compact_ratio);
+        self.set_persistence_cache_bucket(settings.persistence_cache_bucket);
+        Ok(())
+    }
+
+    /// Refine the top K candidates of every search with a more expensive model.
+    /// The prime-vector scoring picks the candidates; only those are passed on.
+    pub fn set_reranker(&mut self, reranker: Reranker) {
+        self.reranker = Some(reranker);
+    }
+
+    /// Go back to ranking by the engine's own scores
+    pub fn clear_reranker(&mut self) {
+        self.reranker = None;
+    }
+
+    /// Replace the clock used for document timestamps and ages
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now_secs()
+    }
+
+    /// Returns the number of documents in the index.
+    pub fn len(&self) -> usize {
+        self.docs.len() - self.removed_count
+    }
+
+    /// Whether a document is indexed under `path`
+    pub fn has_document(&self, path: &Path) -> bool {
+        self.path_index.contains_key(path)
+    }
+
+    /// Paths of the documents from `source`, in index order
+    pub fn document_paths(&self, source: DocumentSource) -> impl Iterator<Item = &Path> {
+        self.docs.iter()
+            .filter(move |doc| !doc.removed && doc.source == source)
+            .map(|doc| doc.path.as_path())
+    }
+
+    /// How `normalized_score` is derived from the combined scores of a result set
+    pub fn set_score_normalization(&mut self, normalization: ScoreNormalization) {
+        self.score_normalization = normalization;
+    }
+
+    /// How filesystem and web scores are made comparable in a mixed index
+    /// (raw by default). Fixed offsets must be finite, and scales finite and > 0.
+    pub fn set_source_calibration(&mut self, calibration: SourceCalibration) -> Result<(), InvalidParameter> {
+        if let SourceCalibration::Scaled { filesystem, web } = calibration {
+            filesystem.check("filesystem calibration")?;
+            web.check("web calibration")?;
+        }
+        self.source_calibration = calibration;
+        Ok(())
+    }
+
+    pub fn source_calibration(&self) -> SourceCalibration {
+        self.source_calibration
+    }
+
+    /// Fit `SourceCalibration::Scaled` on sample queries: each source's raw
+    /// scores for the documents matching them are standardized to mean 0 and
+    /// standard deviation 1, so a document ranks by how it compares with its
+    /// own source's typical matches. A source without matches keeps raw
+    /// scores. The factors are applied and returned.
+    pub fn calibrate_sources(&mut self, queries: &[&str]) -> SourceCalibration {
+        let previous = std::mem::take(&mut self.source_calibration);
+        // Fit on the raw ranking, not one reordered or thinned out
+        let reranker = self.reranker.take();
+        let dedup_threshold = self.dedup_threshold.take();
+        let max_per_directory = self.max_per_directory.take();
+        let mut scores = [Vec::new(), Vec::new()];
+        for query in queries {
+            let SearchOutcome::Ok(results) = self.search_inner(query, self.docs.len(), None, SearchMode::ScoresOnly, None, SearchTrace::default()).0 else {
+                continue;
+            };
+            for result in results.iter().filter(|r| r.resonance > 0.0) {
+                let i = match result.source {
+                    DocumentSource::Filesystem => 0,
+                    DocumentSource::Web => 1,
+                };
+                scores[i].push(result.combined_score);
+            }
+        }
+        self.reranker = reranker;
+        self.dedup_threshold = dedup_threshold;
+        self.max_per_directory = max_per_directory;
+        
+        let calibration = SourceCalibration::Scaled {
+            filesystem: SourceScale::standardizing(&scores[0]),
+            web: SourceScale::standardizing(&scores[1]),
+        };
+        diag!("Calibrated sources on {} queries: {:?} (was {:?})", queries.len(), calibration, previous);
+        self.source_calibration = calibration;
+        calibration
+    }
+
+    /// Age (in days) past which a document counts as stale
+    pub fn set_stale_after_days(&mut self, days: f64) {
+        self.stale_after_days = days.max(0.0);
+    }
+
+    /// Number of documents flagged stale by the last `age_documents` pass
+    pub fn stale_count(&self) -> usize {
+        self.docs.iter().filter(|doc| doc.stale).count()
+    }
+
+    /// Recompute age-dependent metrics so old documents fade in ranking: each
+    /// document's buffering decays by `trend_decay` per day since it was indexed.
+    /// Documents past the staleness threshold are flagged, or dropped if
+    /// `remove_stale` is set. Returns the paths of the stale documents so they
+    /// can be re-crawled or re-indexed.
+    pub fn age_documents(&mut self, remove_stale: bool) -> Vec<PathBuf> {
+        let now = self.now();
+        let retention = (1.0 - self.trend_decay).max(0.0);
+        let mut stale_paths = Vec::new();
+
+        for doc in self.docs.iter_mut().filter(|doc| !doc.removed) {
+            let doc_age = now.saturating_sub(doc.timestamp) as f64 / (24.0 * 3600.0); // Age in days
+
+            // Always start from the undecayed value so repeated passes don't compound.
+            // Documents without persistence state get it when persistence scoring needs it.
+            if !doc.historical_vectors.is_empty() {
+                let dense_vec = to_dense_vector(&doc.vector, self.dense_dimension);
+                doc.buffering = buffering_capacity(&dense_vec) * retention.powf(doc_age);
+            }
+
+            doc.stale = doc_age > self.stale_after_days;
+            if doc.stale {
+                stale_paths.push(doc.path.clone());
+            }
+        }
+
+        if remove_stale && !stale_paths.is_empty() {
+            self.docs.retain(|doc| !doc.stale && !doc.removed);
+            self.removed_count = 0;
+            self.relationships_stale = true;
+            self.reindex_positions();
+        }
+
+        stale_paths
+    }
+
+    /// Cap the tokenizer vocabulary (None for unbounded). See `PrimeTokenizer`
+    /// for the precision tradeoff.
+    pub fn set_max_vocabulary(&mut self, max: Option<usize>) {
+        self.tokenizer.set_max_vocabulary(max);
+    }
+
+    /// Choose how numbers and version strings are tokenized. Applies to
+    /// documents added afterwards and to queries.
+    pub fn set_numeric_tokens(&mut self, policy: NumericTokens) {
+        self.tokenizer.set_numeric_tokens(policy);
+    }
+
+    /// Stem English words so "runs" and "running" match "run". Applies to
+    /// documents added afterwards and to queries, so set it before indexing.
+    pub fn set_stemming(&mut self, stemming: bool) {
+        self.tokenizer.set_stemming(stemming);
+    }
+
+    /// Evict the least frequently used terms until at most `keep` remain and
+    /// strip them from every stored vector. Returns the number of evicted terms.
+    pub fn prune_vocabulary(&mut self, keep: usize) -> usize {
+        let evicted: HashSet<u64> = self.tokenizer.evict_least_frequent(keep).into_iter().collect();
+        if evicted.is_empty() {
+            return 0;
+        }
+
+        let strip = |vector: &mut PrimeVector| {
+            vector.retain(|prime, _| !evicted.contains(prime));
+            normalize(vector);
+        };
+
+        for doc in &mut self.docs {
+            strip(&mut doc.vector);
+            if let Some(biorthogonal) = doc.biorthogonal.get_mut() {
+                strip(&mut biorthogonal.left);
+                strip(&mut biorthogonal.right);
+            }
+        }
+
+        self.postings.retain(|prime, _| !evicted.contains(prime));
+        self.cooccurrences.retain(|prime, _| !evicted.contains(prime));
+        for neighbours in self.cooccurrences.values_mut() {
+            neighbours.retain(|prime, _| !evicted.contains(prime));
+        }
+        // Reversibility was measured between the unpruned vectors
+        self.relationships_stale = true;
+
+        evicted.len()
+    }
+
+    /// Merge another engine's documents into this one, e.g. indexes built on
+    /// different machines. The two vocabularies assign primes independently, so
+    /// `other`'s vectors are remapped through this engine's vocabulary first.
+    /// When both hold a document for the same path the newer one wins.
+    /// Returns the number of documents taken from `other`.
+    pub fn merge(&mut self, mut other: ResonantEngine) -> usize {
+        other.compact();
+        let mapping = self.tokenizer.merge(&other.tokenizer);
+
+        let remap = |vector: &PrimeVector| -> PrimeVector {
+            let mut remapped: PrimeVector = vector.iter()
+                .filter_map(|(prime, &value)| mapping.get(prime).map(|&p| (p, value)))
+                .collect();
+            // Terms dropped by the vocabulary cap leave the vector short
+            if remapped.len() < vector.len() {
+                normalize(&mut remapped);
+            }
+            remapped
+        };
+        // Dense history is remapped sparsely and laid out again once this
+        // engine's dimension has been fitted to the merged vectors, as the
+        // new primes can land past the old length
+        let remap_dense = |dense: &[f64]| -> PrimeVector {
+            dense.iter()
+                .enumerate()
+                .filter(|(_, &value)| value != 0.0)
+                .filter_map(|(prime, &value)| mapping.get(&(prime as u64)).map(|&p| (p, value)))
+                .collect()
+        };
+
+        for (term, neighbours) in &other.cooccurrences {
+            let Some(&term) = mapping.get(term) else {
+                continue;
+            };
+            for (other_term, &count) in neighbours {
+                if let Some(&other_term) = mapping.get(other_term) {
+                    *self.cooccurrences.entry(term).or_default().entry(other_term).or_insert(0) += count;
+                }
+            }
+        }
+
+        for (term, equivalents) in other.synonyms {
+            let equivalents: Vec<&str> = equivalents.iter().map(String::as_str).collect();
+            self.add_synonyms(&term, &equivalents);
+        }
+
+        let mut merged = 0;
+        let mut histories = Vec::new();
+        for mut doc in other.docs {
+            if let Some(&i) = self.path_index.get(&doc.path) {
+                if self.docs[i].timestamp >= doc.timestamp {
+                    continue;
+                }
+            }
+
+            doc.vector = remap(&doc.vector);
+            doc.biorthogonal = OnceLock::new(); // Rebuilt from the remapped vector on demand
+            let history: Vec<PrimeVector> = doc.historical_vectors.iter().map(|dense| remap_dense(dense)).collect();
+            doc.historical_vectors = Vec::new();
+            histories.push((doc.path.clone(), history));
+
+            match self.path_index.get(&doc.path) {
+                Some(&i) => self.docs[i] = doc,
+                None => {
+                    self.path_index.insert(doc.path.clone(), self.docs.len());
+                    self.docs.push(doc);
+                }
+            }
+            merged += 1;
+        }
+        if merged > 0 {
+            self.relationships_stale = true;
+            // Merged vectors were remapped onto this engine's primes
+            self.reindex_positions();
+            let needed = self.docs.iter().map(|doc| dense_length(&doc.vector)).max().unwrap_or(0);
+            self.fit_dense_dimension(needed);
+            for (path, history) in histories.into_iter().filter(|(_, history)| !history.is_empty()) {
+                let doc = &mut self.docs[self.path_index[&path]];
+                doc.historical_vectors = history.iter().map(|vector| to_dense_vector(vector, self.dense_dimension)).collect();
+                doc.buffering = buffering_capacity(&to_dense_vector(&doc.vector, self.dense_dimension));
+            }
+        }
+
+        merged
+    }
+
+    /// Point an indexed document at a new path, keeping its learned state
+    /// (reversibility, historical vectors, timestamp). Titles that were just
+    /// the old file name follow the rename. Returns false if `from` is not indexed.
+    pub fn rename_document(&mut self, from: &Path, to: &Path) -> bool {
+        let Some(i) = self.path_index.remove(from) else {
+            return false;
+        };
+        self.path_index.insert(to.to_path_buf(), i);
+        let doc = &mut self.docs[i];
+        
+        let old_name = from.file_name().and_then(|n| n.to_str());
+        if old_name == Some(doc.title.as_str()) {
+            if let Some(new_name) = to.file_name().and_then(|n| n.to_str()) {
+                doc.title = new_name.to_string();
+            }
+        }
+        doc.path = to.to_path_buf();
+        true
+    }
+
+    /// Mark a document as relevant (or not) to the user. Each call scales its
+    /// combined score by a fixed step, within bounds. Returns false if `path`
+    /// is not indexed.
+    pub fn record_feedback(&mut self, path: &Path, relevant: bool) -> bool {
+        let Some(&i) = self.path_index.get(path) else {
+            return false;
+        };
+        let doc = &mut self.docs[i];
+        let step = if relevant { FEEDBACK_STEP } else { 1.0 / FEEDBACK_STEP };
+        doc.relevance = (doc.relevance * step).clamp(MIN_RELEVANCE, MAX_RELEVANCE);
+        true
+    }
+
+    /// The relevance multiplier of an indexed document
+    pub fn relevance(&self, path: &Path) -> Option<f64> {
+        self.path_index.get(path).map(|&i| self.docs[i].relevance)
+    }
+
+    /// Keep learned state (relevance feedback, reversibility, vector history)
+    /// across `clear_documents`, so it is reapplied when a path is indexed again.
+    /// On by default; turning it off also forgets state waiting to be reapplied.
+    pub fn set_keep_learned_state(&mut self, keep: bool) {
+        self.keep_learned_state = keep;
+        if !keep {
+            self.learned.clear();
+        }
+    }
+
+    /// Drop all documents ahead of a full reindex. Their learned state is held
+    /// back by path and restored as the same paths are added again.
+    pub fn clear_documents(&mut self) {
+        if self.keep_learned_state {
+            let learned: Vec<_> = self.docs.iter()
+                .filter(|doc| doc.has_learned_state())
+                .map(|doc| (doc.path.clone(), doc.learned_state(&self.tokenizer)))
+                .collect();
+            self.learned.extend(learned);
+        }
+        self.docs.clear();
+        self.removed_count = 0;
+        self.path_index.clear();
+        self.postings.clear();
+        self.cooccurrences.clear();
+        // Persistence scoring resumes unless the reindexed documents overflow again
+        self.sparse_only = false;
+        self.relationships_stale = true;
+    }
+
+    /// Learned state per path: that of indexed documents which have learned
+    /// anything, plus any still waiting for its path to be indexed again
+    pub fn learned_state(&self) -> HashMap<PathBuf, LearnedState> {
+        let mut learned = self.learned.clone();
+        for doc in self.docs.iter().filter(|doc| doc.has_learned_state()) {
+            learned.insert(doc.path.clone(), doc.learned_state(&self.tokenizer));
+        }
+        learned
+    }
+
+    /// Save learned state separately from the content index, so it can be
+    /// reapplied with `load_learned_state` after the content is re-extracted
+    pub fn save_learned_state(&self, path: &str) -> io::Result<()> {
+        let serialized = bincode::serialize(&self.learned_state())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, serialized)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Load learned state saved by `save_learned_state`. It is applied right
+    /// away to documents already indexed and kept for paths indexed later.
+    /// Returns the number of paths loaded.
+    pub fn load_learned_state(&mut self, path: &str) -> io::Result<usize> {
+        let serialized = fs::read(path)?;
+        let learned: HashMap<PathBuf, LearnedState> = bincode::deserialize(&serialized)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let count = learned.len();
+        
+        for (path, state) in learned {
+            match self.path_index.get(&path) {
+                Some(&i) => self.docs[i].restore_learned_state(state, self.dense_dimension, &self.tokenizer),
+                None => {
+                    self.learned.insert(path, state);
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Enable or disable quantum scoring (off by default). Each document
+    /// then keeps a biorthogonal form of its vector, built on first use, and
+    /// every candidate is scored against it, roughly doubling per-result cost.
+    pub fn set_use_quantum_score(&mut self, enable: bool) {
+        self.use_quantum_score = enable;
+    }
+
+    /// Enable or disable persistence scoring (off by default). Persistence
+    /// needs every document's reversibility against all the others, which is
+    /// recomputed before the next search whenever documents change: quadratic
+    /// in the index size, and the first search after a bulk index pays for it
+    /// unless `warm_up` is called. Documents also keep dense vectors and a
+    /// vector history while it's on.
+    pub fn set_use_persistence_score(&mut self, enable: bool) {
+        if enable && !self.use_persistence_score {
+            // Reversibility isn't maintained while persistence scoring is off
+            self.relationships_stale = true;
+        }
+        self.use_persistence_score = enable;
+    }
+
+    pub fn uses_quantum_score(&self) -> bool {
+        self.use_quantum_score
+    }
+
+    pub fn uses_persistence_score(&self) -> bool {
+        self.use_persistence_score
+    }
+
+    // Persistence scoring is on and hasn't been suspended by `DenseOverflow`
+    fn persistence_active(&self) -> bool {
+        self.use_persistence_score && !self.sparse_only
+    }
+
+    /// Choose what happens once a document's primes no longer fit the dense
+    /// vectors behind persistence scoring, which start at 1000 components.
+    /// Documents already indexed are fitted under the new policy.
+    pub fn set_dense_overflow(&mut self, overflow: DenseOverflow) {
+        self.dense_overflow = overflow;
+        self.sparse_only = false;
+        self.relationships_stale = true;
+        let needed = self.docs.iter().map(|doc| dense_length(&doc.vector)).max().unwrap_or(0);
+        self.fit_dense_dimension(needed);
+    }
+
+    pub fn dense_overflow(&self) -> DenseOverflow {
+        self.dense_overflow
+    }
+
+    /// Current length of the dense vectors behind persistence scoring
+    pub fn dense_dimension(&self) -> usize {
+        self.dense_dimension
+    }
+
+    /// Whether persistence scoring is suspended because the documents
+    /// outgrew the dense vectors. It lasts until `set_dense_overflow` or
+    /// `clear_documents`.
+    pub fn is_sparse_only(&self) -> bool {
+        self.sparse_only
+    }
+
+    // Make the dense vectors at least `needed` components long, or switch to
+    // sparse-only scoring if `dense_overflow` doesn't allow it
+    fn fit_dense_dimension(&mut self, needed: usize) {
+        if needed <= self.dense_dimension || self.sparse_only {
+            return;
+        }
+        match self.dense_overflow {
+            DenseOverflow::Grow { max_dimension } if needed <= max_dimension => {
+                let dimension = needed.next_power_of_two().min(max_dimension);
+                diag!("⚠️  Vocabulary outgrew {} dense dimensions; widening persistence vectors to {}",
+                    self.dense_dimension, dimension);
+                self.dense_dimension = dimension;
+                for doc in self.docs.iter_mut().filter(|doc| !doc.historical_vectors.is_empty()) {
+                    for dense in &mut doc.historical_vectors {
+                        dense.resize(dimension, 0.0);
+                    }
+                    doc.buffering = buffering_capacity(&to_dense_vector(&doc.vector, dimension));
+                }
+                self.relationships_stale = true;
+            }
+            _ => {
+                diag!("⚠️  Vocabulary needs {} dense dimensions, more than the {} allowed; \
+                    persistence scoring is suspended and results rank on sparse vectors only",
+                    needed, self.dense_dimension);
+                self.sparse_only = true;
+            }
+        }
+    }
+
+    /// Set the relative weights of the score components. The resonance weight
+    /// must be positive so the combined score never loses its base.
+    pub fn set_score_weights(&mut self, weights: ScoreWeights) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("resonance weight", weights.resonance, weights.resonance > 0.0, "a finite value > 0")?;
+        InvalidParameter::check("quantum weight", weights.quantum, weights.quantum >= 0.0, "a finite value >= 0")?;
+        InvalidParameter::check("persistence weight", weights.persistence, weights.persistence >= 0.0, "a finite value >= 0")?;
+        self.score_weights = weights;
+        Ok(())
+    }
+
+    /// Learn the score weights from relevance judgments. Every judged query is
+    /// scored once (without reranking or result dedup), then each weighting on
+    /// a grid of `step` over the enabled components is tried and the one with
+    /// the best mean `metric` is set. The current weights stay unless some
+    /// weighting does strictly better. Relevant paths that aren't indexed
+    /// count as missed. The grid grows with the square of `1 / step`, so
+    /// `step` must be at least 0.01.
+    pub fn optimize_weights(&mut self, judgments: &[Judgment], metric: RankingMetric, step: f64) -> Result<WeightFit, InvalidParameter> {
+        InvalidParameter::check("step", step, (0.01..=0.5).contains(&step), "a value in [0.01, 0.5]")?;
+
+        // Per judgment: each candidate's components, relevance multiplier and
+        // whether it is judged relevant
+        let reranker = self.reranker.take();
+        let dedup_threshold = self.dedup_threshold.take();
+        let max_per_directory = self.max_per_directory.take();
+        let mut scored = Vec::with_capacity(judgments.len());
+        for judgment in judgments {
+            let results = self.search_inner(&judgment.query, usize::MAX, None, SearchMode::ScoresOnly, None, SearchTrace::default()).0.into_results();
+            let candidates: Vec<([f64; 3], f64, bool)> = results.into_iter()
+                .map(|result| {
+                    let relevance = self.relevance(Path::new(&result.path)).unwrap_or(1.0);
+                    let components = [result.score, result.quantum_score, result.persistence_score];
+                    (components, relevance, judgment.relevant.contains(&result.path))
+                })
+                .collect();
+            scored.push((candidates, judgment.relevant.len()));
+        }
+        self.reranker = reranker;
+        self.dedup_threshold = dedup_threshold;
+        self.max_per_directory = max_per_directory;
+
+        let mean_metric = |weights: [f64; 3]| {
+            if scored.is_empty() {
+                return 0.0;
+            }
+            let total: f64 = scored.iter()
+                .map(|(candidates, relevant_total)| {
+                    let mut ranked: Vec<(f64, bool)> = candidates.iter()
+                        .map(|(components, relevance, relevant)| {
+                            // Unweighted components are left out, not multiplied by 0, in case they are infinite
+                            let combined: f64 = components.iter().zip(weights)
+                                .filter(|&(_, w)| w > 0.0)
+                                .map(|(c, w)| c * w)
+                                .sum();
+                            (relevance * combined, *relevant)
+                        })
+                        .collect();
+                    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+                    let ranking: Vec<bool> = ranked.into_iter().map(|(_, relevant)| relevant).collect();
+                    metric.measure(&ranking, *relevant_total)
+                })
+                .sum();
+            total / scored.len() as f64
+        };
+
+        let current = self.effective_score_weights();
+        let baseline = mean_metric([current.resonance, current.quantum, current.persistence]);
+        let mut best: Option<([f64; 3], f64)> = None;
+        let steps = (1.0 / step).round() as usize;
+        for r in 1..=steps {
+            for q in 0..=(steps - r) {
+                let p = steps - r - q;
+                if (q > 0 && !self.use_quantum_score) || (p > 0 && !self.persistence_active()) {
+                    continue;
+                }
+                let weights = [r, q, p].map(|n| n as f64 / steps as f64);
+                let value = mean_metric(weights);
+                if value > best.map_or(baseline, |(_, best)| best) {
+                    best = Some((weights, value));
+                }
+            }
+        }
+
+        let Some(([resonance, quantum, persistence], value)) = best else {
+            return Ok(WeightFit { weights: self.score_weights, metric: baseline, baseline });
+        };
+        self.score_weights = ScoreWeights { resonance, quantum, persistence };
+        Ok(WeightFit { weights: self.score_weights, metric: value, baseline })
+    }
+
+    /// Set how many characters of document text go into each result's snippet.
+    /// Snippets are always a single line of printable text.
+    pub fn set_snippet_length(&mut self, chars: usize) {
+        self.snippet_length = chars;
+    }
+
+    /// With field snippets on (the default), a result's snippet shows the
+    /// first query word found in its content with the text around it, leaving
+    /// out the name and path a file's text also carries, and `term_matches`
+    /// says whether each query word was found in the title, the path or the
+    /// content. A file matched by name and content then shows the content
+    /// match, while the name match is noted. Off, snippets are the start of
+    /// the text and `term_matches` stays empty.
+    pub fn set_field_snippets(&mut self, enabled: bool) {
+        self.field_snippets = enabled;
+    }
+
+    /// The weights as set with `set_score_weights`
+    pub fn score_weights(&self) -> ScoreWeights {
+        self.score_weights
+    }
+
+    /// Choose how query and document vectors are compared for the resonance score
+    pub fn set_similarity_metric(&mut self, metric: SimilarityMetric) {
+        self.similarity_metric = metric;
+    }
+
+    pub fn similarity_metric(&self) -> SimilarityMetric {
+        self.similarity_metric
+    }
+
+    /// Choose how the score components are combined into `combined_score`
+    pub fn set_score_combination(&mut self, combination: ScoreCombination) {
+        self.score_combination = combination;
+    }
+
+    pub fn score_combination(&self) -> ScoreCombination {
+        self.score_combination
+    }
+
+    /// The weights the combined score actually uses: disabled components get
+    /// 0 and the enabled ones are rescaled to sum to 1
+    pub fn effective_score_weights(&self) -> ScoreWeights {
+        let quantum = if self.use_quantum_score { self.score_weights.quantum } else { 0.0 };
+        let persistence = if self.persistence_active() { self.score_weights.persistence } else { 0.0 };
+        let total = self.score_weights.resonance + quantum + persistence;
+        ScoreWeights {
+            resonance: self.score_weights.resonance / total,
+            quantum: quantum / total,
+            persistence: persistence / total,
+        }
+    }
+
+    /// Measure the entropy of texts with fewer than `below_tokens` tokens over
+    /// their character `n`-grams (n of at least 1) instead of their words.
+    /// Word entropy is 0 for a one-word file name and barely varies over a few
+    /// words, so `delta_entropy` says nothing about such documents; n-grams
+    /// give short texts a usable value. Each document is compared with the
+    /// query's entropy measured the same way as its own, whatever the query's
+    /// length. 0 (the default) always uses words. Stored entropies aren't
+    /// recomputed, so set this before indexing.
+    pub fn set_ngram_entropy(&mut self, below_tokens: usize, n: usize) {
+        self.ngram_entropy_below = below_tokens;
+        self.ngram_size = n.max(1);
+    }
+
+    /// Expand queries with up to `n_terms` terms that frequently co-occur with
+    /// the query terms. Pass 0 to disable. Co-occurrence statistics are only
+    /// gathered while expansion is enabled, so set this before indexing.
+    pub fn set_query_expansion(&mut self, n_terms: usize) {
+        self.query_expansion_terms = n_terms;
+    }
+
+    /// Only score documents sharing at least `min` distinct terms with the
+    /// (expanded) query. Candidates come from the inverted index, so searches
+    /// no longer touch every document. Pass 0 to score every document.
+    pub fn set_min_term_overlap(&mut self, min: usize) {
+        self.min_term_overlap = min;
+    }
+
+    /// Weight each query term by its inverse document frequency,
+    /// ln((1 + N) / (1 + df)) + 1, so rare terms count for more than ones
+    /// most documents share. Document frequencies are the lengths of the
+    /// inverted index's posting lists, which adds and removes keep exact, and
+    /// only the query is weighted, so nothing is recomputed as the index
+    /// changes and each search sees the current counts. Off by default.
+    pub fn set_idf_weighting(&mut self, enable: bool) {
+        self.idf_weighting = enable;
+    }
+
+    /// Number of indexed documents containing `term` (or, with stemming on,
+    /// any word with the same stem)
+    pub fn document_frequency(&self, term: &str) -> usize {
+        self.tokenizer.get_prime(&self.tokenizer.term(&term.to_lowercase()))
+            .and_then(|prime| self.postings.get(&prime))
+            .map_or(0, Vec::len)
+    }
+
+    // Scale the query's terms by their current IDF, see `set_idf_weighting`
+    fn apply_idf(&self, mut query_vec: PrimeVector) -> PrimeVector {
+        let documents = self.len() as f64;
+        for (prime, weight) in query_vec.iter_mut() {
+            let frequency = self.postings.get(prime).map_or(0, Vec::len) as f64;
+            *weight *= ((1.0 + documents) / (1.0 + frequency)).ln() + 1.0;
+        }
+        normalize(&mut query_vec);
+        query_vec
+    }
+
+    /// Collapse near-identical results, such as a file and its crawled mirror:
+    /// a result whose document vector has at least `threshold` cosine
+    /// similarity with a better-ranked result is dropped and its path listed
+    /// in that result's `alternates`, so duplicates don't use up the top K.
+    /// `None` (the default) keeps every result.
+    pub fn set_result_dedup(&mut self, threshold: Option<f64>) -> Result<(), InvalidParameter> {
+        if let Some(threshold) = threshold {
+            InvalidParameter::check("dedup_threshold", threshold, (0.0..=1.0).contains(&threshold), "a similarity in [0, 1]")?;
+        }
+        self.dedup_threshold = threshold;
+        Ok(())
+    }
+
+    /// Keep at most `max` results from any one directory (a file's folder, a
+    /// web page's parent URL), so a single project can't fill the top K on
+    /// its own. Results over the cap give way to the next best from
+    /// elsewhere; the order of those kept is unchanged. `None` (the default)
+    /// doesn't limit directories; a cap of 0 counts as 1.
+    pub fn set_max_per_directory(&mut self, max: Option<usize>) {
+        self.max_per_directory = max.map(|max| max.max(1));
+    }
+
+    /// Have `search_filesystem` return up to `page_size` results (default 10),
+    /// ranking `candidate_multiplier` times as many (default 5) before its
+    /// filters run. When the filters leave fewer than a page it ranks deeper
+    /// rather than coming up short, so the multiplier only sets how much is
+    /// ranked up front. Values of 0 count as 1.
+    pub fn set_filesystem_page(&mut self, page_size: usize, candidate_multiplier: usize) {
+        self.filesystem_page_size = page_size.max(1);
+        self.filesystem_candidate_multiplier = candidate_multiplier.max(1);
+    }
+
+    /// Results per `search_filesystem` page and candidates ranked per result,
+    /// see `set_filesystem_page`
+    pub fn filesystem_page(&self) -> (usize, usize) {
+        (self.filesystem_page_size, self.filesystem_candidate_multiplier)
+    }
+
+    /// Set how many following tokens count as co-occurring with a token
+    pub fn set_cooccurrence_window(&mut self, window: usize) {
+        self.cooccurrence_window = window.max(1);
+    }
+
+    /// Make queries for `term` also match documents using any of `equivalents`.
+    /// Both are stored as the terms they tokenize to, so set stemming first.
+    pub fn add_synonyms(&mut self, term: &str, equivalents: &[&str]) {
+        let term = self.tokenizer.term(&term.trim().to_lowercase());
+        // Multi-word equivalents contribute each of their words
+        let equivalents: Vec<String> = equivalents.iter()
+            .flat_map(|equivalent| equivalent.split_whitespace())
+            .map(|word| self.tokenizer.term(&word.to_lowercase()))
+            .collect();
+        let words = self.synonyms.entry(term).or_default();
+        for word in equivalents {
+            if !words.contains(&word) {
+                words.push(word);
+            }
+        }
+    }
+
+    /// Load a synonym dictionary with one entry per line, `term = equivalent, equivalent`.
+    /// Blank lines and lines starting with `#` are ignored. Returns the number of entries loaded.
+    pub fn load_synonyms(&mut self, path: &str) -> io::Result<usize> {
+        let content = fs::read_to_string(path)?;
+        let mut loaded = 0;
+
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((term, equivalents)) = line.split_once('=') else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid synonym line {}: {}", line_number + 1, line)
+                ));
+            };
+            let equivalents: Vec<&str> = equivalents.split(',')
+                .map(str::trim)
+                .filter(|e| !e.is_empty())
+                .collect();
+            self.add_synonyms(term, &equivalents);
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    // Method to set the weight of synonym terms relative to the query term (finite, >= 0)
+    pub fn set_synonym_weight(&mut self, weight: f64) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("synonym_weight", weight, weight >= 0.0, "a finite value >= 0")?;
+        self.synonym_weight = weight;
+        Ok(())
+    }
+
+    /// Add the dictionary synonyms of each query term with reduced weight
+    fn expand_synonyms(&self, query_vec: PrimeVector) -> PrimeVector {
+        let mut expanded = query_vec.clone();
+
+        for (&term, &weight) in &query_vec {
+            let Some(equivalents) = self.tokenizer.get_token(term).and_then(|t| self.synonyms.get(&t)) else {
+                continue;
+            };
+            for word in equivalents {
+                // A word never seen while indexing can't match any document
+                let Some(prime) = self.tokenizer.get_prime(word) else {
+                    continue;
+                };
+                let entry = expanded.entry(prime).or_insert(0.0);
+                *entry = entry.max(weight * self.synonym_weight);
+            }
+        }
+
+        normalize(&mut expanded);
+        expanded
+    }
+
+    /// Record term pairs that appear within the co-occurrence window
+    fn record_cooccurrences(&mut self, tokens: &[u64]) {
+        if self.query_expansion_terms == 0 {
+            return;
+        }
+
+        for (i, &term) in tokens.iter().enumerate() {
+            let end = (i + 1 + self.cooccurrence_window).min(tokens.len());
+            for &other in &tokens[i + 1..end] {
+                if other == term {
+                    continue;
+                }
+                *self.cooccurrences.entry(term).or_default().entry(other).or_insert(0) += 1;
+                *self.cooccurrences.entry(other).or_default().entry(term).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Add the strongest co-occurring terms to a query vector with reduced weight
+    fn expand_query_vector(&self, query_vec: PrimeVector) -> PrimeVector {
+        let mut candidates: HashMap<u64, f64> = HashMap::new();
+
+        for (&term, &weight) in &query_vec {
+            let Some(neighbours) = self.cooccurrences.get(&term) else {
+                continue;
+            };
+            let max_count = neighbours.values().copied().max().unwrap_or(0) as f64;
+            if max_count == 0.0 {
+                continue;
+            }
+            for (&other, &count) in neighbours {
+                if query_vec.contains_key(&other) {
+                    continue;
+                }
+                *candidates.entry(other).or_insert(0.0) += weight * (count as f64 / max_count);
+            }
+        }
+
+        if candidates.is_empty() {
+            return query_vec;
+        }
+
+        let mut ranked: Vec<(u64, f64)> = candidates.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut expanded = query_vec;
+        for (term, strength) in ranked.into_iter().take(self.query_expansion_terms) {
+            expanded.insert(term, strength * QUERY_EXPANSION_WEIGHT);
+        }
+
+        normalize(&mut expanded);
+        expanded
+    }
+
+    // Tokenize and vectorize a document without touching the index. Only needs
+    // `&self`, so batches can be prepared in parallel. The tokens come back too,
+    // for the co-occurrence statistics.
+    fn prepare_document(&self, title: String, text: String, path: PathBuf, source: DocumentSource, timestamp: u64) -> (IndexedDocument, Vec<u64>) {
+        let tokens = self.tokenizer.tokenize(&text);
+        let vec = build_vector(&tokens);
+        let ngram_entropy = tokens.len() < self.ngram_entropy_below;
+        let entropy = if ngram_entropy { char_ngram_entropy(&text, self.ngram_size) } else { shannon_entropy(&tokens) };
+        
+        // Quantum and persistence structures are only built for enabled features;
+        // they are filled in on first use if a feature is switched on later
+        let biorthogonal = self.use_quantum_score.then(|| biorthogonal_from_vector(&vec)).map(OnceLock::from).unwrap_or_default();
+        let (historical_vectors, buffering) = if self.persistence_active() {
+            persistence_state(&vec, self.dense_dimension)
+        } else {
+            (Vec::new(), 0.0)
+        };
+        
+        // Calculate persistence metrics
+        let reversibility = 1.0; // New document is fully reversible with itself
+        
+        let doc = IndexedDocument {
+            title,
+            text,
+            storage: TextStorage::Uncompressed,
+            decompressed: OnceLock::new(),
+            vector: vec,
+            biorthogonal,
+            entropy,
+            ngram_entropy,
+            path,
+            source,
+            timestamp,
+            reversibility,
+            buffering,
+            historical_vectors,
+            first_indexed: timestamp,
+            update_count: 0,
+            stale: false,
+            relevance: 1.0,
+            removed: false,
+            persistence: None,
+        };
+        (doc, tokens)
+    }
+
+    /// Adds a single local file document to the engine's index.
+    #[allow(dead_code)]
+    pub(crate) fn add_local_document(&mut self, title: String, text: String, path: PathBuf) {
+        let timestamp = self.now();
+        let (doc, tokens) = self.prepare_document(title, text, path, DocumentSource::Filesystem, timestamp);
+        self.record_cooccurrences(&tokens);
+        self.insert_document(doc);
+        self.note_document_added();
+    }
+
+    /// Adds a crawled web document to the engine's index.
+    pub fn add_crawled_document(&mut self, doc: CrawledDocument) {
+        let timestamp = self.now();
+        // Store the URL string in the path field
+        let (doc, tokens) = self.prepare_document(doc.title, doc.text, PathBuf::from(doc.url), DocumentSource::Web, timestamp);
+        if tokens.is_empty() {
+            return;
+        }
+        self.record_cooccurrences(&tokens);
+        self.insert_document(doc);
+        self.note_document_added();
+    }
+
+    /// Add crawled web documents in bulk. They are tokenized and vectorized in
+    /// parallel (see `set_indexing_threads`), sharing one vocabulary, then
+    /// indexed in order: the result matches adding them one at a time, apart
+    /// from which prime each new word is given.
+    pub fn add_crawled_documents(&mut self, docs: Vec<CrawledDocument>) {
+        let timestamp = self.now();
+        let engine = &*self;
+        let prepared = on_threads(self.indexing_threads, "indexing", || {
+            docs.into_par_iter()
+                .map(|doc| engine.prepare_document(doc.title, doc.text, PathBuf::from(doc.url), DocumentSource::Web, timestamp))
+                .collect::<Vec<_>>()
+        });
+        
+        for (doc, tokens) in prepared {
+            if tokens.is_empty() {
+                continue;
+            }
+            self.record_cooccurrences(&tokens);
+            self.insert_document(doc);
+            self.note_document_added();
+        }
+    }
+
+    /// Add a document, or replace the one already indexed under the same path.
+    /// A replacement counts as an update: it keeps the original indexing time,
+    /// learned reversibility and vector history of the document it replaces.
+    fn insert_document(&mut self, mut doc: IndexedDocument) {
+        self.relationships_stale = true;
+        let needed = dense_length(&doc.vector);
+        match self.path_index.get(&doc.path) {
+            Some(&i) => {
+                let existing = &mut self.docs[i];
+                if doc.historical_vectors.is_empty() && !existing.historical_vectors.is_empty() {
+                    // Keep the merged history consistent with this document's buffering
+                    doc.ensure_persistence_state(self.dense_dimension);
+                }
+                doc.first_indexed = existing.first_indexed;
+                doc.update_count = existing.update_count.saturating_add(1);
+                doc.reversibility = existing.reversibility;
+                
+                // Only keep a reasonable number of historical vectors (e.g., up to 5)
+                let mut history = std::mem::take(&mut existing.historical_vectors);
+                history.append(&mut doc.historical_vectors);
+                let excess = history.len().saturating_sub(5);
+                history.drain(..excess);
+                doc.historical_vectors = history;
+                
+                for prime in existing.vector.keys() {
+                    if !doc.vector.contains_key(prime) {
+                        remove_posting(&mut self.postings, *prime, i);
+                    }
+                }
+                for &prime in doc.vector.keys() {
+                    if !existing.vector.contains_key(&prime) {
+                        let postings = self.postings.entry(prime).or_default();
+                        // Keep the list in index order
+                        let at = postings.partition_point(|&j| j < i);
+                        postings.insert(at, i);
+                    }
+                }
+                self.docs[i] = doc;
+            }
+            None => {
+                if let Some(state) = self.learned.remove(&doc.path) {
+                    doc.restore_learned_state(state, self.dense_dimension, &self.tokenizer);
+                }
+                let i = self.docs.len();
+                for &prime in doc.vector.keys() {
+                    self.postings.entry(prime).or_default().push(i);
+                }
+                self.path_index.insert(doc.path.clone(), i);
+                self.docs.push(doc);
+            }
+        }
+        self.fit_dense_dimension(needed);
+    }
+
+    /// Remove the document indexed under `path`, e.g. for a deleted file.
+    /// It leaves a tombstone so no other document changes place; the space is
+    /// reclaimed by `compact`, which runs automatically once tombstones pass
+    /// the ratio set with `set_auto_compact_ratio`. Returns false if `path`
+    /// is not indexed.
+    pub fn remove_document(&mut self, path: &Path) -> bool {
+        let Some(i) = self.path_index.remove(path) else {
+            return false;
+        };
+        let doc = &mut self.docs[i];
+        for prime in doc.vector.keys() {
+            remove_posting(&mut self.postings, *prime, i);
+        }
+        // Free the bulk of the document now rather than at compaction
+        doc.removed = true;
+        doc.stale = false;
+        doc.text = String::new();
+        doc.storage = TextStorage::Uncompressed;
+        doc.decompressed = OnceLock::new();
+        doc.vector = PrimeVector::new();
+        doc.biorthogonal = OnceLock::new();
+        doc.historical_vectors = Vec::new();
+        self.removed_count += 1;
+        self.relationships_stale = true;
+
+        if self.auto_compact_ratio.is_some_and(|ratio| self.removed_count as f64 > ratio * self.docs.len() as f64) {
+            self.compact();
+        }
+        true
+    }
+
+    /// Drop the tombstones left by `remove_document`, moving later documents
+    /// down and rebuilding the path index and posting lists to match.
+    /// Returns the number of tombstones reclaimed.
+    pub fn compact(&mut self) -> usize {
+        let reclaimed = self.removed_count;
+        if reclaimed == 0 {
+            return 0;
+        }
+        self.docs.retain(|doc| !doc.removed);
+        self.removed_count = 0;
+        self.reindex_positions();
+        reclaimed
+    }
+
+    /// Compact automatically once tombstones make up more than `ratio` of the
+    /// stored documents (0.0 compacts on every removal). None leaves it to `compact`.
+    pub fn set_auto_compact_ratio(&mut self, ratio: Option<f64>) {
+        self.auto_compact_ratio = ratio.map(|ratio| ratio.max(0.0));
+    }
+
+    /// Keep each document's query-independent persistence (entropy pressure
+    /// from its age and updates, reversibility, buffering) between searches,
+    /// recomputing it once the document's age moves into the next bucket of
+    /// this width or its inputs change; searches then only apply the query's
+    /// entropy factor. Scores drift by at most one bucket's worth of aging.
+    /// Off (None) by default, computing it afresh for every search.
+    pub fn set_persistence_cache_bucket(&mut self, bucket: Option<Duration>) {
+        self.persistence_cache_bucket = bucket;
+        if bucket.is_none() {
+            for doc in &mut self.docs {
+                doc.persistence = None;
+            }
+        }
+    }
+
+    /// Paths of the indexed documents containing `term` (or, with stemming
+    /// on, any word with the same stem)
+    pub fn documents_with_term(&self, term: &str) -> Vec<&Path> {
+        let Some(prime) = self.tokenizer.get_prime(&self.tokenizer.term(&term.to_lowercase())) else {
+            return Vec::new();
+        };
+        self.postings.get(&prime)
+            .map(|postings| postings.iter().map(|&i| self.docs[i].path.as_path()).collect())
+            .unwrap_or_default()
+    }
+
+    // Rebuild the path index and posting lists after documents changed places
+    fn reindex_positions(&mut self) {
+        self.path_index = self.docs.iter()
+            .enumerate()
+            .filter(|(_, doc)| !doc.removed)
+            .map(|(i, doc)| (doc.path.clone(), i))
+            .collect();
+        self.postings.clear();
+        for (i, doc) in self.docs.iter().enumerate() {
+            for &prime in doc.vector.keys() {
+                self.postings.entry(prime).or_default().push(i);
+            }
+        }
+    }
+
+    /// Indices, in index order, of the documents sharing at least
+    /// `min_term_overlap` distinct primes with `query_vec`
+    fn candidate_documents(&self, query_vec: &PrimeVector) -> Vec<usize> {
+        if self.min_term_overlap == 0 {
+            return (0..self.docs.len()).filter(|&i| !self.docs[i].removed).collect();
+        }
+        let mut overlap: HashMap<usize, usize> = HashMap::new();
+        for prime in query_vec.keys() {
+            let Some(postings) = self.postings.get(prime) else { continue };
+            for &i in postings {
+                *overlap.entry(i).or_insert(0) += 1;
+            }
+        }
+        let mut candidates: Vec<usize> = overlap.into_iter()
+            .filter(|&(_, count)| count >= self.min_term_overlap)
+            .map(|(i, _)| i)
+            .collect();
+        candidates.sort_unstable();
+        candidates
+    }
+
+    /// Loads and indexes supported files from a directory and its subdirectories recursively.
+    #[allow(dead_code)]
+    pub fn load_directory<P: AsRef<Path>>(&mut self, folder: P) -> io::Result<()> {
+        let path = folder.as_ref();
+        if !path.is_dir() {
+             return Err(io::Error::new(io::ErrorKind::NotFound, format!("'{}' is not a directory", path.display())));
+        }
+        self.process_directory_recursive(path)
+    }
+
+    /// Recursive helper function to process directories and files.
+    #[allow(dead_code)]
+    fn process_directory_recursive<P: AsRef<Path>>(&mut self, current_dir: P) -> io::Result<()> {
+        for entry in fs::read_dir(current_dir)? {
+            let entry = entry?;
+            let file_path = entry.path();
+
+            if file_path.is_file() {
+                let extension = file_path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+                 let title = file_path.file_stem()
+                                .and_then(|s| s.to_str())
+                                .unwrap_or_else(|| file_path.file_name().and_then(|s| s.to_str()).unwrap_or("unknown file"))
+                                .to_string();
+
+                let text_content = match extension.as_str() {
+                    "txt" => {
+                        match fs::read(&file_path) {
+                            Ok(bytes) => Some(decode_text(&bytes, None, true)),
+                            Err(e) => {
+                                eprintln!("Error reading {}: {}", file_path.display(), e);
+                                None
+                            }
+                        }
+                    }
+                    "html" => {
+                        match fs::read(&file_path) {
+                            Ok(bytes) => {
+                                let html_string = decode_text(&bytes, None, true);
+                                let fragment = Html::parse_document(&html_string);
+                                let text = fragment.root_element().text().collect::<String>();
+                                Some(text)
+                            }
+                            Err(e) => {
+                                eprintln!("Error reading {}: {}", file_path.display(), e);
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+
+                if let Some(text) = text_content {
+                    if !text.trim().is_empty() {
+                         self.add_local_document(title, text, file_path);
+                    } else {
+                        diag!("Skipping empty local document after text extraction: {}", file_path.display());
+                    }
+                }
+            } else if file_path.is_dir() {
+                if let Err(e) = self.process_directory_recursive(&file_path) {
+                    eprintln!("Error traversing directory {}: {}", file_path.display(), e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Set how many documents `warm_up` decompresses ahead of time (100 by default)
+    pub fn set_warm_up_documents(&mut self, count: usize) {
+        self.warm_up_documents = count;
+    }
+
+    /// Do the work the first search would otherwise pay for: refresh reversibility
+    /// (quadratic in the index size), build the structures the enabled scores need,
+    /// and decompress the text of the documents most likely to be returned, going
+    /// by relevance feedback and then recency. Call after loading or bulk indexing.
+    pub fn warm_up(&mut self) {
+        let start = Instant::now();
+        self.update_document_relationships();
+        self.refresh_persistence_cache();
+        if self.use_quantum_score {
+            for doc in &self.docs {
+                doc.biorthogonal();
+            }
+        }
+        
+        let mut hot: Vec<usize> = (0..self.docs.len()).filter(|&i| !self.docs[i].removed).collect();
+        hot.sort_by(|&a, &b| {
+            let (a, b) = (&self.docs[a], &self.docs[b]);
+            b.relevance.partial_cmp(&a.relevance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.timestamp.cmp(&a.timestamp))
+        });
+        let mut decompressed = 0;
+        for i in hot.into_iter().take(self.warm_up_documents) {
+            let doc = &self.docs[i];
+            if doc.is_compressed() && doc.decompressed.get().is_none() {
+                doc.decompress_text();
+                decompressed += 1;
+            }
+        }
+        diag!("Warmed up {} documents ({} decompressed) in {:?}", self.len(), decompressed, start.elapsed());
+    }
+
+    /// Update document relationships and calculate reversibility
+    fn update_document_relationships(&mut self) {
+        // Reversibility only feeds persistence scoring, and only changes with the documents
+        if !self.persistence_active() || !self.relationships_stale {
+            return;
+        }
+        self.relationships_stale = false;
+        // Tombstones would count against every document's reversibility, and
+        // reclaiming them is cheap next to this quadratic refresh
+        self.compact();
+        let dimension = self.dense_dimension;
+        for doc in &mut self.docs {
+            doc.ensure_persistence_state(dimension);
+        }
+        
+        // Create a copy of all document vectors
+        let all_vectors: Vec<Vec<f64>> = self.docs.iter()
+            .map(|doc| {
+                to_dense_vector(&doc.vector, dimension) // Convert to same-sized dense vectors
+            })
+            .collect();
+        
+        // Update reversibility for each document
+        for (i, doc) in self.docs.iter_mut().enumerate() {
+            // Get all vectors except this document's vector
+            let others_vectors: Vec<Vec<f64>> = all_vectors.iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i) // Skip the current document
+                .map(|(_, vec)| vec.clone())
+                .collect();
+            
+            // Update reversibility and add to historical vectors
+            if !others_vectors.is_empty() {
+                let current_vec = &all_vectors[i];
+                doc.reversibility = calculate_reversibility(current_vec, &others_vectors);
+                
+                // Only keep a reasonable number of historical vectors (e.g., up to 5)
+                if doc.historical_vectors.len() < 5 {
+                    doc.historical_vectors.push(current_vec.clone());
+                }
+            }
+        }
+    }
+
+    /// Calculate quantum score for a document given a query
+    fn calculate_quantum_score(&self, query_vec: &PrimeVector, doc: &IndexedDocument) -> f64 {
+        // Calculate basic resonance using dot product
+        let _basic_resonance = dot_product(query_vec, &doc.vector);
+        
+        // Calculate complex resonance with decay
+        // Use doc age for decay factor - newer documents have less decay
+        let now = self.now();
+        let doc_age = (now.saturating_sub(doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
+        let decay_factor = 0.01 * doc_age.min(100.0); // Cap at 100 days
+        
+        let complex_res = resonance_complex(query_vec, &doc.vector, decay_factor);
+        
+        // For biorthogonal scoring
+        let query_bio = build_biorthogonal_vector(&self.tokenizer.tokenize_without_update(query_vec.keys().cloned().collect::<Vec<_>>().as_slice()));
+        let bio_score = match doc.biorthogonal.get() {
+            Some(biorthogonal) => biorthogonal_score(&query_bio, biorthogonal),
+            None => biorthogonal_score(&query_bio, &biorthogonal_from_vector(&doc.vector)),
+        };
+        
+        // Combine scores - weight the real part most heavily but consider phase
+        let quantum_score = complex_res.re * 0.6 + complex_res.im.abs() * 0.2 + bio_score * 0.2;
+        
+        quantum_score
+    }
+    
+    /// What the cached persistence of `doc` depends on at `now`, or None
+    /// while caching is off
+    fn persistence_inputs(&self, doc: &IndexedDocument, now: u64) -> Option<PersistenceInputs> {
+        let bucket = self.persistence_cache_bucket?.as_secs().max(1);
+        Some(PersistenceInputs {
+            age_bucket: now.saturating_sub(doc.timestamp) / bucket,
+            timestamp: doc.timestamp,
+            first_indexed: doc.first_indexed,
+            update_count: doc.update_count,
+            reversibility: doc.reversibility,
+            buffering: doc.buffering,
+            fragility: self.fragility,
+            trend_decay: self.trend_decay,
+            baseline_frequency: self.update_frequency,
+        })
+    }
+
+    /// Update frequency, entropy pressure and the persistence score they give
+    /// for `doc` at `now`, before the query's entropy factor
+    fn base_persistence(&self, doc: &IndexedDocument, now: u64) -> (f64, f64, f64) {
+        let doc_age = (now.saturating_sub(doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
+        let update_frequency = doc.update_frequency(now, self.update_frequency);
+        let pressure = entropy_pressure(doc_age, update_frequency, self.trend_decay);
+        
+        // Calculate persistence score using the thermodynamic model
+        let persistence = persistence_score(
+            doc.reversibility,
+            pressure,
+            doc.buffering,
+            self.fragility
+        );
+        (update_frequency, pressure, persistence)
+    }
+
+    /// `base_persistence`, from the cache while it still applies
+    fn document_persistence(&self, doc: &IndexedDocument, now: u64) -> (f64, f64, f64) {
+        if let (Some(cached), Some(inputs)) = (doc.persistence, self.persistence_inputs(doc, now)) {
+            if cached.inputs == inputs {
+                return (cached.update_frequency, cached.entropy_pressure, cached.persistence);
+            }
+        }
+        self.base_persistence(doc, now)
+    }
+
+    /// Recompute the cached persistence of documents whose inputs changed or
+    /// whose age moved into the next bucket since the last search. Searches
+    /// through `&self` (see `search_shared`) use what this left behind.
+    fn refresh_persistence_cache(&mut self) {
+        if !self.persistence_active() || self.persistence_cache_bucket.is_none() {
+            return;
+        }
+        let now = self.now();
+        for i in 0..self.docs.len() {
+            let doc = &self.docs[i];
+            let Some(inputs) = self.persistence_inputs(doc, now) else { return };
+            if doc.removed || doc.persistence.is_some_and(|cached| cached.inputs == inputs) {
+                continue;
+            }
+            let (update_frequency, entropy_pressure, persistence) = self.base_persistence(doc, now);
+            self.docs[i].persistence = Some(CachedPersistence { inputs, update_frequency, entropy_pressure, persistence });
+        }
+    }
+    
+    /// Calculate persistence score for a document
+    fn calculate_persistence_score(&self, query_entropy: f64, doc: &IndexedDocument) -> f64 {
+        let (_, _, persistence) = self.document_persistence(doc, self.now());
+        
+        // Adjust based on entropy delta with query
+        let entropy_delta = (doc.entropy - query_entropy).abs();
+        let entropy_factor = (-entropy_delta * self.entropy_weight).exp();
+        
+        persistence * entropy_factor
+    }
+
+    /// Performs a search query against the indexed documents.
+    /// Returns a vector of `SearchResult`s, sorted by score in descending order.
+    pub fn search(&mut self, query: &str, top_k: usize) -> Vec<SearchResult> {
+        self.search_outcome(query, top_k).into_results()
+    }
+
+    /// Like `search`, but reports why no results were returned.
+    pub fn search_outcome(&mut self, query: &str, top_k: usize) -> SearchOutcome {
+        self.search_filtered(query, top_k, None)
+    }
+
+    /// Like `search_outcome`, restricted to documents from `source` when given.
+    pub fn search_filtered(&mut self, query: &str, top_k: usize, source: Option<DocumentSource>) -> SearchOutcome {
+        self.search_inner(query, top_k, source, SearchMode::Snippets, None, SearchTrace::default()).0
+    }
+
+    /// Like `search_outcome`, but stops scoring documents once `timeout` has
+    /// elapsed and ranks whatever was scored by then, for interactive use on
+    /// very large indexes.
+    pub fn search_with_deadline(&mut self, query: &str, top_k: usize, timeout: Duration) -> TimedSearch {
+        let (outcome, truncated) = self.search_inner(query, top_k, None, SearchMode::Snippets, Some(Instant::now() + timeout), SearchTrace::default());
+        TimedSearch { outcome, truncated }
+    }
+
+    /// Like `search`, but results carry empty snippets. Skips snippet generation
+    /// and the text decompression it needs, for batch scoring where only the
+    /// scores and ranking matter.
+    pub fn search_scores_only(&mut self, query: &str, top_k: usize) -> Vec<SearchResult> {
+        self.search_inner(query, top_k, None, SearchMode::ScoresOnly, None, SearchTrace::default()).0.into_results()
+    }
+
+    /// Like `search_filtered`, but results carry empty snippets, as with
+    /// `search_scores_only`; `attach_snippets` fills in those that are kept
+    pub fn search_filtered_scores_only(&mut self, query: &str, top_k: usize, source: Option<DocumentSource>) -> SearchOutcome {
+        self.search_inner(query, top_k, source, SearchMode::ScoresOnly, None, SearchTrace::default()).0
+    }
+
+    /// Like `search`, but also returns a `QueryPlan` recording each stage the
+    /// query went through, for debugging why a document was or wasn't found.
+    pub fn search_explain(&mut self, query: &str, top_k: usize) -> (Vec<SearchResult>, QueryPlan) {
+        let mut plan = QueryPlan { query: query.to_string(), ..QueryPlan::default() };
+        let results = self.search_inner(query, top_k, None, SearchMode::Snippets, None, SearchTrace { plan: Some(&mut plan), ..SearchTrace::default() }).0.into_results();
+        (results, plan)
+    }
+
+    // Record a query stage: the terms in `after` that `before` lacks
+    fn plan_stage(&self, name: &'static str, before: &PrimeVector, after: &PrimeVector) -> PlanStage {
+        let mut added: Vec<String> = after.keys()
+            .filter(|prime| !before.contains_key(prime))
+            .map(|&prime| self.term_name(prime))
+            .collect();
+        added.sort();
+        PlanStage { name, added, terms: after.len() }
+    }
+
+    fn term_name(&self, prime: u64) -> String {
+        self.tokenizer.get_token(prime).unwrap_or_else(|| prime.to_string())
+    }
+
+    /// Group search results by how similar their documents are, merging the
+    /// closest groups (average linkage over the documents' prime vectors) until
+    /// `by` says to stop. Clusters come out in the order of their best-ranked
+    /// member; results whose document is no longer indexed stand alone.
+    /// `ClusterBy::Directory` groups by directory instead, keeping rank order.
+    pub fn cluster_results(&self, results: Vec<SearchResult>, by: ClusterBy) -> Vec<ResultCluster> {
+        if by == ClusterBy::Directory {
+            let mut clusters: Vec<ResultCluster> = Vec::new();
+            let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+            for result in results {
+                let position = *positions.entry(result_directory(&result)).or_insert_with(|| {
+                    clusters.push(ResultCluster { members: Vec::new(), representative: 0 });
+                    clusters.len() - 1
+                });
+                clusters[position].members.push(result);
+            }
+            return clusters;
+        }
+
+        let n = results.len();
+        let vectors: Vec<Option<&PrimeVector>> = results.iter()
+            .map(|result| self.path_index.get(Path::new(&result.path)).map(|&i| &self.docs[i].vector))
+            .collect();
+        
+        let mut similarity = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            similarity[i][i] = 1.0;
+            for j in (i + 1)..n {
+                if let (Some(a), Some(b)) = (vectors[i], vectors[j]) {
+                    let s = dot_product(a, b);
+                    similarity[i][j] = s;
+                    similarity[j][i] = s;
+                }
+            }
+        }
+        
+        // Groups stay ordered by their best-ranked member: a merge always
+        // folds the later group into the earlier one
+        let mut groups: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+        while groups.len() > 1 {
+            if let ClusterBy::Count(count) = by {
+                if groups.len() <= count.max(1) {
+                    break;
+                }
+            }
+            
+            let mut closest = (0, 1, f64::NEG_INFINITY);
+            for a in 0..groups.len() {
+                for b in (a + 1)..groups.len() {
+                    let link = average_linkage(&groups[a], &groups[b], &similarity);
+                    if link > closest.2 {
+                        closest = (a, b, link);
+                    }
+                }
+            }
+            
+            let (a, b, link) = closest;
+            if let ClusterBy::Threshold(threshold) = by {
+                if link < threshold {
+                    break;
+                }
+            }
+            let merged = groups.remove(b);
+            groups[a].extend(merged);
+        }
+        
+        let mut slots: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+        groups.into_iter()
+            .map(|mut group| {
+                group.sort_unstable();
+                // Most similar to the others overall; ties go to the better-ranked result
+                let mut representative = 0;
+                let mut best = f64::NEG_INFINITY;
+                for (position, &i) in group.iter().enumerate() {
+                    let total: f64 = group.iter().map(|&j| similarity[i][j]).sum();
+                    if total > best {
+                        best = total;
+                        representative = position;
+                    }
+                }
+                ResultCluster {
+                    members: group.iter().filter_map(|&i| slots[i].take()).collect(),
+                    representative,
+                }
+            })
+            .collect()
+    }
+
+    // Walk `ranked` best first, folding each result into the first kept result
+    // it is a near-duplicate of and skipping those from directories that are
+    // already full, until `top_k` results are kept
+    fn diversify(&self, ranked: Vec<SearchResult>, top_k: usize) -> Vec<SearchResult> {
+        let mut kept: Vec<(SearchResult, Option<&PrimeVector>)> = Vec::new();
+        let mut per_directory: HashMap<PathBuf, usize> = HashMap::new();
+        for result in ranked {
+            let vector = self.path_index.get(Path::new(&result.path)).map(|&i| &self.docs[i].vector);
+            let original = self.dedup_threshold.zip(vector).and_then(|(threshold, vector)| {
+                kept.iter().position(|(_, other)| other.is_some_and(|other| dot_product(vector, other) >= threshold))
+            });
+            if let Some(position) = original {
+                kept[position].0.alternates.push(result.path);
+                continue;
+            }
+            if kept.len() == top_k {
+                break;
+            }
+            if let Some(max) = self.max_per_directory {
+                let count = per_directory.entry(result_directory(&result)).or_insert(0);
+                if *count == max {
+                    continue;
+                }
+                *count += 1;
+            }
+            kept.push((result, vector));
+        }
+        kept.into_iter().map(|(result, _)| result).collect()
+    }
+
+    /// Like `search_outcome`, but through a shared reference, so any number of
+    /// threads can search an engine behind an `RwLock` read lock at once.
+    /// Reversibility isn't refreshed first; see "Concurrency" on `ResonantEngine`.
+    pub fn search_shared(&self, query: &str, top_k: usize) -> SearchOutcome {
+        self.rank_documents(query, top_k, None, SearchMode::Snippets, None, SearchTrace::default()).0
+    }
+
+    /// Like `search_shared` without snippets, and without writing anything:
+    /// scoring only reads the caches earlier searches or `warm_up` left,
+    /// computing what's missing on the spot. The index is left exactly as it
+    /// was, so results don't depend on what other searches ran.
+    pub fn search_readonly(&self, query: &str, top_k: usize) -> Vec<SearchResultNoSnippet> {
+        self.rank_documents(query, top_k, None, SearchMode::ReadOnly, None, SearchTrace::default()).0
+            .into_results()
+            .into_iter()
+            .map(SearchResultNoSnippet::from)
+            .collect()
+    }
+
+    // Returns the outcome and whether `deadline` cut the scan short
+    fn search_inner(
+        &mut self,
+        query: &str,
+        top_k: usize,
+        source: Option<DocumentSource>,
+        mode: SearchMode,
+        deadline: Option<Instant>,
+        trace: SearchTrace,
+    ) -> (SearchOutcome, bool) {
+        // Update document relationships to ensure reversibility is current.
+        // The refresh is quadratic in the index size (see `warm_up`), so searches
+        // under a deadline use the reversibility from the last refresh instead.
+        if deadline.is_none() {
+            self.update_document_relationships();
+        }
+        self.refresh_persistence_cache();
+        self.rank_documents(query, top_k, source, mode, deadline, trace)
+    }
+
+    // The search itself, leaving the index as it is apart from the caches
+    // documents fill in on first use
+    fn rank_documents(
+        &self,
+        query: &str,
+        top_k: usize,
+        source: Option<DocumentSource>,
+        mode: SearchMode,
+        deadline: Option<Instant>,
+        trace: SearchTrace,
+    ) -> (SearchOutcome, bool) {
+        let SearchTrace { mut plan, mut components } = trace;
+        // Queries neither grow the vocabulary nor count towards document
+        // frequencies; words no document has can't match anyway
+        let query_tokens = self.tokenizer.tokenize_known(query);
+        if query_tokens.is_empty() && self.tokenizer.words(query).is_empty() {
+            return (SearchOutcome::EmptyQuery, false);
+        }
+        if self.len() == 0 {
+            return (SearchOutcome::EmptyIndex, false);
+        }
+        if query_tokens.is_empty() {
+            return (SearchOutcome::NoMatches, false);
+        }
+        
+        let mut query_vec = build_vector(&query_tokens);
+        if let Some(plan) = plan.as_deref_mut() {
+            plan.stages.push(self.plan_stage("tokenize", &PrimeVector::new(), &query_vec));
+        }
+        if !self.synonyms.is_empty() {
+            let before = plan.is_some().then(|| query_vec.clone());
+            query_vec = self.expand_synonyms(query_vec);
+            if let (Some(plan), Some(before)) = (plan.as_deref_mut(), before) {
+                plan.stages.push(self.plan_stage("synonyms", &before, &query_vec));
+            }
+        }
+        if self.query_expansion_terms > 0 {
+            let before = plan.is_some().then(|| query_vec.clone());
+            query_vec = self.expand_query_vector(query_vec);
+            if let (Some(plan), Some(before)) = (plan.as_deref_mut(), before) {
+                plan.stages.push(self.plan_stage("co-occurrence", &before, &query_vec));
+            }
+        }
+        if self.idf_weighting {
+            query_vec = self.apply_idf(query_vec);
+        }
+        if let Some(plan) = plan.as_deref_mut() {
+            plan.final_terms = query_vec.iter().map(|(&prime, &weight)| (self.term_name(prime), weight)).collect();
+            plan.final_terms.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+        }
+        // Documents compare with the query's entropy on their own scale
+        let query_word_entropy = shannon_entropy(&query_tokens);
+        let query_ngram_entropy = char_ngram_entropy(query, self.ngram_size);
+
+        // First get all the scores without using 'self' inside the closure
+        let mut results: Vec<SearchResult> = Vec::new();
+        let mut relevances = Vec::new();
+        let now = self.now();
+        let persistence_active = self.persistence_active();
+        let mut truncated = false;
+        
+        // Process each candidate individually to avoid borrowing conflicts
+        let candidates = self.candidate_documents(&query_vec);
+        if let Some(plan) = plan.as_deref_mut() {
+            plan.candidates = candidates.len();
+        }
+        for (scanned, &i) in candidates.iter().enumerate() {
+            let doc = &self.docs[i];
+            // Checking the clock every 64 documents keeps its cost negligible
+            if scanned % 64 == 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                truncated = true;
+                break;
+            }
+            if source.is_some_and(|source| source != doc.source) {
+                continue;
+            }
+            
+            // Standard resonance score
+            let resonance = self.similarity_metric.similarity(&query_vec, &doc.vector);
+            let query_entropy = if doc.ngram_entropy { query_ngram_entropy } else { query_word_entropy };
+            let delta_entropy = (doc.entropy - query_entropy).abs();
+            let standard_score = resonance - delta_entropy * self.entropy_weight;
+            
+            let doc_age = (now.saturating_sub(doc.timestamp) as f64) / (24.0 * 3600.0); // Age in days
+            
+            // Quantum-inspired score, with the decay factor, complex resonance
+            // and biorthogonal score it combines
+            let (quantum_score, decay_factor, complex_res, bio_score) = if self.use_quantum_score {
+                // Calculate directly instead of calling self.method()
+                // Begin quantum score calculation (copied from calculate_quantum_score)
+                let decay_factor = 0.01 * doc_age.min(100.0); // Cap at 100 days
+                
+                let complex_res = resonance_complex(&query_vec, &doc.vector, decay_factor);
+                
+                // For biorthogonal scoring
+                let query_bio = build_biorthogonal_vector(&self.tokenizer.tokenize_without_update(query_vec.keys().cloned().collect::<Vec<_>>().as_slice()));
+                let bio_score = match doc.biorthogonal.get() {
+                    Some(biorthogonal) => biorthogonal_score(&query_bio, biorthogonal),
+                    None if mode == SearchMode::ReadOnly => biorthogonal_score(&query_bio, &biorthogonal_from_vector(&doc.vector)),
+                    None => biorthogonal_score(&query_bio, doc.biorthogonal()),
+                };
+                
+                // Combine scores - weight the real part most heavily but consider phase
+                let quantum = complex_res.re * 0.6 + complex_res.im.abs() * 0.2 + bio_score * 0.2;
+                (quantum, decay_factor, complex_res, bio_score)
+                // End quantum score calculation
+            } else {
+                (0.0, 0.0, Complex::new(0.0, 0.0), 0.0)
+            };
+            
+            // Persistence theory score, with the update frequency, entropy
+            // pressure and entropy factor that go into it
+            let (persistence_score, update_frequency, pressure, entropy_factor) = if persistence_active {
+                // Only the entropy factor depends on the query; the rest is
+                // usually cached from an earlier search
+                let (update_frequency, pressure, persistence) = self.document_persistence(doc, now);
+                
+                // Adjust based on entropy delta with query
+                let entropy_factor = (-delta_entropy * self.entropy_weight).exp();
+                
+                (persistence * entropy_factor, update_frequency, pressure, entropy_factor)
+            } else {
+                (0.0, 0.0, 0.0, 0.0)
+            };
+            
+            if let Some(components) = components.as_deref_mut() {
+                components.push(ScoreComponents {
+                    path: doc.path.to_string_lossy().into_owned(),
+                    title: doc.title.clone(),
+                    resonance,
+                    delta_entropy,
+                    score: standard_score,
+                    age_days: doc_age,
+                    decay_factor,
+                    complex_real: complex_res.re,
+                    complex_imaginary: complex_res.im,
+                    biorthogonal_score: bio_score,
+                    quantum_score,
+                    reversibility: doc.reversibility,
+                    update_frequency,
+                    entropy_pressure: pressure,
+                    buffering: doc.buffering,
+                    entropy_factor,
+                    persistence_score,
+                    relevance: doc.relevance,
+                    combined_score: 0.0,
+                });
+            }
+            
+            relevances.push(doc.relevance);
+            results.push(SearchResult {
+                title: doc.title.clone(),
+                resonance,
+                delta_entropy,
+                score: standard_score,
+                quantum_score,
+                persistence_score,
+                combined_score: 0.0,
+                normalized_score: 0.0,
+                snippet: String::new(), // Filled in for the results kept
+                path: doc.path.to_string_lossy().into_owned(),
+                source: doc.source,
+                alternates: Vec::new(),
+                term_matches: Vec::new(),
+            });
+        }
+
+        if let Some(plan) = plan.as_deref_mut() {
+            plan.scored = results.len();
+        }
+
+        // Now sort results based on combined score
+        let weights = self.effective_score_weights();
+        match self.score_combination {
+            ScoreCombination::Weighted => {
+                for (result, relevance) in results.iter_mut().zip(relevances) {
+                    result.combined_score = relevance * (result.score * weights.resonance
+                        + result.quantum_score * weights.quantum
+                        + result.persistence_score * weights.persistence);
+                }
+            }
+            ScoreCombination::ReciprocalRank => {
+                let ranks = |score: fn(&SearchResult) -> f64| reciprocal_ranks(&results.iter().map(score).collect::<Vec<_>>());
+                let resonance = ranks(|r| r.score);
+                let quantum = ranks(|r| r.quantum_score);
+                let persistence = ranks(|r| r.persistence_score);
+                for (i, (result, relevance)) in results.iter_mut().zip(relevances).enumerate() {
+                    result.combined_score = relevance * (resonance[i] * weights.resonance
+                        + quantum[i] * weights.quantum
+                        + persistence[i] * weights.persistence);
+                }
+            }
+        }
+        self.source_calibration.apply(&mut results);
+        if let Some(components) = components {
+            for (components, result) in components.iter_mut().zip(&results) {
+                components.combined_score = result.combined_score;
+            }
+        }
+        if results.iter().all(|r| r.resonance <= 0.0) {
+            return (SearchOutcome::NoMatches, truncated);
+        }
+
+        // Only the top K need ordering: partition them off first, then sort just those
+        let by_score = |a: &SearchResult, b: &SearchResult| b.combined_score.partial_cmp(&a.combined_score).unwrap_or(std::cmp::Ordering::Equal);
+        if top_k == 0 {
+            results.clear();
+        } else if self.dedup_threshold.is_some() || self.max_per_directory.is_some() {
+            // Duplicates and crowded directories can push anything out of the
+            // top K, so rank everything
+            results.sort_by(by_score);
+            results = self.diversify(results, top_k);
+        } else if top_k < results.len() {
+            results.select_nth_unstable_by(top_k - 1, by_score);
+            results.truncate(top_k);
+        }
+        results.sort_by(by_score);
+        if mode == SearchMode::Snippets {
+            self.attach_snippets(query, &mut results);
+        }
+        if let Some(reranker) = &self.reranker {
+            results = reranker(query, &results);
+        }
+        self.score_normalization.apply(&mut results);
+        if let Some(plan) = plan {
+            plan.top_scores = results.iter().map(|result| (result.path.clone(), result.combined_score)).collect();
+        }
+        (SearchOutcome::Ok(results), truncated)
+    }
+
+    /// Fill in the snippet (and, with field snippets, the term matches) of
+    /// each result from its document, for results of `search_scores_only`
+    /// or `search_filtered_scores_only`
+    pub fn attach_snippets(&self, query: &str, results: &mut [SearchResult]) {
+        // The query's own words, for field snippets
+        let mut query_words = if self.field_snippets { self.tokenizer.words(query) } else { Vec::new() };
+        let mut seen = HashSet::new();
+        query_words.retain(|word| seen.insert(word.clone()));
+
+        for result in results {
+            let Some(&i) = self.path_index.get(Path::new(&result.path)) else {
+                continue;
+            };
+            let doc = &self.docs[i];
+            (result.snippet, result.term_matches) = if self.field_snippets {
+                doc.field_snippet(&self.tokenizer, &query_words, self.snippet_length)
+            } else {
+                (doc.get_snippet(self.snippet_length), Vec::new())
+            };
+        }
+    }
+
+    // Method to set the entropy weight (finite, >= 0)
+    pub fn set_entropy_weight(&mut self, weight: f64) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("entropy_weight", weight, weight >= 0.0, "a finite value >= 0")?;
+        self.entropy_weight = weight;
+        Ok(())
+    }
+    
+    // Method to set the fragility parameter (0, 1]
+    pub fn set_fragility(&mut self, fragility: f64) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("fragility", fragility, fragility > 0.0 && fragility <= 1.0, "a value in (0, 1]")?;
+        self.fragility = fragility;
+        Ok(())
+    }
+    
+    // Method to set the trend decay parameter (finite, >= 0)
+    pub fn set_trend_decay(&mut self, decay: f64) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("trend_decay", decay, decay >= 0.0, "a finite value >= 0")?;
+        self.trend_decay = decay;
+        Ok(())
+    }
+    
+    // Method to set the baseline update frequency (finite, >= 0), used for documents
+    // that have never been re-indexed. Lower means less entropy pressure.
+    pub fn set_update_frequency(&mut self, frequency: f64) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("update_frequency", frequency, frequency >= 0.0, "a finite value >= 0")?;
+        self.update_frequency = frequency;
+        Ok(())
+    }
+    
+    /// Current parameters of the persistence model
+    pub fn persistence_params(&self) -> PersistenceParams {
+        PersistenceParams {
+            entropy_weight: self.entropy_weight,
+            fragility: self.fragility,
+            trend_decay: self.trend_decay,
+            update_frequency: self.update_frequency,
+        }
+    }
+    
+    /// Set the minimum query resonance a document needs to be boosted by a quantum jump
+    pub fn set_quantum_jump_threshold(&mut self, threshold: f64) {
+        self.quantum_jump_threshold = threshold;
+    }
+    
+    /// Set the importance used by `apply_default_quantum_jump`
+    pub fn set_quantum_jump_importance(&mut self, importance: f64) {
+        self.quantum_jump_importance = importance;
+    }
+    
+    /// Set how much of a boosted document's age is forgiven by a quantum jump.
+    ///
+    /// Documents older than a day have their age multiplied by `1.0 - freshening`,
+    /// so 0.5 halves the age, 0.0 leaves timestamps alone and 1.0 makes the
+    /// document brand new. Values are clamped to [0, 1].
+    pub fn set_quantum_jump_freshening(&mut self, freshening: f64) {
+        self.quantum_jump_freshening = freshening.clamp(0.0, 1.0);
+    }
+    
+    /// Apply a quantum jump using the configured default importance
+    pub fn apply_default_quantum_jump(&mut self, query: &str) {
+        self.apply_quantum_jump(query, self.quantum_jump_importance);
+    }
+    
+    // Apply a quantum jump to the documents (for dynamic updates)
+    pub fn apply_quantum_jump(&mut self, query: &str, importance: f64) {
+        let query_tokens = self.tokenizer.tokenize_known(query);
+        if query_tokens.is_empty() {
+            return;
+        }
+        
+        let query_vec = build_vector(&query_tokens);
+        let now = self.now();
+        
+        // Create a simple Hamiltonian for the system
+        for doc in &mut self.docs {
+            // Convert vectors to dense format for quantum operations
+            let doc_dense = to_dense_vector(&doc.vector, 100);
+            let query_dense = to_dense_vector(&query_vec, 100);
+            
+            // Skip if too small
+            if doc_dense.is_empty() || query_dense.is_empty() {
+                continue;
+            }
+            
+            // Calculate resonance as overlap
+            let resonance = dot_product(&query_vec, &doc.vector);
+            
+            // If the document resonates with the query, boost its relevance
+            if resonance > self.quantum_jump_threshold {
+                // Add the query vector to the document's historical vectors,
+                // unless the history hasn't been built yet (persistence scoring off)
+                if !doc.historical_vectors.is_empty() {
+                    let current_vec = to_dense_vector(&doc.vector, self.dense_dimension);
+                    if doc.historical_vectors.len() >= 5 {
+                        // Replace oldest vector
+                        doc.historical_vectors.remove(0);
+                    }
+                    doc.historical_vectors.push(current_vec);
+                }
+                
+                // Increase reversibility based on match strength
+                doc.reversibility = doc.reversibility * 0.9 + 0.1 * (resonance * importance);
+                
+                // Update timestamp to mark it as "fresher"
+                // Only update if significantly older than a day
+                let age = now.saturating_sub(doc.timestamp);
+                if self.quantum_jump_freshening > 0.0 && age > 24 * 3600 {
+                    let kept_age = (age as f64 * (1.0 - self.quantum_jump_freshening)) as u64;
+                    doc.timestamp = now - kept_age;
+                }
+            }
+        }
+    }
+}
+/// A hit from a `FederatedSearch`, tagged with the index it came from.
+pub struct FederatedResult {
+    /// Name of the index where this document ranked highest
+    pub index: String,
+    /// Other indexes that also returned a document with this path
+    pub also_in: Vec<String>,
+    /// Reciprocal rank fusion score summed over every index that returned it
+    pub fused_score: f64,
+    pub result: SearchResult,
+}
+
+/// Searches several separately loaded indexes together without merging them.
+/// Each engine scores the query against its own vocabulary, and the per-engine
+/// rankings are combined with reciprocal rank fusion, so scores that are not
+/// comparable across engines never have to be compared directly.
+pub struct FederatedSearch {
+    engines: Vec<(String, ResonantEngine)>,
+    rrf_k: f64,
+}
+
+impl Default for FederatedSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FederatedSearch {
+    pub fn new() -> Self {
+        Self {
+            engines: Vec::new(),
+            rrf_k: 60.0,
+        }
+    }
+
+    /// Add an engine under `name`, replacing any engine already using that name.
+    pub fn add_engine(&mut self, name: &str, engine: ResonantEngine) {
+        match self.engines.iter_mut().find(|(existing, _)| existing == name) {
+            Some(slot) => slot.1 = engine,
+            None => self.engines.push((name.to_string(), engine)),
+        }
+    }
+
+    pub fn remove_engine(&mut self, name: &str) -> Option<ResonantEngine> {
+        let pos = self.engines.iter().position(|(existing, _)| existing == name)?;
+        Some(self.engines.remove(pos).1)
+    }
+
+    pub fn engine_mut(&mut self, name: &str) -> Option<&mut ResonantEngine> {
+        self.engines.iter_mut().find(|(existing, _)| existing == name).map(|(_, engine)| engine)
+    }
+
+    pub fn index_names(&self) -> impl Iterator<Item = &str> {
+        self.engines.iter().map(|(name, _)| name.as_str())
+    }
+
+    // Method to set the RRF rank offset; larger values flatten the gap between ranks (finite, > 0)
+    pub fn set_rrf_k(&mut self, k: f64) -> Result<(), InvalidParameter> {
+        InvalidParameter::check("rrf_k", k, k > 0.0, "a finite value > 0")?;
+        self.rrf_k = k;
+        Ok(())
+    }
+
+    /// Run `query` against every index and return the fused top `top_k`.
+    /// A document at rank r (from 1) in an index contributes 1 / (rrf_k + r);
+    /// the same path returned by several indexes is reported once with the
+    /// contributions summed.
+    pub fn search(&mut self, query: &str, top_k: usize) -> Vec<FederatedResult> {
+        let mut fused: Vec<FederatedResult> = Vec::new();
+        let mut by_path: HashMap<String, usize> = HashMap::new();
+        // Best single-index contribution per entry, to attribute it to that index
+        let mut best: Vec<f64> = Vec::new();
+
+        for (name, engine) in self.engines.iter_mut() {
+            for (rank, result) in engine.search(query, top_k).into_iter().enumerate() {
+                let contribution = 1.0 / (self.rrf_k + (rank + 1) as f64);
+                match by_path.get(&result.path) {
+                    Some(&i) => {
+                        let entry = &mut fused[i];
+                        entry.fused_score += contribution;
+                        if contribution > best[i] {
+                            best[i] = contribution;
+                            let previous = std::mem::replace(&mut entry.index, name.clone());
+                            entry.also_in.push(previous);
+                            entry.result = result;
+                        } else {
+                            entry.also_in.push(name.clone());
+                        }
+                    }
+                    None => {
+                        by_path.insert(result.path.clone(), fused.len());
+                        best.push(contribution);
+                        fused.push(FederatedResult {
+                            index: name.clone(),
+                            also_in: Vec::new(),
+                            fused_score: contribution,
+                            result,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Stable sort: equal fused scores keep index order, then per-index rank
+        fused.sort_by(|a, b| b.fused_score.total_cmp(&a.fused_score));
+        fused.truncate(top_k);
+        fused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A document of `count` distinct words, so its primes run past `count` small ones
+    fn distinct_words(prefix: &str, count: usize) -> String {
+        (0..count).map(|i| format!("{prefix}{i}")).collect::<Vec<_>>().join(" ")
+    }
+
+    fn engine_with(docs: &[(&str, String)]) -> ResonantEngine {
+        let mut engine = ResonantEngine::new();
+        for (path, text) in docs {
+            engine.add_local_document(path.to_string(), text.clone(), PathBuf::from(path));
+        }
+        engine
+    }
+
+    #[test]
+    fn dense_vectors_grow_under_the_cap_and_fall_back_to_sparse_past_it() {
+        let mut engine = ResonantEngine::new();
+        engine.set_use_persistence_score(true);
+        engine.set_dense_overflow(DenseOverflow::Grow { max_dimension: 4096 });
+
+        // 300 words take primes past 1000 but well under 4096
+        engine.add_local_document("a".into(), distinct_words("alpha", 300), PathBuf::from("/a"));
+        assert!(engine.dense_dimension() > 1000);
+        assert!(engine.dense_dimension().is_power_of_two());
+        assert!(!engine.is_sparse_only());
+
+        // 700 more take them past 4096
+        engine.add_local_document("b".into(), distinct_words("beta", 700), PathBuf::from("/b"));
+        assert!(engine.dense_dimension() <= 4096);
+        assert!(engine.is_sparse_only());
+        assert!(!engine.search("alpha1", 5).is_empty());
+
+        engine.clear_documents();
+        assert!(!engine.is_sparse_only());
+    }
+
+    #[test]
+    fn cached_persistence_matches_a_fresh_computation() {
+        let start = 1_700_000_000;
+        let mut engine = ResonantEngine::new();
+        engine.set_clock(FixedClock(start));
+        engine.set_use_persistence_score(true);
+        engine.add_local_document("a".into(), "apple banana cherry".into(), PathBuf::from("/a"));
+        engine.add_local_document("b".into(), "apple durian".into(), PathBuf::from("/b"));
+        engine.add_local_document("a".into(), "apple banana cherry elder".into(), PathBuf::from("/a"));
+        assert_eq!(engine.settings().persistence_cache_bucket, None);
+
+        let scores = |engine: &mut ResonantEngine| -> Vec<(String, f64)> {
+            let mut scores: Vec<_> = engine.search("apple banana", 5).into_iter()
+                .map(|result| (result.path, result.persistence_score))
+                .collect();
+            scores.sort_by(|a, b| a.0.cmp(&b.0));
+            scores
+        };
+
+        // Cache filled at `start`, read back half a bucket later
+        engine.set_persistence_cache_bucket(Some(Duration::from_secs(3600)));
+        engine.set_clock(FixedClock(start + 60));
+        scores(&mut engine);
+        engine.set_clock(FixedClock(start + 1800));
+        let cached = scores(&mut engine);
+
+        engine.set_persistence_cache_bucket(None);
+        let fresh = scores(&mut engine);
+        assert_eq!(cached.len(), 2);
+        for ((cached_path, cached), (fresh_path, fresh)) in cached.iter().zip(&fresh) {
+            assert_eq!(cached_path, fresh_path);
+            assert!((cached - fresh).abs() <= 1e-3 * fresh.abs().max(1.0), "{cached} vs {fresh}");
+        }
+    }
+
+    #[test]
+    fn short_documents_get_ngram_entropy_and_long_ones_word_entropy() {
+        let long = "the zeppelin manifest lists every passenger who boarded at the northern mooring mast";
+        let mut engine = ResonantEngine::new();
+        engine.set_ngram_entropy(5, 3);
+        engine.add_local_document("name".into(), "invoice_2024".into(), PathBuf::from("/invoice_2024"));
+        engine.add_local_document("long".into(), long.into(), PathBuf::from("/long"));
+
+        let short = &engine.docs[engine.path_index[Path::new("/invoice_2024")]];
+        assert!(short.ngram_entropy);
+        assert!(short.entropy > 1.0, "{}", short.entropy);
+        let long_doc = &engine.docs[engine.path_index[Path::new("/long")]];
+        assert!(!long_doc.ngram_entropy);
+        let expected = shannon_entropy(&engine.tokenizer.tokenize_known(long));
+        assert!((long_doc.entropy - expected).abs() < 1e-9, "{} vs {expected}", long_doc.entropy);
+    }
+
+    #[test]
+    fn queries_are_measured_like_each_document() {
+        let long = "the zeppelin manifest lists every passenger who boarded at the northern mooring mast";
+        let mut engine = ResonantEngine::new();
+        engine.set_ngram_entropy(5, 3);
+        engine.add_local_document("name".into(), "zeppelin manifest".into(), PathBuf::from("/name"));
+        engine.add_local_document("long".into(), long.into(), PathBuf::from("/long"));
+
+        // A query repeating a document's text matches its entropy, long or short
+        for (query, path) in [("zeppelin manifest", "/name"), (long, "/long")] {
+            let results = engine.search(query, 5);
+            let result = results.iter().find(|result| result.path == path).unwrap();
+            assert!(result.delta_entropy < 1e-9, "{query}: {}", result.delta_entropy);
+        }
+    }
+
+    #[test]
+    fn weight_optimization_finds_a_perfect_weighting_and_rejects_tiny_steps() {
+        let mut engine = engine_with(&[
+            ("/manifest", "zeppelin manifest zeppelin manifest".into()),
+            ("/crew", "zeppelin crew roster with the weather report and the passenger list".into()),
+        ]);
+        engine.set_use_quantum_score(true);
+        let judgments = [Judgment { query: "zeppelin manifest".into(), relevant: vec!["/manifest".into()] }];
+
+        for step in [0.0, 0.005, 0.6, f64::NAN] {
+            assert!(engine.optimize_weights(&judgments, RankingMetric::Mrr, step).is_err(), "{step}");
+        }
+        let fit = engine.optimize_weights(&judgments, RankingMetric::Mrr, 0.01).unwrap();
+        assert!(fit.metric >= fit.baseline);
+        assert_eq!(fit.metric, 1.0);
+        assert_eq!(engine.search("zeppelin manifest", 1)[0].path, "/manifest");
+    }
+
+    #[test]
+    fn directory_cap_keeps_each_directory_in_rank_order() {
+        // Fewer other words rank higher, so the most crowded directory holds the best matches
+        let docs: Vec<(String, String)> = [("/a", 6, 0), ("/b", 3, 4), ("/c", 2, 5)].iter()
+            .flat_map(|&(dir, count, padding)| (0..count).map(move |i| (format!("{dir}/{i}"), format!("zeppelin {}", distinct_words(&format!("pad{dir}{i}x"), padding + i)))))
+            .collect();
+        let docs: Vec<(&str, String)> = docs.iter().map(|(path, text)| (path.as_str(), text.clone())).collect();
+        let mut engine = engine_with(&docs);
+        let ranked: Vec<String> = engine.search("zeppelin", 20).into_iter().map(|result| result.path).collect();
+        assert_eq!(ranked.len(), 11);
+        assert!(ranked[..5].iter().filter(|path| path.starts_with("/a/")).count() > 2);
+
+        engine.set_max_per_directory(Some(2));
+        let capped: Vec<String> = engine.search("zeppelin", 5).into_iter().map(|result| result.path).collect();
+        // The best two of each directory, in the order the uncapped search ranked them
+        let mut per_directory: HashMap<&Path, usize> = HashMap::new();
+        let expected: Vec<String> = ranked.iter()
+            .filter(|path| {
+                let count = per_directory.entry(Path::new(path.as_str()).parent().unwrap()).or_insert(0);
+                *count += 1;
+                *count <= 2
+            })
+            .take(5)
+            .cloned()
+            .collect();
+        assert_eq!(capped, expected);
+        assert_eq!(capped.iter().filter(|path| path.starts_with("/a/")).count(), 2);
+    }
+
+    // What the posting lists should hold, found by scanning every document
+    fn scanned_postings(engine: &ResonantEngine) -> HashMap<u64, Vec<usize>> {
+        let mut postings: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, doc) in engine.docs.iter().enumerate().filter(|(_, doc)| !doc.removed) {
+            for &prime in doc.vector.keys() {
+                postings.entry(prime).or_default().push(i);
+            }
+        }
+        postings
+    }
+
+    #[test]
+    fn posting_lists_follow_adds_replacements_and_removals() {
+        let mut engine = engine_with(&[
+            ("/a", "zeppelin manifest".into()),
+            ("/b", "zeppelin crew roster".into()),
+            ("/c", "harbour crane".into()),
+            ("/d", "crew of the harbour crane".into()),
+        ]);
+        engine.set_auto_compact_ratio(None);
+        assert_eq!(engine.postings, scanned_postings(&engine));
+
+        engine.add_local_document("/b".into(), "harbour crew".into(), PathBuf::from("/b"));
+        assert_eq!(engine.postings, scanned_postings(&engine));
+        assert_eq!(engine.documents_with_term("zeppelin"), vec![Path::new("/a")]);
+        assert_eq!(engine.documents_with_term("harbour"), vec![Path::new("/b"), Path::new("/c"), Path::new("/d")]);
+
+        assert!(engine.remove_document(Path::new("/c")));
+        assert_eq!(engine.postings, scanned_postings(&engine));
+        assert_eq!(engine.documents_with_term("crane"), vec![Path::new("/d")]);
+
+        assert_eq!(engine.compact(), 1);
+        assert_eq!(engine.postings, scanned_postings(&engine));
+        assert_eq!(engine.documents_with_term("harbour"), vec![Path::new("/b"), Path::new("/d")]);
+    }
+
+    #[test]
+    fn idf_weighting_follows_adds_and_removes_without_revectorizing() {
+        let docs = [
+            ("/a", "zeppelin harbour mast"),
+            ("/b", "zeppelin crew"),
+            ("/c", "harbour crane"),
+            ("/d", "zeppelin roster"),
+            ("/e", "zeppelin mooring"),
+        ];
+        let resonances = |engine: &mut ResonantEngine| -> HashMap<String, f64> {
+            engine.search("zeppelin harbour", 10).into_iter().map(|result| (result.path, result.resonance)).collect()
+        };
+        let mut engine = engine_with(&docs[..3].iter().map(|(path, text)| (*path, text.to_string())).collect::<Vec<_>>());
+        engine.set_auto_compact_ratio(None);
+        engine.set_idf_weighting(true);
+        let before = resonances(&mut engine);
+        let vectors: Vec<PrimeVector> = engine.docs.iter().map(|doc| doc.vector.clone()).collect();
+
+        for (path, text) in &docs[3..] {
+            engine.add_local_document(path.to_string(), text.to_string(), PathBuf::from(path));
+        }
+        assert!(engine.remove_document(Path::new("/c")));
+        assert_eq!(engine.document_frequency("zeppelin"), 4);
+        assert_eq!(engine.document_frequency("harbour"), 1);
+        // The documents already indexed kept their vectors
+        for (doc, vector) in engine.docs.iter().zip(&vectors).filter(|(doc, _)| !doc.removed) {
+            assert_eq!(&doc.vector, vector, "{}", doc.path.display());
+        }
+
+        // Scores match an index built from scratch on the documents left,
+        // and "harbour", now the rarer term, widens the lead of /a, which has it
+        let after = resonances(&mut engine);
+        let remaining: Vec<(&str, String)> = docs.iter().filter(|(path, _)| *path != "/c").map(|(path, text)| (*path, text.to_string())).collect();
+        let mut rebuilt = engine_with(&remaining);
+        rebuilt.set_idf_weighting(true);
+        let expected = resonances(&mut rebuilt);
+        assert_eq!(after.len(), expected.len());
+        for (path, resonance) in &expected {
+            assert!((after[path] - resonance).abs() < 1e-12, "{path}: {} vs {resonance}", after[path]);
+        }
+        assert!(after["/a"] / after["/b"] > before["/a"] / before["/b"]);
+    }
+
+    #[test]
+    fn candidates_from_posting_lists_rank_like_a_full_scan() {
+        let mut engine = busy_engine();
+        assert!(engine.remove_document(Path::new("/docs/5")));
+        let ranked = |engine: &mut ResonantEngine, query: &str| -> Vec<(String, f64)> {
+            engine.search(query, 40).into_iter()
+                .filter(|result| result.resonance > 0.0)
+                .map(|result| (result.path, result.combined_score))
+                .collect()
+        };
+        for query in BUSY_QUERIES {
+            let indexed = ranked(&mut engine, query);
+            engine.set_min_term_overlap(0);
+            let scanned = ranked(&mut engine, query);
+            engine.set_min_term_overlap(1);
+            assert!(!indexed.is_empty());
+            assert_eq!(indexed.len(), scanned.len(), "{query}");
+            for ((path, score), (scanned_path, scanned_score)) in indexed.iter().zip(&scanned) {
+                assert_eq!(path, scanned_path, "{query}");
+                assert!((score - scanned_score).abs() < 1e-9, "{query}: {path}");
+            }
+        }
+    }
+
+    // An engine over overlapping topics with every scoring feature on and the
+    // text compressed, so searches fill the lazy caches as they go. The same
+    // every time it is built.
+    fn busy_engine() -> ResonantEngine {
+        let topics = ["zeppelin manifest", "harbour crane", "orchard frost", "violin varnish"];
+        let mut engine = ResonantEngine::new();
+        engine.set_clock(FixedClock(1_700_000_000));
+        engine.set_use_quantum_score(true);
+        engine.set_use_persistence_score(true);
+        for i in 0..40 {
+            let text = format!("{} {} {}", topics[i % 4], topics[(i / 4) % 4], distinct_words(&format!("filler{i}x"), 200));
+            engine.add_local_document(format!("doc {i}"), text, PathBuf::from(format!("/docs/{i}")));
+        }
+        assert_eq!(engine.compress_all_documents(None), 40);
+        engine
+    }
+
+    const BUSY_QUERIES: [&str; 4] = ["zeppelin manifest", "harbour", "frost violin", "crane varnish orchard"];
+
+    #[test]
+    fn exported_vectors_have_a_row_per_document_matching_the_metadata() {
+        let mut engine = engine_with(&[
+            ("/a", "zeppelin manifest".into()),
+            ("/gone", "harbour crane".into()),
+            ("/b", "zeppelin crew roster".into()),
+        ]);
+        assert!(engine.remove_document(Path::new("/gone")));
+        engine.set_export_dimension(40);
+        let base = std::env::temp_dir().join(format!("quantum-search-vectors-{}", std::process::id()));
+
+        for format in [VectorFormat::Npy, VectorFormat::Csv, VectorFormat::Binary] {
+            let path = format!("{}.{:?}", base.display(), format);
+            assert_eq!(engine.export_vectors(&path, format).unwrap(), 2);
+            let bytes = fs::read(&path).unwrap();
+            let meta = fs::read_to_string(format!("{path}.meta.csv")).unwrap();
+            let _ = fs::remove_file(&path);
+            let _ = fs::remove_file(format!("{path}.meta.csv"));
+
+            let rows: Vec<Vec<f64>> = match format {
+                VectorFormat::Csv => String::from_utf8(bytes).unwrap().lines()
+                    .map(|line| line.split(',').map(|value| value.parse().unwrap()).collect())
+                    .collect(),
+                VectorFormat::Npy | VectorFormat::Binary => {
+                    let data = if format == VectorFormat::Npy {
+                        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+                        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+                        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+                        assert!(header.contains("'shape': (2, 40)"), "{header}");
+                        &bytes[10 + header_len..]
+                    } else {
+                        assert_eq!(bytes[..8], 2u64.to_le_bytes());
+                        assert_eq!(bytes[8..16], 40u64.to_le_bytes());
+                        &bytes[16..]
+                    };
+                    assert_eq!(data.len(), 2 * 40 * 8);
+                    data.chunks(40 * 8)
+                        .map(|row| row.chunks(8).map(|value| f64::from_le_bytes(value.try_into().unwrap())).collect())
+                        .collect()
+                }
+            };
+            assert_eq!(rows.len(), 2, "{format:?}");
+
+            let mut lines = meta.lines();
+            assert_eq!(lines.next(), Some("row,path,title"));
+            for (row, (line, path)) in lines.zip(["/a", "/b"]).enumerate() {
+                assert_eq!(line, format!("{row},\"{path}\",\"{path}\""));
+                let doc = &engine.docs[engine.path_index[Path::new(path)]];
+                assert_eq!(rows[row], to_dense_vector(&doc.vector, 40), "{format:?} {path}");
+            }
+        }
+    }
+
+    #[test]
+    fn formatted_scores_follow_the_precision_and_score_display() {
+        let result = SearchResult {
+            title: "manifest.txt".into(),
+            resonance: 0.123456,
+            delta_entropy: 0.5,
+            score: 0.1,
+            quantum_score: 0.25,
+            persistence_score: 0.75,
+            combined_score: 1.23456,
+            normalized_score: 0.876,
+            snippet: "the zeppelin manifest".into(),
+            path: "/notes/manifest.txt".into(),
+            source: DocumentSource::Filesystem,
+            alternates: Vec::new(),
+            term_matches: Vec::new(),
+        };
+        let scores = |options: &DisplayOptions| -> String {
+            format_search_result(&result, options).lines().find(|line| line.contains("⚛️")).unwrap().trim().to_string()
+        };
+
+        let mut options = DisplayOptions::default();
+        assert_eq!(scores(&options), "⚛️  Resonance: 0.123 | Quantum: 0.250 | Persistence: 0.750 | Combined: 1.235 (88% match)");
+        options.precision = 5;
+        assert_eq!(scores(&options), "⚛️  Resonance: 0.12346 | Quantum: 0.25000 | Persistence: 0.75000 | Combined: 1.23456 (88% match)");
+
+        options = DisplayOptions { quantum_score: false, persistence_score: false, precision: 1, ..options };
+        options.score_display = ScoreDisplay::Normalized;
+        assert_eq!(scores(&options), "⚛️  Resonance: 0.1 | Match: 0.9");
+        options.score_display = ScoreDisplay::Percent;
+        assert_eq!(scores(&options), "⚛️  Resonance: 0.1 | Match: 88%");
+    }
+
+    #[test]
+    fn text_that_fails_to_compress_is_kept() {
+        let text = "the zeppelin manifest lists every passenger who boarded at the mooring mast";
+        let mut engine = engine_with(&[("/manifest", text.into()), ("/crew", "zeppelin crew roster".into())]);
+        let doc = &mut engine.docs[0];
+
+        // A compressed copy that doesn't read back, as if it were corrupted
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let mut corrupted = encoder.finish().unwrap();
+        corrupted.truncate(corrupted.len() / 2);
+        let verified = doc.verify_compressed(corrupted);
+        assert!(verified.is_err());
+        assert!(!doc.store_compressed(verified));
+        assert_eq!(doc.storage, TextStorage::CompressionFailed);
+        assert_eq!(doc.decompress_text(), text);
+        // It isn't tried again
+        assert!(!doc.compress_text(true));
+
+        assert_eq!(engine.compress_all_documents(None), 1);
+        assert!(!engine.docs[0].is_compressed() && engine.docs[1].is_compressed());
+        let results = engine.search("passenger", 5);
+        assert_eq!(results[0].path, "/manifest");
+        assert!(results[0].snippet.contains("zeppelin manifest"), "{}", results[0].snippet);
+    }
+
+    #[test]
+    fn calibrated_sources_share_the_top_results() {
+        // Files are indexed on their name and path words, each a folder deeper
+        // than the last so their scores spread; pages on their whole body
+        let mut engine = ResonantEngine::new();
+        let topics = ["tides", "ferries", "cranes", "fishing", "lighthouse", "customs"];
+        for (i, topic) in topics.iter().enumerate() {
+            let name = format!("harbour-{topic}.txt");
+            let path = format!("/srv/docs{}/{name}", "/archive".repeat(i));
+            engine.add_local_document(name.clone(), format!("harbour {topic} txt {}", path.replace('/', " ")), PathBuf::from(path));
+        }
+        for (i, topic) in topics.iter().enumerate() {
+            let body = format!("{} the harbour {topic} {}", "news from around the region ".repeat(5 + i), "weather sport and markets ".repeat(5));
+            engine.add_crawled_document(CrawledDocument { url: format!("https://example.com/{topic}"), title: topic.to_string(), text: body });
+        }
+        let web_share = |results: &[SearchResult]| results.iter().filter(|r| r.source == DocumentSource::Web).count();
+
+        // Raw scores favour the short filesystem documents
+        let raw = engine.search("harbour", 6);
+        assert_eq!(web_share(&raw), 0);
+
+        for calibration in [SourceCalibration::PerSourceMinMax, engine.calibrate_sources(&["harbour", "tides", "news"])] {
+            engine.set_source_calibration(calibration).unwrap();
+            let results = engine.search("harbour", 6);
+            let web = web_share(&results);
+            assert!((2..=4).contains(&web), "{calibration:?}: {web} of {} from the web", results.len());
+        }
+        assert!(matches!(engine.source_calibration(), SourceCalibration::Scaled { .. }));
+
+        // Deduplication, a reranker and a directory cap don't change the fit
+        let queries = ["harbour", "tides", "news"];
+        let fitted = engine.calibrate_sources(&queries);
+        engine.set_result_dedup(Some(0.5)).unwrap();
+        engine.set_reranker(Box::new(|_, results: &[SearchResult]| results[..1].to_vec()));
+        engine.set_max_per_directory(Some(1));
+        assert_eq!(engine.calibrate_sources(&queries), fitted);
+        assert_eq!(engine.search("harbour", 6).len(), 1);
+    }
+
+    #[test]
+    fn near_identical_results_fold_into_alternates() {
+        let text = "the zeppelin manifest lists every passenger who boarded at the mooring mast";
+        let mut engine = engine_with(&[
+            ("/notes/manifest.txt", text.into()),
+            ("/notes/crew.txt", "the zeppelin crew roster and the weather report".into()),
+        ]);
+        engine.add_crawled_document(CrawledDocument {
+            url: "https://example.com/manifest".into(),
+            title: "Manifest".into(),
+            text: format!("{text} today"),
+        });
+        let paths = |results: &[SearchResult]| -> Vec<String> { results.iter().map(|result| result.path.clone()).collect() };
+        assert_eq!(engine.search("zeppelin manifest", 5).len(), 3);
+
+        engine.set_result_dedup(Some(0.9)).unwrap();
+        let results = engine.search("zeppelin manifest", 5);
+        assert_eq!(results.len(), 2, "{:?}", paths(&results));
+        let (kept, other) = if results[0].alternates.is_empty() { (&results[1], &results[0]) } else { (&results[0], &results[1]) };
+        let mirror = ["/notes/manifest.txt", "https://example.com/manifest"];
+        assert!(mirror.contains(&kept.path.as_str()));
+        assert_eq!(kept.alternates.len(), 1);
+        assert!(mirror.contains(&kept.alternates[0].as_str()) && kept.alternates[0] != kept.path);
+        assert_eq!(other.path, "/notes/crew.txt");
+        assert!(other.alternates.is_empty());
+        assert!(engine.set_result_dedup(Some(1.5)).is_err());
+    }
+
+    #[test]
+    fn query_plans_record_each_enabled_stage() {
+        let docs = [
+            ("/repair", "car engine repair manual"),
+            ("/classic", "automobile engine restoration"),
+            ("/weather", "harbour weather report"),
+        ];
+        let mut plain = ResonantEngine::new();
+        let mut expanded = ResonantEngine::new();
+        expanded.set_stemming(true);
+        expanded.set_query_expansion(2);
+        expanded.add_synonyms("car", &["automobile"]);
+        for engine in [&mut plain, &mut expanded] {
+            for (path, text) in docs {
+                engine.add_local_document(path.into(), text.into(), PathBuf::from(path));
+            }
+        }
+
+        let (_, plan) = plain.search_explain("car repair", 5);
+        let names: Vec<&str> = plan.stages.iter().map(|stage| stage.name).collect();
+        assert_eq!(names, ["tokenize"]);
+        assert_eq!(plan.stages[0].added, ["car", "repair"]);
+
+        let (results, plan) = expanded.search_explain("cars", 5);
+        let names: Vec<&str> = plan.stages.iter().map(|stage| stage.name).collect();
+        assert_eq!(names, ["tokenize", "synonyms", "co-occurrence"]);
+        assert_eq!(plan.stages[0].added, ["car"]);
+        assert_eq!(plan.stages[1].added, ["automobil"]);
+        assert!(!plan.stages[2].added.is_empty());
+        assert!(plan.stages[2].added.iter().all(|term| term != "car" && term != "automobil"));
+        let final_terms: Vec<&str> = plan.final_terms.iter().map(|(term, _)| term.as_str()).collect();
+        assert!(final_terms.contains(&"car") && final_terms.contains(&"automobil"));
+        assert_eq!(plan.final_terms.len(), plan.stages[2].terms);
+        assert_eq!(plan.candidates, 2);
+        assert_eq!(plan.scored, 2);
+        let top: Vec<(String, f64)> = results.iter().map(|result| (result.path.clone(), result.combined_score)).collect();
+        assert_eq!(plan.top_scores, top);
+    }
+
+    #[test]
+    fn diagnostics_have_every_column_and_a_row_per_scored_pair() {
+        let file = std::env::temp_dir().join(format!("quantum-search-diagnostics-{}.csv", std::process::id()));
+        let path = file.to_str().unwrap();
+        let mut engine = busy_engine();
+        let rows = engine.diagnostics_export(&BUSY_QUERIES, path).unwrap();
+        let csv = fs::read_to_string(&file).unwrap();
+        let _ = fs::remove_file(&file);
+
+        let mut lines = csv.lines();
+        let columns: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(columns[..4], ["query", "path", "title", "resonance"]);
+        for column in ["delta_entropy", "decay_factor", "quantum_score", "reversibility", "persistence_score", "combined_score"] {
+            assert!(columns.contains(&column), "{column}");
+        }
+
+        // Every document with a query word is scored once per query
+        let mut expected = Vec::new();
+        for query in BUSY_QUERIES {
+            let mut paths: Vec<String> = query.split(' ')
+                .flat_map(|word| engine.documents_with_term(word))
+                .map(|path| path.display().to_string())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            paths.sort();
+            expected.extend(paths.into_iter().map(|path| (format!("\"{query}\""), format!("\"{path}\""))));
+        }
+        let pairs: Vec<(String, String)> = lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                assert_eq!(fields.len(), columns.len(), "{line}");
+                assert!(fields[3..].iter().all(|value| value.parse::<f64>().is_ok()), "{line}");
+                (fields[0].to_string(), fields[1].to_string())
+            })
+            .collect();
+        assert_eq!(rows, pairs.len());
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn concurrent_shared_searches_match_a_lone_search() {
+        let ranked = |engine: &ResonantEngine, query: &str| -> Vec<(String, f64, String)> {
+            engine.search_shared(query, 8).into_results().into_iter()
+                .map(|result| (result.path, result.combined_score, result.snippet))
+                .collect()
+        };
+        let lone = busy_engine();
+        let expected: Vec<_> = BUSY_QUERIES.iter().map(|query| ranked(&lone, query)).collect();
+        assert!(expected.iter().all(|results| !results.is_empty()));
+
+        // A cold engine, so the searches race to fill its caches
+        let shared = std::sync::RwLock::new(busy_engine());
+        std::thread::scope(|scope| {
+            for worker in 0..8 {
+                let (shared, expected) = (&shared, &expected);
+                scope.spawn(move || {
+                    for round in 0..25 {
+                        let i = (worker + round) % BUSY_QUERIES.len();
+                        assert_eq!(ranked(&shared.read().unwrap(), BUSY_QUERIES[i]), expected[i]);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn concurrent_readonly_searches_match_and_leave_the_engine_alone() {
+        let ranked = |engine: &ResonantEngine, query: &str| -> Vec<(String, f64)> {
+            engine.search_readonly(query, 8).into_iter()
+                .map(|result| (result.path, result.combined_score))
+                .collect()
+        };
+        let engine = busy_engine();
+        let expected: Vec<_> = BUSY_QUERIES.iter().map(|query| ranked(&engine, query)).collect();
+        assert!(expected.iter().all(|results| !results.is_empty()));
+
+        std::thread::scope(|scope| {
+            for worker in 0..8 {
+                let (engine, expected) = (&engine, &expected);
+                scope.spawn(move || {
+                    for round in 0..25 {
+                        let i = (worker + round) % BUSY_QUERIES.len();
+                        assert_eq!(ranked(engine, BUSY_QUERIES[i]), expected[i]);
+                    }
+                });
+            }
+        });
+        assert!(engine.docs.iter().all(|doc| doc.decompressed.get().is_none()));
+    }
+
+    #[test]
+    fn dense_vectors_keep_their_dimension_by_default() {
+        let mut engine = engine_with(&[("/a", distinct_words("alpha", 300))]);
+        engine.set_use_persistence_score(true);
+        assert_eq!(engine.dense_overflow(), DenseOverflow::SparseOnly);
+        engine.add_local_document("b".into(), distinct_words("beta", 300), PathBuf::from("/b"));
+        assert_eq!(engine.dense_dimension(), 1000);
+        assert!(engine.is_sparse_only());
+    }
+
+    fn history_terms(engine: &ResonantEngine, path: &str) -> Vec<HashSet<String>> {
+        let doc = &engine.docs[engine.path_index[Path::new(path)]];
+        doc.historical_vectors.iter()
+            .map(|dense| dense_to_terms(dense, &engine.tokenizer).into_keys().collect())
+            .collect()
+    }
+
+    #[test]
+    fn learned_history_survives_a_fresh_vocabulary() {
+        let mut before = ResonantEngine::new();
+        before.set_use_persistence_score(true);
+        before.add_local_document("a".into(), "apple banana".into(), PathBuf::from("/a"));
+        before.add_local_document("a".into(), "apple cherry".into(), PathBuf::from("/a"));
+        let history = history_terms(&before, "/a");
+        assert_eq!(history.len(), 2);
+
+        let file = std::env::temp_dir().join(format!("quantum-search-learned-{}", std::process::id()));
+        before.save_learned_state(file.to_str().unwrap()).unwrap();
+
+        // Other words first, so every prime lands somewhere else
+        let mut after = ResonantEngine::new();
+        after.set_use_persistence_score(true);
+        after.add_local_document("z".into(), "zebra yak walrus cherry banana".into(), PathBuf::from("/z"));
+        after.add_local_document("a".into(), "apple cherry".into(), PathBuf::from("/a"));
+        assert_ne!(before.tokenizer.get_prime("apple"), after.tokenizer.get_prime("apple"));
+        assert_eq!(after.load_learned_state(file.to_str().unwrap()).unwrap(), 1);
+        let _ = fs::remove_file(&file);
+
+        let restored = history_terms(&after, "/a");
+        assert_eq!(restored[..2], history[..]);
+    }
+
+    #[test]
+    fn stemmed_lookups_and_synonyms_match_inflections() {
+        let mut engine = ResonantEngine::new();
+        engine.set_stemming(true);
+        engine.add_synonyms("Cats", &["felines"]);
+        engine.add_local_document("pets".into(), "the cat sat".into(), PathBuf::from("/pets"));
+        engine.add_local_document("zoo".into(), "a feline paced".into(), PathBuf::from("/zoo"));
+
+        assert_eq!(engine.document_frequency("cats"), 1);
+        assert_eq!(engine.documents_with_term("Cats"), vec![Path::new("/pets")]);
+        let paths: HashSet<String> = engine.search("cat", 5).into_iter().map(|result| result.path).collect();
+        assert_eq!(paths, HashSet::from(["/pets".to_string(), "/zoo".to_string()]));
+    }
+
+    #[test]
+    fn snippets_show_the_content_match_and_note_the_name_match() {
+        let content = format!("{} the zeppelin budget was approved on friday", "filler ".repeat(60));
+        let text = format!("{content}{METADATA_SEPARATOR}zeppelin-budget.txt /srv/plans/zeppelin-budget.txt");
+        let mut engine = ResonantEngine::new();
+        engine.set_snippet_length(60);
+        engine.add_local_document("zeppelin-budget.txt".into(), text, PathBuf::from("/srv/plans/zeppelin-budget.txt"));
+        engine.add_local_document("other.txt".into(), "a budget elsewhere".into(), PathBuf::from("/srv/other.txt"));
+
+        let results = engine.search("budget", 2);
+        let result = results.iter().find(|result| result.title == "zeppelin-budget.txt").unwrap();
+        assert!(result.snippet.contains("zeppelin budget was approved"), "{}", result.snippet);
+        assert!(!result.snippet.contains(".txt"), "{}", result.snippet);
+        assert_eq!(result.term_matches.len(), 1);
+        assert_eq!(result.term_matches[0].term, "budget");
+        assert_eq!(result.term_matches[0].fields, vec![MatchField::Title, MatchField::Content]);
+    }
+
+    #[test]
+    fn merged_engines_search_both_and_keep_history_on_new_primes() {
+        let mut home = ResonantEngine::new();
+        home.set_use_persistence_score(true);
+        home.set_dense_overflow(DenseOverflow::Grow { max_dimension: 8192 });
+        home.add_local_document("home".into(), distinct_words("home", 300), PathBuf::from("/home/notes"));
+
+        let mut work = ResonantEngine::new();
+        work.set_use_persistence_score(true);
+        work.add_local_document("work".into(), "kiwi mango".into(), PathBuf::from("/work/fruit"));
+        work.add_local_document("work".into(), "kiwi papaya".into(), PathBuf::from("/work/fruit"));
+        let history = history_terms(&work, "/work/fruit");
+
+        assert_eq!(home.merge(work), 1);
+        // Every word of `work` now has a prime past the first 300
+        assert!(home.tokenizer.get_prime("kiwi").unwrap() > 1000);
+        assert_eq!(history_terms(&home, "/work/fruit"), history);
+        assert_eq!(home.search("papaya", 5)[0].path, "/work/fruit");
+        assert_eq!(home.search("home7", 5)[0].path, "/home/notes");
+    }
+
+    #[test]
+    fn searching_leaves_vocabulary_and_document_frequencies_alone() {
+        let mut engine = engine_with(&[("/a", "apple banana".to_string()), ("/b", "apple cherry".to_string())]);
+        let size = engine.tokenizer.vocabulary_size();
+        for _ in 0..3 {
+            engine.search("apple durian", 5);
+            engine.search_shared("banana", 5);
+        }
+        engine.apply_quantum_jump("cherry elderberry", 0.5);
+
+        assert_eq!(engine.tokenizer.vocabulary_size(), size);
+        assert_eq!(engine.tokenizer.get_prime("durian"), None);
+        assert_eq!(engine.tokenizer.document_frequency("apple"), 2);
+        assert_eq!(engine.tokenizer.document_frequency("banana"), 1);
+        assert!(matches!(engine.search_outcome("durian", 5), SearchOutcome::NoMatches));
+        assert!(matches!(engine.search_outcome("!!", 5), SearchOutcome::EmptyQuery));
+    }
+
+    #[test]
+    fn reversibility_is_only_recomputed_once_persistence_is_enabled() {
+        let mut engine = engine_with(&[("/a", "apple banana apple".to_string()), ("/b", "apple cherry".to_string())]);
+        assert!(!engine.uses_quantum_score() && !engine.uses_persistence_score());
+
+        engine.search("apple", 5);
+        engine.warm_up();
+        // Nothing was recomputed: still pending, and every document still at its default
+        assert!(engine.relationships_stale);
+        assert!(engine.docs.iter().all(|doc| doc.reversibility == 1.0));
+
+        engine.set_use_persistence_score(true);
+        engine.search("apple", 5);
+        assert!(!engine.relationships_stale);
+        assert!(engine.docs.iter().any(|doc| doc.reversibility != 1.0));
+    }
+
+    #[test]
+    fn pruning_the_vocabulary_refreshes_reversibility() {
+        let mut engine = engine_with(&[("/a", "apple banana apple".to_string()), ("/b", "apple cherry".to_string())]);
+        engine.set_use_persistence_score(true);
+        engine.search("apple", 5);
+        assert!(!engine.relationships_stale);
+
+        assert_eq!(engine.prune_vocabulary(1), 2);
+        assert!(engine.relationships_stale);
+    }
+
+    #[test]
+    fn metrics_and_combinations_are_chosen_by_name_and_change_the_scores() {
+        for metric in SimilarityMetric::ALL {
+            assert_eq!(SimilarityMetric::from_name(&metric.as_str().to_uppercase()), Some(metric));
+        }
+        for combination in ScoreCombination::ALL {
+            assert_eq!(ScoreCombination::from_name(&combination.as_str().to_uppercase()), Some(combination));
+        }
+        assert_eq!(SimilarityMetric::from_name("manhattan"), None);
+        assert_eq!(ScoreCombination::from_name("max"), None);
+
+        let mut engine = engine_with(&[
+            ("/a", "zeppelin over the harbour".into()),
+            ("/b", "zeppelin zeppelin zeppelin over the harbour".into()),
+        ]);
+        let scores = |engine: &mut ResonantEngine| -> HashMap<String, (f64, f64)> {
+            engine.search("zeppelin", 5).into_iter().map(|result| (result.path, (result.resonance, result.combined_score))).collect()
+        };
+        // Cosine counts how often the query word occurs, Jaccard only whether it does
+        engine.set_similarity_metric(SimilarityMetric::from_name("cosine").unwrap());
+        let cosine = scores(&mut engine);
+        assert!(cosine["/b"].0 > cosine["/a"].0);
+        engine.set_similarity_metric(SimilarityMetric::from_name("jaccard").unwrap());
+        let jaccard = scores(&mut engine);
+        assert!((jaccard["/a"].0 - jaccard["/b"].0).abs() < 1e-12, "{jaccard:?}");
+
+        // Reciprocal ranks keep the order of the weighted scores but not their values
+        engine.set_similarity_metric(SimilarityMetric::Cosine);
+        engine.set_score_combination(ScoreCombination::from_name("rrf").unwrap());
+        let fused = scores(&mut engine);
+        assert!(fused["/b"].1 > fused["/a"].1);
+        assert!(fused["/b"].1 != cosine["/b"].1);
+    }
+
+    #[test]
+    fn query_expansion_finds_documents_with_only_a_co_occurring_term() {
+        // "tokio" never appears without "async"
+        let docs = [
+            ("/a", "tokio async runtime"),
+            ("/b", "tokio async tasks"),
+            ("/c", "tokio async channels"),
+            ("/d", "async executors explained"),
+            ("/e", "harbour crane schedule"),
+        ];
+        let matches = |expansion: usize| -> Vec<String> {
+            let mut engine = ResonantEngine::new();
+            engine.set_query_expansion(expansion);
+            for (path, text) in docs {
+                engine.add_local_document(path.to_string(), text.to_string(), PathBuf::from(path));
+            }
+            engine.search("tokio", 5).into_iter().filter(|result| result.resonance > 0.0).map(|result| result.path).collect()
+        };
+
+        let mut plain = matches(0);
+        plain.sort();
+        assert_eq!(plain, ["/a", "/b", "/c"]);
+
+        // Expanded with "async" at reduced weight: the async-only document
+        // turns up, below every document with the query term itself
+        let expanded = matches(1);
+        assert_eq!(expanded.len(), 4, "{expanded:?}");
+        assert_eq!(expanded[3], "/d");
+    }
+
+    #[test]
+    fn queries_without_words_are_empty_queries() {
+        let mut engine = engine_with(&[("/a", "apple banana".to_string())]);
+        for query in ["", "   ", "!! -- ??"] {
+            assert!(matches!(engine.search_outcome(query, 5), SearchOutcome::EmptyQuery), "{query:?}");
+        }
+        // Even before anything is indexed
+        assert!(matches!(ResonantEngine::new().search_outcome("!!", 5), SearchOutcome::EmptyQuery));
+    }
+
+    #[test]
+    fn searching_before_indexing_reports_an_empty_index() {
+        let mut engine = ResonantEngine::new();
+        assert!(matches!(engine.search_outcome("apple", 5), SearchOutcome::EmptyIndex));
+
+        // Removing the last document empties it again
+        engine.add_local_document("/a".into(), "apple banana".into(), PathBuf::from("/a"));
+        assert!(engine.remove_document(Path::new("/a")));
+        assert!(matches!(engine.search_outcome("apple", 5), SearchOutcome::EmptyIndex));
+    }
+
+    #[test]
+    fn words_no_document_shares_are_no_matches() {
+        let mut engine = engine_with(&[("/a", "apple banana".to_string()), ("/b", "apple cherry".to_string())]);
+        assert!(matches!(engine.search_outcome("durian", 5), SearchOutcome::NoMatches));
+        // Still in the vocabulary, but no longer in any document
+        engine.add_local_document("/c".into(), "durian".into(), PathBuf::from("/c"));
+        assert!(engine.remove_document(Path::new("/c")));
+        assert!(matches!(engine.search_outcome("durian", 5), SearchOutcome::NoMatches));
+        assert!(engine.search("durian", 5).is_empty());
+    }
+
+    #[test]
+    fn matching_queries_return_ranked_results() {
+        let mut engine = engine_with(&[("/a", "apple banana apple".to_string()), ("/b", "apple cherry".to_string()), ("/c", "cherry".to_string())]);
+        let SearchOutcome::Ok(results) = engine.search_outcome("apple", 5) else {
+            panic!("expected results");
+        };
+        let paths: Vec<&str> = results.iter().filter(|result| result.resonance > 0.0).map(|result| result.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/b"]);
+        assert!(results.windows(2).all(|pair| pair[0].combined_score >= pair[1].combined_score));
+    }
+
+    #[test]
+    fn compressing_twice_only_compresses_once() {
+        let mut engine = ResonantEngine::new();
+        for i in 0..250 {
+            engine.add_local_document(format!("/docs/{i}"), format!("report number {i} {}", distinct_words("w", 20)), PathBuf::from(format!("/docs/{i}")));
+        }
+        let (tx, mut rx) = mpsc::channel(16);
+        assert_eq!(engine.compress_all_documents(Some(tx)), 250);
+        assert!(engine.docs.iter().all(IndexedDocument::is_compressed));
+        assert!(engine.docs[7].decompress_text().starts_with("report number 7 "));
+        // Every 100 documents, then once at the end
+        let mut updates = Vec::new();
+        while let Ok(progress) = rx.try_recv() {
+            updates.push((progress.processed, progress.total));
+        }
+        // Threads may send out of order
+        updates.sort();
+        assert_eq!(updates, [(100, 250), (200, 250), (250, 250)]);
+
+        let (tx, mut rx) = mpsc::channel(16);
+        assert_eq!(engine.compress_all_documents(Some(tx)), 0);
+        let last = std::iter::from_fn(|| rx.try_recv().ok()).last().unwrap();
+        assert_eq!((last.processed, last.compressed), (250, 0));
+    }
+
+    #[test]
+    fn quantum_jumps_boost_only_documents_over_the_threshold() {
+        let start = 1_700_000_000;
+        let mut engine = ResonantEngine::new();
+        engine.set_clock(FixedClock(start));
+        for (path, text) in [
+            ("/strong", "zeppelin zeppelin harbour".to_string()),
+            ("/weak", format!("zeppelin {}", distinct_words("w", 20))),
+            ("/none", "harbour crane".to_string()),
+        ] {
+            engine.add_local_document(path.into(), text, PathBuf::from(path));
+        }
+        let now = start + 10 * 86_400;
+        engine.set_clock(FixedClock(now));
+
+        let resonance: HashMap<String, f64> = engine.search("zeppelin", 5).into_iter().map(|result| (result.path, result.resonance)).collect();
+        assert!(resonance["/strong"] > resonance["/weak"] && resonance["/weak"] > 0.0);
+        engine.set_quantum_jump_threshold((resonance["/strong"] + resonance["/weak"]) / 2.0);
+        engine.set_quantum_jump_importance(0.5);
+        engine.set_quantum_jump_freshening(0.5);
+
+        let state = |engine: &ResonantEngine, path: &str| {
+            let doc = engine.docs.iter().find(|doc| doc.path == Path::new(path)).unwrap();
+            (doc.reversibility, doc.timestamp)
+        };
+        let before: Vec<(f64, u64)> = ["/strong", "/weak", "/none"].iter().map(|path| state(&engine, path)).collect();
+        engine.apply_default_quantum_jump("zeppelin");
+
+        // Boosted by the configured importance, and half its age forgiven
+        let (reversibility, timestamp) = state(&engine, "/strong");
+        assert!((reversibility - (before[0].0 * 0.9 + 0.1 * resonance["/strong"] * 0.5)).abs() < 1e-12);
+        assert_eq!(timestamp, now - 5 * 86_400);
+        // Below the threshold, or not matching at all: untouched
+        assert_eq!(state(&engine, "/weak"), before[1]);
+        assert_eq!(state(&engine, "/none"), before[2]);
+    }
+
+    #[test]
+    fn snapshot_keeps_the_state_it_was_taken_in() {
+        let mut engine = engine_with(&[("/a", "lantern harbour".to_string()), ("/b", "zeppelin crane".to_string())]);
+        let snapshot = engine.snapshot();
+        let reversibility: Vec<f64> = snapshot.documents.iter().map(|doc| doc.reversibility).collect();
+
+        engine.remove_document(Path::new("/a"));
+        engine.add_local_document("/c".into(), "quarry gravel".to_string(), PathBuf::from("/c"));
+        engine.set_quantum_jump_threshold(0.0);
+        engine.apply_default_quantum_jump("zeppelin");
+
+        let paths = |snapshot: &EngineSnapshot| snapshot.documents.iter().map(|doc| doc.path.to_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(paths(&snapshot), ["/a", "/b"]);
+        assert_eq!(snapshot.documents.iter().map(|doc| doc.reversibility).collect::<Vec<_>>(), reversibility);
+
+        let current = engine.snapshot();
+        assert_eq!(paths(&current), ["/b", "/c"]);
+        assert_ne!(current.documents[0].reversibility, snapshot.documents[1].reversibility);
+    }
+
+    #[test]
+    fn persistence_setters_reject_out_of_range_values() {
+        let mut engine = ResonantEngine::new();
+        let defaults = engine.persistence_params();
+
+        for fragility in [0.0, -0.5, 1.5, f64::NAN] {
+            assert_eq!(engine.set_fragility(fragility).unwrap_err().name, "fragility");
+        }
+        assert!(engine.set_entropy_weight(-1.0).is_err());
+        assert!(engine.set_entropy_weight(f64::INFINITY).is_err());
+        assert!(engine.set_trend_decay(-0.1).is_err());
+        assert!(engine.set_update_frequency(-0.1).is_err());
+        assert!(engine.set_update_frequency(f64::NAN).is_err());
+        assert_eq!(engine.persistence_params(), defaults);
+
+        engine.set_fragility(1.0).unwrap();
+        engine.set_update_frequency(0.0).unwrap();
+        assert_eq!(engine.persistence_params(), PersistenceParams { fragility: 1.0, update_frequency: 0.0, ..defaults });
+    }
+
+    #[test]
+    fn baseline_update_frequency_feeds_the_persistence_score() {
+        let start = 1_700_000_000;
+        let mut engine = ResonantEngine::new();
+        engine.set_clock(FixedClock(start));
+        engine.add_local_document("/a".into(), "lantern harbour".to_string(), PathBuf::from("/a"));
+        let now = start + 3 * 86_400;
+        // As if persistence scoring had been learning for a while
+        engine.docs[0].reversibility = 0.5;
+        engine.docs[0].buffering = 1.0;
+
+        let (frequency, _, low) = engine.base_persistence(&engine.docs[0], now);
+        assert_eq!(frequency, engine.persistence_params().update_frequency);
+        engine.set_update_frequency(5.0).unwrap();
+        let (frequency, _, high) = engine.base_persistence(&engine.docs[0], now);
+        assert_eq!(frequency, 5.0);
+        assert!(high < low);
+    }
+
+    #[test]
+    fn reindexed_documents_resist_entropy_differently() {
+        let start = 1_700_000_000;
+        let mut engine = ResonantEngine::new();
+        engine.set_clock(FixedClock(start));
+        engine.add_local_document("/still".into(), "lantern harbour".to_string(), PathBuf::from("/still"));
+        for _ in 0..10 {
+            engine.add_local_document("/busy".into(), "lantern harbour".to_string(), PathBuf::from("/busy"));
+        }
+        let now = start + 3 * 86_400;
+        for doc in &mut engine.docs {
+            doc.reversibility = 0.5;
+            doc.buffering = 1.0;
+        }
+        let (still, busy) = (&engine.docs[0], &engine.docs[1]);
+        assert_eq!((still.update_count, busy.update_count), (0, 9));
+        assert_eq!(still.timestamp, busy.timestamp);
+
+        let (still_frequency, _, still_score) = engine.base_persistence(still, now);
+        let (busy_frequency, _, busy_score) = engine.base_persistence(busy, now);
+        assert_eq!(still_frequency, engine.persistence_params().update_frequency);
+        assert_eq!(busy_frequency, 3.0);
+        assert_ne!(still_score, busy_score);
+    }
+
+    #[test]
+    fn normalized_scores_put_the_top_result_at_one_and_keep_the_order() {
+        let mut engine = busy_engine();
+        for query in BUSY_QUERIES {
+            let results = engine.search(query, 10);
+            assert!(results.len() > 2);
+            assert_eq!(results[0].normalized_score, 1.0);
+            assert!(results.windows(2).all(|pair| pair[0].normalized_score >= pair[1].normalized_score));
+            assert!(results.iter().all(|result| (0.0..=1.0).contains(&result.normalized_score)));
+        }
+
+        engine.set_score_normalization(ScoreNormalization::Softmax);
+        let results = engine.search("harbour", 10);
+        assert!(results.windows(2).all(|pair| pair[0].normalized_score >= pair[1].normalized_score));
+        assert!((results.iter().map(|result| result.normalized_score).sum::<f64>() - 1.0).abs() < 1e-9);
+
+        engine.set_score_normalization(ScoreNormalization::None);
+        let results = engine.search("harbour", 10);
+        assert!(results.iter().all(|result| result.normalized_score == result.combined_score));
+    }
+
+    #[test]
+    fn synonyms_find_documents_that_only_use_the_equivalent() {
+        let mut engine = engine_with(&[
+            ("/vco", "vco patch cable".to_string()),
+            ("/osc", "oscillator tuning module".to_string()),
+            ("/crane", "harbour crane".to_string()),
+        ]);
+        let paths = |engine: &mut ResonantEngine| -> Vec<String> {
+            engine.search("vco", 5).into_iter().map(|result| result.path).collect()
+        };
+        assert_eq!(paths(&mut engine), ["/vco"]);
+
+        let file = std::env::temp_dir().join(format!("quantum-search-synonyms-{}.txt", std::process::id()));
+        fs::write(&file, "# eurorack\nvco = oscillator, voltage controlled oscillator\n").unwrap();
+        let loaded = engine.load_synonyms(file.to_str().unwrap());
+        let _ = fs::remove_file(&file);
+        assert_eq!(loaded.unwrap(), 1);
+
+        // The literal match still ranks first
+        assert_eq!(paths(&mut engine), ["/vco", "/osc"]);
+    }
+
+    #[test]
+    fn scoring_state_is_only_built_for_the_features_in_use() {
+        let mut engine = engine_with(&[("/a", "lantern harbour".to_string()), ("/b", "harbour crane".to_string())]);
+        engine.add_crawled_document(CrawledDocument {
+            url: "https://example.com/crane".into(),
+            title: "Cranes".into(),
+            text: "crane gantry harbour".into(),
+        });
+        assert_eq!(engine.search("harbour", 5).len(), 3);
+        for doc in &engine.docs {
+            assert!(doc.biorthogonal.get().is_none(), "{} has a biorthogonal vector", doc.path.display());
+            assert!(doc.historical_vectors.is_empty(), "{} has dense vectors", doc.path.display());
+        }
+
+        // Built on first use once a feature is turned on
+        engine.set_use_quantum_score(true);
+        engine.search("harbour", 5);
+        assert!(engine.docs.iter().all(|doc| doc.biorthogonal.get().is_some()));
+        assert!(engine.docs.iter().all(|doc| doc.historical_vectors.is_empty()));
+        engine.set_use_persistence_score(true);
+        engine.search("harbour", 5);
+        assert!(engine.docs.iter().all(|doc| !doc.historical_vectors.is_empty()));
+    }
+
+    #[test]
+    fn autosave_fires_after_the_configured_number_of_additions() {
+        let file = std::env::temp_dir().join(format!("quantum-search-autosave-{}.checkpoint", std::process::id()));
+        let _ = fs::remove_file(&file);
+        let mut engine = ResonantEngine::new();
+        engine.set_autosave_path(Some(file.to_str().unwrap().to_string()));
+        engine.set_autosave_every_n_docs(3);
+
+        let saved_count = || fs::read_to_string(&file).ok().map(|checkpoint| {
+            checkpoint.lines().find_map(|line| line.strip_prefix("# Total documents: ")).unwrap().parse::<usize>().unwrap()
+        });
+        let mut saves = Vec::new();
+        for i in 0..7 {
+            engine.add_local_document(format!("doc {i}"), format!("lantern {}", distinct_words("w", i + 1)), PathBuf::from(format!("/docs/{i}")));
+            saves.push(saved_count());
+        }
+        let _ = fs::remove_file(&file);
+        assert_eq!(saves, [None, None, Some(3), Some(3), Some(3), Some(6), Some(6)]);
+    }
+
+    #[test]
+    fn results_can_be_filtered_to_local_files_or_web_pages() {
+        let mut engine = engine_with(&[("/notes/crane.txt", "harbour crane".to_string()), ("/notes/boat.txt", "harbour ferry".to_string())]);
+        engine.add_crawled_document(CrawledDocument {
+            url: "https://example.com/harbour".into(),
+            title: "Harbour".into(),
+            text: "harbour pilots and tugs".into(),
+        });
+        let found = |engine: &mut ResonantEngine, source: Option<DocumentSource>| -> Vec<(String, DocumentSource)> {
+            let mut found: Vec<_> = engine.search_filtered("harbour", 10, source).into_results()
+                .into_iter().map(|result| (result.path, result.source)).collect();
+            found.sort_by(|a, b| a.0.cmp(&b.0));
+            found
+        };
+
+        assert_eq!(found(&mut engine, Some(DocumentSource::Filesystem)), [
+            ("/notes/boat.txt".to_string(), DocumentSource::Filesystem),
+            ("/notes/crane.txt".to_string(), DocumentSource::Filesystem),
+        ]);
+        assert_eq!(found(&mut engine, Some(DocumentSource::Web)), [("https://example.com/harbour".to_string(), DocumentSource::Web)]);
+        assert_eq!(found(&mut engine, None).len(), 3);
+    }
+
+    #[test]
+    fn scores_only_search_ranks_like_search_without_decompressing() {
+        let mut engine = busy_engine();
+        let ranked = |results: Vec<SearchResult>| -> Vec<(String, f64)> {
+            results.into_iter().map(|result| (result.path, result.combined_score)).collect()
+        };
+        let scores_only: Vec<_> = BUSY_QUERIES.iter().map(|query| engine.search_scores_only(query, 10)).collect();
+        assert!(engine.docs.iter().all(|doc| doc.is_compressed() && doc.decompressed.get().is_none()));
+        assert!(scores_only.iter().flatten().all(|result| result.snippet.is_empty()));
+
+        for (query, scores_only) in BUSY_QUERIES.iter().zip(scores_only) {
+            let full = engine.search(query, 10);
+            assert!(full.iter().all(|result| !result.snippet.is_empty()));
+            assert_eq!(ranked(scores_only), ranked(full));
+        }
+    }
+
+    #[test]
+    fn federated_results_are_attributed_to_the_index_they_came_from() {
+        let mut federated = FederatedSearch::new();
+        federated.add_engine("work", engine_with(&[
+            ("/work/cranes.txt", "harbour crane rota".to_string()),
+            ("/shared/map.txt", "harbour map".to_string()),
+        ]));
+        federated.add_engine("personal", engine_with(&[
+            ("/home/ferry.txt", "harbour ferry tickets".to_string()),
+            ("/shared/map.txt", "harbour map".to_string()),
+            ("/home/recipes.txt", "marzipan recipes".to_string()),
+        ]));
+
+        let results = federated.search("harbour", 10);
+        let found: HashMap<&str, &FederatedResult> = results.iter().map(|hit| (hit.result.path.as_str(), hit)).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!((found["/work/cranes.txt"].index.as_str(), found["/work/cranes.txt"].also_in.len()), ("work", 0));
+        assert_eq!((found["/home/ferry.txt"].index.as_str(), found["/home/ferry.txt"].also_in.len()), ("personal", 0));
+
+        // Returned by both: reported once, credited to both
+        let shared = found["/shared/map.txt"];
+        let mut sources = vec![shared.index.as_str()];
+        sources.extend(shared.also_in.iter().map(String::as_str));
+        sources.sort();
+        assert_eq!(sources, ["personal", "work"]);
+        assert!(shared.fused_score > found["/work/cranes.txt"].fused_score);
+    }
+
+    #[test]
+    fn a_tiny_deadline_truncates_the_scan_and_returns_promptly() {
+        let mut engine = ResonantEngine::new();
+        engine.set_use_quantum_score(true);
+        for i in 0..5000 {
+            engine.add_local_document(format!("doc {i}"), format!("lantern {}", distinct_words(&format!("d{i}x"), 8)), PathBuf::from(format!("/docs/{i}")));
+        }
+        engine.warm_up();
+
+        let start = Instant::now();
+        let timed = engine.search_with_deadline("lantern", 10, Duration::ZERO);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(timed.truncated);
+        assert!(timed.outcome.into_results().len() < 10);
+
+        let timed = engine.search_with_deadline("lantern", 10, Duration::from_secs(600));
+        assert!(!timed.truncated);
+        assert_eq!(timed.outcome.into_results().len(), 10);
+    }
+
+    #[test]
+    fn two_topical_groups_cluster_apart() {
+        let texts: Vec<(String, String)> = (0..3).flat_map(|i| [
+            (format!("/harbour/{i}"), format!("guide harbour crane dock ship {}", distinct_words(&format!("h{i}x"), 2))),
+            (format!("/violin/{i}"), format!("guide violin varnish bow string {}", distinct_words(&format!("v{i}x"), 2))),
+        ]).collect();
+        let docs: Vec<(&str, String)> = texts.iter().map(|(path, text)| (path.as_str(), text.clone())).collect();
+        let mut engine = engine_with(&docs);
+
+        for by in [ClusterBy::Threshold(0.3), ClusterBy::Count(2)] {
+            let results = engine.search("guide", 10);
+            assert_eq!(results.len(), 6);
+            let clusters = engine.cluster_results(results, by);
+            assert_eq!(clusters.len(), 2);
+            for cluster in &clusters {
+                let topic = cluster.members[0].path.split('/').nth(1).unwrap().to_string();
+                assert_eq!(cluster.members.len(), 3);
+                assert!(cluster.members.iter().all(|member| member.path.starts_with(&format!("/{topic}/"))));
+            }
+        }
+    }
+
+    #[test]
+    fn a_reranker_reorders_only_the_returned_candidates() {
+        let texts: Vec<String> = (1..=6).map(|i| format!("lantern {}", distinct_words("w", i * 3))).collect();
+        let docs: Vec<(&str, String)> = ["/1", "/2", "/3", "/4", "/5", "/6"].into_iter().zip(texts).collect();
+        let mut engine = engine_with(&docs);
+        let paths = |results: Vec<SearchResult>| -> Vec<String> { results.into_iter().map(|result| result.path).collect() };
+        let ranked = paths(engine.search("lantern", 3));
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = std::sync::Arc::clone(&seen);
+        engine.set_reranker(Box::new(move |query, candidates| {
+            recorded.lock().unwrap().push((query.to_string(), candidates.len()));
+            candidates.iter().rev().cloned().collect()
+        }));
+        let reranked = paths(engine.search("lantern", 3));
+
+        assert_eq!(reranked, ranked.iter().rev().cloned().collect::<Vec<_>>());
+        assert_eq!(*seen.lock().unwrap(), [("lantern".to_string(), 3)]);
+        engine.clear_reranker();
+        assert_eq!(paths(engine.search("lantern", 3)), ranked);
+    }
+
+    #[test]
+    fn compaction_keeps_the_remaining_results() {
+        let mut engine = busy_engine();
+        engine.set_auto_compact_ratio(None);
+        let removed = ["/docs/5", "/docs/17", "/docs/30"];
+        for path in removed {
+            assert!(engine.remove_document(Path::new(path)));
+        }
+        assert!(!engine.remove_document(Path::new("/docs/5")));
+        let ranked = |engine: &mut ResonantEngine| -> Vec<Vec<(String, f64)>> {
+            BUSY_QUERIES.iter().map(|query| {
+                engine.search(query, 40).into_iter().map(|result| (result.path, result.combined_score)).collect()
+            }).collect()
+        };
+        let before = ranked(&mut engine);
+        assert!(before.iter().flatten().all(|(path, _)| !removed.contains(&path.as_str())));
+
+        assert_eq!(engine.compact(), removed.len());
+        assert_eq!(engine.len(), 37);
+        let after = ranked(&mut engine);
+        assert_eq!(before.len(), after.len());
+        for (before, after) in before.iter().zip(&after) {
+            assert_eq!(before.len(), after.len());
+            for ((path, score), (after_path, after_score)) in before.iter().zip(after) {
+                assert_eq!(path, after_path);
+                assert!((score - after_score).abs() < 1e-9, "{path}: {score} vs {after_score}");
+            }
+        }
+    }
+
+    #[test]
+    fn effective_weights_cover_only_the_enabled_components() {
+        let mut engine = ResonantEngine::new();
+        engine.set_score_weights(ScoreWeights { resonance: 0.6, quantum: 0.3, persistence: 0.1 }).unwrap();
+        let cases = [
+            (false, false, (1.0, 0.0, 0.0)),
+            (true, false, (0.6 / 0.9, 0.3 / 0.9, 0.0)),
+            (false, true, (0.6 / 0.7, 0.0, 0.1 / 0.7)),
+            (true, true, (0.6, 0.3, 0.1)),
+        ];
+        for (quantum, persistence, (resonance_weight, quantum_weight, persistence_weight)) in cases {
+            engine.set_use_quantum_score(quantum);
+            engine.set_use_persistence_score(persistence);
+            let weights = engine.effective_score_weights();
+            let case = format!("quantum {quantum}, persistence {persistence}: {weights:?}");
+            assert!((weights.resonance - resonance_weight).abs() < 1e-12, "{case}");
+            assert!((weights.quantum - quantum_weight).abs() < 1e-12, "{case}");
+            assert!((weights.persistence - persistence_weight).abs() < 1e-12, "{case}");
+            assert!((weights.resonance + weights.quantum + weights.persistence - 1.0).abs() < 1e-12, "{case}");
+        }
+    }
+
+    #[test]
+    fn snippets_strip_control_characters() {
+        let text = "\u{FEFF}  zeppelin\0\0manifest\tdeck\r\n\r\nbell\u{7}here \u{1b}crew\u{200B}list \u{85}end\n".to_string();
+        let mut engine = engine_with(&[("/log", text)]);
+        for field_snippets in [false, true] {
+            engine.set_field_snippets(field_snippets);
+            let results = engine.search("zeppelin", 5);
+            let snippet = results[0].snippet.trim_end_matches("...");
+            assert!(snippet.starts_with("zeppelin manifest deck bell here crew list end"), "{snippet:?}");
+            assert!(!snippet.chars().any(|c| c.is_control() || is_invisible(c)), "{snippet:?}");
+            assert!(!snippet.contains("  ") && snippet.trim() == snippet, "{snippet:?}");
+        }
+    }
+
+    #[test]
+    fn documents_past_the_staleness_threshold_are_flagged() {
+        let day = 86_400;
+        let start = 1_700_000_000;
+        let mut engine = ResonantEngine::new();
+        for (path, days) in [("/old", 0), ("/recent", 80), ("/new", 99)] {
+            engine.set_clock(FixedClock(start + days * day));
+            engine.add_local_document(path.into(), "harbour crane".to_string(), PathBuf::from(path));
+        }
+        engine.set_clock(FixedClock(start + 100 * day));
+        engine.set_stale_after_days(30.0);
+        assert_eq!(engine.stale_count(), 0);
+
+        assert_eq!(engine.age_documents(false), [PathBuf::from("/old")]);
+        assert_eq!(engine.stale_count(), 1);
+        assert_eq!(engine.search("harbour", 5).len(), 3);
+
+        engine.set_clock(FixedClock(start + 115 * day));
+        assert_eq!(engine.age_documents(true), [PathBuf::from("/old"), PathBuf::from("/recent")]);
+        let remaining: Vec<String> = engine.search("harbour", 5).into_iter().map(|result| result.path).collect();
+        assert_eq!(remaining, ["/new"]);
+        assert_eq!(engine.stale_count(), 0);
+    }
+}
@@ -0,0 +1,287 @@
+// src/enhanced_engine.rs - Enhanced engine integration for local filesystem search
+
+use crate::engine::{DocumentSource, ResonantEngine, METADATA_SEPARATOR};
+use crate::filesystem_indexer::{FilesystemIndexer, IndexedFile};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+/// What `ResonantEngine::reconcile` changed to match the indexer
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileReport {
+    /// Indexed files that had no document
+    pub added: Vec<PathBuf>,
+    /// Documents for files the indexer no longer has
+    pub removed: Vec<PathBuf>,
+}
+
+impl ReconcileReport {
+    /// Whether the engine already matched the indexer
+    pub fn in_sync(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl ResonantEngine {
+    /// Add a filesystem document to the quantum index. The extracted content
+    /// comes first so snippets, taken from the start of the text, show what
+    /// is in the file as they do for web pages; the name, path, type and tags
+    /// follow so they stay searchable. Files without content yet
+    /// (metadata-first indexing fills it in later) get the metadata alone.
+    pub fn add_filesystem_document(&mut self, file: &IndexedFile) {
+        let mut metadata = file.display_name.clone();
+        
+        // Add path components as searchable content
+        for component in file.path.components() {
+            if let Some(name) = component.as_os_str().to_str() {
+                metadata.push_str(" ");
+                metadata.push_str(name);
+            }
+        }
+        
+        // Add file type information
+        metadata.push(' ');
+        metadata.push_str(file.file_type.as_str());
+        
+        // Add tags, comments and download URLs from extended attributes
+        for tag in &file.metadata_tags {
+            metadata.push(' ');
+            metadata.push_str(tag);
+        }
+        
+        let content = match file.content_text() {
+            Some(text) if !text.trim().is_empty() => format!("{}{}{}", text, METADATA_SEPARATOR, metadata),
+            _ => metadata,
+        };
+        
+        self.add_local_document(file.display_name.clone(), content, file.path.clone());
+    }
+    
+    /// Handle a newly created file that may be a moved or renamed one.
+    /// If its content matches a recently removed file, both the indexer entry
+    /// and the quantum document are moved in place so learned relevance
+    /// survives the reorganisation. Returns true if a move was detected.
+    pub fn relocate_filesystem_document(&mut self, indexer: &mut FilesystemIndexer, path: &Path) -> bool {
+        match indexer.detect_move(path) {
+            Some(old_path) => self.rename_document(&old_path, path),
+            None => false,
+        }
+    }
+    
+    /// Bulk add filesystem documents with progress reporting
+    pub fn add_filesystem_documents<'a>(&mut self, files: impl Iterator<Item = &'a IndexedFile>, progress_callback: Option<impl Fn(usize)>) {
+        let mut count = 0;
+        
+        for file in files {
+            self.add_filesystem_document(file);
+            count += 1;
+            
+            if let Some(ref callback) = progress_callback {
+                if count % 100 == 0 {
+                    callback(count);
+                }
+            }
+        }
+        
+        if let Some(ref callback) = progress_callback {
+            callback(count);
+        }
+    }
+    
+    /// Add a document for every indexed file that doesn't have one yet, so
+    /// calling it again on the same engine after it was stopped carries on
+    /// from there; documents aren't saved, so a new process starts over.
+    /// Files whose content is extracted (`embedding_ready`) go first; the
+    /// rest get name-only documents until their content arrives. `stop`
+    /// (the indexer's `stop_handle`) is checked and `progress` called with
+    /// the number of documents built and the latest path every
+    /// `check_interval` files. Returns false if the build was stopped.
+    pub fn build_filesystem_documents(
+        &mut self,
+        indexer: &FilesystemIndexer,
+        stop: &AtomicBool,
+        mut progress: impl FnMut(usize, &Path),
+    ) -> bool {
+        let mut pending: Vec<&IndexedFile> = indexer.get_all_files()
+            .filter(|file| !self.has_document(&file.path))
+            .collect();
+        pending.sort_by_key(|file| !file.embedding_ready);
+        
+        let mut built = 0;
+        for batch in pending.chunks(indexer.check_interval()) {
+            if stop.load(AtomicOrdering::Relaxed) {
+                return false;
+            }
+            for file in batch {
+                self.add_filesystem_document(file);
+            }
+            built += batch.len();
+            progress(built, &batch[batch.len() - 1].path);
+        }
+        true
+    }
+    
+    /// Make the filesystem documents match `indexer` again: documents are added
+    /// for indexed files that lack one and removed for files the indexer no
+    /// longer has. Crawled pages are left alone. Returns what was changed.
+    pub fn reconcile(&mut self, indexer: &FilesystemIndexer) -> ReconcileReport {
+        let removed: Vec<PathBuf> = self.document_paths(DocumentSource::Filesystem)
+            .filter(|path| indexer.get_file_by_path(path).is_none())
+            .map(Path::to_path_buf)
+            .collect();
+        for path in &removed {
+            self.remove_document(path);
+        }
+        
+        let mut added = Vec::new();
+        for file in indexer.get_all_files() {
+            if !self.has_document(&file.path) {
+                self.add_filesystem_document(file);
+                added.push(file.path.clone());
+            }
+        }
+        
+        ReconcileReport { added, removed }
+    }
+    
+    /// Search with filesystem-specific optimizations. Ranks a page's worth
+    /// of candidates times the multiplier (see `set_filesystem_page`), filters
+    /// them, and ranks deeper while the filters leave less than a full page
+    /// and there are documents left to rank.
+    pub fn search_filesystem(&mut self, query: &str, file_type_filter: Option<&str>, max_age_days: Option<u64>) -> Vec<crate::engine::SearchResult> {
+        let (page_size, multiplier) = self.filesystem_page();
+        let file_type = file_type_filter.map(str::to_lowercase);
+        let cutoff = max_age_days.map(|max_age| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .saturating_sub(max_age * 24 * 3600)
+        });
+        
+        let mut depth = page_size.saturating_mul(multiplier);
+        loop {
+            // Skip crawled web pages. Snippets wait until the page is settled.
+            let mut results = self.search_filtered_scores_only(query, depth, Some(DocumentSource::Filesystem)).into_results();
+            let exhausted = results.len() < depth || depth >= self.len();
+            
+            // Apply filesystem-specific filters
+            if let Some(file_type) = &file_type {
+                results.retain(|result| {
+                    result.path.to_lowercase().contains(file_type) ||
+                    result.title.to_lowercase().contains(file_type)
+                });
+            }
+            
+            if let Some(cutoff) = cutoff {
+                results.retain(|result| {
+                    // Try to get file modification time
+                    if let Ok(metadata) = std::fs::metadata(&result.path) {
+                        if let Ok(modified) = metadata.modified() {
+                            if let Ok(duration) = modified.duration_since(std::time::UNIX_EPOCH) {
+                                return duration.as_secs() > cutoff;
+                            }
+                        }
+                    }
+                    true // Keep if we can't determine age
+                });
+            }
+            
+            if results.len() >= page_size || exhausted {
+                results.truncate(page_size);
+                self.attach_snippets(query, &mut results);
+                return results;
+            }
+            depth = depth.saturating_mul(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filtered_filesystem_searches_still_fill_a_page() {
+        let mut engine = ResonantEngine::new();
+        for i in 0..60 {
+            engine.add_local_document(format!("trace-{i}.log"), "zeppelin zeppelin".into(), PathBuf::from(format!("/logs/trace-{i}.log")));
+        }
+        for i in 0..12 {
+            let text = format!("zeppelin notes with many other words to dilute it, draft {i}");
+            engine.add_local_document(format!("notes-{i}.txt"), text, PathBuf::from(format!("/notes/notes-{i}.txt")));
+        }
+        assert_eq!(engine.filesystem_page(), (10, 5));
+        // The logs fill the first 50 ranked
+        assert!(engine.search_scores_only("zeppelin", 50).iter().all(|result| result.path.ends_with(".log")));
+
+        let results = engine.search_filesystem("zeppelin", Some("txt"), None);
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(|result| result.path.ends_with(".txt")));
+        assert!(results.iter().all(|result| result.snippet.contains("zeppelin notes")));
+    }
+
+    #[tokio::test]
+    async fn a_stopped_build_carries_on_where_it_stopped() {
+        let root = std::env::temp_dir().join(format!("quantum-search-build-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..6 {
+            std::fs::write(root.join(format!("note-{i}.txt")), format!("zeppelin note {i}")).unwrap();
+        }
+        let mut indexer = FilesystemIndexer::new();
+        indexer.set_check_interval(2);
+        indexer.index_path(&root, None).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        // Stopped after the first batch
+        let mut engine = ResonantEngine::new();
+        let stop = AtomicBool::new(false);
+        let finished = engine.build_filesystem_documents(&indexer, &stop, |_, _| stop.store(true, AtomicOrdering::Relaxed));
+        assert!(!finished);
+        assert_eq!(engine.len(), 2);
+
+        let stop = AtomicBool::new(false);
+        let mut reported = Vec::new();
+        assert!(engine.build_filesystem_documents(&indexer, &stop, |built, _| reported.push(built)));
+        assert_eq!(reported, vec![2, 4]);
+        assert_eq!(engine.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn reconcile_brings_the_engine_back_in_line_with_the_indexer() {
+        let root = std::env::temp_dir().join(format!("quantum-search-reconcile-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..3 {
+            std::fs::write(root.join(format!("note-{i}.txt")), format!("zeppelin note {i}")).unwrap();
+        }
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&root, None).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        // One file has its document, a deleted file still has one, and a
+        // crawled page is not the indexer's business
+        let mut engine = ResonantEngine::new();
+        engine.add_filesystem_document(indexer.get_file_by_path(&root.join("note-0.txt")).unwrap());
+        let gone = root.join("deleted.txt");
+        engine.add_local_document("deleted.txt".into(), "zeppelin".into(), gone.clone());
+        engine.add_crawled_document(crate::crawler::CrawledDocument {
+            url: "https://example.com/zeppelin".into(),
+            title: "Zeppelin".into(),
+            text: "zeppelin history".into(),
+        });
+
+        let report = engine.reconcile(&indexer);
+        assert!(!report.in_sync());
+        let mut added = report.added.clone();
+        added.sort();
+        assert_eq!(added, vec![root.join("note-1.txt"), root.join("note-2.txt")]);
+        assert_eq!(report.removed, vec![gone.clone()]);
+
+        assert!(!engine.has_document(&gone));
+        assert!(indexer.get_all_files().all(|file| engine.has_document(&file.path)));
+        assert!(engine.has_document(Path::new("https://example.com/zeppelin")));
+        assert_eq!(engine.len(), 4);
+        assert!(engine.reconcile(&indexer).in_sync());
+    }
+}
@@ -36,6 +36,32 @@ pub fn shannon_entropy(primes: &[u64]) -> f64 {
     entropy
 }
 
+/// Calculates the Shannon entropy of the overlapping character `n`-grams of
+/// `text`, lowercased with runs of whitespace read as one space. Meaningful
+/// for texts too short for word entropy, such as file names: "main.rs" has
+/// one word-level token but five distinct trigrams. Texts shorter than `n`
+/// characters count as a single n-gram.
+pub fn char_ngram_entropy(text: &str, n: usize) -> f64 {
+    let chars: Vec<char> = text.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ").chars().collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+    let n = n.clamp(1, chars.len());
+
+    let mut counts: HashMap<&[char], usize> = HashMap::new();
+    for ngram in chars.windows(n) {
+        *counts.entry(ngram).or_insert(0) += 1;
+    }
+
+    let total_count = (chars.len() - n + 1) as f64;
+    counts.values()
+        .map(|&count| {
+            let p = count as f64 / total_count;
+            -p * f64::log2(p)
+        })
+        .sum()
+}
+
 /// Apply non-Hermitian decay to a quantum state vector
 pub fn apply_non_hermitian_decay(
     state_vector: &VectorComplex<f64>, 
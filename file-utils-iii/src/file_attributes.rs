@@ -0,0 +1,162 @@
+// src/file_attributes.rs
+//
+// Searchable text kept alongside a file rather than in it: extended
+// attributes on Linux and macOS (tags, comments, download URLs) and the
+// Zone.Identifier stream Windows attaches to downloads. Only read with the
+// `extended-attributes` feature; everywhere else a file simply has no tags.
+
+use std::path::Path;
+
+// Attribute values larger than this are not text worth indexing
+#[cfg(all(feature = "extended-attributes", unix))]
+const MAX_ATTRIBUTE_BYTES: usize = 64 * 1024;
+
+/// The tags, comments and provenance URLs attached to `path`. Attributes
+/// that can't be read, or a filesystem without them, give no tags.
+#[cfg(all(feature = "extended-attributes", unix))]
+pub fn read_tags(path: &Path) -> Vec<String> {
+    let Ok(names) = xattr::list(path) else {
+        return Vec::new();
+    };
+    let mut tags = Vec::new();
+    for name in names {
+        let Some(name) = name.to_str() else { continue };
+        // User attributes (Linux: user.xdg.tags, user.xdg.origin.url, ...) and
+        // Spotlight metadata (macOS: Finder tags and comments, where from)
+        if !name.starts_with("user.") && !name.starts_with("com.apple.metadata:") {
+            continue;
+        }
+        let Ok(Some(value)) = xattr::get(path, name) else { continue };
+        if value.len() > MAX_ATTRIBUTE_BYTES {
+            continue;
+        }
+
+        let values = if value.starts_with(b"bplist00") {
+            bplist_strings(&value)
+        } else {
+            match String::from_utf8(value) {
+                Ok(text) if name == "user.xdg.tags" => text.split(',').map(str::to_string).collect(),
+                Ok(text) => vec![text],
+                Err(_) => continue, // Binary data
+            }
+        };
+        for value in values {
+            // Finder tags carry their colour as "\n<digit>"
+            let value = value.split('\n').next().unwrap_or_default().trim();
+            if !value.is_empty() && !tags.iter().any(|tag| tag == value) {
+                tags.push(value.to_string());
+            }
+        }
+    }
+    tags
+}
+
+/// The URLs a downloaded file came from, recorded by Windows in the file's
+/// Zone.Identifier alternate data stream
+#[cfg(all(feature = "extended-attributes", windows))]
+pub fn read_tags(path: &Path) -> Vec<String> {
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":Zone.Identifier");
+    let Ok(content) = std::fs::read_to_string(&stream) else {
+        return Vec::new();
+    };
+    content.lines()
+        .filter_map(|line| line.strip_prefix("HostUrl=").or_else(|| line.strip_prefix("ReferrerUrl=")))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty() && url != "about:internet")
+        .collect()
+}
+
+#[cfg(not(all(feature = "extended-attributes", any(unix, windows))))]
+pub fn read_tags(_path: &Path) -> Vec<String> {
+    Vec::new()
+}
+
+// Every string object in a binary property list, which is how macOS stores
+// Spotlight attributes. A malformed trailer gives none; objects that are
+// malformed themselves are skipped.
+#[cfg(all(feature = "extended-attributes", unix))]
+fn bplist_strings(data: &[u8]) -> Vec<String> {
+    let read_int = |bytes: &[u8]| bytes.iter().fold(0usize, |n, &b| n.saturating_mul(256).saturating_add(b as usize));
+
+    // The trailer: offset and reference sizes, object count, top object, offset table position
+    let Some(trailer) = data.len().checked_sub(32).and_then(|start| data.get(start..)) else {
+        return Vec::new();
+    };
+    let offset_size = trailer[6] as usize;
+    let object_count = read_int(&trailer[8..16]);
+    let table = read_int(&trailer[24..32]);
+    // Every object takes at least a byte, and the offset table sits before the trailer
+    let table_end = object_count.saturating_mul(offset_size).saturating_add(table);
+    if !(1..=8).contains(&offset_size) || object_count > data.len() || table_end > data.len() - 32 {
+        return Vec::new();
+    }
+
+    let mut strings = Vec::new();
+    for i in 0..object_count {
+        let at = table.saturating_add(i.saturating_mul(offset_size));
+        let Some(entry) = data.get(at..at.saturating_add(offset_size)) else { break };
+        let offset = read_int(entry);
+
+        let Some(&marker) = data.get(offset) else { continue };
+        let (kind, mut len, mut start) = (marker >> 4, (marker & 0xF) as usize, offset + 1);
+        if kind != 0x5 && kind != 0x6 {
+            continue;
+        }
+        if len == 0xF {
+            // The length follows as an integer object of 2^n bytes
+            let Some(&int_marker) = data.get(start) else { continue };
+            let size = 1usize << (int_marker & 0xF).min(3);
+            let Some(bytes) = data.get(start + 1..start + 1 + size) else { continue };
+            len = read_int(bytes);
+            start += 1 + size;
+        }
+
+        let text = if kind == 0x5 {
+            // ASCII
+            data.get(start..start.saturating_add(len)).map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        } else {
+            // UTF-16, big-endian
+            data.get(start..start.saturating_add(len.saturating_mul(2))).map(|bytes| {
+                let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+                String::from_utf16_lossy(&units)
+            })
+        };
+        strings.extend(text);
+    }
+    strings
+}
+
+#[cfg(all(test, feature = "extended-attributes", unix))]
+mod tests {
+    use super::*;
+
+    // "bplist00", one ASCII string object, a one-entry offset table and the trailer
+    fn plist(offset_size: u8, object_count: u64, table: u64) -> Vec<u8> {
+        let mut data = b"bplist00".to_vec();
+        data.push(0x57);
+        data.extend_from_slice(b"Project");
+        data.push(8);
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&[offset_size, 1]);
+        data.extend_from_slice(&object_count.to_be_bytes());
+        data.extend_from_slice(&0u64.to_be_bytes());
+        data.extend_from_slice(&table.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn binary_plist_strings_are_read() {
+        assert_eq!(bplist_strings(&plist(1, 1, 16)), vec!["Project".to_string()]);
+    }
+
+    #[test]
+    fn malformed_binary_plist_trailers_give_nothing() {
+        assert!(bplist_strings(&plist(0, 1, 16)).is_empty());
+        assert!(bplist_strings(&plist(9, 1, 16)).is_empty());
+        assert!(bplist_strings(&plist(1, u64::MAX, 16)).is_empty());
+        assert!(bplist_strings(&plist(1, 1, u64::MAX)).is_empty());
+        assert!(bplist_strings(&plist(1, 1, 17)).is_empty());
+        assert!(bplist_strings(b"bplist00").is_empty());
+    }
+}
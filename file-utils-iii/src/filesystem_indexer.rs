@@ -1,19 +1,26 @@
 // src/filesystem_indexer.rs - Blazing fast filesystem indexing with metadata extraction
 
-use std::collections::{HashMap, BTreeMap};
+use std::collections::{HashMap, HashSet, BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 use std::fs::{self, Metadata};
-use std::io::{self, Read};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc;
-use walkdir::{WalkDir, DirEntry};
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, Semaphore};
+use walkdir::WalkDir;
 use regex::Regex;
 use flate2::write::GzEncoder;
-use flate2::read::GzDecoder;
+use flate2::read::{GzDecoder, MultiGzDecoder};
 use flate2::Compression;
 use serde::{Serialize, Deserialize};
+use crate::tokenizer::NumericTokens;
+use crate::encoding::decode_text;
+use crate::file_attributes;
+#[cfg(feature = "email")]
+use crate::mail_archive::{self, MailMessage};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FileType {
     Text,
     Code,
@@ -27,6 +34,7 @@ pub enum FileType {
     Data,
     Log,
     Markdown,
+    Email,
     Unknown,
 }
 
@@ -68,9 +76,154 @@ impl FileType {
             // Markdown
             "md" | "markdown" | "mdown" | "mkd" => FileType::Markdown,
             
+            // Mail: single messages and mbox folders
+            "eml" | "mbox" | "mbx" => FileType::Email,
+            
             _ => FileType::Unknown,
         }
     }
+
+    /// Stable lowercase keyword for this file type.
+    ///
+    /// This is the vocabulary used when file types are matched against
+    /// queries or folded into searchable text, so it must not change when
+    /// variants are renamed.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Text => "text",
+            FileType::Code => "code",
+            FileType::Document => "document",
+            FileType::Image => "image",
+            FileType::Audio => "audio",
+            FileType::Video => "video",
+            FileType::Archive => "archive",
+            FileType::Binary => "binary",
+            FileType::Config => "config",
+            FileType::Data => "data",
+            FileType::Log => "log",
+            FileType::Markdown => "markdown",
+            FileType::Email => "email",
+            FileType::Unknown => "unknown",
+        }
+    }
+}
+
+impl std::str::FromStr for FileType {
+    type Err = String;
+
+    /// Parse a keyword produced by `FileType::as_str` (case-insensitive)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(FileType::Text),
+            "code" => Ok(FileType::Code),
+            "document" => Ok(FileType::Document),
+            "image" => Ok(FileType::Image),
+            "audio" => Ok(FileType::Audio),
+            "video" => Ok(FileType::Video),
+            "archive" => Ok(FileType::Archive),
+            "binary" => Ok(FileType::Binary),
+            "config" => Ok(FileType::Config),
+            "data" => Ok(FileType::Data),
+            "log" => Ok(FileType::Log),
+            "markdown" => Ok(FileType::Markdown),
+            "email" => Ok(FileType::Email),
+            "unknown" => Ok(FileType::Unknown),
+            other => Err(format!("Unknown file type: {}", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Single-file compression formats whose contents can be indexed as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextCompression {
+    Gzip,
+    #[cfg(feature = "compressed-text")]
+    Bzip2,
+    #[cfg(feature = "compressed-text")]
+    Xz,
+}
+
+impl TextCompression {
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "gz" => Some(TextCompression::Gzip),
+            #[cfg(feature = "compressed-text")]
+            "bz2" => Some(TextCompression::Bzip2),
+            #[cfg(feature = "compressed-text")]
+            "xz" => Some(TextCompression::Xz),
+            _ => None,
+        }
+    }
+    
+    fn decoder(self, file: fs::File) -> Box<dyn Read> {
+        match self {
+            // Multi-member aware: rotated logs are often concatenated gzip streams
+            TextCompression::Gzip => Box::new(MultiGzDecoder::new(file)),
+            #[cfg(feature = "compressed-text")]
+            TextCompression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+            #[cfg(feature = "compressed-text")]
+            TextCompression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(file)),
+        }
+    }
+}
+
+/// Algorithm used to fingerprint file contents for change detection and dedup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    /// 128-bit XXH3 - fast and stable across platforms and Rust versions
+    Xxh3_128,
+    /// 256-bit BLAKE3 - cryptographic strength for large corpora
+    Blake3,
+    /// 64-bit SipHash via `DefaultHasher`, only found in indexes written
+    /// before the algorithm was recorded. Not stable across Rust versions.
+    LegacySip64,
+}
+
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        HashAlgorithm::Xxh3_128
+    }
+}
+
+impl HashAlgorithm {
+    pub fn hash(&self, content: &[u8]) -> ContentHash {
+        let bytes = match self {
+            HashAlgorithm::Xxh3_128 => xxhash_rust::xxh3::xxh3_128(content).to_le_bytes().to_vec(),
+            HashAlgorithm::Blake3 => blake3::hash(content).as_bytes().to_vec(),
+            HashAlgorithm::LegacySip64 => {
+                use std::collections::hash_map::DefaultHasher;
+                use std::hash::Hasher;
+
+                // As `str::hash` did for the old indexes: the bytes, then a 0xff terminator
+                let mut hasher = DefaultHasher::new();
+                hasher.write(content);
+                hasher.write_u8(0xff);
+                hasher.finish().to_le_bytes().to_vec()
+            }
+        };
+
+        ContentHash { algorithm: *self, bytes }
+    }
+}
+
+/// A content fingerprint tagged with the algorithm that produced it.
+/// Hashes from different algorithms never compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContentHash {
+    pub algorithm: HashAlgorithm,
+    pub bytes: Vec<u8>,
+}
+
+impl ContentHash {
+    pub fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,7 +234,7 @@ pub struct IndexedFile {
     pub size: u64,
     pub modified: u64,
     pub created: u64,
-    pub content_hash: Option<u64>,
+    pub content_hash: Option<ContentHash>,
     pub text_content: Option<String>,
     pub compressed_content: Option<Vec<u8>>,
     pub metadata_tags: Vec<String>,
@@ -100,17 +253,27 @@ impl IndexedFile {
             .unwrap_or("");
             
         let file_type = FileType::from_extension(extension);
+        // Maildir messages have no extension of their own. Neither do mail
+        // clients' mbox folders, but telling them apart means reading them,
+        // which waits for content extraction.
+        #[cfg(feature = "email")]
+        let file_type = match file_type {
+            FileType::Unknown if mail_archive::is_maildir_message(&path) => FileType::Email,
+            file_type => file_type,
+        };
         
         let modified = metadata.modified()
-            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok())
             .map(|d| d.as_secs())
             .unwrap_or(0);
             
         let created = metadata.created()
-            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok())
             .map(|d| d.as_secs())
             .unwrap_or(modified);
         
+        let metadata_tags = file_attributes::read_tags(&path);
+        
         Self {
             path,
             display_name,
@@ -121,26 +284,50 @@ impl IndexedFile {
             content_hash: None,
             text_content: None,
             compressed_content: None,
-            metadata_tags: Vec::new(),
+            metadata_tags,
             embedding_ready: false,
         }
     }
     
-    /// Extract text content from the file based on its type
-    pub fn extract_text_content(&mut self) -> io::Result<()> {
-        match self.file_type {
+    /// Extract text content from the file based on its type. With `read_compressed`,
+    /// single-file compressed text (`access.log.gz`) is decompressed and handled
+    /// as the type of the file inside. Text that isn't UTF-8 is transcoded, from
+    /// the detected encoding with `detect_encoding` and from Windows-1252 without.
+    pub fn extract_text_content(
+        &mut self,
+        hash_algorithm: HashAlgorithm,
+        numeric_tokens: NumericTokens,
+        read_compressed: bool,
+        detect_encoding: bool,
+    ) -> io::Result<()> {
+        let (file_type, compression) = match self.compressed_inner_type().filter(|_| read_compressed) {
+            Some((compression, inner_type)) => (inner_type, Some(compression)),
+            None => (self.file_type.clone(), None),
+        };
+        
+        match file_type {
             FileType::Text | FileType::Code | FileType::Markdown | FileType::Config => {
-                self.extract_plain_text()?;
+                self.extract_plain_text(hash_algorithm, compression, detect_encoding)?;
             },
             FileType::Document => {
-                self.extract_document_text()?;
+                self.extract_document_text(numeric_tokens)?;
             },
             FileType::Log => {
-                self.extract_log_content()?;
+                self.extract_log_content(compression, detect_encoding)?;
+            },
+            #[cfg(feature = "email")]
+            FileType::Email if !mail_archive::is_mbox(&self.path) => {
+                self.extract_email_text(hash_algorithm)?;
+            },
+            // An mbox's messages are read when it is tracked, see
+            // `FilesystemIndexer::track_indexed`
+            #[cfg(feature = "email")]
+            FileType::Unknown if mail_archive::starts_like_mbox(&self.path) => {
+                self.file_type = FileType::Email;
             },
             _ => {
                 // For other file types, try to extract filename and path keywords
-                self.extract_metadata_content();
+                self.extract_metadata_content(numeric_tokens);
             }
         }
         
@@ -151,45 +338,126 @@ impl IndexedFile {
             }
         }
         
+        self.embedding_ready = true;
         Ok(())
     }
     
-    fn extract_plain_text(&mut self) -> io::Result<()> {
+    // The compression and type of the file inside a single-file compressed
+    // wrapper, going by the stem; `None` for everything else, tarballs included
+    fn compressed_inner_type(&self) -> Option<(TextCompression, FileType)> {
+        let compression = TextCompression::from_extension(self.path.extension()?.to_str()?)?;
+        let stem = Path::new(self.path.file_stem()?);
+        match FileType::from_extension(stem.extension()?.to_str()?) {
+            FileType::Archive | FileType::Unknown => None,
+            inner_type => Some((compression, inner_type)),
+        }
+    }
+    
+    // Read the file as text, decompressing it first if `compression` is set.
+    // `None` if the text is over `limit` bytes; for compressed files the limit
+    // applies to the decompressed size, so a small file can't expand without bound.
+    // Non-UTF-8 text is transcoded (see `decode_text`) instead of failing.
+    fn read_text(&self, compression: Option<TextCompression>, limit: u64, detect_encoding: bool) -> io::Result<Option<String>> {
+        let bytes = match compression {
+            None => {
+                if self.size > limit {
+                    return Ok(None);
+                }
+                fs::read(&self.path)?
+            }
+            Some(compression) => {
+                let mut bytes = Vec::new();
+                compression.decoder(fs::File::open(&self.path)?)
+                    .take(limit + 1)
+                    .read_to_end(&mut bytes)?;
+                if bytes.len() as u64 > limit {
+                    return Ok(None);
+                }
+                bytes
+            }
+        };
+        Ok(Some(decode_text(&bytes, None, detect_encoding)))
+    }
+    
+    fn extract_plain_text(&mut self, hash_algorithm: HashAlgorithm, compression: Option<TextCompression>, detect_encoding: bool) -> io::Result<()> {
         // Limit file size to avoid memory issues
-        if self.size > 10_000_000 { // 10MB limit
+        let Some(content) = self.read_text(compression, 10_000_000, detect_encoding)? else { // 10MB limit
             self.text_content = Some(format!("Large file: {} ({} bytes)", 
                                             self.display_name, self.size));
             return Ok(());
-        }
-        
-        let content = fs::read_to_string(&self.path)?;
+        };
         
         // Clean and normalize the content
         let cleaned = self.clean_text_content(&content);
         self.text_content = Some(cleaned);
         
         // Generate content hash for change detection
-        self.content_hash = Some(self.calculate_content_hash(&content));
+        self.content_hash = Some(hash_algorithm.hash(content.as_bytes()));
         
         Ok(())
     }
     
-    fn extract_document_text(&mut self) -> io::Result<()> {
+    fn extract_document_text(&mut self, numeric_tokens: NumericTokens) -> io::Result<()> {
         // For now, just use filename and metadata
         // TODO: Integrate with document parsing libraries
-        self.extract_metadata_content();
+        self.extract_metadata_content(numeric_tokens);
+        Ok(())
+    }
+    
+    // A single message (.eml or maildir): subject as the name, body as the
+    // content, sender, recipients and date as tags. Mailboxes are indexed
+    // message by message instead, see `FilesystemIndexer::track_mailbox`.
+    #[cfg(feature = "email")]
+    fn extract_email_text(&mut self, hash_algorithm: HashAlgorithm) -> io::Result<()> {
+        let raw = fs::read(&self.path)?;
+        let message = mail_archive::parse_message(&raw)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        self.apply_message(message, &raw, hash_algorithm);
         Ok(())
     }
     
-    fn extract_log_content(&mut self) -> io::Result<()> {
+    #[cfg(feature = "email")]
+    fn apply_message(&mut self, message: MailMessage, raw: &[u8], hash_algorithm: HashAlgorithm) {
+        if !message.subject.is_empty() {
+            self.display_name = message.subject;
+        }
+        self.metadata_tags.extend(message.tags);
+        self.text_content = Some(self.clean_text_content(&message.body));
+        self.content_hash = Some(hash_algorithm.hash(raw));
+    }
+    
+    // The entry for the `number`th message of `mailbox`, content included,
+    // under its virtual path (see `mail_archive::message_path`)
+    #[cfg(feature = "email")]
+    fn from_message(mailbox: &IndexedFile, number: usize, message: MailMessage, raw: &[u8], hash_algorithm: HashAlgorithm) -> Self {
+        let received = message.date.unwrap_or(mailbox.modified);
+        let mut file = Self {
+            path: mail_archive::message_path(&mailbox.path, number),
+            display_name: "(no subject)".to_string(),
+            file_type: FileType::Email,
+            size: raw.len() as u64,
+            modified: received,
+            created: received,
+            content_hash: None,
+            text_content: None,
+            compressed_content: None,
+            metadata_tags: mailbox.metadata_tags.clone(),
+            embedding_ready: true,
+        };
+        file.apply_message(message, raw, hash_algorithm);
+        if file.text_content.as_ref().is_some_and(|content| content.len() > 1024) {
+            file.compress_content();
+        }
+        file
+    }
+    
+    fn extract_log_content(&mut self, compression: Option<TextCompression>, detect_encoding: bool) -> io::Result<()> {
         // For log files, extract last N lines and key patterns
-        if self.size > 1_000_000 { // 1MB limit for logs
+        let Some(content) = self.read_text(compression, 1_000_000, detect_encoding)? else { // 1MB limit for logs
             self.text_content = Some(format!("Large log file: {} ({} bytes)", 
                                             self.display_name, self.size));
             return Ok(());
-        }
-        
-        let content = fs::read_to_string(&self.path)?;
+        };
         
         // Extract error patterns, timestamps, and key information
         let log_summary = self.extract_log_patterns(&content);
@@ -198,7 +466,7 @@ impl IndexedFile {
         Ok(())
     }
     
-    fn extract_metadata_content(&mut self) {
+    fn extract_metadata_content(&mut self, numeric_tokens: NumericTokens) {
         let mut content = Vec::new();
         
         // Add filename without extension
@@ -212,10 +480,10 @@ impl IndexedFile {
         }
         
         // Add file type
-        content.push(format!("{:?}", self.file_type));
+        content.push(self.file_type.as_str().to_string());
         
         // Split camelCase and snake_case filenames
-        let filename_words = self.extract_filename_words(&self.display_name);
+        let filename_words = self.extract_filename_words(&self.display_name, numeric_tokens);
         content.extend(filename_words);
         
         self.text_content = Some(content.join(" "));
@@ -256,7 +524,7 @@ impl IndexedFile {
         patterns.join(" ")
     }
     
-    fn extract_filename_words(&self, filename: &str) -> Vec<String> {
+    fn extract_filename_words(&self, filename: &str, numeric_tokens: NumericTokens) -> Vec<String> {
         let mut words = Vec::new();
         
         // Split on common separators
@@ -268,24 +536,46 @@ impl IndexedFile {
         let camel_split = camel_re.replace_all(filename, "$1 $2");
         words.extend(camel_split.split_whitespace().map(|s| s.to_string()));
         
-        // Filter out empty and very short words
-        words.into_iter()
-            .filter(|w| w.len() > 1)
-            .collect()
+        // Filter out empty and very short words; numbers are up to the policy
+        let mut words: Vec<String> = words.into_iter()
+            .filter(|w| if NumericTokens::is_number(w) { numeric_tokens.keeps(w) } else { w.len() > 1 })
+            .collect();
+        words.extend(numeric_tokens.versions(filename));
+        words
     }
     
+    // Store the content gzipped, unless the compressed copy doesn't read
+    // back to it, in which case it stays uncompressed
     fn compress_content(&mut self) {
         if let Some(ref content) = self.text_content {
             let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
             if encoder.write_all(content.as_bytes()).is_ok() {
                 if let Ok(compressed) = encoder.finish() {
-                    self.compressed_content = Some(compressed);
-                    self.text_content = None; // Clear uncompressed content
+                    let mut read_back = String::new();
+                    let intact = GzDecoder::new(&compressed[..]).read_to_string(&mut read_back).is_ok()
+                        && read_back == *content;
+                    if intact {
+                        self.compressed_content = Some(compressed);
+                        self.text_content = None; // Clear uncompressed content
+                    }
                 }
             }
         }
     }
     
+    /// Extracted content, decompressed if needed; `None` until content has been extracted
+    pub fn content_text(&self) -> Option<String> {
+        match (&self.text_content, &self.compressed_content) {
+            (Some(text), _) => Some(text.clone()),
+            (None, Some(compressed)) => {
+                let mut text = String::new();
+                GzDecoder::new(&compressed[..]).read_to_string(&mut text).ok()?;
+                Some(text)
+            },
+            (None, None) => None,
+        }
+    }
+    
     pub fn get_text_content(&mut self) -> String {
         if let Some(ref content) = self.text_content {
             content.clone()
@@ -302,14 +592,106 @@ impl IndexedFile {
             self.display_name.clone()
         }
     }
-    
-    fn calculate_content_hash(&self, content: &str) -> u64 {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        
-        let mut hasher = DefaultHasher::new();
-        content.hash(&mut hasher);
-        hasher.finish()
+}
+
+/// Layout of `IndexedFile` before content hashes recorded their algorithm.
+/// Only used to read old index files.
+#[derive(Deserialize)]
+struct LegacyIndexedFile {
+    path: PathBuf,
+    display_name: String,
+    file_type: FileType,
+    size: u64,
+    modified: u64,
+    created: u64,
+    content_hash: Option<u64>,
+    text_content: Option<String>,
+    compressed_content: Option<Vec<u8>>,
+    metadata_tags: Vec<String>,
+    embedding_ready: bool,
+}
+
+impl From<LegacyIndexedFile> for IndexedFile {
+    fn from(legacy: LegacyIndexedFile) -> Self {
+        Self {
+            path: legacy.path,
+            display_name: legacy.display_name,
+            file_type: legacy.file_type,
+            size: legacy.size,
+            modified: legacy.modified,
+            created: legacy.created,
+            content_hash: legacy.content_hash.map(|h| ContentHash {
+                algorithm: HashAlgorithm::LegacySip64,
+                bytes: h.to_le_bytes().to_vec(),
+            }),
+            text_content: legacy.text_content,
+            compressed_content: legacy.compressed_content,
+            metadata_tags: legacy.metadata_tags,
+            embedding_ready: legacy.embedding_ready,
+        }
+    }
+}
+
+/// Magic prefix identifying a versioned index file
+const INDEX_MAGIC: &[u8; 6] = b"QRSIDX";
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct IndexHeader {
+    version: u32,
+    hash_algorithm: HashAlgorithm,
+}
+
+/// Serializable summary of the index, for reporting and monitoring
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStats {
+    pub file_count: usize,
+    pub total_size: u64,
+    pub file_types: BTreeMap<String, usize>,
+}
+
+/// How `get_similar_files` decides what counts as similar
+#[derive(Debug, Clone)]
+pub struct SimilarityOptions {
+    /// Maximum size difference in bytes
+    pub size_tolerance: u64,
+    /// When set, also compare extracted text and drop files below this word overlap (0.0-1.0)
+    pub min_content_similarity: Option<f64>,
+    /// Keep only the best matches
+    pub max_results: Option<usize>,
+}
+
+impl Default for SimilarityOptions {
+    fn default() -> Self {
+        Self {
+            size_tolerance: 1024 * 1024, // Within 1MB size
+            min_content_similarity: None,
+            max_results: Some(50),
+        }
+    }
+}
+
+/// IO policy for roots on network shares, where a stat or read can hang
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkOptions {
+    /// Give up on a single filesystem call after this long
+    pub io_timeout: Duration,
+    /// Extra attempts for calls that timed out or failed transiently
+    pub retries: u32,
+    /// Delay before the first retry, growing linearly with each attempt
+    pub retry_backoff: Duration,
+    /// Maximum filesystem calls in flight against the share, including hung ones
+    pub max_concurrent: usize,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            io_timeout: Duration::from_secs(30),
+            retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            max_concurrent: 4,
+        }
     }
 }
 
@@ -318,6 +700,143 @@ pub struct IndexProgress {
     pub files_indexed: usize,
     pub dirs_scanned: usize,
     pub current_path: String,
+    /// Network calls that were retried
+    pub io_retries: usize,
+    /// Network files and directories skipped because they stopped responding
+    pub unreachable: usize,
+}
+
+// Counters shared between the walk and the progress reporter task
+#[derive(Default)]
+struct ProgressCounters {
+    files_indexed: AtomicUsize,
+    dirs_scanned: AtomicUsize,
+    current_path: Mutex<String>,
+    io_retries: AtomicUsize,
+    unreachable: AtomicUsize,
+}
+
+impl ProgressCounters {
+    fn snapshot(&self) -> IndexProgress {
+        IndexProgress {
+            files_indexed: self.files_indexed.load(Ordering::Relaxed),
+            dirs_scanned: self.dirs_scanned.load(Ordering::Relaxed),
+            current_path: self.current_path.lock().unwrap().clone(),
+            io_retries: self.io_retries.load(Ordering::Relaxed),
+            unreachable: self.unreachable.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Content extraction running in the background for files indexed
+/// metadata-first. Results are picked up with `FilesystemIndexer::apply_content_fill`.
+pub struct ContentFill {
+    total: usize,
+    extracted: Arc<AtomicUsize>,
+    // Each file with the error its content couldn't be read with, if any
+    results: std::sync::mpsc::Receiver<(IndexedFile, Option<io::Error>)>,
+}
+
+impl ContentFill {
+    /// Files whose content has been extracted so far
+    pub fn extracted(&self) -> usize {
+        self.extracted.load(Ordering::Relaxed)
+    }
+    
+    /// Files that were waiting for content when the fill started
+    pub fn total(&self) -> usize {
+        self.total
+    }
+    
+    pub fn is_finished(&self) -> bool {
+        self.extracted() >= self.total
+    }
+}
+
+/// What the indexer was doing when a file or directory failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexStage {
+    /// Listing a directory
+    Walk,
+    /// Reading a file's metadata
+    Metadata,
+    /// Reading a file's content; the file is indexed by name only
+    Extract,
+    /// A network share stopped answering
+    Unreachable,
+}
+
+impl IndexStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexStage::Walk => "walk",
+            IndexStage::Metadata => "metadata",
+            IndexStage::Extract => "extract",
+            IndexStage::Unreachable => "unreachable",
+        }
+    }
+}
+
+/// A file or directory the indexer couldn't read, see `FilesystemIndexer::index_errors`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexError {
+    pub path: PathBuf,
+    pub stage: IndexStage,
+    /// The `io::ErrorKind`, e.g. `PermissionDenied`
+    pub kind: String,
+    pub message: String,
+}
+
+/// File format for `FilesystemIndexer::write_error_report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// An array of objects with `path`, `stage`, `kind` and `message`
+    Json,
+    /// The same fields as columns, with a header line
+    Csv,
+}
+
+impl ReportFormat {
+    /// Pick the format from a file name: `.csv`, anything else JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("csv") => ReportFormat::Csv,
+            _ => ReportFormat::Json,
+        }
+    }
+}
+
+/// Every tunable of a `FilesystemIndexer`, for keeping in a config file; see
+/// `FilesystemIndexer::settings` and `FilesystemIndexer::apply_settings`.
+/// Patterns are regexes in source form. Missing fields take their defaults
+/// when deserializing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexerSettings {
+    pub excluded_patterns: Vec<String>,
+    pub max_file_size: u64,
+    pub hash_algorithm: HashAlgorithm,
+    pub index_hidden: bool,
+    pub hidden_allowlist: Vec<String>,
+    pub metadata_first: bool,
+    pub numeric_tokens: NumericTokens,
+    pub read_compressed: bool,
+    pub detect_encoding: bool,
+    pub extract_retries: u32,
+    pub extract_retry_backoff: Duration,
+    pub move_detection_window: Duration,
+    pub progress_interval: Duration,
+    pub check_interval: usize,
+    pub max_depth: usize,
+    pub network_roots: Vec<PathBuf>,
+    pub network_options: NetworkOptions,
+}
+
+impl Default for IndexerSettings {
+    fn default() -> Self {
+        FilesystemIndexer::new().settings()
+    }
 }
 
 pub struct FilesystemIndexer {
@@ -326,6 +845,50 @@ pub struct FilesystemIndexer {
     total_size: u64,
     excluded_patterns: Vec<Regex>,
     max_file_size: u64,
+    hash_algorithm: HashAlgorithm,
+    // Removed files kept briefly so a delete+create pair can be recognised as a move
+    recently_removed: Vec<(Instant, IndexedFile)>,
+    move_detection_window: Duration,
+    progress_interval: Duration,
+    // Set to interrupt `index_path`; checked before every directory entry
+    stop: Arc<AtomicBool>,
+    // Entries visited between progress path updates and yields to the reporter
+    check_interval: usize,
+    // Directory levels below a root that are walked, see `set_max_depth`
+    max_depth: usize,
+    // Directories with contents left out because they are at `max_depth`
+    depth_truncated: BTreeSet<PathBuf>,
+    network_roots: Vec<PathBuf>,
+    network_options: NetworkOptions,
+    index_hidden: bool,
+    // Hidden file names indexed even when hidden files are skipped
+    hidden_allowlist: Vec<Regex>,
+    // Record only metadata while walking; content is filled in by `start_content_fill`
+    metadata_first: bool,
+    numeric_tokens: NumericTokens,
+    // Decompress `.gz` (and `.bz2`/`.xz` with the `compressed-text` feature) text files
+    read_compressed: bool,
+    // Guess the encoding of text that isn't UTF-8 rather than assume Windows-1252
+    detect_encoding: bool,
+    // Extra content reads for transient failures, and the delay before the first
+    extract_retries: u32,
+    extract_retry_backoff: Duration,
+    // Files whose content couldn't be read, for `retry_failed_extractions`
+    extraction_failures: HashSet<PathBuf>,
+    // The latest error for each path and stage, for `write_error_report`
+    index_errors: BTreeMap<(PathBuf, IndexStage), IndexError>,
+}
+
+// What content extraction needs from the indexer, copied so it can run
+// without borrowing it
+#[derive(Clone, Copy)]
+struct ExtractOptions {
+    hash_algorithm: HashAlgorithm,
+    numeric_tokens: NumericTokens,
+    read_compressed: bool,
+    detect_encoding: bool,
+    retries: u32,
+    retry_backoff: Duration,
 }
 
 impl FilesystemIndexer {
@@ -338,9 +901,221 @@ impl FilesystemIndexer {
             total_size: 0,
             excluded_patterns,
             max_file_size: 100_000_000, // 100MB default limit
+            hash_algorithm: HashAlgorithm::default(),
+            recently_removed: Vec::new(),
+            move_detection_window: Duration::from_secs(5),
+            progress_interval: Duration::from_millis(250),
+            stop: Arc::new(AtomicBool::new(false)),
+            check_interval: 256,
+            max_depth: 20,
+            depth_truncated: BTreeSet::new(),
+            network_roots: Vec::new(),
+            network_options: NetworkOptions::default(),
+            index_hidden: false,
+            hidden_allowlist: Vec::new(),
+            metadata_first: false,
+            numeric_tokens: NumericTokens::default(),
+            read_compressed: true,
+            detect_encoding: true,
+            extract_retries: 3,
+            extract_retry_backoff: Duration::from_millis(200),
+            extraction_failures: HashSet::new(),
+            index_errors: BTreeMap::new(),
+        }
+    }
+    
+    /// The indexer's current tunables
+    pub fn settings(&self) -> IndexerSettings {
+        IndexerSettings {
+            excluded_patterns: self.excluded_patterns.iter().map(|re| re.as_str().to_string()).collect(),
+            max_file_size: self.max_file_size,
+            hash_algorithm: self.hash_algorithm,
+            index_hidden: self.index_hidden,
+            hidden_allowlist: self.hidden_allowlist.iter().map(|re| re.as_str().to_string()).collect(),
+            metadata_first: self.metadata_first,
+            numeric_tokens: self.numeric_tokens,
+            read_compressed: self.read_compressed,
+            detect_encoding: self.detect_encoding,
+            extract_retries: self.extract_retries,
+            extract_retry_backoff: self.extract_retry_backoff,
+            move_detection_window: self.move_detection_window,
+            progress_interval: self.progress_interval,
+            check_interval: self.check_interval,
+            max_depth: self.max_depth,
+            network_roots: self.network_roots.clone(),
+            network_options: self.network_options.clone(),
+        }
+    }
+    
+    /// Apply tunables saved with `settings`. Nothing changes if a pattern
+    /// doesn't compile.
+    pub fn apply_settings(&mut self, settings: &IndexerSettings) -> Result<(), regex::Error> {
+        let compile = |patterns: &[String]| patterns.iter().map(|p| Regex::new(p)).collect::<Result<Vec<_>, _>>();
+        let excluded_patterns = compile(&settings.excluded_patterns)?;
+        let hidden_allowlist = compile(&settings.hidden_allowlist)?;
+        
+        self.excluded_patterns = excluded_patterns;
+        self.hidden_allowlist = hidden_allowlist;
+        self.max_file_size = settings.max_file_size;
+        self.set_hash_algorithm(settings.hash_algorithm);
+        self.set_index_hidden(settings.index_hidden);
+        self.set_metadata_first(settings.metadata_first);
+        self.set_numeric_tokens(settings.numeric_tokens);
+        self.set_read_compressed(settings.read_compressed);
+        self.set_detect_encoding(settings.detect_encoding);
+        self.set_extract_retries(settings.extract_retries, settings.extract_retry_backoff);
+        self.set_move_detection_window(settings.move_detection_window);
+        self.set_progress_interval(settings.progress_interval);
+        self.set_check_interval(settings.check_interval);
+        self.set_max_depth(settings.max_depth);
+        self.network_roots.clear();
+        for root in &settings.network_roots {
+            self.add_network_root(root.clone());
+        }
+        self.set_network_options(settings.network_options.clone());
+        Ok(())
+    }
+    
+    /// How often `index_path` reports progress
+    pub fn set_progress_interval(&mut self, interval: Duration) {
+        self.progress_interval = interval.max(Duration::from_millis(1));
+    }
+    
+    /// A flag that interrupts `index_path` when set, e.g. from a Ctrl-C
+    /// handler. It is checked before every directory entry, so even a scan
+    /// inside one directory of hundreds of thousands of files stops promptly.
+    /// The flag stays set until cleared.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+    
+    /// How many directory entries a walk visits between updates of the
+    /// reported current path. Each update also lets the progress reporter run,
+    /// so large directories report progress while they are being read.
+    pub fn set_check_interval(&mut self, entries: usize) {
+        self.check_interval = entries.max(1);
+    }
+    
+    pub fn check_interval(&self) -> usize {
+        self.check_interval
+    }
+    
+    /// How many directory levels below a root `index_path` descends (20 by
+    /// default, 0 counts as 1). Directories at the limit are listed but their
+    /// contents aren't indexed; see `depth_truncated` for which ones that was.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth.max(1);
+    }
+    
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    
+    /// Directories whose contents were left out of the index by the last
+    /// walk of their root because they are at the depth limit. On network
+    /// shares these aren't listed to check, so empty ones are included.
+    pub fn depth_truncated(&self) -> impl Iterator<Item = &Path> {
+        self.depth_truncated.iter().map(PathBuf::as_path)
+    }
+    
+    /// Treat `root` and everything below it as a network share, indexed with
+    /// timeouts, retries and limited concurrency
+    pub fn add_network_root(&mut self, root: PathBuf) {
+        if !self.network_roots.contains(&root) {
+            self.network_roots.push(root);
+        }
+    }
+    
+    pub fn set_network_options(&mut self, options: NetworkOptions) {
+        self.network_options = options;
+    }
+    
+    pub fn network_options(&self) -> &NetworkOptions {
+        &self.network_options
+    }
+    
+    /// Check that a (possibly network) directory answers within `timeout`.
+    /// The probe runs on its own thread so a hung mount cannot block the caller.
+    pub fn check_network_path(path: &Path, timeout: Duration) -> io::Result<()> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let probe_path = path.to_path_buf();
+        std::thread::spawn(move || {
+            let _ = tx.send(fs::metadata(&probe_path));
+        });
+        
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(metadata)) if metadata.is_dir() => Ok(()),
+            Ok(Ok(_)) => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a directory")),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "path did not respond")),
+        }
+    }
+    
+    /// Index only file metadata while walking, so name search is available as
+    /// soon as the walk finishes. Content is extracted afterwards with
+    /// `start_content_fill`; `IndexedFile::embedding_ready` marks files that have it.
+    pub fn set_metadata_first(&mut self, metadata_first: bool) {
+        self.metadata_first = metadata_first;
+    }
+    
+    /// Index dotfiles and hidden/system files instead of skipping them
+    pub fn set_index_hidden(&mut self, index_hidden: bool) {
+        self.index_hidden = index_hidden;
+    }
+    
+    /// Always index hidden files whose name matches `pattern` (a regex), e.g. `^\.bashrc$`
+    pub fn allow_hidden(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.hidden_allowlist.push(Regex::new(pattern)?);
+        Ok(())
+    }
+    
+    /// How long a removed file is remembered for move detection (zero disables it)
+    pub fn set_move_detection_window(&mut self, window: Duration) {
+        self.move_detection_window = window;
+        if window.is_zero() {
+            self.recently_removed.clear();
         }
     }
     
+    /// Choose whether numbers and version strings in file names become search
+    /// words for newly indexed files
+    pub fn set_numeric_tokens(&mut self, policy: NumericTokens) {
+        self.numeric_tokens = policy;
+    }
+    
+    /// Index the text inside single-file compressed files such as `access.log.gz`
+    /// (on by default). Off, they are indexed by name like other archives.
+    /// `.bz2` and `.xz` need the `compressed-text` feature; `.gz` is always read.
+    pub fn set_read_compressed(&mut self, read_compressed: bool) {
+        self.read_compressed = read_compressed;
+    }
+    
+    /// Text files that aren't UTF-8 (Latin-1, Shift-JIS, ...) are transcoded
+    /// rather than skipped. With detection on (the default) the encoding is
+    /// guessed from the content; off, they are read as Windows-1252.
+    pub fn set_detect_encoding(&mut self, detect: bool) {
+        self.detect_encoding = detect;
+    }
+    
+    /// Retry reading a file's content up to `retries` more times when the
+    /// failure looks temporary, such as a file held open by an antivirus
+    /// scanner on Windows, waiting `backoff` times the attempt number in
+    /// between. Missing files and denied permissions fail straight away.
+    /// Files that still fail wait for `retry_failed_extractions`.
+    pub fn set_extract_retries(&mut self, retries: u32, backoff: Duration) {
+        self.extract_retries = retries;
+        self.extract_retry_backoff = backoff;
+    }
+    
+    /// Choose the algorithm used for content hashes of newly indexed files
+    pub fn set_hash_algorithm(&mut self, algorithm: HashAlgorithm) {
+        self.hash_algorithm = algorithm;
+    }
+    
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+    
     fn default_excluded_patterns() -> Vec<Regex> {
         let patterns = vec![
             // System directories
@@ -382,105 +1157,622 @@ impl FilesystemIndexer {
             .collect()
     }
     
+    /// Index everything under `root_path`. Progress is reported every
+    /// progress interval, followed by a final update with the true totals.
+    /// Fails with `ErrorKind::Interrupted` if the stop flag (see `stop_handle`)
+    /// is set; files indexed up to that point are kept.
     pub async fn index_path(&mut self, root_path: &Path, progress_tx: Option<mpsc::Sender<IndexProgress>>) -> io::Result<()> {
-        let mut files_indexed = 0;
-        let mut dirs_scanned = 0;
+        let counters = Arc::new(ProgressCounters::default());
+        self.depth_truncated.retain(|dir| !dir.starts_with(root_path));
+        let on_network = self.network_roots.iter().any(|root| root_path.starts_with(root));
+        
+        let reporter = progress_tx.clone().map(|tx| {
+            let counters = counters.clone();
+            let mut ticker = tokio::time::interval(self.progress_interval);
+            tokio::spawn(async move {
+                loop {
+                    ticker.tick().await;
+                    if tx.send(counters.snapshot()).await.is_err() {
+                        break;
+                    }
+                }
+            })
+        });
+        
+        let finished = if on_network {
+            self.walk_network(root_path, &counters).await
+        } else {
+            self.walk_local(root_path, &counters).await
+        };
+        
+        // Stop the reporter before the final update so nothing stale arrives after it
+        if let Some(reporter) = reporter {
+            reporter.abort();
+            let _ = reporter.await;
+        }
+        
+        // Send final progress update
+        if let Some(ref tx) = progress_tx {
+            let status = if finished { "Indexing complete" } else { "Indexing interrupted" };
+            let _ = tx.send(IndexProgress {
+                current_path: status.to_string(),
+                ..counters.snapshot()
+            }).await;
+        }
         
+        if !finished {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "indexing interrupted"));
+        }
+        Ok(())
+    }
+    
+    // Called before each directory entry a walk visits; false once the stop
+    // flag is set. Every `check_interval` entries the entry becomes the
+    // reported path and the walk yields, so the progress reporter keeps
+    // running inside huge directories, skipped entries included.
+    async fn checkpoint(&self, path: &Path, visited: &mut usize, counters: &ProgressCounters) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        *visited += 1;
+        if *visited % self.check_interval == 0 {
+            *counters.current_path.lock().unwrap() = path.to_string_lossy().to_string();
+            tokio::task::yield_now().await;
+        }
+        true
+    }
+    
+    // Returns false if the walk was stopped early
+    async fn walk_local(&mut self, root_path: &Path, counters: &ProgressCounters) -> bool {
         let walker = WalkDir::new(root_path)
             .follow_links(false)
-            .max_depth(20) // Reasonable depth limit
+            .max_depth(self.max_depth)
             .into_iter();
+        let mut visited = 0;
         
         for entry in walker {
             match entry {
                 Ok(entry) => {
+                    if !self.checkpoint(entry.path(), &mut visited, counters).await {
+                        return false;
+                    }
                     if entry.file_type().is_dir() {
-                        dirs_scanned += 1;
-                        
-                        // Send progress update for directories
-                        if let Some(ref tx) = progress_tx {
-                            if dirs_scanned % 100 == 0 {
-                                let _ = tx.send(IndexProgress {
-                                    files_indexed,
-                                    dirs_scanned,
-                                    current_path: entry.path().to_string_lossy().to_string(),
-                                }).await;
-                            }
+                        counters.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+                        // WalkDir doesn't descend past the limit; note what that leaves out
+                        if entry.depth() == self.max_depth && fs::read_dir(entry.path()).is_ok_and(|mut dir| dir.next().is_some()) {
+                            self.depth_truncated.insert(entry.path().to_path_buf());
                         }
                         continue;
                     }
                     
-                    if self.should_index_file(&entry) {
+                    if self.should_index_file(entry.path(), || entry.metadata().ok()) {
+                        *counters.current_path.lock().unwrap() = entry.path().to_string_lossy().to_string();
+                        
                         match self.index_single_file(entry.path()).await {
                             Ok(true) => {
-                                files_indexed += 1;
-                                
-                                // Send progress update for files
-                                if let Some(ref tx) = progress_tx {
-                                    if files_indexed % 50 == 0 {
-                                        let _ = tx.send(IndexProgress {
-                                            files_indexed,
-                                            dirs_scanned,
-                                            current_path: entry.path().to_string_lossy().to_string(),
-                                        }).await;
-                                    }
-                                }
+                                counters.files_indexed.fetch_add(1, Ordering::Relaxed);
                             },
                             Ok(false) => {
                                 // File was skipped, no action needed
                             },
                             Err(e) => {
                                 eprintln!("Error indexing {}: {}", entry.path().display(), e);
+                                self.record_error(entry.path(), IndexStage::Metadata, &e);
                             }
                         }
                     }
                 },
                 Err(e) => {
                     eprintln!("Error walking directory: {}", e);
+                    let path = e.path().unwrap_or(root_path).to_path_buf();
+                    self.record_error(&path, IndexStage::Walk, &io::Error::from(e));
                 }
             }
         }
-        
-        // Send final progress update
-        if let Some(ref tx) = progress_tx {
-            let _ = tx.send(IndexProgress {
-                files_indexed,
-                dirs_scanned,
-                current_path: "Indexing complete".to_string(),
-            }).await;
-        }
-        
-        Ok(())
+        true
     }
     
-    async fn index_single_file(&mut self, path: &Path) -> io::Result<bool> {
-        let metadata = fs::metadata(path)?;
+    // Walk a network share without WalkDir, so every directory listing and
+    // file read can be timed out, retried, and skipped if the share stops
+    // answering. Returns false if the walk was stopped early, which also
+    // abandons a call the share is sitting on.
+    async fn walk_network(&mut self, root_path: &Path, counters: &ProgressCounters) -> bool {
+        let options = self.network_options.clone();
+        let stop = self.stop.clone();
+        let semaphore = Arc::new(Semaphore::new(options.max_concurrent.max(1)));
+        let mut pending = vec![(root_path.to_path_buf(), 0)];
+        let mut visited = 0;
         
-        // Skip files that are too large
-        if metadata.len() > self.max_file_size {
-            return Ok(false);
+        while let Some((dir, depth)) = pending.pop() {
+            if !self.checkpoint(&dir, &mut visited, counters).await {
+                return false;
+            }
+            let listing = {
+                let dir = dir.clone();
+                Self::network_io(&options, &semaphore, counters, &stop, move || Self::list_dir(&dir)).await
+            };
+            let entries = match listing {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted && stop.load(Ordering::Relaxed) => return false,
+                Err(e) => {
+                    diag!("Skipping unreachable directory {}: {}", dir.display(), e);
+                    counters.unreachable.fetch_add(1, Ordering::Relaxed);
+                    self.record_error(&dir, IndexStage::Unreachable, &e);
+                    continue;
+                }
+            };
+            counters.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+            
+            for (path, is_dir, metadata) in entries {
+                if !self.checkpoint(&path, &mut visited, counters).await {
+                    return false;
+                }
+                if is_dir {
+                    // Same depth limit as local walks: `path` is depth + 1 levels down
+                    if depth + 1 < self.max_depth {
+                        pending.push((path, depth + 1));
+                    } else {
+                        self.depth_truncated.insert(path);
+                    }
+                    continue;
+                }
+                
+                if !self.should_index_file(&path, || metadata) {
+                    continue;
+                }
+                *counters.current_path.lock().unwrap() = path.to_string_lossy().to_string();
+                
+                let max_file_size = self.max_file_size;
+                let extract = (!self.metadata_first).then(|| self.extract_options());
+                let file_path = path.clone();
+                let built = Self::network_io(&options, &semaphore, counters, &stop, move || {
+                    Self::build_indexed_file(&file_path, max_file_size, extract)
+                }).await;
+                
+                match built {
+                    Ok(Some((indexed_file, extract_error))) => {
+                        if extract.is_some() {
+                            self.note_extraction(&indexed_file, extract_error.as_ref());
+                        }
+                        self.track_indexed(indexed_file);
+                        counters.files_indexed.fetch_add(1, Ordering::Relaxed);
+                    },
+                    Ok(None) => {
+                        // File was skipped, no action needed
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted && stop.load(Ordering::Relaxed) => return false,
+                    Err(e) => {
+                        diag!("Skipping unreachable file {}: {}", path.display(), e);
+                        counters.unreachable.fetch_add(1, Ordering::Relaxed);
+                        self.record_error(&path, IndexStage::Unreachable, &e);
+                    }
+                }
+            }
         }
-        
+        true
+    }
+    
+    fn list_dir(dir: &Path) -> io::Result<Vec<(PathBuf, bool, Option<Metadata>)>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let is_dir = entry.file_type()?.is_dir();
+            // Attributes are only needed to spot hidden files on Windows, where they come with the listing
+            #[cfg(windows)]
+            let metadata = entry.metadata().ok();
+            #[cfg(not(windows))]
+            let metadata = None;
+            entries.push((entry.path(), is_dir, metadata));
+        }
+        Ok(entries)
+    }
+    
+    // Run a blocking filesystem call under the network timeout, retry policy and
+    // concurrency limit. A call that times out keeps its permit until it returns,
+    // so hung calls count against the limit instead of piling up on the share.
+    // Calls run on detached threads rather than the blocking pool, which the
+    // runtime would wait on at shutdown. Setting `stop` abandons the call, hung
+    // or not, with `ErrorKind::Interrupted`.
+    async fn network_io<T, F>(
+        options: &NetworkOptions,
+        semaphore: &Arc<Semaphore>,
+        counters: &ProgressCounters,
+        stop: &AtomicBool,
+        op: F,
+    ) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: Fn() -> io::Result<T> + Send + Sync + 'static,
+    {
+        let op = Arc::new(op);
+        let mut attempt = 0;
+        
+        loop {
+            let call = {
+                let op = op.clone();
+                let semaphore = semaphore.clone();
+                async move {
+                    let permit = semaphore.acquire_owned().await
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    std::thread::spawn(move || {
+                        let _permit = permit;
+                        let _ = tx.send(op());
+                    });
+                    rx.await.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+                }
+            };
+            
+            let stopped = async {
+                while !stop.load(Ordering::Relaxed) {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            };
+            let result = tokio::select! {
+                result = tokio::time::timeout(options.io_timeout, call) => result
+                    .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "network operation timed out"))),
+                _ = stopped => return Err(io::Error::new(io::ErrorKind::Interrupted, "indexing interrupted")),
+            };
+            
+            match result {
+                Err(ref e) if attempt < options.retries && Self::is_transient(e) => {
+                    attempt += 1;
+                    counters.io_retries.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(options.retry_backoff * attempt).await;
+                },
+                result => return result,
+            }
+        }
+    }
+    
+    fn is_transient(error: &io::Error) -> bool {
+        // Windows reports a file held open by another process as a sharing
+        // or lock violation
+        #[cfg(windows)]
+        if matches!(error.raw_os_error(), Some(32 | 33)) {
+            return true;
+        }
+        matches!(
+            error.kind(),
+            io::ErrorKind::TimedOut
+                | io::ErrorKind::Interrupted
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::ResourceBusy
+        )
+    }
+    
+    // Run a local filesystem call, retrying transient failures up to `retries`
+    // times with a delay of `backoff` times the attempt number. Sleeps the
+    // thread between attempts, so keep it off the async runtime's threads.
+    fn retry_transient<T>(retries: u32, backoff: Duration, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(ref e) if attempt < retries && Self::is_transient(e) => {
+                    attempt += 1;
+                    std::thread::sleep(backoff * attempt);
+                },
+                result => return result,
+            }
+        }
+    }
+    
+    fn extract_options(&self) -> ExtractOptions {
+        ExtractOptions {
+            hash_algorithm: self.hash_algorithm,
+            numeric_tokens: self.numeric_tokens,
+            read_compressed: self.read_compressed,
+            detect_encoding: self.detect_encoding,
+            retries: self.extract_retries,
+            retry_backoff: self.extract_retry_backoff,
+        }
+    }
+    
+    fn extract_content(file: &mut IndexedFile, options: ExtractOptions) -> io::Result<()> {
+        Self::retry_transient(options.retries, options.retry_backoff, || {
+            file.extract_text_content(options.hash_algorithm, options.numeric_tokens, options.read_compressed, options.detect_encoding)
+        })
+    }
+    
+    // Remember whether extracting `file`'s content failed, and why, for
+    // `retry_failed_extractions` and the error report
+    fn note_extraction(&mut self, file: &IndexedFile, error: Option<&io::Error>) {
+        if file.embedding_ready {
+            self.extraction_failures.remove(&file.path);
+            self.index_errors.remove(&(file.path.clone(), IndexStage::Extract));
+        } else {
+            self.extraction_failures.insert(file.path.clone());
+            if let Some(e) = error {
+                self.record_error(&file.path, IndexStage::Extract, e);
+            }
+        }
+    }
+    
+    fn record_error(&mut self, path: &Path, stage: IndexStage, error: &io::Error) {
+        self.index_errors.insert((path.to_path_buf(), stage), IndexError {
+            path: path.to_path_buf(),
+            stage,
+            kind: format!("{:?}", error.kind()),
+            message: error.to_string(),
+        });
+    }
+    
+    // Drop the recorded errors of a path that is no longer indexed there
+    fn forget_errors(&mut self, path: &Path) {
+        for stage in [IndexStage::Walk, IndexStage::Metadata, IndexStage::Extract, IndexStage::Unreachable] {
+            self.index_errors.remove(&(path.to_path_buf(), stage));
+        }
+    }
+    
+    async fn index_single_file(&mut self, path: &Path) -> io::Result<bool> {
+        let extract = (!self.metadata_first).then(|| self.extract_options());
+        // Reading, and waiting out transient errors between attempts, blocks
+        let (file_path, max_file_size) = (path.to_path_buf(), self.max_file_size);
+        let built = tokio::task::spawn_blocking(move || Self::build_indexed_file(&file_path, max_file_size, extract))
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        match built? {
+            Some((indexed_file, extract_error)) => {
+                if extract.is_some() {
+                    self.note_extraction(&indexed_file, extract_error.as_ref());
+                }
+                self.track_indexed(indexed_file);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+    
+    // Read a file's metadata, and its content if `extract` is given; `None` if
+    // it is too large to index. A file whose content can't be read is still
+    // indexed, without `embedding_ready`, and comes with the error.
+    fn build_indexed_file(path: &Path, max_file_size: u64, extract: Option<ExtractOptions>) -> io::Result<Option<(IndexedFile, Option<io::Error>)>> {
+        let metadata = fs::metadata(path)?;
+        
+        // Skip files that are too large
+        if metadata.len() > max_file_size {
+            return Ok(None);
+        }
+        
         let mut indexed_file = IndexedFile::new(path.to_path_buf(), &metadata);
+        let Some(options) = extract else {
+            return Ok(Some((indexed_file, None)));
+        };
         
         // Extract text content based on file type
-        if let Err(e) = indexed_file.extract_text_content() {
-            eprintln!("Warning: Could not extract content from {}: {}", path.display(), e);
+        let extract_error = Self::extract_content(&mut indexed_file, options).err();
+        if let Some(ref e) = extract_error {
+            diag!("Warning: Could not extract content from {}: {}", path.display(), e);
             // Continue indexing with just metadata
         }
         
+        Ok(Some((indexed_file, extract_error)))
+    }
+    
+    /// Number of indexed files still waiting for their content
+    pub fn pending_content_count(&self) -> usize {
+        self.files.values().filter(|file| !file.embedding_ready).count()
+    }
+    
+    /// Start extracting content for every file that doesn't have it yet, on a
+    /// background thread. The index stays usable meanwhile; call
+    /// `apply_content_fill` periodically to store what has been extracted.
+    pub fn start_content_fill(&self) -> ContentFill {
+        let pending: Vec<IndexedFile> = self.files.values()
+            .filter(|file| !file.embedding_ready)
+            .cloned()
+            .collect();
+        let total = pending.len();
+        let extracted = Arc::new(AtomicUsize::new(0));
+        let (tx, results) = std::sync::mpsc::channel();
+        
+        let options = self.extract_options();
+        let counter = extracted.clone();
+        std::thread::spawn(move || {
+            for mut file in pending {
+                let error = Self::extract_content(&mut file, options).err();
+                if let Some(ref e) = error {
+                    diag!("Warning: Could not extract content from {}: {}", file.path.display(), e);
+                }
+                counter.fetch_add(1, Ordering::Relaxed);
+                if tx.send((file, error)).is_err() {
+                    break; // The fill was dropped
+                }
+            }
+        });
+        
+        ContentFill { total, extracted, results }
+    }
+    
+    /// Store the content extracted by `fill` since the last call. Files that
+    /// changed or were removed in the meantime are left alone. Returns the
+    /// paths that gained content, whose search vectors should be rebuilt,
+    /// and those of files found to be mailboxes, now indexed as their
+    /// messages instead, whose vectors should go.
+    pub fn apply_content_fill(&mut self, fill: &ContentFill) -> Vec<PathBuf> {
+        let mut updated = Vec::new();
+        while let Ok((filled, error)) = fill.results.try_recv() {
+            let Some(file) = self.files.get(&filled.path) else {
+                continue;
+            };
+            if file.embedding_ready || file.modified != filled.modified || file.size != filled.size {
+                continue;
+            }
+            #[cfg(feature = "email")]
+            let found_mailbox = filled.file_type != file.file_type;
+            self.note_extraction(&filled, error.as_ref());
+            if !filled.embedding_ready {
+                continue;
+            }
+            // Turned out to be an mbox: index its messages in its place
+            #[cfg(feature = "email")]
+            if found_mailbox {
+                if let Some(stand_in) = self.files.remove(&filled.path) {
+                    self.untrack_stats(&stand_in);
+                }
+                let mailbox = filled.path.clone();
+                self.track_indexed(filled);
+                updated.push(mailbox.clone());
+                updated.extend(self.files.keys()
+                    .filter(|path| mail_archive::mailbox_of(path).is_some_and(|of| of == mailbox))
+                    .cloned());
+                continue;
+            }
+            if let Some(file) = self.files.get_mut(&filled.path) {
+                file.text_content = filled.text_content;
+                file.compressed_content = filled.compressed_content;
+                file.content_hash = filled.content_hash;
+                file.embedding_ready = true;
+                updated.push(filled.path);
+            }
+        }
+        updated
+    }
+    
+    /// Number of files whose content couldn't be read when they were indexed
+    pub fn failed_extraction_count(&self) -> usize {
+        self.extraction_failures.len()
+    }
+    
+    /// Try again to read the content of files that failed before, e.g. ones
+    /// that were locked during the scan. Returns the paths that gained
+    /// content, whose search vectors should be rebuilt; files that fail
+    /// again stay recorded for the next pass.
+    pub fn retry_failed_extractions(&mut self) -> Vec<PathBuf> {
+        let options = self.extract_options();
+        let mut updated = Vec::new();
+        for path in std::mem::take(&mut self.extraction_failures) {
+            let Some(file) = self.files.get_mut(&path) else {
+                continue; // No longer indexed
+            };
+            if file.embedding_ready {
+                continue;
+            }
+            match Self::extract_content(file, options) {
+                Ok(()) => {
+                    self.index_errors.remove(&(path.clone(), IndexStage::Extract));
+                    updated.push(path);
+                },
+                Err(e) => {
+                    diag!("Warning: Could not extract content from {}: {}", path.display(), e);
+                    self.record_error(&path, IndexStage::Extract, &e);
+                    self.extraction_failures.insert(path);
+                }
+            }
+        }
+        updated
+    }
+    
+    /// Files and directories that couldn't be read since the index was last
+    /// cleared, with the latest error for each path and stage, by path.
+    /// Entries go away once the file is read successfully or removed.
+    pub fn index_errors(&self) -> impl Iterator<Item = &IndexError> {
+        self.index_errors.values()
+    }
+    
+    pub fn index_error_count(&self) -> usize {
+        self.index_errors.len()
+    }
+    
+    /// Write `index_errors` to `path`, for reviewing after a scan. Returns
+    /// the number of entries written.
+    pub fn write_error_report(&self, path: &Path, format: ReportFormat) -> io::Result<usize> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        match format {
+            ReportFormat::Json => {
+                let errors: Vec<&IndexError> = self.index_errors().collect();
+                serde_json::to_writer_pretty(&mut file, &errors)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+            ReportFormat::Csv => {
+                writeln!(file, "path,stage,kind,message")?;
+                for error in self.index_errors() {
+                    writeln!(file, "\"{}\",{},{},\"{}\"",
+                        error.path.to_string_lossy().replace('"', "\"\""),
+                        error.stage.as_str(),
+                        error.kind,
+                        error.message.replace('"', "\"\"")
+                    )?;
+                }
+            }
+        }
+        file.flush()?;
+        Ok(self.index_errors.len())
+    }
+    
+    fn track_file(&mut self, indexed_file: IndexedFile) {
         // Update statistics
         *self.file_type_stats.entry(indexed_file.file_type.clone()).or_insert(0) += 1;
         self.total_size += indexed_file.size;
         
-        // Store the indexed file
-        self.files.insert(path.to_path_buf(), indexed_file);
-        
-        Ok(true)
+        // Store the indexed file, dropping the stats of any entry it replaces
+        if let Some(old_file) = self.files.insert(indexed_file.path.clone(), indexed_file) {
+            self.untrack_stats(&old_file);
+        }
+    }
+    
+    // Track a newly built entry. With the `email` feature an mbox is tracked
+    // as the messages in it, which are read right away even when indexing
+    // metadata first, since they have no file of their own to read later.
+    fn track_indexed(&mut self, indexed_file: IndexedFile) {
+        #[cfg(feature = "email")]
+        if indexed_file.file_type == FileType::Email && mail_archive::is_mbox(&indexed_file.path) {
+            // Its own entry only stood in for the messages
+            self.extraction_failures.remove(&indexed_file.path);
+            if let Err(e) = self.track_mailbox(&indexed_file) {
+                eprintln!("Error reading mailbox {}: {}", indexed_file.path.display(), e);
+                self.record_error(&indexed_file.path, IndexStage::Extract, &e);
+            }
+            return;
+        }
+        self.track_file(indexed_file);
+    }
+    
+    // Replace the messages indexed from `mailbox` with those it holds now.
+    // Messages that can't be parsed are recorded as extraction errors.
+    #[cfg(feature = "email")]
+    fn track_mailbox(&mut self, mailbox: &IndexedFile) -> io::Result<usize> {
+        let messages = mail_archive::read_mbox(&mailbox.path)?;
+        self.remove_mailbox_messages(&mailbox.path);
+        let mut tracked = 0;
+        for (i, raw) in messages.iter().enumerate() {
+            match mail_archive::parse_message(raw) {
+                Ok(message) => {
+                    self.track_file(IndexedFile::from_message(mailbox, i + 1, message, raw, self.hash_algorithm));
+                    tracked += 1;
+                }
+                Err(e) => {
+                    let path = mail_archive::message_path(&mailbox.path, i + 1);
+                    self.record_error(&path, IndexStage::Extract, &io::Error::new(io::ErrorKind::InvalidData, e.to_string()));
+                }
+            }
+        }
+        diag!("Indexed {} messages from {}", tracked, mailbox.path.display());
+        Ok(tracked)
+    }
+    
+    // Drop every message indexed from `mailbox`
+    #[cfg(feature = "email")]
+    fn remove_mailbox_messages(&mut self, mailbox: &Path) -> usize {
+        let messages: Vec<PathBuf> = self.files.keys()
+            .filter(|path| mail_archive::mailbox_of(path).is_some_and(|of| of == mailbox))
+            .cloned()
+            .collect();
+        for path in &messages {
+            self.forget_errors(path);
+            if let Some(file) = self.files.remove(path) {
+                self.untrack_stats(&file);
+            }
+        }
+        messages.len()
     }
     
-    fn should_index_file(&self, entry: &DirEntry) -> bool {
-        let path_str = entry.path().to_string_lossy();
+    fn should_index_file(&self, path: &Path, metadata: impl FnOnce() -> Option<Metadata>) -> bool {
+        let path_str = path.to_string_lossy();
         
         // Check against excluded patterns
         for pattern in &self.excluded_patterns {
@@ -489,36 +1781,55 @@ impl FilesystemIndexer {
             }
         }
         
-        // Skip hidden files on Unix systems
+        if self.index_hidden || !Self::is_hidden(path, metadata) {
+            return true;
+        }
+        
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        self.hidden_allowlist.iter().any(|pattern| pattern.is_match(&filename))
+    }
+    
+    // `metadata` is only consulted on Windows, where hidden is an attribute
+    #[cfg_attr(not(windows), allow(unused_variables))]
+    fn is_hidden(path: &Path, metadata: impl FnOnce() -> Option<Metadata>) -> bool {
+        // Dotfiles on Unix systems
         #[cfg(unix)]
         {
-            if let Some(filename) = entry.file_name().to_str() {
+            if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
                 if filename.starts_with('.') && filename.len() > 1 {
-                    return false;
+                    return true;
                 }
             }
         }
         
-        // Skip system files on Windows
+        // Hidden and system files on Windows
         #[cfg(windows)]
         {
-            if let Ok(metadata) = entry.metadata() {
+            if let Some(metadata) = metadata() {
                 use std::os::windows::fs::MetadataExt;
                 const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
                 const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
                 
                 let attrs = metadata.file_attributes();
                 if (attrs & FILE_ATTRIBUTE_HIDDEN) != 0 || (attrs & FILE_ATTRIBUTE_SYSTEM) != 0 {
-                    return false;
+                    return true;
                 }
             }
         }
         
-        true
+        false
     }
     
     pub fn save_index(&self, path: &str) -> io::Result<()> {
-        let serialized = bincode::serialize(&self.files)
+        let header = IndexHeader {
+            version: INDEX_VERSION,
+            hash_algorithm: self.hash_algorithm,
+        };
+        
+        let mut serialized = INDEX_MAGIC.to_vec();
+        bincode::serialize_into(&mut serialized, &header)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        bincode::serialize_into(&mut serialized, &self.files)
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
         // Compress the index
@@ -539,8 +1850,7 @@ impl FilesystemIndexer {
         decoder.read_to_end(&mut serialized)?;
         
         // Deserialize
-        self.files = bincode::deserialize(&serialized)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        self.files = Self::deserialize_files(&serialized)?;
         
         // Rebuild statistics
         self.rebuild_stats();
@@ -548,14 +1858,64 @@ impl FilesystemIndexer {
         Ok(())
     }
     
-    fn rebuild_stats(&mut self) {
-        self.file_type_stats.clear();
-        self.total_size = 0;
+    /// Decode either a versioned index or a legacy bare file map
+    fn deserialize_files(serialized: &[u8]) -> io::Result<HashMap<PathBuf, IndexedFile>> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        
+        if let Some(mut body) = serialized.strip_prefix(&INDEX_MAGIC[..]) {
+            let header: IndexHeader = bincode::deserialize_from(&mut body).map_err(invalid)?;
+            if header.version > INDEX_VERSION {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Index version {} is newer than supported version {}", header.version, INDEX_VERSION)
+                ));
+            }
+            return bincode::deserialize_from(&mut body).map_err(invalid);
+        }
+        
+        let legacy: HashMap<PathBuf, LegacyIndexedFile> = bincode::deserialize(serialized).map_err(invalid)?;
+        Ok(legacy.into_iter().map(|(path, file)| (path, file.into())).collect())
+    }
+    
+    /// Recompute the type counts and total size from the indexed files
+    pub fn rebuild_stats(&mut self) {
+        let (file_type_stats, total_size) = self.compute_stats();
+        self.file_type_stats = file_type_stats;
+        self.total_size = total_size;
+    }
+    
+    /// Check the incrementally maintained statistics against the indexed files
+    pub fn verify_stats(&self) -> bool {
+        let (file_type_stats, total_size) = self.compute_stats();
+        let tracked: HashMap<FileType, usize> = self.file_type_stats.iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(file_type, &count)| (file_type.clone(), count))
+            .collect();
+        
+        tracked == file_type_stats && self.total_size == total_size
+    }
+    
+    fn compute_stats(&self) -> (HashMap<FileType, usize>, u64) {
+        let mut file_type_stats = HashMap::new();
+        let mut total_size = 0;
         
         for file in self.files.values() {
-            *self.file_type_stats.entry(file.file_type.clone()).or_insert(0) += 1;
-            self.total_size += file.size;
+            *file_type_stats.entry(file.file_type.clone()).or_insert(0) += 1;
+            total_size += file.size;
+        }
+        
+        (file_type_stats, total_size)
+    }
+    
+    // Take a file that is leaving the index out of the statistics
+    fn untrack_stats(&mut self, file: &IndexedFile) {
+        if let Some(count) = self.file_type_stats.get_mut(&file.file_type) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.file_type_stats.remove(&file.file_type);
+            }
         }
+        self.total_size = self.total_size.saturating_sub(file.size);
     }
     
     pub fn search_by_name(&self, pattern: &str) -> Vec<&IndexedFile> {
@@ -607,10 +1967,10 @@ impl FilesystemIndexer {
             .collect()
     }
     
-    pub fn update_file(&mut self, path: &Path) -> io::Result<bool> {
+    pub async fn update_file(&mut self, path: &Path) -> io::Result<bool> {
         if let Ok(metadata) = fs::metadata(path) {
             let modified = metadata.modified()
-                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok())
                 .map(|d| d.as_secs())
                 .unwrap_or(0);
             
@@ -621,13 +1981,9 @@ impl FilesystemIndexer {
                 }
             }
             
-            // Remove old entry if it exists
+            // Remove old entry if it exists, under its old type in case the type changed
             if let Some(old_file) = self.files.remove(path) {
-                // Update statistics
-                if let Some(count) = self.file_type_stats.get_mut(&old_file.file_type) {
-                    *count = count.saturating_sub(1);
-                }
-                self.total_size = self.total_size.saturating_sub(old_file.size);
+                self.untrack_stats(&old_file);
             }
             
             // Add new entry
@@ -640,25 +1996,133 @@ impl FilesystemIndexer {
     }
     
     pub fn remove_file(&mut self, path: &Path) -> bool {
+        self.extraction_failures.remove(path);
+        self.forget_errors(path);
         if let Some(file) = self.files.remove(path) {
-            // Update statistics
-            if let Some(count) = self.file_type_stats.get_mut(&file.file_type) {
-                *count = count.saturating_sub(1);
+            self.untrack_stats(&file);
+            
+            // Remember it in case it reappears elsewhere
+            if file.content_hash.is_some() && !self.move_detection_window.is_zero() {
+                self.recently_removed.push((Instant::now(), file));
             }
-            self.total_size = self.total_size.saturating_sub(file.size);
             true
         } else {
+            // A mailbox is indexed as its messages
+            #[cfg(feature = "email")]
+            if self.remove_mailbox_messages(path) > 0 {
+                return true;
+            }
             false
         }
     }
     
+    /// Move an indexed entry to a new path without re-extracting its content
+    pub fn rename_file(&mut self, from: &Path, to: &Path) -> bool {
+        if from == to {
+            return self.files.contains_key(from);
+        }
+        if self.files.contains_key(from) {
+            // The destination is being replaced
+            self.remove_file(to);
+        }
+        
+        match self.files.remove(from) {
+            Some(mut file) => {
+                file.path = to.to_path_buf();
+                file.display_name = to.file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                if self.extraction_failures.remove(from) {
+                    self.extraction_failures.insert(to.to_path_buf());
+                }
+                self.forget_errors(from);
+                self.files.insert(to.to_path_buf(), file);
+                true
+            },
+            None => false,
+        }
+    }
+    
+    /// Check whether a newly created file has the same content as one removed
+    /// within the move detection window. If so, the old entry is moved to
+    /// `path` and the path it used to live at is returned.
+    pub fn detect_move(&mut self, path: &Path) -> Option<PathBuf> {
+        let window = self.move_detection_window;
+        self.recently_removed.retain(|(removed_at, _)| removed_at.elapsed() <= window);
+        if self.recently_removed.is_empty() || self.files.contains_key(path) {
+            return None;
+        }
+        
+        let metadata = fs::metadata(path).ok()?;
+        if !self.recently_removed.iter().any(|(_, file)| file.content_hash.is_some() && file.size == metadata.len()) {
+            return None;
+        }
+        
+        // Hash what indexing would have hashed (decoded text, a whole message),
+        // not the raw bytes, so it compares with the removed file's hash
+        let options = self.extract_options();
+        let mut created = IndexedFile::new(path.to_path_buf(), &metadata);
+        created.extract_text_content(options.hash_algorithm, options.numeric_tokens, options.read_compressed, options.detect_encoding).ok()?;
+        let hash = created.content_hash?;
+        
+        let position = self.recently_removed.iter().position(|(_, file)| {
+            file.size == metadata.len() && file.content_hash.as_ref() == Some(&hash)
+        })?;
+        
+        let (_, file) = self.recently_removed.remove(position);
+        let old_path = file.path.clone();
+        
+        // Put the entry back under its old path and move it, keeping stats consistent
+        *self.file_type_stats.entry(file.file_type.clone()).or_insert(0) += 1;
+        self.total_size += file.size;
+        if let Some(replaced) = self.files.insert(old_path.clone(), file) {
+            self.untrack_stats(&replaced);
+        }
+        self.rename_file(&old_path, path);
+        
+        Some(old_path)
+    }
+    
+    /// Add another index's files to this one, e.g. one built on another machine.
+    /// Where both index the same path, the more recently modified entry wins.
+    /// Returns the paths taken from `other`, whose documents need rebuilding.
+    pub fn merge(&mut self, other: FilesystemIndexer) -> Vec<PathBuf> {
+        let mut merged = Vec::new();
+        for (path, file) in other.files {
+            if let Some(existing) = self.files.get(&path) {
+                if existing.modified >= file.modified {
+                    continue;
+                }
+            }
+            self.track_file(file);
+            merged.push(path);
+        }
+        merged
+    }
+    
     pub fn clear(&mut self) {
+        self.recently_removed.clear();
+        self.extraction_failures.clear();
+        self.index_errors.clear();
+        self.depth_truncated.clear();
         self.files.clear();
         self.file_type_stats.clear();
         self.total_size = 0;
     }
     
     // Public getters
+    pub fn stats(&self) -> IndexStats {
+        IndexStats {
+            file_count: self.files.len(),
+            total_size: self.total_size,
+            file_types: self.file_type_stats.iter()
+                .filter(|(_, &count)| count > 0)
+                .map(|(file_type, &count)| (file_type.as_str().to_string(), count))
+                .collect(),
+        }
+    }
+    
     pub fn file_count(&self) -> usize {
         self.files.len()
     }
@@ -715,8 +2179,7 @@ impl FilesystemIndexer {
             }
             
             // File type match
-            let file_type_str = format!("{:?}", file.file_type).to_lowercase();
-            if file_type_str.contains(word) {
+            if file.file_type.as_str().contains(word) {
                 score += 3.0;
             }
         }
@@ -737,25 +2200,675 @@ impl FilesystemIndexer {
         score
     }
     
-    pub fn get_similar_files(&self, target_file: &IndexedFile) -> Vec<&IndexedFile> {
-        self.files.values()
+    /// Files of the same type and similar size (and optionally content), best match first
+    pub fn get_similar_files(&self, target_file: &IndexedFile, options: &SimilarityOptions) -> Vec<(&IndexedFile, f64)> {
+        let target_words = options.min_content_similarity.and(Self::content_words(target_file));
+        
+        let mut scored_files: Vec<(&IndexedFile, f64)> = self.files.values()
             .filter(|file| {
                 file.path != target_file.path &&
                 file.file_type == target_file.file_type &&
-                (file.size as i64 - target_file.size as i64).abs() < 1024 * 1024 // Within 1MB size
+                file.size.abs_diff(target_file.size) <= options.size_tolerance
             })
-            .collect()
+            .filter_map(|file| {
+                let size_score = if options.size_tolerance == 0 {
+                    1.0
+                } else {
+                    1.0 - file.size.abs_diff(target_file.size) as f64 / options.size_tolerance as f64
+                };
+                
+                let (Some(min_similarity), Some(target_words)) = (options.min_content_similarity, &target_words) else {
+                    return Some((file, size_score));
+                };
+                
+                let content_score = match (&file.content_hash, &target_file.content_hash) {
+                    (Some(a), Some(b)) if a == b => 1.0,
+                    _ => Self::content_words(file)
+                        .map(|words| Self::jaccard(&words, target_words))
+                        .unwrap_or(0.0),
+                };
+                
+                if content_score < min_similarity {
+                    return None;
+                }
+                
+                Some((file, 0.5 * size_score + 0.5 * content_score))
+            })
+            .collect();
+        
+        scored_files.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        
+        if let Some(max_results) = options.max_results {
+            scored_files.truncate(max_results);
+        }
+        
+        scored_files
+    }
+    
+    fn content_words(file: &IndexedFile) -> Option<HashSet<String>> {
+        let text = file.content_text()?;
+        Some(text.split_whitespace().map(|word| word.to_lowercase()).collect())
+    }
+    
+    fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+        let union = a.union(b).count();
+        if union == 0 {
+            return 0.0;
+        }
+        a.intersection(b).count() as f64 / union as f64
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ResonantEngine;
+
+    // A scratch directory under the system temp dir, removed when dropped
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("quantum-search-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            TempTree(root)
+        }
+
+        fn write(&self, relative: &str, content: &[u8]) -> PathBuf {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Apply `fill` until it is done, returning every path that gained content
+    fn finish_fill(indexer: &mut FilesystemIndexer, fill: &ContentFill) -> Vec<PathBuf> {
+        let mut updated = Vec::new();
+        loop {
+            let finished = fill.is_finished();
+            updated.extend(indexer.apply_content_fill(fill));
+            if finished {
+                return updated;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn metadata_first_finds_names_at_once_and_contents_after_the_fill() {
+        let tree = TempTree::new("two-phase");
+        tree.write("quarterly-report.txt", b"the zeppelin manifest lists every passenger");
+        let mut indexer = FilesystemIndexer::new();
+        indexer.set_metadata_first(true);
+        indexer.index_path(&tree.0, None).await.unwrap();
+        assert_eq!(indexer.pending_content_count(), 1);
+
+        let mut engine = ResonantEngine::new();
+        for file in indexer.get_all_files() {
+            engine.add_filesystem_document(file);
+        }
+        assert_eq!(engine.search("quarterly", 5).len(), 1);
+        assert!(engine.search("zeppelin", 5).is_empty());
+
+        let fill = indexer.start_content_fill();
+        for path in finish_fill(&mut indexer, &fill) {
+            engine.add_filesystem_document(indexer.get_file_by_path(&path).unwrap());
+        }
+        assert_eq!(indexer.pending_content_count(), 0);
+        assert_eq!(engine.search("zeppelin", 5).len(), 1);
+        assert_eq!(engine.search("quarterly", 5).len(), 1);
+    }
+
+    #[test]
+    fn transient_errors_are_retried_and_permanent_ones_are_not() {
+        let mut calls = 0;
+        let read = FilesystemIndexer::retry_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 { Err(io::Error::from(io::ErrorKind::ResourceBusy)) } else { Ok("content") }
+        });
+        assert_eq!(read.unwrap(), "content");
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let read: io::Result<()> = FilesystemIndexer::retry_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert_eq!(read.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn legacy_hashes_match_the_ones_old_indexes_stored() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        // How `calculate_content_hash` fingerprinted text before hashes were tagged
+        let content = "Grüße aus dem alten Index\n";
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let stored: IndexedFile = LegacyIndexedFile {
+            path: PathBuf::from("/notes/gruss.txt"),
+            display_name: "gruss.txt".into(),
+            file_type: FileType::Text,
+            size: content.len() as u64,
+            modified: 0,
+            created: 0,
+            content_hash: Some(hasher.finish()),
+            text_content: Some(content.into()),
+            compressed_content: None,
+            metadata_tags: Vec::new(),
+            embedding_ready: false,
+        }.into();
+
+        assert_eq!(stored.content_hash, Some(HashAlgorithm::LegacySip64.hash(content.as_bytes())));
+    }
+
+    #[cfg(feature = "email")]
+    const MAILBOX: &[u8] = b"From ana@example.com Mon Mar  4 09:12:00 2024
+From: Ana <ana@example.com>
+To: Bo <bo@example.com>
+Subject: Zeppelin boarding passes
+Date: Mon, 4 Mar 2024 09:12:00 +0000
+
+The passes for the airship are attached.
+
+From bo@example.com Tue Mar  5 17:40:00 2024
+From: Bo <bo@example.com>
+To: Ana <ana@example.com>
+Subject: Re: dinner
+Date: Tue, 5 Mar 2024 17:40:00 +0000
+
+Bring the marzipan, please.
+";
+
+    #[cfg(feature = "email")]
+    #[tokio::test]
+    async fn mbox_messages_are_found_by_subject_and_body() {
+        let tree = TempTree::new("mbox");
+        // Named like a mail client's folder, without an extension
+        let mailbox = tree.write("Mail/Inbox", MAILBOX);
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&tree.0, None).await.unwrap();
+        assert!(indexer.get_file_by_path(&mailbox).is_none());
+        assert_eq!(indexer.get_all_files().count(), 2);
+
+        let mut engine = ResonantEngine::new();
+        for file in indexer.get_all_files() {
+            engine.add_filesystem_document(file);
+        }
+        let by_subject = engine.search("zeppelin", 5);
+        assert_eq!(by_subject.len(), 1);
+        assert_eq!(PathBuf::from(&by_subject[0].path), mail_archive::message_path(&mailbox, 1));
+        let by_body = engine.search("marzipan", 5);
+        assert_eq!(by_body.len(), 1);
+        assert_eq!(by_body[0].title, "Re: dinner");
+    }
+
+    #[cfg(feature = "email")]
+    #[tokio::test]
+    async fn metadata_first_reads_extensionless_files_only_during_the_fill() {
+        let tree = TempTree::new("mbox-fill");
+        let mailbox = tree.write("Inbox", MAILBOX);
+        tree.write("LICENSE", b"Permission is hereby granted");
+        let mut indexer = FilesystemIndexer::new();
+        indexer.set_metadata_first(true);
+        indexer.index_path(&tree.0, None).await.unwrap();
+        assert_eq!(indexer.get_file_by_path(&mailbox).unwrap().file_type, FileType::Unknown);
+
+        let fill = indexer.start_content_fill();
+        let updated = finish_fill(&mut indexer, &fill);
+        assert!(indexer.get_file_by_path(&mailbox).is_none());
+        assert!(updated.contains(&mailbox));
+        for number in 1..=2 {
+            let message = mail_archive::message_path(&mailbox, number);
+            assert!(updated.contains(&message));
+            assert_eq!(indexer.get_file_by_path(&message).unwrap().file_type, FileType::Email);
+        }
+        assert_eq!(indexer.get_file_by_path(&tree.0.join("LICENSE")).unwrap().file_type, FileType::Unknown);
+    }
+
+    #[cfg(all(feature = "extended-attributes", unix))]
+    #[tokio::test]
+    async fn files_are_found_by_their_extended_attribute_tags() {
+        let tree = TempTree::new("xattr-tags");
+        let path = tree.write("scan-0042.pdf.txt", b"nothing in here mentions it");
+        if xattr::set(&path, "user.xdg.tags", b"zeppelin,archive").is_err() {
+            return; // The temp filesystem doesn't take user attributes
+        }
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&tree.0, None).await.unwrap();
+
+        let mut engine = ResonantEngine::new();
+        for file in indexer.get_all_files() {
+            engine.add_filesystem_document(file);
+        }
+        let results = engine.search("zeppelin", 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, path.display().to_string());
+    }
+
+    #[tokio::test]
+    async fn error_report_lists_contents_that_could_not_be_read() {
+        let tree = TempTree::new("error-report");
+        tree.write("readable.txt", b"plain words");
+        // Named as gzip but isn't, so it stays unreadable even to root
+        let broken = tree.write("field-notes.txt.gz", b"not gzip at all");
+        let mut indexer = FilesystemIndexer::new();
+        indexer.set_metadata_first(true);
+        indexer.index_path(&tree.0, None).await.unwrap();
+        assert_eq!(indexer.index_error_count(), 0);
+
+        let fill = indexer.start_content_fill();
+        finish_fill(&mut indexer, &fill);
+        let report = tree.0.join("errors.csv");
+        assert_eq!(indexer.write_error_report(&report, ReportFormat::Csv).unwrap(), 1);
+
+        let csv = fs::read_to_string(&report).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("path,stage,kind,message"));
+        let entry = lines.next().unwrap();
+        assert!(entry.starts_with(&format!("\"{}\",{},", broken.display(), IndexStage::Extract.as_str())), "{}", entry);
+        assert_eq!(lines.next(), None);
+    }
+
+    #[tokio::test]
+    async fn moved_files_keep_their_learned_relevance() {
+        let tree = TempTree::new("move");
+        // Latin-1, so the decoded text that gets hashed differs from the bytes on disk
+        let old_path = tree.write("notes/caf\u{e9}-menu.txt", b"caf\xe9 cr\xe8me br\xfbl\xe9e and espresso");
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&tree.0, None).await.unwrap();
+        let mut engine = ResonantEngine::new();
+        for file in indexer.get_all_files() {
+            engine.add_filesystem_document(file);
+        }
+        assert!(engine.record_feedback(&old_path, true));
+        let relevance = engine.relevance(&old_path).unwrap();
+
+        let new_path = tree.0.join("archive-menu.txt");
+        fs::rename(&old_path, &new_path).unwrap();
+        assert!(indexer.remove_file(&old_path));
+        assert!(engine.relocate_filesystem_document(&mut indexer, &new_path));
+
+        assert!(indexer.get_file_by_path(&old_path).is_none());
+        assert!(indexer.get_file_by_path(&new_path).is_some());
+        assert_eq!(engine.relevance(&old_path), None);
+        assert_eq!(engine.relevance(&new_path), Some(relevance));
+    }
+
+    #[tokio::test]
+    async fn merging_indexes_takes_the_newer_files_and_reports_them() {
+        let laptop = TempTree::new("merge-laptop");
+        laptop.write("plans.txt", b"old zeppelin plans");
+        let desktop = TempTree::new("merge-desktop");
+        let shared = desktop.write("recipes.txt", b"kiwi sorbet");
+        let mut ours = FilesystemIndexer::new();
+        ours.index_path(&laptop.0, None).await.unwrap();
+        let mut theirs = FilesystemIndexer::new();
+        theirs.index_path(&desktop.0, None).await.unwrap();
+
+        let merged = ours.merge(theirs);
+        assert_eq!(merged, vec![shared.clone()]);
+        assert_eq!(ours.file_count(), 2);
+
+        let mut engine = ResonantEngine::new();
+        for file in ours.get_all_files() {
+            engine.add_filesystem_document(file);
+        }
+        assert_eq!(engine.search("sorbet", 5)[0].path, shared.display().to_string());
+        assert_eq!(engine.search("zeppelin", 5).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn directories_past_the_depth_limit_are_reported_until_it_is_raised() {
+        let tree = TempTree::new("depth");
+        let nested: String = (0..22).map(|level| format!("d{level}/")).collect();
+        let deep = tree.write(&format!("{nested}deep.txt"), b"zeppelin at the bottom");
+        let shallow = tree.write("shallow.txt", b"zeppelin on top");
+        let mut indexer = FilesystemIndexer::new();
+        assert_eq!(indexer.max_depth(), 20);
+        indexer.index_path(&tree.0, None).await.unwrap();
+
+        assert!(indexer.get_file_by_path(&shallow).is_some());
+        assert!(indexer.get_file_by_path(&deep).is_none());
+        // The directory at depth 20 is listed, but its contents weren't indexed
+        let limit: PathBuf = (0..20).fold(tree.0.clone(), |path, level| path.join(format!("d{level}")));
+        assert_eq!(indexer.depth_truncated().collect::<Vec<_>>(), vec![limit.as_path()]);
+
+        indexer.set_max_depth(30);
+        indexer.index_path(&tree.0, None).await.unwrap();
+        assert!(indexer.get_file_by_path(&deep).is_some());
+        assert_eq!(indexer.depth_truncated().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn a_huge_directory_reports_progress_and_stops_partway() {
+        let tree = TempTree::new("huge-dir");
+        let total = 1500;
+        for i in 0..total {
+            tree.write(&format!("spool/{i:05}.txt"), b"zeppelin");
+        }
+        let spool = tree.0.join("spool");
+        let mut indexer = FilesystemIndexer::new();
+        indexer.set_check_interval(10);
+        indexer.set_progress_interval(Duration::from_millis(1));
+        let stop = indexer.stop_handle();
+        let (tx, mut rx) = mpsc::channel::<IndexProgress>(16);
+        // Stop once progress from inside the directory shows files indexed there
+        let watcher = tokio::spawn(async move {
+            let mut inside = Vec::new();
+            while let Some(progress) = rx.recv().await {
+                if progress.files_indexed > 0 && Path::new(&progress.current_path).starts_with(&spool) {
+                    inside.push(progress.files_indexed);
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+            inside
+        });
 
+        let result = indexer.index_path(&tree.0, Some(tx)).await;
+        let inside = watcher.await.unwrap();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(!inside.is_empty());
+        let indexed = indexer.file_count();
+        assert!(indexed < total, "{indexed}");
+        assert!(inside.iter().all(|&count| count <= indexed));
+    }
+
+    #[tokio::test]
+    async fn a_stopped_scan_returns_promptly_with_the_files_it_finished() {
+        let tree = TempTree::new("stop");
+        let total = 400;
+        for i in 0..total {
+            tree.write(&format!("batch-{}/note-{i}.txt", i % 8), format!("zeppelin note {i}").as_bytes());
+        }
 
+        for network in [false, true] {
+            let mut indexer = FilesystemIndexer::new();
+            if network {
+                indexer.add_network_root(tree.0.clone());
+            }
+            indexer.set_check_interval(1);
+            indexer.set_progress_interval(Duration::from_millis(1));
+            // Stop as soon as the first files are reported indexed
+            let stop = indexer.stop_handle();
+            let (tx, mut rx) = mpsc::channel::<IndexProgress>(16);
+            let watcher = tokio::spawn(async move {
+                while let Some(progress) = rx.recv().await {
+                    if progress.files_indexed > 0 {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
 
+            let start = Instant::now();
+            let result = indexer.index_path(&tree.0, Some(tx)).await;
+            watcher.await.unwrap();
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+            assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
 
+            // Only whole files, each of them one that exists, with its content read
+            let indexed = indexer.file_count();
+            assert!(indexed > 0 && indexed < total, "{network}: {indexed}");
+            for file in indexer.get_all_files() {
+                assert!(file.path.starts_with(&tree.0) && file.path.is_file());
+                let number = file.path.file_stem().unwrap().to_string_lossy().trim_start_matches("note-").to_string();
+                assert_eq!(file.content_text(), Some(format!("zeppelin note {number}")));
+            }
 
+            // Clearing the flag lets a rescan complete the index
+            indexer.stop_handle().store(false, Ordering::Relaxed);
+            indexer.index_path(&tree.0, None).await.unwrap();
+            assert_eq!(indexer.file_count(), total);
+        }
+    }
 
+    #[test]
+    fn file_type_keywords_are_stable_and_round_trip() {
+        let keywords = [
+            (FileType::Text, "text"),
+            (FileType::Code, "code"),
+            (FileType::Document, "document"),
+            (FileType::Image, "image"),
+            (FileType::Audio, "audio"),
+            (FileType::Video, "video"),
+            (FileType::Archive, "archive"),
+            (FileType::Binary, "binary"),
+            (FileType::Config, "config"),
+            (FileType::Data, "data"),
+            (FileType::Log, "log"),
+            (FileType::Markdown, "markdown"),
+            (FileType::Email, "email"),
+            (FileType::Unknown, "unknown"),
+        ];
+        for (file_type, keyword) in keywords {
+            assert_eq!(file_type.as_str(), keyword);
+            assert_eq!(keyword.parse::<FileType>(), Ok(file_type.clone()));
+            assert_eq!(format!(" {} ", keyword.to_uppercase()).parse::<FileType>(), Ok(file_type));
+        }
+        assert!("Markdown Document".parse::<FileType>().is_err());
+    }
 
+    #[tokio::test]
+    async fn similar_files_respect_the_tolerance_and_cap_best_first() {
+        let tree = TempTree::new("similar");
+        let target = tree.write("target.txt", &[b'a'; 1000]);
+        for (name, size) in [("far.txt", 1400), ("near.txt", 1010), ("middle.txt", 1100), ("huge.txt", 5000)] {
+            tree.write(name, &vec![b'b'; size]);
+        }
+        tree.write("other.csv", &[b'c'; 1000]);
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&tree.0, None).await.unwrap();
+        let target = indexer.get_file_by_path(&target).unwrap();
 
+        let names = |options: &SimilarityOptions| -> Vec<String> {
+            indexer.get_similar_files(target, options).into_iter().map(|(file, _)| file.display_name.clone()).collect()
+        };
+        let mut options = SimilarityOptions { size_tolerance: 600, min_content_similarity: None, max_results: None };
+        assert_eq!(names(&options), ["near.txt", "middle.txt", "far.txt"]);
+        options.max_results = Some(2);
+        assert_eq!(names(&options), ["near.txt", "middle.txt"]);
 
+        let scores: Vec<f64> = indexer.get_similar_files(target, &options).into_iter().map(|(_, score)| score).collect();
+        assert!(scores[0] > scores[1] && scores[1] > 0.0);
+    }
 
+    #[tokio::test]
+    async fn stats_survive_churn_as_rebuild_stats_would_compute_them() {
+        let tree = TempTree::new("churn");
+        let names = ["a.txt", "b.txt", "c.csv", "d.rs"];
+        for name in names {
+            tree.write(name, b"seed");
+        }
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&tree.0, None).await.unwrap();
 
+        let base = SystemTime::now();
+        for round in 1..=20u64 {
+            let name = names[round as usize % names.len()];
+            let path = tree.0.join(name);
+            if round % 5 == 0 {
+                fs::remove_file(&path).unwrap();
+            } else {
+                fs::write(&path, vec![b'x'; (round * 37) as usize]).unwrap();
+                fs::File::options().write(true).open(&path).unwrap()
+                    .set_modified(base + Duration::from_secs(round * 10)).unwrap();
+            }
+            indexer.update_file(&path).await.unwrap();
+            if round % 7 == 0 {
+                indexer.remove_file(&tree.0.join(names[0]));
+            }
+            assert!(indexer.verify_stats(), "stats drifted in round {}", round);
+        }
+
+        let (file_type_stats, total_size) = (indexer.file_type_stats.clone(), indexer.total_size);
+        indexer.rebuild_stats();
+        assert_eq!(indexer.total_size, total_size);
+        let live = |stats: &HashMap<FileType, usize>| -> HashMap<FileType, usize> {
+            stats.iter().filter(|(_, &count)| count > 0).map(|(kind, &count)| (kind.clone(), count)).collect()
+        };
+        assert_eq!(live(&indexer.file_type_stats), live(&file_type_stats));
+    }
+
+    #[test]
+    fn dotfiles_are_skipped_unless_enabled_or_allowlisted() {
+        let wanted = |indexer: &FilesystemIndexer| -> Vec<&str> {
+            ["/home/u/.bashrc", "/home/u/.env.example", "/home/u/notes.txt"].into_iter()
+                .filter(|path| indexer.should_index_file(Path::new(path), || None))
+                .collect()
+        };
+        let mut indexer = FilesystemIndexer::new();
+        assert_eq!(wanted(&indexer), ["/home/u/notes.txt"]);
+
+        indexer.allow_hidden(r"^\.env\.example$").unwrap();
+        assert_eq!(wanted(&indexer), ["/home/u/.env.example", "/home/u/notes.txt"]);
+
+        indexer.set_index_hidden(true);
+        assert_eq!(wanted(&indexer), ["/home/u/.bashrc", "/home/u/.env.example", "/home/u/notes.txt"]);
+    }
+
+    #[tokio::test]
+    async fn the_last_progress_update_has_the_true_totals() {
+        let tree = TempTree::new("progress-totals");
+        let total = 130;
+        for i in 0..total {
+            tree.write(&format!("part-{}/note-{i}.txt", i % 3), format!("zeppelin note {i}").as_bytes());
+        }
+
+        for network in [false, true] {
+            let mut indexer = FilesystemIndexer::new();
+            if network {
+                indexer.add_network_root(tree.0.clone());
+            }
+            indexer.set_progress_interval(Duration::from_millis(1));
+            let (tx, mut rx) = mpsc::channel::<IndexProgress>(16);
+            let collector = tokio::spawn(async move {
+                let mut updates = Vec::new();
+                while let Some(progress) = rx.recv().await {
+                    updates.push(progress);
+                }
+                updates
+            });
+            indexer.index_path(&tree.0, Some(tx)).await.unwrap();
+            let updates = collector.await.unwrap();
+
+            assert_eq!(indexer.file_count(), total);
+            let last = updates.last().unwrap();
+            assert_eq!((last.files_indexed, last.current_path.as_str()), (total, "Indexing complete"), "network {network}");
+            assert!(last.dirs_scanned >= 3);
+            assert!(updates.windows(2).all(|pair| pair[0].files_indexed <= pair[1].files_indexed));
+        }
+    }
+
+    #[tokio::test]
+    async fn network_calls_time_out_and_are_retried() {
+        let options = NetworkOptions {
+            io_timeout: Duration::from_millis(50),
+            retries: 2,
+            retry_backoff: Duration::from_millis(1),
+            max_concurrent: 2,
+        };
+        let semaphore = Arc::new(Semaphore::new(options.max_concurrent));
+        let counters = ProgressCounters::default();
+        let stop = AtomicBool::new(false);
+
+        // A stat that hangs once, as on a share that is waking up, then answers
+        let calls = Arc::new(AtomicUsize::new(0));
+        let attempts = calls.clone();
+        let start = Instant::now();
+        let answer = FilesystemIndexer::network_io(&options, &semaphore, &counters, &stop, move || {
+            if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                std::thread::sleep(Duration::from_secs(2));
+            }
+            Ok(42)
+        }).await;
+        assert_eq!(answer.unwrap(), 42);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!((calls.load(Ordering::Relaxed), counters.io_retries.load(Ordering::Relaxed)), (2, 1));
+
+        // One that never answers gives up after the retries
+        let hung: io::Result<()> = FilesystemIndexer::network_io(&options, &semaphore, &counters, &stop, || {
+            std::thread::sleep(Duration::from_secs(2));
+            Ok(())
+        }).await;
+        assert_eq!(hung.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(counters.io_retries.load(Ordering::Relaxed), 3);
+
+        // The hung calls still hold every permit, so nothing more reaches the share
+        let blocked: io::Result<()> = FilesystemIndexer::network_io(&options, &semaphore, &counters, &stop, || Ok(())).await;
+        assert_eq!(blocked.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(counters.io_retries.load(Ordering::Relaxed), 5);
+
+        // A missing file is an answer, not a reason to retry
+        let semaphore = Arc::new(Semaphore::new(options.max_concurrent));
+        let missing: io::Result<()> = FilesystemIndexer::network_io(&options, &semaphore, &counters, &stop, || {
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        }).await;
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(counters.io_retries.load(Ordering::Relaxed), 5);
+    }
+
+    fn gzip(text: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(text).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn gzipped_text_is_searchable_by_its_contents() {
+        let tree = TempTree::new("gzip");
+        let log = tree.write("access.log.gz", &gzip(b"2024-03-04 09:12:00 ERROR zeppelin mooring mast timed out\n"));
+        let notes = tree.write("notes.txt.gz", &gzip(b"the harbour crane needs new cables"));
+        // Expands past the plain-text limit from a few kilobytes
+        let bomb = tree.write("bomb.txt.gz", &gzip(&vec![b'a'; 11_000_000]));
+
+        for read_compressed in [true, false] {
+            let mut indexer = FilesystemIndexer::new();
+            indexer.set_read_compressed(read_compressed);
+            indexer.index_path(&tree.0, None).await.unwrap();
+            let mut engine = ResonantEngine::new();
+            for file in indexer.get_all_files() {
+                engine.add_filesystem_document(file);
+            }
+
+            let found = |engine: &mut ResonantEngine, query: &str| -> Vec<String> {
+                engine.search(query, 5).into_iter().map(|result| result.path).collect()
+            };
+            if read_compressed {
+                assert_eq!(indexer.get_file_by_path(&log).unwrap().file_type, FileType::Archive);
+                assert_eq!(found(&mut engine, "zeppelin"), [log.display().to_string()]);
+                assert_eq!(found(&mut engine, "cables"), [notes.display().to_string()]);
+                let bomb = indexer.get_file_by_path(&bomb).unwrap().content_text().unwrap();
+                assert!(bomb.starts_with("Large file: bomb.txt.gz"), "{}", &bomb[..40.min(bomb.len())]);
+            } else {
+                assert!(found(&mut engine, "zeppelin").is_empty());
+                assert!(found(&mut engine, "cables").is_empty());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn file_snippets_show_the_extracted_content() {
+        let tree = TempTree::new("file-snippet");
+        let path = tree.write("minutes/harbour-board.txt", b"Minutes of the harbour board: the zeppelin mooring mast stays until spring.");
+        let mut indexer = FilesystemIndexer::new();
+        indexer.index_path(&tree.0, None).await.unwrap();
+        let mut engine = ResonantEngine::new();
+        engine.add_filesystem_document(indexer.get_file_by_path(&path).unwrap());
+
+        let results = engine.search("zeppelin", 5);
+        assert_eq!(results.len(), 1);
+        let snippet = &results[0].snippet;
+        assert!(snippet.starts_with("Minutes of the harbour board: the zeppelin mooring mast"), "{snippet}");
+        assert!(!snippet.contains("harbour-board.txt") && !snippet.contains("minutes/"), "{snippet}");
+    }
+}
@@ -0,0 +1,386 @@
+// src/fuzzy_search.rs - Advanced fuzzy matching for "I can't remember the name" scenarios
+
+use crate::filesystem_indexer::{IndexedFile, FileType};
+use crate::tokenizer::NumericTokens;
+use std::collections::BinaryHeap;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Heap entry for top-K selection. Ordered so the weakest match (lowest score,
+// then latest seen) sits at the top of the max-heap and is evicted first.
+struct RankedMatch<'a> {
+    score: f64,
+    seq: usize,
+    file: &'a IndexedFile,
+}
+
+impl PartialEq for RankedMatch<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for RankedMatch<'_> {}
+
+impl PartialOrd for RankedMatch<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedMatch<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.score.total_cmp(&self.score).then(self.seq.cmp(&other.seq))
+    }
+}
+
+pub struct FuzzyMatcher {
+    // Weights for different match types
+    exact_weight: f64,
+    prefix_weight: f64,
+    substring_weight: f64,
+    soundex_weight: f64,
+    levenshtein_weight: f64,
+    // Score files on the rayon pool (only with the `parallel` feature)
+    parallel: bool,
+    numeric_tokens: NumericTokens,
+}
+
+impl FuzzyMatcher {
+    pub fn new() -> Self {
+        Self {
+            exact_weight: 10.0,
+            prefix_weight: 8.0,
+            substring_weight: 5.0,
+            soundex_weight: 3.0,
+            levenshtein_weight: 2.0,
+            parallel: cfg!(feature = "parallel"),
+            numeric_tokens: NumericTokens::default(),
+        }
+    }
+    
+    /// Choose whether numbers and version strings in names are matched as words
+    pub fn set_numeric_tokens(&mut self, policy: NumericTokens) {
+        self.numeric_tokens = policy;
+    }
+    
+    /// Enable or disable parallel scoring. Has no effect unless the crate is
+    /// built with the `parallel` feature.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+    
+    pub fn find_matches<'a>(
+        &self,
+        files: impl Iterator<Item = &'a IndexedFile> + Send,
+        query: &str,
+        max_results: usize
+    ) -> Vec<(&'a IndexedFile, f64)> {
+        if max_results == 0 {
+            return Vec::new();
+        }
+        
+        let query_lower = query.to_lowercase();
+        let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+        
+        // Keep only the best max_results matches while scanning; ties keep
+        // the earlier file, same as a stable sort of every match would
+        let score_into = |mut heap: BinaryHeap<RankedMatch<'a>>, (seq, file): (usize, &'a IndexedFile)| {
+            let score = self.calculate_fuzzy_score(file, &query_lower, &query_words);
+            if score > 0.0 {
+                Self::offer(&mut heap, RankedMatch { score, seq, file }, max_results);
+            }
+            heap
+        };
+        
+        #[cfg(feature = "parallel")]
+        let heap = if self.parallel {
+            // Each worker keeps its own top-K; the partial heaps are then merged
+            files.enumerate()
+                .par_bridge()
+                .fold(|| BinaryHeap::with_capacity(max_results + 1), score_into)
+                .reduce(BinaryHeap::new, |mut merged, partial| {
+                    for m in partial {
+                        Self::offer(&mut merged, m, max_results);
+                    }
+                    merged
+                })
+        } else {
+            files.enumerate().fold(BinaryHeap::with_capacity(max_results + 1), score_into)
+        };
+        #[cfg(not(feature = "parallel"))]
+        let heap = files.enumerate().fold(BinaryHeap::with_capacity(max_results + 1), score_into);
+        
+        // Ascending order of the heap's Ord is best-first
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|m| (m.file, m.score))
+            .collect()
+    }
+    
+    // Push into a heap holding at most `limit` entries, evicting the weakest
+    fn offer<'a>(heap: &mut BinaryHeap<RankedMatch<'a>>, candidate: RankedMatch<'a>, limit: usize) {
+        if heap.len() < limit {
+            heap.push(candidate);
+        } else if heap.peek().is_some_and(|weakest| candidate < *weakest) {
+            heap.pop();
+            heap.push(candidate);
+        }
+    }
+    
+    fn calculate_fuzzy_score(&self, file: &IndexedFile, query: &str, query_words: &[&str]) -> f64 {
+        let mut total_score = 0.0;
+        
+        // Get searchable text from file
+        let searchable_text = self.get_searchable_text(file);
+        let filename_lower = file.display_name.to_lowercase();
+        let path_lower = file.path.to_string_lossy().to_lowercase();
+        
+        // Score against filename
+        total_score += self.score_text_match(&filename_lower, query, query_words) * 2.0; // Filename gets double weight
+        
+        // Score against full path
+        total_score += self.score_text_match(&path_lower, query, query_words);
+        
+        // Score against extracted content
+        total_score += self.score_text_match(&searchable_text, query, query_words) * 0.5;
+        
+        // Bonus for file type relevance
+        total_score += self.score_file_type_relevance(file, query_words);
+        
+        // Recency boost
+        total_score *= self.calculate_recency_multiplier(file);
+        
+        total_score
+    }
+    
+    fn get_searchable_text(&self, file: &IndexedFile) -> String {
+        let mut text = Vec::new();
+        
+        // Add filename words
+        text.extend(self.extract_words(&file.display_name));
+        
+        // Add directory names
+        for component in file.path.components() {
+            if let Some(name) = component.as_os_str().to_str() {
+                text.extend(self.extract_words(name));
+            }
+        }
+        
+        // Add file type
+        text.push(file.file_type.as_str().to_string());
+        
+        text.join(" ")
+    }
+    
+    fn extract_words(&self, text: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        
+        // Split on common separators
+        let separators = regex::Regex::new(r"[_\-\.\s/\\]+").unwrap();
+        words.extend(separators.split(text).map(|s| s.to_lowercase()));
+        
+        // Split camelCase
+        let camel_re = regex::Regex::new(r"([a-z])([A-Z])").unwrap();
+        let camel_split = camel_re.replace_all(text, "$1 $2");
+        words.extend(camel_split.split_whitespace().map(|s| s.to_lowercase()));
+        
+        // Filter meaningful words; numbers are up to the policy
+        words.retain(|w| if NumericTokens::is_number(w) { self.numeric_tokens.keeps(w) } else { w.len() > 1 });
+        words.extend(self.numeric_tokens.versions(text));
+        words
+    }
+    
+    fn score_text_match(&self, text: &str, query: &str, query_words: &[&str]) -> f64 {
+        let mut score = 0.0;
+        
+        // Exact match
+        if text == query {
+            score += self.exact_weight;
+        }
+        
+        // Prefix match
+        if text.starts_with(query) {
+            score += self.prefix_weight;
+        }
+        
+        // Substring match
+        if text.contains(query) {
+            score += self.substring_weight;
+        }
+        
+        // Word-by-word matching
+        for word in query_words {
+            if text.contains(word) {
+                score += self.substring_weight * 0.8;
+            }
+            
+            // Fuzzy word matching
+            score += self.score_fuzzy_word_match(text, word);
+        }
+        
+        score
+    }
+    
+    fn score_fuzzy_word_match(&self, text: &str, word: &str) -> f64 {
+        let mut best_score: f64 = 0.0;
+        
+        // Split text into words and check each
+        for text_word in text.split_whitespace() {
+            let mut word_score = 0.0;
+            
+            // Levenshtein distance
+            let distance = self.levenshtein_distance(word, text_word);
+            let max_len = word.len().max(text_word.len());
+            if max_len > 0 {
+                let similarity = 1.0 - (distance as f64 / max_len as f64);
+                if similarity > 0.7 { // Only consider good matches
+                    word_score += self.levenshtein_weight * similarity;
+                }
+            }
+            
+            // Soundex matching for phonetic similarity
+            if self.soundex_match(word, text_word) {
+                word_score += self.soundex_weight;
+            }
+            
+            best_score = best_score.max(word_score);
+        }
+        
+        best_score
+    }
+    
+    fn score_file_type_relevance(&self, file: &IndexedFile, query_words: &[&str]) -> f64 {
+        let file_type_keywords = match file.file_type {
+            FileType::Code => vec!["code", "source", "script", "program"],
+            FileType::Document => vec!["doc", "document", "text", "paper"],
+            FileType::Image => vec!["image", "picture", "photo", "graphic"],
+            FileType::Audio => vec!["audio", "sound", "music", "song"],
+            FileType::Video => vec!["video", "movie", "clip", "film"],
+            FileType::Archive => vec!["archive", "zip", "compressed"],
+            FileType::Config => vec!["config", "configuration", "settings"],
+            FileType::Data => vec!["data", "database", "csv", "excel"],
+            FileType::Log => vec!["log", "logs", "debug", "error"],
+            FileType::Markdown => vec!["markdown", "readme", "documentation"],
+            FileType::Email => vec!["email", "mail", "message", "inbox"],
+            _ => vec![],
+        };
+        
+        let mut relevance_score = 0.0;
+        for keyword in file_type_keywords {
+            for query_word in query_words {
+                if keyword.contains(query_word) || query_word.contains(keyword) {
+                    relevance_score += 2.0;
+                }
+            }
+        }
+        
+        relevance_score
+    }
+    
+    fn calculate_recency_multiplier(&self, file: &IndexedFile) -> f64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        
+        let age_days = (now - file.modified) / (24 * 3600);
+        
+        match age_days {
+            0..=1 => 1.5,      // Last day: 50% boost
+            2..=7 => 1.3,      // Last week: 30% boost
+            8..=30 => 1.1,     // Last month: 10% boost
+            31..=90 => 1.0,    // Last 3 months: no change
+            _ => 0.9,          // Older: 10% penalty
+        }
+    }
+    
+    fn levenshtein_distance(&self, s1: &str, s2: &str) -> usize {
+        let len1 = s1.chars().count();
+        let len2 = s2.chars().count();
+        
+        if len1 == 0 { return len2; }
+        if len2 == 0 { return len1; }
+        
+        let mut matrix = vec![vec![0; len2 + 1]; len1 + 1];
+        
+        // Initialize first row and column
+        for i in 0..=len1 {
+            matrix[i][0] = i;
+        }
+        for j in 0..=len2 {
+            matrix[0][j] = j;
+        }
+        
+        let s1_chars: Vec<char> = s1.chars().collect();
+        let s2_chars: Vec<char> = s2.chars().collect();
+        
+        for i in 1..=len1 {
+            for j in 1..=len2 {
+                let cost = if s1_chars[i-1] == s2_chars[j-1] { 0 } else { 1 };
+                matrix[i][j] = std::cmp::min(
+                    std::cmp::min(
+                        matrix[i-1][j] + 1,     // deletion
+                        matrix[i][j-1] + 1      // insertion
+                    ),
+                    matrix[i-1][j-1] + cost     // substitution
+                );
+            }
+        }
+        
+        matrix[len1][len2]
+    }
+    
+    fn soundex_match(&self, word1: &str, word2: &str) -> bool {
+        if word1.len() < 3 || word2.len() < 3 {
+            return false;
+        }
+        
+        self.soundex(word1) == self.soundex(word2)
+    }
+    
+    fn soundex(&self, word: &str) -> String {
+        if word.is_empty() {
+            return "0000".to_string();
+        }
+        
+        let word = word.to_uppercase();
+        let chars: Vec<char> = word.chars().collect();
+        let mut result = String::new();
+        
+        // First character is always kept
+        result.push(chars[0]);
+        
+        let mut prev_code = self.soundex_code(chars[0]);
+        
+        for &ch in chars.iter().skip(1) {
+            let code = self.soundex_code(ch);
+            if code != '0' && code != prev_code {
+                result.push(code);
+                if result.len() == 4 {
+                    break;
+                }
+            }
+            prev_code = code;
+        }
+        
+        // Pad with zeros
+        while result.len() < 4 {
+            result.push('0');
+        }
+        
+        result
+    }
+    
+    fn soundex_code(&self, ch: char) -> char {
+        match ch {
+            'B' | 'F' | 'P' | 'V' => '1',
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => '2',
+            'D' | 'T' => '3',
+            'L' => '4',
+            'M' | 'N' => '5',
+            'R' => '6',
+            _ => '0',
+        }
+    }
+}
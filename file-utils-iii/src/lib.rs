@@ -1,22 +1,43 @@
 // src/lib.rs
 
+#[macro_use]
+pub mod diagnostics;
 pub mod tokenizer;
 pub mod entropy;
 pub mod prime_hilbert;
 pub mod engine;
 pub mod crawler;
+pub mod page_renderer;
+pub mod encoding;
+pub mod file_attributes;
+#[cfg(feature = "email")]
+pub mod mail_archive;
+pub mod filesystem_indexer;
+pub mod enhanced_engine;
 pub mod quantum_types;
 
 // Re-export key types and functions
 pub use engine::ResonantEngine;
-pub use engine::SearchResult;
-pub use crawler::CrawledDocument;
+pub use tokenizer::NumericTokens;
+pub use engine::{FederatedSearch, FederatedResult};
+pub use engine::{SearchResult, SearchResultNoSnippet, DocumentSource, Reranker, MatchField, TermMatch};
+pub use engine::{DisplayOptions, ScoreDisplay, format_search_result};
+pub use engine::{SearchOutcome, ScoreNormalization, SourceCalibration, SourceScale, SimilarityMetric, ScoreCombination, TimedSearch, QueryPlan, PlanStage};
+pub use engine::{ClusterBy, ResultCluster};
+pub use engine::{EngineSnapshot, DocumentSnapshot, LearnedState, VectorFormat};
+pub use engine::{PersistenceParams, ScoreWeights, InvalidParameter, EngineSettings, DenseOverflow};
+pub use engine::{Judgment, RankingMetric, WeightFit};
+pub use engine::{Clock, SystemClock, FixedClock};
+pub use crawler::{CrawledDocument, CrawlState, SeedConfig, load_seed_config, normalize_url};
+pub use page_renderer::PageRenderer;
+pub use encoding::decode_text;
 pub use prime_hilbert::{PrimeVector, BiorthogonalVector};
 pub use quantum_types::{MatrixComplex, VectorComplex};
 
 // Export key persistence theory functions
 pub use entropy::{
     shannon_entropy,
+    char_ngram_entropy,
     calculate_reversibility,
     entropy_pressure,
     buffering_capacity,
@@ -0,0 +1,144 @@
+// src/mail_archive.rs
+//
+// Local mail archives, indexed one entry per message: mbox files (a whole
+// folder in one file, each message starting with a "From " line), maildir
+// folders (one file per message under cur/ and new/) and single .eml files.
+// Only compiled with the `email` feature.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use mailparse::{DispositionType, MailHeaderMap, MailParseError, ParsedMail};
+use scraper::Html;
+
+/// What gets indexed from one message
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub subject: String,
+    /// The text parts, or the HTML parts' text when there is no plain text
+    pub body: String,
+    /// Sender, recipients and date, e.g. "From: Ana <ana@example.com>"
+    pub tags: Vec<String>,
+    /// The Date header as seconds since the epoch
+    pub date: Option<u64>,
+}
+
+pub fn parse_message(raw: &[u8]) -> Result<MailMessage, MailParseError> {
+    let mail = mailparse::parse_mail(raw)?;
+    let subject = mail.headers.get_first_value("Subject").unwrap_or_default().trim().to_string();
+
+    let mut tags = Vec::new();
+    for header in ["From", "To", "Cc", "Date"] {
+        for value in mail.headers.get_all_values(header) {
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !value.is_empty() {
+                tags.push(format!("{}: {}", header, value));
+            }
+        }
+    }
+    let date = mail.headers.get_first_value("Date")
+        .and_then(|date| mailparse::dateparse(&date).ok())
+        .and_then(|seconds| u64::try_from(seconds).ok());
+
+    let mut body = body_text(&mail, "text/plain");
+    if body.trim().is_empty() {
+        let html = body_text(&mail, "text/html");
+        body = Html::parse_fragment(&html).root_element().text().collect::<Vec<_>>().join(" ");
+    }
+
+    Ok(MailMessage { subject, body, tags, date })
+}
+
+// The decoded text of every `mimetype` part, attachments aside
+fn body_text(part: &ParsedMail, mimetype: &str) -> String {
+    if part.get_content_disposition().disposition == DispositionType::Attachment {
+        return String::new();
+    }
+    if part.subparts.is_empty() {
+        if part.ctype.mimetype.eq_ignore_ascii_case(mimetype) {
+            part.get_body().unwrap_or_default()
+        } else {
+            String::new()
+        }
+    } else {
+        part.subparts.iter()
+            .map(|subpart| body_text(subpart, mimetype))
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Whether the mail entry at `path` is an mbox: named .mbox or .mbx, or
+/// without an extension outside a maildir, as mail clients name their
+/// folders (found by `starts_like_mbox`). Doesn't read the file.
+pub fn is_mbox(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("mbox") || ext.eq_ignore_ascii_case("mbx"),
+        None => !is_maildir_message(path),
+    }
+}
+
+/// Whether `path` has no extension and starts with a "From " line, as the
+/// mbox folders of mail clients do
+pub fn starts_like_mbox(path: &Path) -> bool {
+    let mut start = [0u8; 5];
+    path.extension().is_none()
+        && fs::File::open(path).and_then(|mut file| file.read_exact(&mut start)).is_ok()
+        && &start == b"From "
+}
+
+/// Whether `path` is a message in a maildir: a file in its cur/ or new/
+/// folder, next to the tmp/ folder every maildir has
+pub fn is_maildir_message(path: &Path) -> bool {
+    let Some(folder) = path.parent() else {
+        return false;
+    };
+    matches!(folder.file_name().and_then(|name| name.to_str()), Some("cur" | "new"))
+        && folder.parent().is_some_and(|maildir| maildir.join("tmp").is_dir())
+}
+
+/// The messages of an mbox, in order. Each starts after a "From " line at
+/// the start of the file or after a blank line; body lines escaped as
+/// ">From " lose one '>'.
+pub fn split_mbox(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    let mut after_blank = true;
+
+    for line in data.split_inclusive(|&b| b == b'\n') {
+        if after_blank && line.starts_with(b"From ") {
+            messages.extend(current.take());
+            current = Some(Vec::new());
+        } else if let Some(message) = current.as_mut() {
+            let quoted = line.iter().take_while(|&&b| b == b'>').count();
+            if quoted > 0 && line[quoted..].starts_with(b"From ") {
+                message.extend_from_slice(&line[1..]);
+            } else {
+                message.extend_from_slice(line);
+            }
+        }
+        after_blank = line.iter().all(|b| b.is_ascii_whitespace());
+    }
+    messages.extend(current);
+    messages
+}
+
+pub fn read_mbox(path: &Path) -> io::Result<Vec<Vec<u8>>> {
+    Ok(split_mbox(&fs::read(path)?))
+}
+
+/// The virtual path of the `number`th message (from 1) of an mbox:
+/// `Inbox.mbox#3`
+pub fn message_path(mbox: &Path, number: usize) -> PathBuf {
+    let mut path = mbox.as_os_str().to_owned();
+    path.push(format!("#{}", number));
+    PathBuf::from(path)
+}
+
+/// The mbox a virtual message path points into, if it is one
+pub fn mailbox_of(path: &Path) -> Option<PathBuf> {
+    let (mbox, number) = path.to_str()?.rsplit_once('#')?;
+    number.parse::<usize>().ok()?;
+    Some(PathBuf::from(mbox))
+}
@@ -1,36 +1,63 @@
 // main.rs - Quantum Resonant Local Filesystem Search Engine
 // Enhanced for blazing-fast local filesystem indexing and searching
 
+#[macro_use]
+mod diagnostics;
 mod tokenizer;
 mod entropy;
 mod prime_hilbert;
 mod engine;
+mod encoding;
+mod crawler;
+mod page_renderer;
 mod filesystem_indexer;
+mod file_attributes;
+#[cfg(feature = "email")]
+mod mail_archive;
 mod quantum_types;
 mod file_watcher;
 mod fuzzy_search;
+mod enhanced_engine;
+mod cli_enhancements;
+mod performance_monitor;
+mod query_processor;
+mod settings;
 
-use engine::ResonantEngine;
-use filesystem_indexer::{FilesystemIndexer, IndexedFile};
+use engine::{ResonantEngine, SearchOutcome, SearchResult, ClusterBy, DisplayOptions, ScoreCombination, ScoreDisplay, ScoreWeights, SimilarityMetric, VectorFormat, format_search_result};
+use filesystem_indexer::{FilesystemIndexer, IndexedFile, IndexProgress, ContentFill, ReportFormat};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use file_watcher::FileWatcher;
 use fuzzy_search::FuzzyMatcher;
-use std::io::{self, Write};
+use settings::Settings;
+use std::io;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH, Instant};
+use std::time::{Duration, SystemTime, UNIX_EPOCH, Instant};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
 use std::thread;
 use tokio::sync::mpsc;
 use ctrlc;
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
-    println!("=====================================================");
-    println!("🧠 Quantum Resonant Local Filesystem Search Engine");
-    println!("    \"The closest thing to mindreading for files\"");
-    println!("=====================================================");
+    // Results go to stdout, everything else to stderr; `--quiet` drops the rest
+    diagnostics::set_quiet(parse_quiet(std::env::args().skip(1)));
+    
+    diag!("=====================================================");
+    diag!("🧠 Quantum Resonant Local Filesystem Search Engine");
+    diag!("    \"The closest thing to mindreading for files\"");
+    diag!("=====================================================");
+
+    let stats_format = parse_stats_format(std::env::args().skip(1));
+    let display_options = parse_display_options(std::env::args().skip(1));
+    let cluster_by = parse_cluster_by(std::env::args().skip(1));
+    let error_report = parse_error_report(std::env::args().skip(1));
+
+    let config_path = parse_config_path(std::env::args().skip(1)).or_else(Settings::default_path);
 
     // Initialize the quantum engine
-    let mut engine = ResonantEngine::new();
+    let engine = ResonantEngine::new();
     let engine_arc = Arc::new(Mutex::new(engine));
     
     // Initialize filesystem indexer
@@ -42,10 +69,13 @@ async fn main() -> io::Result<()> {
     // Setup graceful shutdown
     let running = Arc::new(Mutex::new(true));
     let running_clone = running.clone();
+    // Also interrupts a filesystem scan in progress
+    let stop_indexing = indexer.stop_handle();
     
     ctrlc::set_handler(move || {
-        println!("\n🛑 Gracefully shutting down...");
+        diag!("\n🛑 Gracefully shutting down...");
         *running_clone.lock().unwrap() = false;
+        stop_indexing.store(true, Ordering::Relaxed);
     }).expect("Error setting Ctrl-C handler");
 
     // Check for existing index
@@ -53,9 +83,8 @@ async fn main() -> io::Result<()> {
     let resume_from_index = Path::new(index_path).exists();
     
     if resume_from_index {
-        println!("📁 Found existing quantum index. Load it? (y/n)");
-        print!("> ");
-        io::stdout().flush()?;
+        diag!("📁 Found existing quantum index. Load it? (y/n)");
+        diag_print!("> ");
         
         let mut choice = String::new();
         io::stdin().read_line(&mut choice)?;
@@ -63,76 +92,140 @@ async fn main() -> io::Result<()> {
         if choice.trim().to_lowercase().starts_with('y') {
             let start = Instant::now();
             indexer.load_index(index_path)?;
-            println!("⚡ Loaded {} files in {:?}", indexer.file_count(), start.elapsed());
+            diag!("⚡ Loaded {} files in {:?}", indexer.file_count(), start.elapsed());
+        }
+    }
+
+    // Relevance feedback and other learned state live apart from the content index
+    let learned_path = "quantum_fs_learned.db";
+    if Path::new(learned_path).exists() {
+        match engine_arc.lock().unwrap().load_learned_state(learned_path) {
+            Ok(count) => diag!("🧠 Restored learned relevance for {} paths", count),
+            Err(e) => eprintln!("❌ Could not load learned state: {}", e),
         }
     }
 
-    // Configure search paths
-    let search_paths = configure_search_paths()?;
+    // Search paths and quantum features: saved settings, or ask on first run
+    let search_paths = configure(&engine_arc, &mut indexer, config_path.as_deref())?;
     
-    // Configure quantum features
-    configure_quantum_features(&engine_arc)?;
+    // Command-line options override the saved settings for this run
+    if let Some(threshold) = parse_dedup_threshold(std::env::args().skip(1)) {
+        if let Err(e) = engine_arc.lock().unwrap().set_result_dedup(Some(threshold)) {
+            diag!("⚠️  Not collapsing duplicate results: {}", e);
+        }
+    }
+    if let Some(max) = parse_max_per_directory(std::env::args().skip(1)) {
+        engine_arc.lock().unwrap().set_max_per_directory(Some(max));
+    }
+    if let Some(depth) = parse_max_depth(std::env::args().skip(1)) {
+        indexer.set_max_depth(depth);
+    }
+    if let Some(metric) = parse_similarity_metric(std::env::args().skip(1)) {
+        engine_arc.lock().unwrap().set_similarity_metric(metric);
+    }
+    if let Some(combination) = parse_score_combination(std::env::args().skip(1)) {
+        engine_arc.lock().unwrap().set_score_combination(combination);
+    }
     
     // Start filesystem indexing if needed
     if indexer.file_count() == 0 || should_reindex()? {
-        println!("🔍 Starting quantum filesystem scan...");
+        diag!("🔍 Starting quantum filesystem scan...");
         let start = Instant::now();
         
         // Create progress channel
         let (progress_tx, mut progress_rx) = mpsc::channel::<IndexProgress>(1000);
         
-        // Spawn progress monitor
+        // Spawn progress monitor; the indexer already paces its updates
         let progress_handle = tokio::spawn(async move {
-            let mut last_update = Instant::now();
             while let Some(progress) = progress_rx.recv().await {
-                if last_update.elapsed().as_millis() > 500 { // Update every 500ms
-                    print!("\r📂 Indexed: {} files, {} dirs, Current: {}", 
-                           progress.files_indexed, 
-                           progress.dirs_scanned,
-                           truncate_path(&progress.current_path, 60));
-                    io::stdout().flush().unwrap();
-                    last_update = Instant::now();
+                diag_print!("\r📂 Indexed: {} files, {} dirs, Current: {:<60}", 
+                       progress.files_indexed, 
+                       progress.dirs_scanned,
+                       truncate_path(&progress.current_path, 60));
+                if progress.io_retries > 0 || progress.unreachable > 0 {
+                    diag_print!(" ({} retries, {} unreachable)", progress.io_retries, progress.unreachable);
                 }
             }
         });
         
         // Index all search paths
+        let mut interrupted = false;
         for path in &search_paths {
-            indexer.index_path(path, Some(progress_tx.clone())).await?;
+            match indexer.index_path(path, Some(progress_tx.clone())).await {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    interrupted = true;
+                    break;
+                },
+                result => result?,
+            }
         }
         
         drop(progress_tx); // Close channel
         progress_handle.await.unwrap();
+        report_index_errors(&indexer, error_report.as_deref());
+        
+        if interrupted {
+            // Keep what was scanned: loading it next time gives a usable partial
+            // index, and a rescan completes it
+            indexer.save_index(index_path)?;
+            diag!("\n🛑 Scan interrupted after {} files; the partial index was saved to {}, rescan to complete it", indexer.file_count(), index_path);
+            return Ok(());
+        }
         
-        println!("\n⚡ Quantum scan complete! {} files indexed in {:?}", 
+        diag!("\n⚡ Quantum scan complete! {} files indexed in {:?}", 
                 indexer.file_count(), start.elapsed());
         
         // Save the index
         indexer.save_index(index_path)?;
-        println!("💾 Index saved to {}", index_path);
+        diag!("💾 Index saved to {}", index_path);
     }
 
     // Build quantum vectors for all indexed files
-    println!("🧮 Building quantum resonance vectors...");
+    diag!("🧮 Building quantum resonance vectors...");
     let start = Instant::now();
-    build_quantum_index(&engine_arc, &indexer).await;
-    println!("⚡ Quantum vectors built in {:?}", start.elapsed());
+    if !build_quantum_index(&engine_arc, &indexer).await {
+        diag!("\n🛑 Vector build interrupted after {} of {} files; the index is saved, so the next start builds them again from it", engine_arc.lock().unwrap().len(), indexer.file_count());
+        return Ok(());
+    }
+    // Pay the one-off costs now rather than on the first query
+    engine_arc.lock().unwrap().warm_up();
+    diag!("\n⚡ Quantum vectors built in {:?}", start.elapsed());
+
+    // Fill in file contents in the background; search quality improves as they arrive
+    let mut content_fill = start_content_fill(&indexer);
+
+    // Periodically fade old documents and flag stale ones
+    let aging_engine = engine_arc.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            ticker.tick().await;
+            aging_engine.lock().unwrap().age_documents(false);
+        }
+    });
 
     // Start file watcher for real-time updates
     let watcher = Arc::new(Mutex::new(FileWatcher::new()));
     start_file_watcher(watcher.clone(), &search_paths, engine_arc.clone())?;
 
     // Main search loop
-    println!("\n🚠 Quantum search ready! Enter queries or commands:");
-    println!("Commands: 'reindex', 'stats', 'fuzzy <pattern>', 'quantum <query>', 'quit'");
+    diag!("\n🚠 Quantum search ready! Enter queries or commands:");
+    diag!("Commands: 'reindex', 'repair', 'retry', 'merge <index file>', 'stats [json]', 'fuzzy <pattern>', 'quantum <query>', 'explain <query>', 'relevant <path>', 'irrelevant <path>', 'vectors <file.npy|.csv|.bin>', 'diagnostics <queries file> <out.csv>', 'quit'");
     
     loop {
         if !*running.lock().unwrap() {
             break;
         }
 
-        print!("\n🔮 > ");
-        io::stdout().flush()?;
+        if let Some(ref fill) = content_fill {
+            if apply_content_fill(&mut indexer, fill, &engine_arc, index_path) {
+                content_fill = None;
+                // Contents that couldn't be read only show up now
+                report_index_errors(&indexer, error_report.as_deref());
+            }
+        }
+
+        diag_print!("\n🔮 > ");
 
         let mut input = String::new();
         match io::stdin().read_line(&mut input) {
@@ -142,21 +235,55 @@ async fn main() -> io::Result<()> {
 
                 match input {
                     "quit" | "exit" => break,
-                    "stats" => show_stats(&engine_arc, &indexer),
+                    "stats" => show_stats(&engine_arc, &indexer, stats_format),
+                    "stats json" => show_stats(&engine_arc, &indexer, StatsFormat::Json),
+                    "stats text" => show_stats(&engine_arc, &indexer, StatsFormat::Text),
                     "reindex" => {
-                        reindex_filesystem(&mut indexer, &search_paths, &engine_arc).await?;
+                        let reindexed = reindex_filesystem(&mut indexer, &search_paths, &engine_arc).await;
+                        report_index_errors(&indexer, error_report.as_deref());
+                        match reindexed {
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}, // Reported by reindex_filesystem
+                            result => result?,
+                        }
+                        // A metadata-first reindex leaves contents to fill in
+                        // again; any fill still running is for the old files
+                        content_fill = start_content_fill(&indexer);
+                        save_learned_state(&engine_arc, learned_path);
+                    },
+                    "repair" => repair_index(&mut indexer, &engine_arc),
+                    "retry" => {
+                        retry_extractions(&mut indexer, &engine_arc, index_path);
+                        report_index_errors(&indexer, error_report.as_deref());
+                    },
+                    input if input.starts_with("merge ") => {
+                        merge_index(&mut indexer, &engine_arc, input[6..].trim(), index_path);
                     },
                     input if input.starts_with("fuzzy ") => {
                         let pattern = &input[6..];
                         fuzzy_search(&fuzzy_matcher, &indexer, pattern);
                     },
+                    input if input.starts_with("relevant ") => {
+                        record_feedback(&engine_arc, input[9..].trim(), true);
+                    },
+                    input if input.starts_with("irrelevant ") => {
+                        record_feedback(&engine_arc, input[11..].trim(), false);
+                    },
+                    input if input.starts_with("vectors ") => {
+                        export_vectors(&engine_arc, input[8..].trim());
+                    },
+                    input if input.starts_with("diagnostics ") => {
+                        export_diagnostics(&engine_arc, input[12..].trim());
+                    },
+                    input if input.starts_with("explain ") => {
+                        explain_search(&engine_arc, input[8..].trim());
+                    },
                     input if input.starts_with("quantum ") => {
                         let query = &input[8..];
-                        quantum_search(&engine_arc, query).await;
+                        quantum_search(&engine_arc, query, &display_options, cluster_by).await;
                     },
                     query => {
                         // Default to quantum search
-                        quantum_search(&engine_arc, query).await;
+                        quantum_search(&engine_arc, query, &display_options, cluster_by).await;
                     }
                 }
             },
@@ -167,20 +294,63 @@ async fn main() -> io::Result<()> {
         }
     }
 
-    println!("🌟 Quantum search session ended. Index preserved for next time!");
+    save_learned_state(&engine_arc, learned_path);
+    diag!("🌟 Quantum search session ended. Index preserved for next time!");
     Ok(())
 }
 
 // Configuration functions
 
-fn configure_search_paths() -> io::Result<Vec<PathBuf>> {
-    println!("\n📂 Configure search paths:");
-    println!("1. Scan entire drive (C:\\ or /)");
-    println!("2. Scan home directory");
-    println!("3. Custom paths");
-    println!("4. Network paths (SMB/NFS)");
-    print!("> ");
-    io::stdout().flush()?;
+/// Apply the settings saved at `config_path`. If there are none yet, ask for
+/// the search paths and quantum features instead and save the answers, along
+/// with every other tunable, for next time. Settings that can't be used are an
+/// error, so the file is never overwritten with fresh answers.
+fn configure(
+    engine_arc: &Arc<Mutex<ResonantEngine>>,
+    indexer: &mut FilesystemIndexer,
+    config_path: Option<&Path>
+) -> io::Result<Vec<PathBuf>> {
+    if let Some(path) = config_path {
+        let applied = match Settings::load(path) {
+            Ok(Some(settings)) => settings.apply(&mut engine_arc.lock().unwrap(), indexer).map(|()| Some(settings)),
+            other => other,
+        };
+        match applied {
+            Ok(Some(settings)) => {
+                diag!("⚙️  Using settings from {}", path.display());
+                return Ok(settings.search_paths);
+            },
+            Ok(None) => {},
+            Err(e) => return Err(io::Error::new(e.kind(), format!(
+                "could not use settings from {}: {} (fix or delete the file to be asked again)",
+                path.display(), e
+            ))),
+        }
+    }
+    
+    let search_paths = configure_search_paths(indexer)?;
+    configure_quantum_features(engine_arc)?;
+    // Names first so search is usable right away; contents are extracted
+    // afterwards. Saved with the rest, so it can be turned off in the file.
+    indexer.set_metadata_first(true);
+    
+    if let Some(path) = config_path {
+        let settings = Settings::capture(&search_paths, &engine_arc.lock().unwrap(), indexer);
+        match settings.save(path) {
+            Ok(()) => diag!("💾 Settings saved to {} (delete it to be asked again)", path.display()),
+            Err(e) => eprintln!("❌ Could not save settings: {}", e),
+        }
+    }
+    Ok(search_paths)
+}
+
+fn configure_search_paths(indexer: &mut FilesystemIndexer) -> io::Result<Vec<PathBuf>> {
+    diag!("\n📂 Configure search paths:");
+    diag!("1. Scan entire drive (C:\\ or /)");
+    diag!("2. Scan home directory");
+    diag!("3. Custom paths");
+    diag!("4. Network paths (SMB/NFS)");
+    diag_print!("> ");
 
     let mut choice = String::new();
     io::stdin().read_line(&mut choice)?;
@@ -200,7 +370,7 @@ fn configure_search_paths() -> io::Result<Vec<PathBuf>> {
             }
         },
         "3" => {
-            println!("Enter paths separated by newlines (empty line to finish):");
+            diag!("Enter paths separated by newlines (empty line to finish):");
             let mut paths = Vec::new();
             loop {
                 let mut path_input = String::new();
@@ -211,35 +381,58 @@ fn configure_search_paths() -> io::Result<Vec<PathBuf>> {
                 let path = PathBuf::from(path_input);
                 if path.exists() {
                     paths.push(path);
-                    println!("✓ Added: {}", path_input);
+                    diag!("✓ Added: {}", path_input);
                 } else {
-                    println!("⚠️  Path doesn't exist: {}", path_input);
+                    diag!("⚠️  Path doesn't exist: {}", path_input);
                 }
             }
             Ok(paths)
         },
         "4" => {
-            println!("Enter network paths (//server/share or /mnt/network):");
-            // Network path handling would go here
-            Ok(vec![PathBuf::from(".")])
+            let mut options = indexer.network_options().clone();
+            diag_print!("Max concurrent network operations [{}]: ", options.max_concurrent);
+            let mut limit_input = String::new();
+            io::stdin().read_line(&mut limit_input)?;
+            if let Ok(limit) = limit_input.trim().parse::<usize>() {
+                options.max_concurrent = limit.max(1);
+            }
+            
+            diag!("Enter network paths (//server/share or /mnt/network), empty line to finish:");
+            let mut paths = Vec::new();
+            loop {
+                let mut path_input = String::new();
+                io::stdin().read_line(&mut path_input)?;
+                let path_input = path_input.trim();
+                if path_input.is_empty() { break; }
+                
+                let path = PathBuf::from(path_input);
+                match FilesystemIndexer::check_network_path(&path, options.io_timeout) {
+                    Ok(()) => {
+                        indexer.add_network_root(path.clone());
+                        paths.push(path);
+                        diag!("✓ Added: {}", path_input);
+                    },
+                    Err(e) => diag!("⚠️  Network path unreachable: {} ({})", path_input, e),
+                }
+            }
+            indexer.set_network_options(options);
+            Ok(paths)
         },
         _ => Ok(vec![PathBuf::from(".")]),
     }
 }
 
 fn configure_quantum_features(engine_arc: &Arc<Mutex<ResonantEngine>>) -> io::Result<()> {
-    println!("\n⚛️  Configure quantum features:");
+    diag!("\n⚛️  Configure quantum features:");
     
-    println!("Enable quantum-inspired scoring? (y/n)");
-    print!("> ");
-    io::stdout().flush()?;
+    diag!("Enable quantum-inspired scoring? (y/n)");
+    diag_print!("> ");
     let mut quantum_choice = String::new();
     io::stdin().read_line(&mut quantum_choice)?;
     let use_quantum = quantum_choice.trim().to_lowercase().starts_with('y');
     
-    println!("Enable persistence theory scoring? (y/n)");
-    print!("> ");
-    io::stdout().flush()?;
+    diag!("Enable persistence theory scoring? (y/n)");
+    diag_print!("> ");
     let mut persistence_choice = String::new();
     io::stdin().read_line(&mut persistence_choice)?;
     let use_persistence = persistence_choice.trim().to_lowercase().starts_with('y');
@@ -250,27 +443,25 @@ fn configure_quantum_features(engine_arc: &Arc<Mutex<ResonantEngine>>) -> io::Re
         engine.set_use_persistence_score(use_persistence);
         
         if use_persistence {
-            println!("Fragility parameter (0.1-1.0, default 0.2):");
-            print!("> ");
-            io::stdout().flush()?;
+            diag!("Fragility parameter (0.1-1.0, default 0.2):");
+            diag_print!("> ");
             let mut fragility_input = String::new();
             io::stdin().read_line(&mut fragility_input)?;
             if let Ok(fragility) = fragility_input.trim().parse::<f64>() {
-                if fragility > 0.0 && fragility <= 1.0 {
-                    engine.set_fragility(fragility);
+                if let Err(e) = engine.set_fragility(fragility) {
+                    diag!("⚠️  {}. Keeping the default.", e);
                 }
             }
         }
     }
     
-    println!("⚡ Quantum configuration complete!");
+    diag!("⚡ Quantum configuration complete!");
     Ok(())
 }
 
 fn should_reindex() -> io::Result<bool> {
-    println!("🔄 Rebuild index from scratch? (y/n)");
-    print!("> ");
-    io::stdout().flush()?;
+    diag!("🔄 Rebuild index from scratch? (y/n)");
+    diag_print!("> ");
     
     let mut choice = String::new();
     io::stdin().read_line(&mut choice)?;
@@ -279,61 +470,99 @@ fn should_reindex() -> io::Result<bool> {
 
 // Search functions
 
-async fn quantum_search(engine_arc: &Arc<Mutex<ResonantEngine>>, query: &str) {
+async fn quantum_search(
+    engine_arc: &Arc<Mutex<ResonantEngine>>,
+    query: &str,
+    display_options: &DisplayOptions,
+    cluster_by: Option<ClusterBy>
+) {
     let start = Instant::now();
     
-    let results = {
+    let outcome = {
         let mut engine = engine_arc.lock().unwrap();
-        engine.search(query, 10)
+        engine.search_outcome(query, 10)
     };
     
     let elapsed = start.elapsed();
     
-    if results.is_empty() {
-        println!("🔍 No quantum resonance found for '{}'", query);
-        println!("💡 Try: fuzzy search, different terms, or check file extensions");
-        return;
-    }
+    let results = match outcome {
+        SearchOutcome::Ok(results) => results,
+        SearchOutcome::EmptyQuery => {
+            diag!("🔍 '{}' contains no searchable terms", query);
+            diag!("💡 Try: words or identifiers rather than punctuation");
+            return;
+        },
+        SearchOutcome::EmptyIndex => {
+            diag!("📭 Nothing is indexed yet");
+            diag!("💡 Try: 'reindex' to scan your search paths");
+            return;
+        },
+        SearchOutcome::NoMatches => {
+            diag!("🔍 No quantum resonance found for '{}'", query);
+            diag!("💡 Try: fuzzy search, different terms, or check file extensions");
+            return;
+        },
+    };
     
-    println!("\n🌟 Quantum Resonant Matches for '{}' ({:?}):", query, elapsed);
-    println!("{:─<80}", "");
+    diag!("\n🌟 Quantum Resonant Matches for '{}' ({:?}):", query, elapsed);
+    diag!("{:─<80}", "");
     
-    for (i, result) in results.iter().enumerate() {
-        println!("[{}] 📄 {}", i + 1, result.title);
-        println!("    📂 {}", truncate_path(&result.path, 70));
-        
-        let combined_score = result.score * 0.4 + result.quantum_score * 0.3 + result.persistence_score * 0.3;
-        println!("    ⚛️  Resonance: {:.3} | Quantum: {:.3} | Persistence: {:.3} | Combined: {:.3}",
-                result.resonance, result.quantum_score, result.persistence_score, combined_score);
-        
-        println!("    📝 {}", truncate_text(&result.snippet, 100));
-        
-        // Show file type and size if available
-        if let Ok(metadata) = std::fs::metadata(&result.path) {
-            let size = format_file_size(metadata.len());
-            let modified = metadata.modified()
-                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-                .map(|d| format_duration_ago(d.as_secs()))
-                .unwrap_or_else(|| "unknown".to_string());
-            println!("    📊 Size: {} | Modified: {}", size, modified);
+    let Some(cluster_by) = cluster_by else {
+        for (i, result) in results.iter().enumerate() {
+            print_search_result(i + 1, result, display_options);
+        }
+        return;
+    };
+    
+    let clusters = engine_arc.lock().unwrap().cluster_results(results, cluster_by);
+    let mut rank = 0;
+    for (i, cluster) in clusters.iter().enumerate() {
+        if cluster_by == ClusterBy::Directory {
+            let directory = Path::new(&cluster.representative().path).parent().unwrap_or(Path::new(""));
+            println!("📁 {} ({} results)", directory.display(), cluster.members.len());
+        } else {
+            println!("📚 Cluster {} ({} results): {}", i + 1, cluster.members.len(), cluster.representative().title);
+        }
+        for result in &cluster.members {
+            rank += 1;
+            print_search_result(rank, result, display_options);
         }
-        
-        println!();
     }
 }
 
+fn explain_search(engine_arc: &Arc<Mutex<ResonantEngine>>, query: &str) {
+    let (_, plan) = engine_arc.lock().unwrap().search_explain(query, 10);
+    print!("{}", plan);
+}
+
+fn print_search_result(rank: usize, result: &SearchResult, display_options: &DisplayOptions) {
+    println!("[{}] {}", rank, format_search_result(result, display_options));
+    
+    // Show file type and size if available
+    if let Ok(metadata) = std::fs::metadata(&result.path) {
+        let size = format_file_size(metadata.len());
+        let modified = metadata.modified()
+            .ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| format_duration_ago(d.as_secs()))
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("    📊 Size: {} | Modified: {}", size, modified);
+    }
+    
+    println!();
+}
+
 fn fuzzy_search(fuzzy_matcher: &FuzzyMatcher, indexer: &FilesystemIndexer, pattern: &str) {
     let start = Instant::now();
     let matches = fuzzy_matcher.find_matches(indexer.get_all_files(), pattern, 10);
     let elapsed = start.elapsed();
     
     if matches.is_empty() {
-        println!("🔍 No fuzzy matches found for '{}'", pattern);
+        diag!("🔍 No fuzzy matches found for '{}'", pattern);
         return;
     }
     
-    println!("\n🎯 Fuzzy Matches for '{}' ({:?}):", pattern, elapsed);
-    println!("{:─<80}", "");
+    diag!("\n🎯 Fuzzy Matches for '{}' ({:?}):", pattern, elapsed);
+    diag!("{:─<80}", "");
     
     for (i, (file, score)) in matches.iter().enumerate() {
         println!("[{}] 📄 {} (score: {:.2})", i + 1, file.display_name, score);
@@ -341,7 +570,7 @@ fn fuzzy_search(fuzzy_matcher: &FuzzyMatcher, indexer: &FilesystemIndexer, patte
         
         if let Ok(metadata) = std::fs::metadata(&file.path) {
             let size = format_file_size(metadata.len());
-            println!("    📊 Size: {} | Type: {:?}", size, file.file_type);
+            println!("    📊 Size: {} | Type: {}", size, file.file_type);
         }
         println!();
     }
@@ -349,11 +578,79 @@ fn fuzzy_search(fuzzy_matcher: &FuzzyMatcher, indexer: &FilesystemIndexer, patte
 
 // Support functions
 
-async fn build_quantum_index(engine_arc: &Arc<Mutex<ResonantEngine>>, indexer: &FilesystemIndexer) {
-    // This would integrate with your existing engine to build quantum vectors
-    // for all indexed files
-    println!("Building quantum resonance matrix...");
-    // Implementation depends on your existing engine structure
+// Start extracting the contents of files indexed metadata-first, if there are any
+fn start_content_fill(indexer: &FilesystemIndexer) -> Option<ContentFill> {
+    if indexer.pending_content_count() == 0 {
+        return None;
+    }
+    let fill = indexer.start_content_fill();
+    diag!("📥 Extracting contents of {} files in the background", fill.total());
+    Some(fill)
+}
+
+// Store extracted contents and rebuild their vectors; true once the fill is done
+fn apply_content_fill(
+    indexer: &mut FilesystemIndexer,
+    fill: &ContentFill,
+    engine_arc: &Arc<Mutex<ResonantEngine>>,
+    index_path: &str
+) -> bool {
+    // Check before draining so nothing extracted in between is left behind
+    let finished = fill.is_finished();
+    let updated = indexer.apply_content_fill(fill);
+    if !updated.is_empty() {
+        let mut engine = engine_arc.lock().unwrap();
+        for path in &updated {
+            match indexer.get_file_by_path(path) {
+                Some(file) => engine.add_filesystem_document(file),
+                // A mailbox, indexed as its messages now
+                None => { engine.remove_document(path); }
+            }
+        }
+    }
+    
+    if !finished {
+        diag!("📥 Contents extracted: {}/{}", fill.extracted(), fill.total());
+        return false;
+    }
+    
+    diag!("✅ Content extraction complete ({} files)", fill.total());
+    engine_arc.lock().unwrap().warm_up();
+    if let Err(e) = indexer.save_index(index_path) {
+        eprintln!("❌ Could not save index: {}", e);
+    }
+    true
+}
+
+/// Build vectors for the indexed files that don't have them yet, reporting
+/// progress like the scan does. Returns false if Ctrl-C stopped the build.
+async fn build_quantum_index(engine_arc: &Arc<Mutex<ResonantEngine>>, indexer: &FilesystemIndexer) -> bool {
+    let total = indexer.file_count();
+    let (progress_tx, mut progress_rx) = mpsc::channel::<IndexProgress>(16);
+    let progress_handle = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            diag_print!("\r🧮 Vectors: {}/{} files, Current: {:<60}",
+                   progress.files_indexed,
+                   total,
+                   truncate_path(&progress.current_path, 60));
+        }
+    });
+    
+    let stop = indexer.stop_handle();
+    let finished = engine_arc.lock().unwrap().build_filesystem_documents(indexer, &stop, |built, path| {
+        // A full channel just means the monitor is behind; the next update replaces this one
+        let _ = progress_tx.try_send(IndexProgress {
+            files_indexed: built,
+            dirs_scanned: 0,
+            current_path: path.display().to_string(),
+            io_retries: 0,
+            unreachable: 0,
+        });
+    });
+    
+    drop(progress_tx);
+    progress_handle.await.unwrap();
+    finished
 }
 
 fn start_file_watcher(
@@ -362,47 +659,489 @@ fn start_file_watcher(
     _engine: Arc<Mutex<ResonantEngine>>
 ) -> io::Result<()> {
     // File watcher implementation for real-time updates
-    println!("👁️  File watcher started for real-time updates");
+    diag!("👁️  File watcher started for real-time updates");
     Ok(())
 }
 
 async fn reindex_filesystem(
     indexer: &mut FilesystemIndexer,
     paths: &[PathBuf],
-    _engine_arc: &Arc<Mutex<ResonantEngine>>
+    engine_arc: &Arc<Mutex<ResonantEngine>>
 ) -> io::Result<()> {
-    println!("🔄 Starting full reindex...");
+    diag!("🔄 Starting full reindex...");
     indexer.clear();
     
     for path in paths {
-        indexer.index_path(path, None).await?;
+        if let Err(e) = indexer.index_path(path, None).await {
+            if e.kind() == io::ErrorKind::Interrupted {
+                // Search what was reached rather than documents for files the
+                // cleared index no longer has
+                let report = engine_arc.lock().unwrap().reconcile(indexer);
+                diag!("🛑 Reindex stopped after {} files ({} documents added, {} removed)",
+                      indexer.file_count(), report.added.len(), report.removed.len());
+            }
+            return Err(e);
+        }
+    }
+    
+    // Rebuild the documents; learned state is reapplied to paths that are still there
+    let mut engine = engine_arc.lock().unwrap();
+    engine.clear_documents();
+    for file in indexer.get_all_files() {
+        engine.add_filesystem_document(file);
     }
+    engine.warm_up();
     
-    println!("✅ Reindex complete! {} files indexed", indexer.file_count());
+    diag!("✅ Reindex complete! {} files indexed", indexer.file_count());
     Ok(())
 }
 
-fn show_stats(engine_arc: &Arc<Mutex<ResonantEngine>>, indexer: &FilesystemIndexer) {
-    let engine = engine_arc.lock().unwrap();
+fn record_feedback(engine_arc: &Arc<Mutex<ResonantEngine>>, path: &str, relevant: bool) {
+    let mut engine = engine_arc.lock().unwrap();
+    if engine.record_feedback(Path::new(path), relevant) {
+        let relevance = engine.relevance(Path::new(path)).unwrap_or(1.0);
+        diag!("🎯 Relevance of {} is now {:.2}", path, relevance);
+    } else {
+        diag!("❓ {} is not indexed", path);
+    }
+}
+
+fn export_vectors(engine_arc: &Arc<Mutex<ResonantEngine>>, path: &str) {
+    if let Err(e) = engine_arc.lock().unwrap().export_vectors(path, VectorFormat::from_path(path)) {
+        eprintln!("❌ Could not export vectors: {}", e);
+    }
+}
+
+// `args` is "<queries file> <out.csv>", the queries one per line
+fn export_diagnostics(engine_arc: &Arc<Mutex<ResonantEngine>>, args: &str) {
+    let Some((queries_path, out_path)) = args.rsplit_once(' ') else {
+        eprintln!("❌ Usage: diagnostics <queries file> <out.csv>");
+        return;
+    };
+    let queries = match std::fs::read_to_string(queries_path.trim()) {
+        Ok(queries) => queries,
+        Err(e) => {
+            eprintln!("❌ Could not read queries from {}: {}", queries_path.trim(), e);
+            return;
+        }
+    };
+    let queries: Vec<&str> = queries.lines().map(str::trim).filter(|query| !query.is_empty()).collect();
+    if let Err(e) = engine_arc.lock().unwrap().diagnostics_export(&queries, out_path) {
+        eprintln!("❌ Could not export diagnostics: {}", e);
+    }
+}
+
+fn save_learned_state(engine_arc: &Arc<Mutex<ResonantEngine>>, learned_path: &str) {
+    if let Err(e) = engine_arc.lock().unwrap().save_learned_state(learned_path) {
+        eprintln!("❌ Could not save learned state: {}", e);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatsFormat {
+    Text,
+    Json,
+}
+
+/// Everything the `stats` command reports, in a form that can be serialized
+#[derive(Debug, Serialize)]
+struct Stats {
+    files_indexed: usize,
+    quantum_vectors: usize,
+    stale_documents: usize,
+    awaiting_content: usize,
+    file_types: BTreeMap<String, usize>,
+    total_size: u64,
+    quantum_scoring: bool,
+    persistence_scoring: bool,
+    score_weights: ScoreWeights,
+    similarity_metric: SimilarityMetric,
+    score_combination: ScoreCombination,
+}
+
+fn merge_index(indexer: &mut FilesystemIndexer, engine_arc: &Arc<Mutex<ResonantEngine>>, other_path: &str, index_path: &str) {
+    let mut other = FilesystemIndexer::new();
+    if let Err(e) = other.load_index(other_path) {
+        eprintln!("❌ Could not load index {}: {}", other_path, e);
+        return;
+    }
+    
+    let merged = indexer.merge(other);
+    diag!("🔀 Merged {} files from {} ({} files total)", merged.len(), other_path, indexer.file_count());
+    
+    // Search the merged files straight away, replacing documents of older versions
+    let mut engine = engine_arc.lock().unwrap();
+    for path in &merged {
+        if let Some(file) = indexer.get_file_by_path(path) {
+            engine.add_filesystem_document(file);
+        }
+    }
+    drop(engine);
+    
+    match indexer.save_index(index_path) {
+        Ok(()) => diag!("💾 Index saved to {}", index_path),
+        Err(e) => eprintln!("❌ Could not save index: {}", e),
+    }
+}
+
+// Read again the files whose content couldn't be read when they were indexed
+fn retry_extractions(indexer: &mut FilesystemIndexer, engine_arc: &Arc<Mutex<ResonantEngine>>, index_path: &str) {
+    let pending = indexer.failed_extraction_count();
+    if pending == 0 {
+        diag!("✅ No files are waiting for their content");
+        return;
+    }
+    
+    let updated = indexer.retry_failed_extractions();
+    if !updated.is_empty() {
+        let mut engine = engine_arc.lock().unwrap();
+        for path in &updated {
+            if let Some(file) = indexer.get_file_by_path(path) {
+                engine.add_filesystem_document(file);
+            }
+        }
+        drop(engine);
+        if let Err(e) = indexer.save_index(index_path) {
+            eprintln!("❌ Could not save index: {}", e);
+        }
+    }
+    diag!("🔁 Read {} of {} files; {} still failing", updated.len(), pending, indexer.failed_extraction_count());
+}
+
+// Say how many files and directories couldn't be read or were too deep to
+// index, and write the unreadable ones out to the `--error-report` file if
+// one was given
+fn report_index_errors(indexer: &FilesystemIndexer, report_path: Option<&Path>) {
+    let count = indexer.index_error_count();
+    if count > 0 {
+        diag!("⚠️  {} files or directories could not be read", count);
+    }
+    let truncated: Vec<&Path> = indexer.depth_truncated().collect();
+    if let Some(first) = truncated.first() {
+        diag!("⚠️  {} directories are nested more than {} levels deep and their contents were not indexed (e.g. {}); raise the limit with --max-depth",
+              truncated.len(), indexer.max_depth(), first.display());
+    }
+    let Some(path) = report_path else { return };
+    match indexer.write_error_report(path, ReportFormat::from_path(path)) {
+        Ok(written) => diag!("📝 {} indexing errors written to {}", written, path.display()),
+        Err(e) => eprintln!("❌ Could not write error report: {}", e),
+    }
+}
+
+// Rebuild the index statistics if they drifted, and bring the engine's
+// documents back in line with the indexed files
+fn repair_index(indexer: &mut FilesystemIndexer, engine_arc: &Arc<Mutex<ResonantEngine>>) {
+    if indexer.verify_stats() {
+        diag!("✅ Index statistics are consistent");
+    } else {
+        indexer.rebuild_stats();
+        diag!("🔧 Index statistics were out of sync and have been rebuilt");
+    }
+    
+    let report = engine_arc.lock().unwrap().reconcile(indexer);
+    if report.in_sync() {
+        diag!("✅ Every indexed file has a document");
+    } else {
+        diag!("🔧 Documents were out of sync: {} added, {} removed", report.added.len(), report.removed.len());
+    }
+}
+
+/// Pick up `--quiet` (or `-q`) from the command line
+fn parse_quiet(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == "--quiet" || arg == "-q")
+}
+
+/// Pick up `--stats-format json|text` from the command line (text by default)
+fn parse_stats_format(mut args: impl Iterator<Item = String>) -> StatsFormat {
+    while let Some(arg) = args.next() {
+        let value = if arg == "--stats-format" {
+            args.next()
+        } else {
+            arg.strip_prefix("--stats-format=").map(|v| v.to_string())
+        };
+        
+        match value.as_deref() {
+            Some("json") => return StatsFormat::Json,
+            Some("text") => return StatsFormat::Text,
+            Some(other) => diag!("⚠️  Unknown stats format '{}', using text", other),
+            None => {},
+        }
+    }
+    StatsFormat::Text
+}
+
+/// Pick up `--clusters <n>` or `--cluster-threshold <similarity>` from the
+/// command line to group search results by topic, or `--group-by-directory`
+/// to group them by folder (off by default)
+fn parse_cluster_by(mut args: impl Iterator<Item = String>) -> Option<ClusterBy> {
+    let mut cluster_by = None;
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let parsed = match flag.as_str() {
+            "--group-by-directory" => Some(ClusterBy::Directory),
+            "--clusters" => value.or_else(|| args.next())
+                .and_then(|v| v.parse().ok())
+                .map(ClusterBy::Count),
+            "--cluster-threshold" => value.or_else(|| args.next())
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|threshold| threshold.is_finite())
+                .map(ClusterBy::Threshold),
+            _ => continue,
+        };
+        match parsed {
+            Some(by) => cluster_by = Some(by),
+            None => diag!("⚠️  Ignoring {}: expected a number", flag),
+        }
+    }
+    cluster_by
+}
+
+/// Pick up `--config <path>` from the command line, to use a settings file
+/// other than the one in the user's config directory
+fn parse_config_path(mut args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        let value = if arg == "--config" {
+            args.next()
+        } else {
+            arg.strip_prefix("--config=").map(|v| v.to_string())
+        };
+        if value.is_some() {
+            return value.map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Pick up `--error-report <path>` from the command line to write every file
+/// that couldn't be indexed to a report after each scan (`.csv`, or JSON)
+fn parse_error_report(mut args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        let value = if arg == "--error-report" {
+            args.next()
+        } else {
+            arg.strip_prefix("--error-report=").map(|v| v.to_string())
+        };
+        if value.is_some() {
+            return value.map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Pick up `--dedup-threshold <similarity>` from the command line to fold
+/// near-identical results into one (off by default)
+fn parse_dedup_threshold(mut args: impl Iterator<Item = String>) -> Option<f64> {
+    let mut threshold = None;
+    while let Some(arg) = args.next() {
+        let value = if arg == "--dedup-threshold" {
+            args.next()
+        } else {
+            arg.strip_prefix("--dedup-threshold=").map(|v| v.to_string())
+        };
+        let Some(value) = value else { continue };
+        
+        match value.parse::<f64>() {
+            Ok(similarity) => threshold = Some(similarity),
+            Err(_) => diag!("⚠️  Ignoring --dedup-threshold: expected a number"),
+        }
+    }
+    threshold
+}
+
+/// Pick up `--max-per-directory <n>` from the command line to keep at most n
+/// results from any one folder (no limit by default)
+fn parse_max_per_directory(mut args: impl Iterator<Item = String>) -> Option<usize> {
+    let mut max = None;
+    while let Some(arg) = args.next() {
+        let value = if arg == "--max-per-directory" {
+            args.next()
+        } else {
+            arg.strip_prefix("--max-per-directory=").map(|v| v.to_string())
+        };
+        let Some(value) = value else { continue };
+        
+        match value.parse::<usize>() {
+            Ok(n) => max = Some(n),
+            Err(_) => diag!("⚠️  Ignoring --max-per-directory: expected a whole number"),
+        }
+    }
+    max
+}
+
+/// Pick up `--max-depth <n>` from the command line to index n directory
+/// levels below each search path (20 by default)
+fn parse_max_depth(mut args: impl Iterator<Item = String>) -> Option<usize> {
+    let mut depth = None;
+    while let Some(arg) = args.next() {
+        let value = if arg == "--max-depth" {
+            args.next()
+        } else {
+            arg.strip_prefix("--max-depth=").map(|v| v.to_string())
+        };
+        let Some(value) = value else { continue };
+        
+        match value.parse::<usize>() {
+            Ok(n) => depth = Some(n),
+            Err(_) => diag!("⚠️  Ignoring --max-depth: expected a whole number"),
+        }
+    }
+    depth
+}
+
+/// Pick up `--metric dot|cosine|jaccard` from the command line to choose how
+/// queries are compared with documents
+fn parse_similarity_metric(mut args: impl Iterator<Item = String>) -> Option<SimilarityMetric> {
+    let mut metric = None;
+    while let Some(arg) = args.next() {
+        let value = if arg == "--metric" {
+            args.next()
+        } else {
+            arg.strip_prefix("--metric=").map(|v| v.to_string())
+        };
+        let Some(value) = value else { continue };
+        
+        match SimilarityMetric::from_name(&value) {
+            Some(parsed) => metric = Some(parsed),
+            None => diag!("⚠️  Ignoring unknown metric '{}' (expected dot, cosine or jaccard)", value),
+        }
+    }
+    metric
+}
+
+/// Pick up `--combine weighted|rrf` from the command line to choose how the
+/// score components are combined
+fn parse_score_combination(mut args: impl Iterator<Item = String>) -> Option<ScoreCombination> {
+    let mut combination = None;
+    while let Some(arg) = args.next() {
+        let value = if arg == "--combine" {
+            args.next()
+        } else {
+            arg.strip_prefix("--combine=").map(|v| v.to_string())
+        };
+        let Some(value) = value else { continue };
+        
+        match ScoreCombination::from_name(&value) {
+            Some(parsed) => combination = Some(parsed),
+            None => diag!("⚠️  Ignoring unknown combination '{}' (expected weighted or rrf)", value),
+        }
+    }
+    combination
+}
+
+/// Pick up `--show resonance,entropy,quantum,persistence,combined,snippet`
+/// from the command line to choose the result fields shown, `--precision <n>`
+/// for the decimal places of scores and `--scores raw|normalized|percent`
+/// for how the combined score is shown
+fn parse_display_options(mut args: impl Iterator<Item = String>) -> DisplayOptions {
+    let mut options = DisplayOptions::default();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        if !matches!(flag.as_str(), "--show" | "--precision" | "--scores") {
+            continue;
+        }
+        let Some(value) = value.or_else(|| args.next()) else { continue };
+        
+        if flag == "--precision" {
+            match value.parse::<usize>() {
+                Ok(precision) => options.precision = precision.min(12),
+                Err(_) => diag!("⚠️  Ignoring --precision: expected a number of decimal places"),
+            }
+            continue;
+        }
+        if flag == "--scores" {
+            match ScoreDisplay::from_name(&value) {
+                Some(display) => options.score_display = display,
+                None => diag!("⚠️  Ignoring unknown score display '{}' (expected raw, normalized or percent)", value),
+            }
+            continue;
+        }
+        
+        options = DisplayOptions {
+            resonance: false,
+            delta_entropy: false,
+            quantum_score: false,
+            persistence_score: false,
+            combined_score: false,
+            snippet: false,
+            matches: false,
+            ..options
+        };
+        for field in value.split(',').map(str::trim) {
+            match field {
+                "resonance" => options.resonance = true,
+                "entropy" => options.delta_entropy = true,
+                "quantum" => options.quantum_score = true,
+                "persistence" => options.persistence_score = true,
+                "combined" => options.combined_score = true,
+                "snippet" => options.snippet = true,
+                "matches" => options.matches = true,
+                other => diag!("⚠️  Unknown result field '{}'", other),
+            }
+        }
+    }
+    options
+}
+
+fn collect_stats(engine: &ResonantEngine, indexer: &FilesystemIndexer) -> Stats {
+    let index_stats = indexer.stats();
+    
+    Stats {
+        files_indexed: index_stats.file_count,
+        quantum_vectors: engine.len(),
+        stale_documents: engine.stale_count(),
+        awaiting_content: indexer.pending_content_count(),
+        file_types: index_stats.file_types,
+        total_size: index_stats.total_size,
+        quantum_scoring: engine.uses_quantum_score(),
+        persistence_scoring: engine.uses_persistence_score(),
+        score_weights: engine.effective_score_weights(),
+        similarity_metric: engine.similarity_metric(),
+        score_combination: engine.score_combination(),
+    }
+}
+
+fn show_stats(engine_arc: &Arc<Mutex<ResonantEngine>>, indexer: &FilesystemIndexer, format: StatsFormat) {
+    let stats = {
+        let engine = engine_arc.lock().unwrap();
+        collect_stats(&engine, indexer)
+    };
+    
+    if format == StatsFormat::Json {
+        match serde_json::to_string_pretty(&stats) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing stats: {}", e),
+        }
+        return;
+    }
+    
+    let on_off = |enabled: bool| if enabled { "enabled" } else { "disabled" };
     
     println!("\n📊 Quantum Search Engine Statistics:");
     println!("{:─<50}", "");
-    println!("📁 Total files indexed: {}", indexer.file_count());
-    println!("🧮 Quantum vectors: {}", engine.len());
+    println!("📁 Total files indexed: {}", stats.files_indexed);
+    println!("🧮 Quantum vectors: {}", stats.quantum_vectors);
+    println!("🕰️  Stale documents: {}", stats.stale_documents);
+    println!("📥 Awaiting content: {}", stats.awaiting_content);
     
-    let stats = indexer.get_file_type_stats();
     println!("\n📋 File type distribution:");
-    for (file_type, count) in stats.iter().take(10) {
-        println!("   {:?}: {}", file_type, count);
+    for (file_type, count) in &stats.file_types {
+        println!("   {}: {}", file_type, count);
     }
     
-    let total_size = indexer.get_total_size();
-    println!("\n💾 Total indexed size: {}", format_file_size(total_size));
+    println!("\n💾 Total indexed size: {}", format_file_size(stats.total_size));
     
     println!("\n⚛️  Quantum features:");
-    println!("   Quantum scoring: enabled");
-    println!("   Persistence theory: enabled");
-    println!("   Real-time monitoring: active");
+    println!("   Quantum scoring: {}", on_off(stats.quantum_scoring));
+    println!("   Persistence theory: {}", on_off(stats.persistence_scoring));
+    println!("   Score weights: resonance {:.2}, quantum {:.2}, persistence {:.2}",
+             stats.score_weights.resonance, stats.score_weights.quantum, stats.score_weights.persistence);
+    println!("   Similarity metric: {}, combined by: {}", stats.similarity_metric.as_str(), stats.score_combination.as_str());
 }
 
 // Utility functions
@@ -415,14 +1154,6 @@ fn truncate_path(path: &str, max_len: usize) -> String {
     }
 }
 
-fn truncate_text(text: &str, max_len: usize) -> String {
-    if text.len() <= max_len {
-        text.to_string()
-    } else {
-        format!("{}...", &text[..max_len - 3])
-    }
-}
-
 fn format_file_size(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = bytes as f64;
@@ -459,9 +1190,38 @@ fn format_duration_ago(seconds: u64) -> String {
     }
 }
 
-#[derive(Debug)]
-struct IndexProgress {
-    files_indexed: usize,
-    dirs_scanned: usize,
-    current_path: String,
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_stats_carry_every_field_the_text_shows() {
+        let stats = collect_stats(&ResonantEngine::new(), &FilesystemIndexer::new());
+        let json: serde_json::Value = serde_json::to_value(&stats).unwrap();
+        let fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        for field in [
+            "files_indexed", "quantum_vectors", "stale_documents", "awaiting_content", "file_types", "total_size",
+            "quantum_scoring", "persistence_scoring", "score_weights", "similarity_metric", "score_combination",
+        ] {
+            assert!(fields.contains(&field), "{field} missing from {fields:?}");
+        }
+        // Nothing watches the filesystem yet, so it isn't reported
+        assert!(!fields.contains(&"realtime_monitoring"));
+    }
+
+    #[test]
+    fn display_flags_set_the_fields_precision_and_score_display() {
+        let args = |args: &[&str]| parse_display_options(args.iter().map(|arg| arg.to_string()));
+        let options = args(&["--precision", "6", "--scores=percent", "--show", "resonance,combined"]);
+        assert_eq!(options.precision, 6);
+        assert_eq!(options.score_display, ScoreDisplay::Percent);
+        assert!(options.resonance && options.combined_score);
+        assert!(!options.quantum_score && !options.snippet);
+
+        // Unusable values leave the defaults
+        let options = args(&["--precision", "many", "--scores", "fancy"]);
+        assert_eq!(options.precision, DisplayOptions::default().precision);
+        assert_eq!(options.score_display, ScoreDisplay::Raw);
+        assert_eq!(args(&["--precision=40"]).precision, 12);
+    }
+}
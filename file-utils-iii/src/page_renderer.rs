@@ -0,0 +1,99 @@
+// src/page_renderer.rs
+//
+// Rendering pages in a browser for the crawler. Single-page apps send an empty
+// shell and build their content with JavaScript, so the raw HTML has no text
+// to index; a renderer runs the scripts and hands back the resulting DOM. The
+// browser-backed renderer is behind the `js-rendering` feature.
+
+use futures::future::BoxFuture;
+use reqwest::Url;
+use std::error::Error;
+
+pub type RenderError = Box<dyn Error + Send + Sync>;
+
+/// Renders a page the way a browser shows it, with its scripts run
+pub trait PageRenderer: Send + Sync {
+    /// The page's HTML once it has loaded
+    fn render<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<String, RenderError>>;
+}
+
+#[cfg(feature = "js-rendering")]
+pub use chrome::ChromeRenderer;
+
+#[cfg(feature = "js-rendering")]
+mod chrome {
+    use super::{PageRenderer, RenderError};
+    use chromiumoxide::{Browser, BrowserConfig};
+    use futures::future::BoxFuture;
+    use futures::StreamExt;
+    use reqwest::Url;
+    use std::time::Duration;
+    use tokio::sync::OnceCell;
+
+    /// Renders pages in headless Chrome or Chromium, found on the PATH or
+    /// through the CHROME environment variable. The browser starts with the
+    /// first page that needs it and is shared by all crawl workers.
+    pub struct ChromeRenderer {
+        browser: OnceCell<Browser>,
+        timeout: Duration,
+    }
+
+    impl Default for ChromeRenderer {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl ChromeRenderer {
+        pub fn new() -> Self {
+            Self {
+                browser: OnceCell::new(),
+                timeout: Duration::from_secs(30),
+            }
+        }
+
+        /// Give up on a page that hasn't loaded after this long (30 seconds by default)
+        pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+            self.timeout = timeout;
+            self
+        }
+
+        async fn browser(&self) -> Result<&Browser, RenderError> {
+            self.browser.get_or_try_init(|| async {
+                let config = BrowserConfig::builder().build()?;
+                let (browser, mut handler) = Browser::launch(config).await?;
+                // The browser only makes progress while its event stream is polled
+                tokio::spawn(async move {
+                    while let Some(event) = handler.next().await {
+                        if event.is_err() {
+                            break;
+                        }
+                    }
+                });
+                Ok::<_, RenderError>(browser)
+            }).await
+        }
+    }
+
+    impl PageRenderer for ChromeRenderer {
+        fn render<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<String, RenderError>> {
+            Box::pin(async move {
+                let browser = self.browser().await?;
+                // Opening the page already waits for it to load, so the timeout covers that too
+                let mut opened = None;
+                let loaded = tokio::time::timeout(self.timeout, async {
+                    let page = opened.insert(browser.new_page(url.as_str()).await?);
+                    page.wait_for_navigation().await?;
+                    page.content().await
+                }).await;
+                if let Some(page) = opened {
+                    let _ = page.close().await;
+                }
+                match loaded {
+                    Ok(html) => Ok(html?),
+                    Err(_) => Err(format!("page didn't load within {:?}", self.timeout).into()),
+                }
+            })
+        }
+    }
+}
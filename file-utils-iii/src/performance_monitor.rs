@@ -0,0 +1,67 @@
+// src/performance_monitor.rs - Performance monitoring and statistics
+
+use std::time::{Instant, Duration};
+use std::collections::VecDeque;
+
+pub struct PerformanceMonitor {
+    search_times: VecDeque<Duration>,
+    index_times: VecDeque<Duration>,
+    max_samples: usize,
+}
+
+impl PerformanceMonitor {
+    pub fn new() -> Self {
+        Self {
+            search_times: VecDeque::new(),
+            index_times: VecDeque::new(),
+            max_samples: 100,
+        }
+    }
+    
+    pub fn record_search_time(&mut self, duration: Duration) {
+        self.search_times.push_back(duration);
+        if self.search_times.len() > self.max_samples {
+            self.search_times.pop_front();
+        }
+    }
+    
+    pub fn record_index_time(&mut self, duration: Duration) {
+        self.index_times.push_back(duration);
+        if self.index_times.len() > self.max_samples {
+            self.index_times.pop_front();
+        }
+    }
+    
+    pub fn get_average_search_time(&self) -> Duration {
+        if self.search_times.is_empty() {
+            return Duration::from_millis(0);
+        }
+        
+        let total: Duration = self.search_times.iter().sum();
+        total / self.search_times.len() as u32
+    }
+    
+    pub fn get_average_index_time(&self) -> Duration {
+        if self.index_times.is_empty() {
+            return Duration::from_millis(0);
+        }
+        
+        let total: Duration = self.index_times.iter().sum();
+        total / self.index_times.len() as u32
+    }
+    
+    pub fn print_statistics(&self) {
+        println!("\n📊 Performance Statistics:");
+        println!("   Average search time: {:?}", self.get_average_search_time());
+        println!("   Average index time: {:?}", self.get_average_index_time());
+        println!("   Search samples: {}", self.search_times.len());
+        println!("   Index samples: {}", self.index_times.len());
+        
+        if !self.search_times.is_empty() {
+            let fastest = self.search_times.iter().min().unwrap();
+            let slowest = self.search_times.iter().max().unwrap();
+            println!("   Fastest search: {:?}", fastest);
+            println!("   Slowest search: {:?}", slowest);
+        }
+    }
+}
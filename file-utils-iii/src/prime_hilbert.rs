@@ -9,6 +9,15 @@ use crate::quantum_types::{VectorComplex};
 /// and values are normalized frequencies (f64).
 pub type PrimeVector = HashMap<u64, f64>;
 
+/// True for vectors that carry no direction: empty, or with a zero or
+/// non-finite norm. Every scoring function here treats such a vector as
+/// matching nothing, scoring 0 against anything (including another
+/// degenerate vector) instead of producing NaN.
+pub fn is_degenerate(vector: &PrimeVector) -> bool {
+    let norm_sq: f64 = vector.values().map(|v| v * v).sum();
+    !(norm_sq.is_finite() && norm_sq > 0.0)
+}
+
 /// A biorthogonal representation with left and right prime vectors
 pub struct BiorthogonalVector {
     pub left: PrimeVector,
@@ -19,6 +28,8 @@ pub struct BiorthogonalVector {
 ///
 /// This function counts the occurrences of each prime, calculates the L2 norm
 /// of the frequency counts, and then normalizes the counts by dividing by the norm.
+/// Empty input gives an empty (degenerate) vector; any other input gives a unit
+/// vector, so a single repeated token becomes {prime: 1.0}.
 pub fn build_vector(primes: &[u64]) -> PrimeVector {
     if primes.is_empty() {
         return HashMap::new();
@@ -44,7 +55,9 @@ pub fn build_vector(primes: &[u64]) -> PrimeVector {
     vector
 }
 
-/// Converts a PrimeVector to a dense vector representation
+/// Converts a PrimeVector to a dense vector representation: component `p`
+/// holds the weight of prime `p`. Primes of `dimension` or more don't fit and
+/// are left out; `dense_length` says how long the vector must be to keep them.
 pub fn to_dense_vector(vector: &PrimeVector, dimension: usize) -> Vec<f64> {
     let mut dense = vec![0.0; dimension];
     
@@ -57,6 +70,13 @@ pub fn to_dense_vector(vector: &PrimeVector, dimension: usize) -> Vec<f64> {
     dense
 }
 
+/// Length a dense vector needs to hold every term of `vector`
+pub fn dense_length(vector: &PrimeVector) -> usize {
+    vector.keys()
+        .max()
+        .map_or(0, |&prime| usize::try_from(prime).map_or(usize::MAX, |p| p.saturating_add(1)))
+}
+
 /// Build a complex-valued vector with phase information
 pub fn build_complex_vector(primes: &[u64], phases: &[f64]) -> VectorComplex<f64> {
     if primes.is_empty() {
@@ -77,7 +97,8 @@ pub fn build_complex_vector(primes: &[u64], phases: &[f64]) -> VectorComplex<f64
     
     for (i, &prime) in primes.iter().enumerate() {
         let magnitude = *counts.get(&prime).unwrap_or(&0) as f64 / norm;
-        let phase = if i < phases.len() { phases[i] } else { 0.0 };
+        // Missing or non-finite phases fall back to 0 rather than poisoning the value with NaN
+        let phase = phases.get(i).copied().filter(|p| p.is_finite()).unwrap_or(0.0);
         let complex_val = Complex::from_polar(magnitude, phase);
         result.push(complex_val);
     }
@@ -87,13 +108,16 @@ pub fn build_complex_vector(primes: &[u64], phases: &[f64]) -> VectorComplex<f64
 
 /// Build a biorthogonal representation of a document
 pub fn build_biorthogonal_vector(primes: &[u64]) -> BiorthogonalVector {
-    let base_vector = build_vector(primes);
-    
+    biorthogonal_from_vector(&build_vector(primes))
+}
+
+/// Build a biorthogonal representation from an already built prime vector
+pub fn biorthogonal_from_vector(base_vector: &PrimeVector) -> BiorthogonalVector {
     // For demonstration, we'll create a right vector with slight variations
     // In a real application, these could represent different aspects of the document
     let mut right_vector = PrimeVector::new();
     
-    for (&prime, &value) in &base_vector {
+    for (&prime, &value) in base_vector {
         // Modify the weights slightly for the right vector
         right_vector.insert(prime, value * (1.0 + 0.1 * (prime % 2) as f64));
     }
@@ -107,7 +131,7 @@ pub fn build_biorthogonal_vector(primes: &[u64]) -> BiorthogonalVector {
     }
     
     BiorthogonalVector {
-        left: base_vector,
+        left: base_vector.clone(),
         right: right_vector,
     }
 }
@@ -117,7 +141,12 @@ pub fn build_biorthogonal_vector(primes: &[u64]) -> BiorthogonalVector {
 /// This function iterates over the union of keys from both vectors and
 /// sums the product of corresponding values. It efficiently handles
 /// sparse vectors where many prime frequencies are zero.
+/// Returns 0 when either vector is empty, or if the sum is not finite.
 pub fn dot_product(vec1: &PrimeVector, vec2: &PrimeVector) -> f64 {
+    if vec1.is_empty() || vec2.is_empty() {
+        return 0.0;
+    }
+
     // Get the union of keys from both vectors
     let keys1: HashSet<_> = vec1.keys().collect();
     let keys2: HashSet<_> = vec2.keys().collect();
@@ -131,7 +160,30 @@ pub fn dot_product(vec1: &PrimeVector, vec2: &PrimeVector) -> f64 {
         dot_prod += val1 * val2;
     }
 
-    dot_prod
+    if dot_prod.is_finite() { dot_prod } else { 0.0 }
+}
+
+/// Dot product over the product of the vectors' norms, in [-1, 1].
+/// Returns 0 when either vector is empty or has no length.
+pub fn cosine_similarity(vec1: &PrimeVector, vec2: &PrimeVector) -> f64 {
+    let norm = |vector: &PrimeVector| vector.values().map(|v| v * v).sum::<f64>().sqrt();
+    let norms = norm(vec1) * norm(vec2);
+    if norms > 0.0 && norms.is_finite() {
+        dot_product(vec1, vec2) / norms
+    } else {
+        0.0
+    }
+}
+
+/// Share of the primes in either vector that appear in both, ignoring weights
+pub fn jaccard_similarity(vec1: &PrimeVector, vec2: &PrimeVector) -> f64 {
+    let shared = vec1.keys().filter(|prime| vec2.contains_key(prime)).count();
+    let union = vec1.len() + vec2.len() - shared;
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
 }
 
 /// Calculates the biorthogonal score between two biorthogonal vectors
@@ -139,10 +191,76 @@ pub fn biorthogonal_score(query: &BiorthogonalVector, doc: &BiorthogonalVector)
     dot_product(&query.left, &doc.right) + dot_product(&query.right, &doc.left)
 }
 
-/// Calculates a complex resonance score with both magnitude and phase
+/// Calculates a complex resonance score with both magnitude and phase.
+/// A degenerate vector on either side resonates with nothing, so the result
+/// is zero rather than carrying the decay factor alone.
 pub fn resonance_complex(vec1: &PrimeVector, vec2: &PrimeVector, decay_factor: f64) -> Complex<f64> {
+    if is_degenerate(vec1) || is_degenerate(vec2) {
+        return Complex::new(0.0, 0.0);
+    }
     let dot_real = dot_product(vec1, vec2);
+    let decay_factor = if decay_factor.is_finite() { decay_factor } else { 0.0 };
     
     // Use the decay factor as a basis for imaginary component
     Complex::new(dot_real, decay_factor)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectors with no direction, each in its own way
+    fn degenerate_vectors() -> Vec<(&'static str, PrimeVector)> {
+        vec![
+            ("empty", PrimeVector::new()),
+            ("zero", HashMap::from([(3, 0.0), (5, 0.0)])),
+            ("nan", HashMap::from([(3, f64::NAN), (5, 0.5)])),
+            ("infinite", HashMap::from([(3, f64::INFINITY)])),
+        ]
+    }
+
+    #[test]
+    fn built_vectors_are_empty_or_unit_length() {
+        assert!(build_vector(&[]).is_empty());
+        assert!(is_degenerate(&build_vector(&[])));
+        assert_eq!(build_vector(&[7, 7, 7]), HashMap::from([(7, 1.0)]));
+        assert!(build_complex_vector(&[], &[]).is_empty());
+        // Missing phases count as 0
+        let complex = build_complex_vector(&[7, 11], &[f64::NAN]);
+        assert!(complex.iter().all(|value| value.re.is_finite() && value.im == 0.0));
+    }
+
+    #[test]
+    fn degenerate_vectors_score_zero_against_anything() {
+        let single = build_vector(&[3]);
+        let regular = build_vector(&[3, 5, 5]);
+        for (name, degenerate) in degenerate_vectors() {
+            assert!(is_degenerate(&degenerate), "{name}");
+            for (other_name, other) in [("single", &single), ("regular", &regular), (name, &degenerate)] {
+                let case = format!("{name} with {other_name}");
+                for (a, b) in [(&degenerate, other), (other, &degenerate)] {
+                    assert_eq!(dot_product(a, b), 0.0, "dot, {case}");
+                    assert_eq!(cosine_similarity(a, b), 0.0, "cosine, {case}");
+                    assert_eq!(resonance_complex(a, b, 0.5), Complex::new(0.0, 0.0), "resonance, {case}");
+                    let score = biorthogonal_score(&biorthogonal_from_vector(a), &biorthogonal_from_vector(b));
+                    assert_eq!(score, 0.0, "biorthogonal, {case}");
+                }
+            }
+        }
+        assert_eq!(jaccard_similarity(&PrimeVector::new(), &PrimeVector::new()), 0.0);
+        assert_eq!(jaccard_similarity(&PrimeVector::new(), &regular), 0.0);
+    }
+
+    #[test]
+    fn single_token_vectors_match_themselves_fully() {
+        let single = build_vector(&[3]);
+        assert!(!is_degenerate(&single));
+        assert_eq!(dot_product(&single, &single), 1.0);
+        assert_eq!(cosine_similarity(&single, &single), 1.0);
+        assert_eq!(jaccard_similarity(&single, &single), 1.0);
+        assert_eq!(resonance_complex(&single, &single, f64::NAN), Complex::new(1.0, 0.0));
+        assert_eq!(dot_product(&single, &build_vector(&[5])), 0.0);
+        assert_eq!(to_dense_vector(&single, 5), vec![0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(dense_length(&single), 4);
+        assert_eq!(dense_length(&PrimeVector::new()), 0);
+    }
+}
@@ -0,0 +1,131 @@
+// src/query_processor.rs - Advanced query processing for natural language queries
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+pub struct QueryProcessor {
+    stop_words: HashSet<String>,
+    file_type_keywords: HashMap<String, Vec<String>>,
+}
+
+impl QueryProcessor {
+    pub fn new() -> Self {
+        let stop_words = [
+            "the", "a", "an", "and", "or", "but", "in", "on", "at", "to", "for", "of", "with", "by",
+            "from", "up", "about", "into", "through", "during", "before", "after", "above", "below",
+            "between", "among", "within", "without", "under", "over", "inside", "outside", "beside",
+            "near", "far", "around", "across", "behind", "beyond", "beneath", "below", "above"
+        ].iter().map(|&s| s.to_string()).collect();
+        
+        let mut file_type_keywords = HashMap::new();
+        file_type_keywords.insert("code".to_string(), vec!["rust".to_string(), "python".to_string(), "javascript".to_string(), "cpp".to_string()]);
+        file_type_keywords.insert("document".to_string(), vec!["pdf".to_string(), "word".to_string(), "text".to_string()]);
+        file_type_keywords.insert("image".to_string(), vec!["jpg".to_string(), "png".to_string(), "gif".to_string()]);
+        
+        Self {
+            stop_words,
+            file_type_keywords,
+        }
+    }
+    
+    pub fn process_query(&self, query: &str) -> ProcessedQuery {
+        let cleaned = self.clean_query(query);
+        let tokens = self.tokenize(&cleaned);
+        let filtered = self.remove_stop_words(tokens);
+        let (keywords, file_type_hints, time_hints) = self.extract_hints(filtered);
+        
+        ProcessedQuery {
+            original: query.to_string(),
+            keywords,
+            file_type_hints,
+            time_hints,
+        }
+    }
+    
+    fn clean_query(&self, query: &str) -> String {
+        // Remove special characters but keep meaningful ones
+        let re = Regex::new(r"[^\w\s\-_\.]").unwrap();
+        re.replace_all(query, " ").to_string()
+    }
+    
+    fn tokenize(&self, query: &str) -> Vec<String> {
+        query.split_whitespace()
+            .map(|s| s.to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+    
+    fn remove_stop_words(&self, tokens: Vec<String>) -> Vec<String> {
+        tokens.into_iter()
+            .filter(|token| !self.stop_words.contains(token))
+            .collect()
+    }
+    
+    fn extract_hints(&self, tokens: Vec<String>) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let mut keywords = Vec::new();
+        let mut file_type_hints = Vec::new();
+        let mut time_hints = Vec::new();
+        
+        for token in tokens {
+            // Check for file type hints
+            if let Some(types) = self.file_type_keywords.get(&token) {
+                file_type_hints.extend(types.clone());
+                continue;
+            }
+            
+            // Check for time hints
+            if self.is_time_hint(&token) {
+                time_hints.push(token.clone());
+                continue;
+            }
+            
+            // Regular keyword
+            keywords.push(token);
+        }
+        
+        (keywords, file_type_hints, time_hints)
+    }
+    
+    fn is_time_hint(&self, token: &str) -> bool {
+        matches!(token, 
+            "today" | "yesterday" | "recent" | "new" | "old" | "latest" | 
+            "last" | "week" | "month" | "year" | "daily" | "weekly" | "monthly"
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct ProcessedQuery {
+    pub original: String,
+    pub keywords: Vec<String>,
+    pub file_type_hints: Vec<String>,
+    pub time_hints: Vec<String>,
+}
+
+impl ProcessedQuery {
+    pub fn to_search_string(&self) -> String {
+        self.keywords.join(" ")
+    }
+    
+    pub fn has_file_type_filter(&self) -> bool {
+        !self.file_type_hints.is_empty()
+    }
+    
+    pub fn has_time_filter(&self) -> bool {
+        !self.time_hints.is_empty()
+    }
+    
+    pub fn get_age_filter_days(&self) -> Option<u64> {
+        for hint in &self.time_hints {
+            match hint.as_str() {
+                "today" => return Some(1),
+                "yesterday" => return Some(2),
+                "recent" | "new" => return Some(7),
+                "week" => return Some(7),
+                "month" => return Some(30),
+                _ => continue,
+            }
+        }
+        None
+    }
+}
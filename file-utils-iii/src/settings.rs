@@ -0,0 +1,186 @@
+// src/settings.rs
+//
+// Configuration kept between runs: the answers to the setup prompts and every
+// tunable of the engine and indexer, so the prompts only appear on first run.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::engine::{EngineSettings, ResonantEngine};
+use crate::filesystem_indexer::{FilesystemIndexer, IndexerSettings};
+
+/// Search paths plus engine and indexer tunables, stored as JSON. Missing
+/// fields take their defaults, so older files keep loading.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub search_paths: Vec<PathBuf>,
+    pub engine: EngineSettings,
+    pub indexer: IndexerSettings,
+}
+
+impl Settings {
+    /// `quantum-search/settings.json` under the user's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("quantum-search").join("settings.json"))
+    }
+
+    /// The configuration currently in effect
+    pub fn capture(search_paths: &[PathBuf], engine: &ResonantEngine, indexer: &FilesystemIndexer) -> Self {
+        Settings {
+            search_paths: search_paths.to_vec(),
+            engine: engine.settings(),
+            indexer: indexer.settings(),
+        }
+    }
+
+    /// Put the engine and indexer tunables into effect
+    pub fn apply(&self, engine: &mut ResonantEngine, indexer: &mut FilesystemIndexer) -> io::Result<()> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        engine.apply_settings(&self.engine).map_err(|e| invalid(e.to_string()))?;
+        indexer.apply_settings(&self.indexer).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// The settings saved at `path`; `None` if nothing has been saved there yet
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write the settings to `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+    use crate::engine::{DenseOverflow, ScoreCombination, ScoreNormalization, ScoreWeights, SimilarityMetric, SourceCalibration, SourceScale};
+    use crate::filesystem_indexer::{HashAlgorithm, NetworkOptions};
+    use crate::tokenizer::NumericTokens;
+
+    // Every field spelled out and moved off its default, so a new tunable
+    // can't be added without being covered here
+    fn customised() -> Settings {
+        Settings {
+            search_paths: vec![PathBuf::from("/srv/notes"), PathBuf::from("/srv/papers")],
+            engine: EngineSettings {
+                use_quantum_score: true,
+                use_persistence_score: true,
+                score_weights: ScoreWeights { resonance: 0.6, quantum: 0.1, persistence: 0.3 },
+                score_normalization: ScoreNormalization::Softmax,
+                source_calibration: SourceCalibration::Scaled {
+                    filesystem: SourceScale { offset: 0.1, scale: 2.0 },
+                    web: SourceScale { offset: -0.2, scale: 0.5 },
+                },
+                similarity_metric: SimilarityMetric::Cosine,
+                score_combination: ScoreCombination::ReciprocalRank,
+                entropy_weight: 0.3,
+                fragility: 0.4,
+                trend_decay: 0.7,
+                update_frequency: 2.5,
+                quantum_jump_threshold: 0.6,
+                quantum_jump_importance: 0.9,
+                quantum_jump_freshening: 0.4,
+                snippet_length: 320,
+                field_snippets: false,
+                min_term_overlap: 2,
+                idf_weighting: false,
+                dedup_threshold: Some(0.8),
+                max_per_directory: Some(3),
+                filesystem_page_size: 25,
+                filesystem_candidate_multiplier: 8,
+                query_expansion_terms: 4,
+                cooccurrence_window: 7,
+                ngram_entropy_below: 40,
+                ngram_size: 4,
+                synonyms: BTreeMap::from([("car".to_string(), vec!["automobile".to_string()])]),
+                synonym_weight: 0.6,
+                max_vocabulary: Some(50_000),
+                numeric_tokens: NumericTokens::KeepVersions,
+                stemming: true,
+                compression_threads: 3,
+                verify_compression: true,
+                indexing_threads: 5,
+                export_dimension: 512,
+                dense_overflow: DenseOverflow::Grow { max_dimension: 4096 },
+                autosave_path: Some("/srv/index.bin".into()),
+                autosave_every_n_docs: 250,
+                autosave_interval: Some(Duration::from_secs(90)),
+                stale_after_days: 14.0,
+                keep_learned_state: false,
+                warm_up_documents: 64,
+                auto_compact_ratio: Some(0.4),
+                persistence_cache_bucket: Some(Duration::from_secs(600)),
+            },
+            indexer: IndexerSettings {
+                excluded_patterns: vec![r"\.cache".into()],
+                max_file_size: 4 * 1024 * 1024,
+                hash_algorithm: HashAlgorithm::Blake3,
+                index_hidden: true,
+                hidden_allowlist: vec![r"\.config".into()],
+                metadata_first: false,
+                numeric_tokens: NumericTokens::Discard,
+                read_compressed: false,
+                detect_encoding: false,
+                extract_retries: 5,
+                extract_retry_backoff: Duration::from_millis(40),
+                move_detection_window: Duration::from_secs(9),
+                progress_interval: Duration::from_millis(750),
+                check_interval: 128,
+                max_depth: 6,
+                network_roots: vec![PathBuf::from("/mnt/share")],
+                network_options: NetworkOptions {
+                    io_timeout: Duration::from_secs(12),
+                    retries: 1,
+                    retry_backoff: Duration::from_millis(300),
+                    max_concurrent: 2,
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn every_setting_survives_save_load_and_apply() {
+        let settings = customised();
+        let path = std::env::temp_dir()
+            .join(format!("quantum-search-settings-{}", std::process::id()))
+            .join("settings.json");
+        settings.save(&path).unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(loaded.as_ref(), Some(&settings));
+
+        let mut engine = ResonantEngine::new();
+        let mut indexer = FilesystemIndexer::new();
+        assert_ne!(Settings::capture(&settings.search_paths, &engine, &indexer), settings);
+        settings.apply(&mut engine, &mut indexer).unwrap();
+        assert_eq!(Settings::capture(&settings.search_paths, &engine, &indexer), settings);
+    }
+
+    #[test]
+    fn nothing_saved_is_not_an_error_but_a_broken_file_is() {
+        let dir = std::env::temp_dir().join(format!("quantum-search-broken-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(Settings::load(&dir.join("missing.json")).unwrap().is_none());
+        fs::write(dir.join("broken.json"), "{ \"engine\": ").unwrap();
+        let broken = Settings::load(&dir.join("broken.json"));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(broken.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
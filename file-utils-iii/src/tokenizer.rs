@@ -1,16 +1,135 @@
 // src/tokenizer.rs
 
 use regex::Regex;
-use std::collections::HashMap;
-use primal::Primes; // Import the Primes struct
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use primal::is_prime;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Serialize, Deserialize};
+
+/// How purely numeric words and version strings become search terms. Applied
+/// by the tokenizer and the filename/fuzzy word extractors alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NumericTokens {
+    /// Drop words made only of digits ("2024", "3")
+    Discard,
+    /// Keep numeric words as ordinary terms. The default, so fuzzy search
+    /// matches names by their numbers too ("2024" in "report_2024.pdf");
+    /// the fuzzy matcher ignored numbers before, as `Discard` still does.
+    #[default]
+    Keep,
+    /// Keep numeric words, and also keep dotted versions ("v2.1", "3.11") as
+    /// single terms next to the parts they split into
+    KeepVersions,
+}
+
+impl NumericTokens {
+    /// Whether `word` is made only of digits
+    pub fn is_number(word: &str) -> bool {
+        !word.is_empty() && word.chars().all(|c| c.is_ascii_digit())
+    }
+
+    /// Whether `word` survives this policy
+    pub fn keeps(self, word: &str) -> bool {
+        self != NumericTokens::Discard || !Self::is_number(word)
+    }
+
+    /// Version strings in `text` to add as whole terms (none unless `KeepVersions`)
+    pub fn versions(self, text: &str) -> Vec<String> {
+        if self != NumericTokens::KeepVersions {
+            return Vec::new();
+        }
+        static VERSION_RE: OnceLock<Regex> = OnceLock::new();
+        let version_re = VERSION_RE.get_or_init(|| Regex::new(r"(?i)\bv?\d+(?:\.\d+)+\b").expect("Failed to create version regex"));
+        version_re.find_iter(text).map(|m| m.as_str().to_lowercase()).collect()
+    }
+}
+
+// Vocabulary maps are split into independently locked shards, so texts can be
+// tokenized from many threads at once: a lookup only locks the shard holding
+// its key, and only new words take a write lock.
+const SHARDS: usize = 16;
+
+struct Sharded<K, V> {
+    shards: Vec<RwLock<HashMap<K, V>>>,
+    hasher: RandomState,
+}
+
+impl<K: Hash + Eq, V> Sharded<K, V> {
+    fn new() -> Self {
+        Sharded {
+            shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &RwLock<HashMap<K, V>> {
+        &self.shards[self.hasher.hash_one(key) as usize % SHARDS]
+    }
+
+    fn shard_mut<Q: Hash + ?Sized>(&mut self, key: &Q) -> &mut HashMap<K, V> {
+        let index = self.hasher.hash_one(key) as usize % SHARDS;
+        self.shards[index].get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn read<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockReadGuard<'_, HashMap<K, V>> {
+        self.shard(key).read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockWriteGuard<'_, HashMap<K, V>> {
+        self.shard(key).write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Every shard, for callers that have the tokenizer to themselves
+    fn maps_mut(&mut self) -> impl Iterator<Item = &mut HashMap<K, V>> {
+        self.shards.iter_mut().map(|shard| shard.get_mut().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn maps(&self) -> impl Iterator<Item = RwLockReadGuard<'_, HashMap<K, V>>> {
+        self.shards.iter().map(|shard| shard.read().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+struct PrimeEntry {
+    token: String,
+    // Number of tokenize calls the token appeared in
+    frequency: AtomicU64,
+}
 
 /// A tokenizer that maps words to unique prime numbers.
+///
+/// Tokenizing only needs `&self`, so one tokenizer can be shared by threads
+/// indexing documents in parallel; they all see one consistent vocabulary.
+/// New primes are claimed with a compare-and-swap on the last prime handed
+/// out, so no two tokens ever get the same prime.
+///
+/// The vocabulary can optionally be capped with `set_max_vocabulary`. Once the
+/// cap is reached, unseen tokens are dropped instead of being assigned a prime,
+/// which bounds memory and vector dimensionality at the cost of precision for
+/// rare terms. `evict_least_frequent` frees room by removing the tokens that
+/// appear in the fewest documents.
+///
+/// Primes are `u64`s and are never reused. Should they ever run out, new
+/// tokens are dropped as if the vocabulary were capped, with a warning.
+///
+/// With `set_stemming`, English words are reduced to their stem first, so
+/// "runs" and "running" share the prime of "run".
 pub struct PrimeTokenizer {
-    token_to_prime: HashMap<String, u64>,
-    prime_to_token: HashMap<u64, String>,
-    current_prime: u64,
+    token_to_prime: Sharded<String, u64>,
+    prime_to_token: Sharded<u64, PrimeEntry>,
+    current_prime: AtomicU64,
+    vocabulary_size: AtomicUsize,
     word_regex: Regex,
-    primal_generator: Primes, // Keep the Primes struct instance
+    max_vocabulary: Option<usize>,
+    numeric_tokens: NumericTokens,
+    // Reduce words to their Porter stem before looking up their prime
+    stemming: bool,
+    stemmer: Stemmer,
+    // Set once no prime is left to hand out
+    primes_exhausted: AtomicBool,
 }
 
 impl PrimeTokenizer {
@@ -18,42 +137,278 @@ impl PrimeTokenizer {
     pub fn new() -> Self {
         let word_regex = Regex::new(r"\b\w+\b").expect("Failed to create word regex");
         PrimeTokenizer {
-            token_to_prime: HashMap::new(),
-            prime_to_token: HashMap::new(),
-            current_prime: 2, // Start with the first prime
+            token_to_prime: Sharded::new(),
+            prime_to_token: Sharded::new(),
+            current_prime: AtomicU64::new(2), // Tokens get the primes after this one
+            vocabulary_size: AtomicUsize::new(0),
             word_regex,
-            primal_generator: Primes::all(), // Create a prime number iterator
+            max_vocabulary: None,
+            numeric_tokens: NumericTokens::default(),
+            stemming: false,
+            stemmer: Stemmer::create(Algorithm::English),
+            primes_exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Choose how numbers and version strings are tokenized
+    pub fn set_numeric_tokens(&mut self, policy: NumericTokens) {
+        self.numeric_tokens = policy;
+    }
+
+    pub fn numeric_tokens(&self) -> NumericTokens {
+        self.numeric_tokens
+    }
+
+    /// Stem words (English, Porter-style) before they get a prime, so
+    /// inflections of a word share one. Off by default. Words already
+    /// tokenized keep their primes, so texts tokenized before and after a
+    /// change don't match on inflected words; set this before indexing.
+    pub fn set_stemming(&mut self, stemming: bool) {
+        self.stemming = stemming;
+    }
+
+    pub fn stemming(&self) -> bool {
+        self.stemming
+    }
+
+    /// Cap the number of distinct tokens (None for unbounded)
+    pub fn set_max_vocabulary(&mut self, max: Option<usize>) {
+        self.max_vocabulary = max;
+    }
+
+    pub fn max_vocabulary(&self) -> Option<usize> {
+        self.max_vocabulary
+    }
+
+    /// Number of distinct tokens currently assigned a prime
+    pub fn vocabulary_size(&self) -> usize {
+        self.vocabulary_size.load(Ordering::Acquire)
+    }
+
+    /// Number of tokenized texts a token has appeared in
+    pub fn document_frequency(&self, token: &str) -> u64 {
+        self.get_prime(token)
+            .and_then(|prime| {
+                self.prime_to_token.read(&prime).get(&prime).map(|entry| entry.frequency.load(Ordering::Relaxed))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Remove the least frequently used tokens until at most `keep` remain.
+    /// Returns the primes that were released so callers can drop them from
+    /// stored vectors. Released primes are never handed out again.
+    pub fn evict_least_frequent(&mut self, keep: usize) -> Vec<u64> {
+        let size = *self.vocabulary_size.get_mut();
+        if size <= keep {
+            return Vec::new();
+        }
+
+        // Rarest first; among equally rare tokens evict the newest
+        let mut by_frequency: Vec<(u64, u64)> = self.prime_to_token.maps_mut()
+            .flat_map(|map| map.iter().map(|(&prime, entry)| (prime, entry.frequency.load(Ordering::Relaxed))).collect::<Vec<_>>())
+            .collect();
+        by_frequency.sort_by_key(|&(prime, frequency)| (frequency, std::cmp::Reverse(prime)));
+
+        let evicted: Vec<u64> = by_frequency.into_iter().take(size - keep).map(|(prime, _)| prime).collect();
+        for prime in &evicted {
+            if let Some(entry) = self.prime_to_token.shard_mut(prime).remove(prime) {
+                self.token_to_prime.shard_mut(&entry.token).remove(&entry.token);
+            }
+        }
+        *self.vocabulary_size.get_mut() -= evicted.len();
+
+        evicted
+    }
+
+    // Claim a vocabulary slot, unless the cap is reached
+    fn reserve_slot(&self) -> bool {
+        match self.max_vocabulary {
+            None => {
+                self.vocabulary_size.fetch_add(1, Ordering::AcqRel);
+                true
+            }
+            Some(max) => self.vocabulary_size
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |size| (size < max).then_some(size + 1))
+                .is_ok(),
+        }
+    }
+
+    // The next prime after the last one handed out, or None if there is no
+    // larger prime that fits in a u64. Threads racing for the same prime
+    // retry from whichever prime won.
+    fn next_prime(&self) -> Option<u64> {
+        let mut current = self.current_prime.load(Ordering::Acquire);
+        loop {
+            let next = (current.checked_add(1)?..=u64::MAX).find(|&n| is_prime(n))?;
+            match self.current_prime.compare_exchange_weak(current, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Some(next),
+                Err(actual) => current = actual,
+            }
         }
     }
 
+    /// Whether new tokens are being dropped because every prime is taken
+    pub fn primes_exhausted(&self) -> bool {
+        self.primes_exhausted.load(Ordering::Acquire)
+    }
+
+    /// The prime for `token`, assigning the next free one if it is new.
+    /// Returns None if the token is new and the vocabulary cap is reached
+    /// or the primes have run out.
+    fn assign_prime(&self, token: &str) -> Option<u64> {
+        if let Some(&prime) = self.token_to_prime.read(token).get(token) {
+            return Some(prime);
+        }
+
+        let mut shard = self.token_to_prime.write(token);
+        // Another thread may have assigned it since the read above
+        if let Some(&prime) = shard.get(token) {
+            return Some(prime);
+        }
+        if !self.reserve_slot() {
+            return None;
+        }
+
+        let Some(prime) = self.next_prime() else {
+            self.vocabulary_size.fetch_sub(1, Ordering::AcqRel);
+            if !self.primes_exhausted.swap(true, Ordering::AcqRel) {
+                diag!("⚠️  Ran out of u64 primes for new tokens; unseen words are no longer indexed");
+            }
+            return None;
+        };
+        // Register the prime before the token becomes visible, so anyone who
+        // finds the token also finds its entry
+        self.prime_to_token.write(&prime).insert(prime, PrimeEntry {
+            token: token.to_string(),
+            frequency: AtomicU64::new(0),
+        });
+        shard.insert(token.to_string(), prime);
+        Some(prime)
+    }
+
+    /// Bring another tokenizer's vocabulary and document frequencies into this one.
+    /// Returns how each of `other`'s primes maps to a prime here; tokens that
+    /// didn't fit under the vocabulary cap are missing from the map.
+    pub fn merge(&mut self, other: &PrimeTokenizer) -> HashMap<u64, u64> {
+        // Assign in the other tokenizer's order so the result is deterministic
+        let mut tokens: Vec<(u64, String, u64)> = other.prime_to_token.maps()
+            .flat_map(|map| {
+                map.iter()
+                    .map(|(&prime, entry)| (prime, entry.token.clone(), entry.frequency.load(Ordering::Relaxed)))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        tokens.sort_by_key(|(prime, _, _)| *prime);
+
+        let mut mapping = HashMap::new();
+        for (other_prime, token, frequency) in tokens {
+            if let Some(prime) = self.assign_prime(&token) {
+                mapping.insert(other_prime, prime);
+                if let Some(entry) = self.prime_to_token.read(&prime).get(&prime) {
+                    entry.frequency.fetch_add(frequency, Ordering::Relaxed);
+                }
+            }
+        }
+
+        mapping
+    }
+
     /// Tokenizes the input text into a vector of prime numbers.
-    pub fn tokenize(&mut self, text: &str) -> Vec<u64> {
-        let lower_text = text.to_lowercase();
+    pub fn tokenize(&self, text: &str) -> Vec<u64> {
         let mut primes_list = Vec::new(); // Renamed from 'primes' to avoid shadowing
 
-        for mat in self.word_regex.find_iter(&lower_text) {
-            let token = mat.as_str().to_string();
-            if !self.token_to_prime.contains_key(&token) {
-                // Find the next prime greater than the current_prime using the iterator
-                // We skip primes until we find one greater than the current_prime
-                let next_p_usize = self.primal_generator
-                    .by_ref() // Use by_ref to borrow the iterator mutably
-                    // Cast p (usize) to u64 for comparison with self.current_prime (u64)
-                    .find(|&p| p as u64 > self.current_prime)
-                    .expect("Should always be able to find a next prime"); // Assuming primes are infinite
-
-                // Cast the usize result to u64 before storing
-                let next_p = next_p_usize as u64;
-
-                self.token_to_prime.insert(token.clone(), next_p);
-                self.prime_to_token.insert(next_p, token.clone());
-                self.current_prime = next_p; // Update current_prime to the newly assigned prime
+        for word in &self.words(text) {
+            // Out of vocabulary tokens are skipped once the cap is reached
+            if let Some(prime) = self.assign_prime(word) {
+                primes_list.push(prime);
             }
-            primes_list.push(*self.token_to_prime.get(&token).unwrap());
         }
 
+        self.count_text(&primes_list);
         primes_list
     }
+
+    /// Tokenizes like `tokenize`, also returning where each token's word is
+    /// in `text`: byte offsets of its start and end, so `&text[start..end]`
+    /// is the word as written, e.g. for highlighting it in a snippet. The
+    /// whole-version terms `NumericTokens::KeepVersions` adds have no single
+    /// word and aren't included; their parts are.
+    pub fn tokenize_with_spans(&self, text: &str) -> Vec<(u64, usize, usize)> {
+        let spans = self.spans(text, |term| self.assign_prime(term));
+        let primes: Vec<u64> = spans.iter().map(|&(prime, _, _)| prime).collect();
+        self.count_text(&primes);
+        spans
+    }
+
+    /// Spans like `tokenize_with_spans`, but leaving the vocabulary untouched
+    /// as `tokenize_known` does, e.g. for finding query words in a snippet
+    pub fn tokenize_known_with_spans(&self, text: &str) -> Vec<(u64, usize, usize)> {
+        self.spans(text, |term| self.get_prime(term))
+    }
+
+    // Each word of `text` that `prime` gives a prime for, with its byte offsets
+    fn spans(&self, text: &str, prime: impl Fn(&str) -> Option<u64>) -> Vec<(u64, usize, usize)> {
+        // Match the original text rather than a lowercased copy, whose byte
+        // offsets can differ
+        self.word_regex.find_iter(text)
+            .filter_map(|mat| {
+                let word = mat.as_str().to_lowercase();
+                if !self.numeric_tokens.keeps(&word) {
+                    return None;
+                }
+                Some((prime(&self.term(&word))?, mat.start(), mat.end()))
+            })
+            .collect()
+    }
+
+    // Count one more text for each distinct prime in `primes`
+    fn count_text(&self, primes: &[u64]) {
+        let distinct: HashSet<u64> = primes.iter().copied().collect();
+        for prime in distinct {
+            if let Some(entry) = self.prime_to_token.read(&prime).get(&prime) {
+                entry.frequency.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Tokenizes like `tokenize`, but leaves the vocabulary untouched: words
+    /// without a prime yet are dropped and no frequencies are counted.
+    pub fn tokenize_known(&self, text: &str) -> Vec<u64> {
+        self.words(text).iter().filter_map(|word| self.get_prime(word)).collect()
+    }
+
+    // The words of `text` that become tokens, lowercased and stemmed if
+    // stemming is on
+    pub(crate) fn words(&self, text: &str) -> Vec<String> {
+        let lower_text = text.to_lowercase();
+        let mut words: Vec<String> = self.word_regex.find_iter(&lower_text)
+            .map(|mat| mat.as_str())
+            .filter(|word| self.numeric_tokens.keeps(word))
+            .map(|word| self.term(word))
+            .collect();
+        words.extend(self.numeric_tokens.versions(&lower_text));
+        words
+    }
+    
+    // The term a lowercase word becomes: its stem with stemming on, itself otherwise
+    pub(crate) fn term(&self, word: &str) -> String {
+        if self.stemming {
+            self.stemmer.stem(word).into_owned()
+        } else {
+            word.to_string()
+        }
+    }
+
+    // Byte offset of the first word of `text` that becomes one of `words`
+    // (as returned by `words`), splitting words as `tokenize` does
+    pub(crate) fn find_word(&self, text: &str, words: &[String]) -> Option<usize> {
+        self.word_regex.find_iter(text)
+            .find(|mat| {
+                let term = self.term(&mat.as_str().to_lowercase());
+                words.contains(&term)
+            })
+            .map(|mat| mat.start())
+    }
     
     /// Tokenizes the input prime numbers without updating the vocabulary.
     /// This is useful when we want to generate tokens without affecting the tokenizer's state.
@@ -65,20 +420,148 @@ impl PrimeTokenizer {
     #[allow(dead_code)]
     /// Prints the current vocabulary (token to prime mapping).
     pub fn print_vocab(&self) {
-        for (token, prime) in &self.token_to_prime {
-            println!("{}: {}", token, prime);
+        for map in self.token_to_prime.maps() {
+            for (token, prime) in map.iter() {
+                println!("{}: {}", token, prime);
+            }
         }
     }
 
     #[allow(dead_code)]
     /// Returns the token associated with a prime number, if it exists.
-    pub fn get_token(&self, prime: u64) -> Option<&String> {
-        self.prime_to_token.get(&prime)
+    pub fn get_token(&self, prime: u64) -> Option<String> {
+        self.prime_to_token.read(&prime).get(&prime).map(|entry| entry.token.clone())
     }
 
     #[allow(dead_code)]
     /// Returns the prime number associated with a token, if it exists.
-    pub fn get_prime(&self, token: &str) -> Option<&u64> {
-        self.token_to_prime.get(token)
+    pub fn get_prime(&self, token: &str) -> Option<u64> {
+        self.token_to_prime.read(token).get(token).copied()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_terms_unless_discarded() {
+        let mut tokenizer = PrimeTokenizer::new();
+        let tokens = tokenizer.tokenize("python3 notes 2024");
+        assert_eq!(tokens.len(), 3);
+        assert!(tokens.contains(&tokenizer.get_prime("2024").unwrap()));
+
+        tokenizer.set_numeric_tokens(NumericTokens::Discard);
+        assert!(tokenizer.tokenize("2024").is_empty());
+        assert_eq!(tokenizer.tokenize("python3"), vec![tokenizer.get_prime("python3").unwrap()]);
+    }
+
+    #[test]
+    fn versions_are_whole_terms_only_when_asked_for() {
+        assert_eq!(NumericTokens::KeepVersions.versions("release V2.1 needs python 3.11"), vec!["v2.1", "3.11"]);
+        assert!(NumericTokens::Keep.versions("release v2.1").is_empty());
+    }
+
+    #[test]
+    fn stemming_gives_inflections_one_prime() {
+        let mut tokenizer = PrimeTokenizer::new();
+        tokenizer.set_stemming(true);
+        assert_eq!(tokenizer.tokenize("cats"), tokenizer.tokenize("cat"));
+        assert_eq!(tokenizer.tokenize("running runs"), tokenizer.tokenize("run run"));
+    }
+
+    #[test]
+    fn without_stemming_inflections_keep_their_own_primes() {
+        let tokenizer = PrimeTokenizer::new();
+        assert_ne!(tokenizer.tokenize("cats"), tokenizer.tokenize("cat"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn spans_slice_back_to_multi_byte_words() {
+        let tokenizer = PrimeTokenizer::new();
+        let text = "Ça coûte 20 € à Zürich, İstanbul";
+        let spans = tokenizer.tokenize_with_spans(text);
+        let words: Vec<&str> = spans.iter().map(|&(_, start, end)| &text[start..end]).collect();
+        assert_eq!(words, ["Ça", "coûte", "20", "à", "Zürich", "İstanbul"]);
+        for &(prime, start, end) in &spans {
+            assert_eq!(tokenizer.get_token(prime), Some(text[start..end].to_lowercase()));
+        }
+    }
+
+    #[test]
+    fn known_spans_leave_the_vocabulary_alone() {
+        let tokenizer = PrimeTokenizer::new();
+        tokenizer.tokenize("crème brûlée");
+        let size = tokenizer.vocabulary_size();
+        let text = "Une crème très brûlée";
+        let spans = tokenizer.tokenize_known_with_spans(text);
+        let words: Vec<&str> = spans.iter().map(|&(_, start, end)| &text[start..end]).collect();
+        assert_eq!(words, ["crème", "brûlée"]);
+        assert_eq!(tokenizer.vocabulary_size(), size);
+        assert_eq!(tokenizer.document_frequency("crème"), 1);
+    }
+
+    #[test]
+    fn vocabulary_stops_growing_at_the_cap_and_keeps_common_terms() {
+        let mut tokenizer = PrimeTokenizer::new();
+        tokenizer.set_max_vocabulary(Some(3));
+        for text in ["common alpha", "common beta", "common gamma delta", "common epsilon"] {
+            tokenizer.tokenize(text);
+        }
+        assert_eq!(tokenizer.vocabulary_size(), 3);
+        assert_eq!(tokenizer.document_frequency("common"), 4);
+        assert_eq!(tokenizer.get_prime("gamma"), None);
+
+        tokenizer.evict_least_frequent(1);
+        assert_eq!(tokenizer.vocabulary_size(), 1);
+        assert!(tokenizer.get_prime("common").is_some());
+    }
+
+    #[test]
+    fn parallel_tokenizing_shares_one_consistent_vocabulary() {
+        const THREADS: usize = 8;
+        const DOCS: usize = 50;
+        let tokenizer = PrimeTokenizer::new();
+        // Every document has the shared word, a word per thread and a word of its own
+        let results: Vec<Vec<(String, Vec<u64>)>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|thread| {
+                    let tokenizer = &tokenizer;
+                    scope.spawn(move || {
+                        (0..DOCS)
+                            .map(|doc| {
+                                let text = format!("shared thread{thread} doc{thread}x{doc}");
+                                let primes = tokenizer.tokenize(&text);
+                                (text, primes)
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        assert_eq!(tokenizer.vocabulary_size(), 1 + THREADS + THREADS * DOCS);
+        let mut seen = HashMap::new();
+        for (text, primes) in results.iter().flatten() {
+            let words: Vec<&str> = text.split(' ').collect();
+            assert_eq!(primes.len(), words.len(), "{text}");
+            for (word, &prime) in words.iter().zip(primes) {
+                assert_eq!(tokenizer.get_prime(word), Some(prime), "{word}");
+                assert_eq!(tokenizer.get_token(prime).as_deref(), Some(*word));
+                // No prime was handed to two tokens
+                assert_eq!(*seen.entry(prime).or_insert(*word), *word);
+            }
+        }
+        assert_eq!(seen.len(), tokenizer.vocabulary_size());
+        assert_eq!(tokenizer.document_frequency("shared"), (THREADS * DOCS) as u64);
+        assert_eq!(tokenizer.document_frequency("thread3"), DOCS as u64);
+
+        // Eviction after a parallel build still keeps the most frequent tokens
+        let mut tokenizer = tokenizer;
+        let evicted = tokenizer.evict_least_frequent(1 + THREADS);
+        assert_eq!(evicted.len(), THREADS * DOCS);
+        assert!(tokenizer.get_prime("shared").is_some());
+        assert!((0..THREADS).all(|thread| tokenizer.get_prime(&format!("thread{thread}")).is_some()));
+        assert_eq!(tokenizer.get_prime("doc0x0"), None);
+    }
+}
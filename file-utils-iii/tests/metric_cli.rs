@@ -0,0 +1,113 @@
+// tests/metric_cli.rs
+
+//! Runs the `quantum-search` binary end to end with `--metric`
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// A scratch directory holding the files to search, the settings and
+/// whatever the binary saves to its working directory
+struct Workspace(PathBuf);
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("quantum-search-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("docs")).unwrap();
+        Workspace(root)
+    }
+
+    fn docs(&self) -> PathBuf {
+        self.0.join("docs")
+    }
+
+    fn write(&self, name: &str, content: &str) {
+        fs::write(self.docs().join(name), content).unwrap();
+    }
+
+    /// Index the docs, run `input` through the prompt and return what was
+    /// printed to stdout
+    fn run(&self, args: &[&str], input: &str) -> String {
+        // Contents are read during the scan, so every run sees the same documents
+        let settings = serde_json::json!({
+            "search_paths": [self.docs()],
+            "indexer": { "metadata_first": false },
+        });
+        let config = self.0.join("settings.json");
+        fs::write(&config, settings.to_string()).unwrap();
+        // Start from a fresh index, without the prompt to load the last one
+        let _ = fs::remove_file(self.0.join("quantum_fs_index.db"));
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_quantum-search"))
+            .arg("--config").arg(&config)
+            .args(args)
+            .current_dir(&self.0)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("the binary starts");
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{:?}", output.status);
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The resonance shown for each result, by file name
+fn resonances(stdout: &str) -> HashMap<String, f64> {
+    let mut resonances = HashMap::new();
+    let mut title = None;
+    for line in stdout.lines() {
+        if let Some((_, name)) = line.split_once("📄 ") {
+            title = Some(name.trim().to_string());
+        } else if let Some((_, value)) = line.split_once("Resonance: ") {
+            let value = value.split(" |").next().unwrap().trim().parse().unwrap();
+            resonances.insert(title.take().expect("a result title"), value);
+        }
+    }
+    resonances
+}
+
+fn search(workspace: &Workspace, metric: &str, query: &str) -> (HashMap<String, f64>, String) {
+    let stdout = workspace.run(
+        &["--metric", metric, "--show", "resonance", "--precision", "6"],
+        &format!("{}\nstats json\nquit\n", query),
+    );
+    (resonances(&stdout), stdout)
+}
+
+#[test]
+fn metric_flag_scores_results_by_cosine_similarity() {
+    let workspace = Workspace::new("metric");
+    // Named alike so both documents have the same other terms; only how
+    // often the query word occurs differs
+    workspace.write("a.txt", "zeppelin over the harbour");
+    workspace.write("b.txt", "zeppelin zeppelin zeppelin over the harbour");
+    workspace.write("c.txt", "weather report for the harbour");
+
+    let (cosine, stdout) = search(&workspace, "cosine", "zeppelin");
+    assert!(stdout.contains(r#""similarity_metric": "Cosine""#), "{stdout}");
+    let (a, b) = (cosine["a.txt"], cosine["b.txt"]);
+    assert!(!cosine.contains_key("c.txt"));
+    // Cosine with a one-word query is count / |term counts|. With s the sum
+    // of the squared counts of the shared other terms, a = 1 / sqrt(1 + s),
+    // so b = 3 / sqrt(9 + s) follows from a
+    let others = 1.0 / (a * a) - 1.0;
+    assert!((b - 3.0 / (9.0 + others).sqrt()).abs() < 1e-4, "a = {a}, b = {b}");
+    assert!(b > a && b <= 1.0);
+
+    // Jaccard ignores how often a term occurs, so the flag really changes the scoring
+    let (jaccard, stdout) = search(&workspace, "jaccard", "zeppelin");
+    assert!(stdout.contains(r#""similarity_metric": "Jaccard""#), "{stdout}");
+    assert!((jaccard["a.txt"] - jaccard["b.txt"]).abs() < 1e-6, "{jaccard:?}");
+}
@@ -0,0 +1,59 @@
+// tests/quiet_cli.rs
+
+//! Runs the `quantum-search` binary end to end to check that only results
+//! reach stdout
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Index a scratch directory, search it for `query` and quit
+fn run(root: &PathBuf, args: &[&str], query: &str) -> Output {
+    // Start from a fresh index, without the prompt to load the last one
+    let _ = fs::remove_file(root.join("quantum_fs_index.db"));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_quantum-search"))
+        .arg("--config").arg(root.join("settings.json"))
+        .args(args)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the binary starts");
+    child.stdin.take().unwrap().write_all(format!("{}\nquit\n", query).as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output.status);
+    output
+}
+
+#[test]
+fn quiet_runs_print_results_and_nothing_else() {
+    let root = std::env::temp_dir().join(format!("quantum-search-quiet-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("docs/a.txt"), "zeppelin over the harbour").unwrap();
+    fs::write(root.join("docs/b.txt"), "weather report for the harbour").unwrap();
+    let settings = serde_json::json!({
+        "search_paths": [root.join("docs")],
+        "indexer": { "metadata_first": false },
+    });
+    fs::write(root.join("settings.json"), settings.to_string()).unwrap();
+
+    let quiet = run(&root, &["--quiet"], "zeppelin");
+    let chatty = run(&root, &[], "zeppelin");
+    let _ = fs::remove_dir_all(&root);
+
+    let stdout = String::from_utf8(quiet.stdout).unwrap();
+    assert!(stdout.starts_with("[1] 📄 a.txt\n"), "{stdout}");
+    assert!(!stdout.contains("b.txt"), "{stdout}");
+    // Every line belongs to a result: its numbered title or an indented detail
+    for line in stdout.lines().filter(|line| !line.is_empty()) {
+        assert!(line.starts_with("[1] ") || line.starts_with("    "), "not a result line: {line:?}");
+    }
+    assert!(quiet.stderr.is_empty(), "{}", String::from_utf8_lossy(&quiet.stderr));
+
+    // Without the flag the same results are printed, and the chatter goes to stderr
+    assert_eq!(String::from_utf8(chatty.stdout).unwrap(), stdout);
+    assert!(!chatty.stderr.is_empty());
+}
@@ -1,6 +1,7 @@
 use chrono::{NaiveDate};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use crate::date_utils::{haab_new_year, tzolkin_date, YearBearerSystem, GMT_CORRELATION};
 
 // First, let's define our astronomical constants
 lazy_static! {
@@ -36,7 +37,7 @@ pub fn moon_phase(jdn: i32) -> String {
     
     // Calculate the phase angle (0 to 1, where 0 = new moon, 0.5 = full moon)
     // The offset 2451550.1 is the Julian Day for a known new moon (January 6, 2000)
-    let phase = ((jdn as f64 - 2451550.1) % lunar_month) / lunar_month;
+    let phase = (jdn as f64 - 2451550.1).rem_euclid(lunar_month) / lunar_month;
     
     // Convert the phase to a descriptive string with appropriate emoji
     match phase {
@@ -59,7 +60,7 @@ pub fn venus_phase(jdn: i32) -> String {
     
     // Calculate phase angle (0 to 1)
     // The offset 2451996.706 corresponds to an inferior conjunction of Venus
-    let phase = ((jdn as f64 - 2451996.706) % venus_period) / venus_period;
+    let phase = (jdn as f64 - 2451996.706).rem_euclid(venus_period) / venus_period;
     
     // Venus phases have special significance in Maya astronomy
     match phase {
@@ -72,32 +73,38 @@ pub fn venus_phase(jdn: i32) -> String {
     }.to_string()
 }
 
-/// Determines the Year Bearer (year god) for a given Julian Day Number
-pub fn year_bearer(jdn: i32) -> String {
-    // The Year Bearer system uses four day signs: Ik', Manik', Eb', and Kab'an
-    let year_bearers = [
-        "Ik' (White)",
-        "Manik' (Deer)",
-        "Eb' (Grass)",
-        "Kab'an (Earth)",
-    ];
+/// Determines the Year Bearer (year god) for a day, given as days since the
+/// creation date: the Tzolk'in day name on which its Haab' year began
+pub fn year_bearer(days_since_creation: i32, system: YearBearerSystem) -> String {
+    let new_year = haab_new_year(days_since_creation, system.new_year_offset());
+    let name = tzolkin_date(new_year).yucatec_name;
     
-    // Calculate the year position in the cycle
-    let year_position = ((jdn - 2456282).rem_euclid(1461)) / 365;    // 1461 = 4 * 365.25 (approx)
-    year_bearers[year_position as usize].to_string()
+    // The bearers of all three systems, with their meaning
+    let meaning = match name.as_str() {
+        "Ik'" => "White",
+        "Manik'" => "Deer",
+        "Eb'" => "Grass",
+        "Kab'an" => "Earth",
+        "Ak'b'al" => "Night",
+        "Lamat" => "Star",
+        "B'en" => "Reed",
+        "Etz'nab'" => "Flint",
+        "K'an" => "Seed",
+        "Muluk" => "Water",
+        "Ix" => "Jaguar",
+        "Kawak" => "Storm",
+        _ => return name,
+    };
+    format!("{} ({})", name, meaning)
 }
 
 /// Calculates the next seasonal event (solstice or equinox) and days until it
 pub fn next_solstice_or_equinox(year: i32, month: i32, day: i32) -> (String, i32) {
     let current_date = NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap();
     
-    // Find the next seasonal event
+    // Find the next seasonal event, in date order through this year
     for &(event_month, event_day, event_name) in SEASONAL_DATES.iter() {
-        let event_date = NaiveDate::from_ymd_opt(
-            if event_month < month { year + 1 } else { year },
-            event_month as u32,
-            event_day as u32
-        ).unwrap();
+        let event_date = NaiveDate::from_ymd_opt(year, event_month as u32, event_day as u32).unwrap();
         
         if event_date > current_date {
             let days_until = event_date.signed_duration_since(current_date).num_days();
@@ -118,7 +125,7 @@ pub fn next_eclipse(jdn: i32) -> String {
     
     // Calculate position in eclipse cycle
     // The offset 2451550.1 is a known eclipse date
-    let eclipse_phase = ((jdn as f64 - 2451550.1) % saros) / saros;
+    let eclipse_phase = (jdn as f64 - 2451550.1).rem_euclid(saros) / saros;
     
     match eclipse_phase {
         p if p < 0.01 => "🌑 Possible Solar Eclipse".to_string(),
@@ -136,7 +143,7 @@ pub fn historical_event(jdn: i32) -> Option<&'static str> {
     lazy_static! {
         static ref HISTORICAL_EVENTS: HashMap<i32, &'static str> = {
             let mut m = HashMap::new();
-            m.insert(584283, "🌎 The Maya creation date (0.0.0.0.0)");
+            m.insert(GMT_CORRELATION, "🌎 The Maya creation date (0.0.0.0.0)");
             m.insert(1710534, "📜 Earliest Long Count Date Found (7.16.3.2.13)");
             m.insert(1722559, "🏛️ Dedication of Temple of the Cross at Palenque");
             m.insert(1729974, "⚔️ Teotihuacan Influence Over Tikal Begins");
@@ -147,4 +154,47 @@ pub fn historical_event(jdn: i32) -> Option<&'static str> {
     }
     
     HISTORICAL_EVENTS.get(&jdn).copied()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_utils::haab_date;
+
+    // The creation date is 4 Ajaw 8 Kumk'u, so the first 0 Pop is 17 days later, on 13 Kab'an
+    #[test]
+    fn known_dates_carry_the_bearer_of_the_selected_system() {
+        assert_eq!(year_bearer(17, YearBearerSystem::Classic), "Kab'an (Earth)");
+        assert_eq!(year_bearer(16, YearBearerSystem::Classic), "Eb' (Grass)");
+        // The later sets reckon the year from one and two days after the seating of Pop
+        assert_eq!(year_bearer(17, YearBearerSystem::Mayapan), "B'en (Reed)");
+        assert_eq!(year_bearer(18, YearBearerSystem::Mayapan), "Etz'nab' (Flint)");
+        assert_eq!(year_bearer(19, YearBearerSystem::Colonial), "Kawak (Storm)");
+
+        // Pakal's death, 9.12.11.5.18 6 Etz'nab' 11 Yax, fell 191 days into a year borne by 10 Manik'
+        let pakal_death = 9 * 144_000 + 12 * 7_200 + 11 * 360 + 5 * 20 + 18;
+        assert_eq!(year_bearer(pakal_death, YearBearerSystem::Classic), "Manik' (Deer)");
+    }
+
+    #[test]
+    fn the_bearer_changes_on_the_haab_new_year_only() {
+        for system in [YearBearerSystem::Classic, YearBearerSystem::Mayapan, YearBearerSystem::Colonial] {
+            let mut seen = Vec::new();
+            // Across several years, including the drift the 365-day year has against 1461/4
+            for days in 1_000_000..1_000_000 + 4 * 365 {
+                let changed = year_bearer(days, system) != year_bearer(days - 1, system);
+                let new_year = haab_date(days - system.new_year_offset());
+                assert_eq!(changed, new_year.day == 0 && new_year.yucatec_month == "Pop", "{system:?} on day {days}");
+                if changed {
+                    seen.push(tzolkin_date(days).yucatec_name);
+                }
+            }
+            // Each new year the next bearer of the set takes over
+            assert_eq!(seen.len(), 4);
+            let bearers = system.bearers();
+            let first = bearers.iter().position(|bearer| *bearer == seen[0]).unwrap();
+            for (i, name) in seen.iter().enumerate() {
+                assert_eq!(name, bearers[(first + i) % 4]);
+            }
+        }
+    }
+}
@@ -6,6 +6,12 @@ pub const HAAB_GLYPH_PATH: &str = "assets/haab/glyphs/";
 pub struct Config {
     pub tzolkin_glyphs: HashMap<String, String>,
     pub haab_glyphs: HashMap<String, String>,
+    // Pack each glyph type into one texture rather than one texture per glyph
+    pub glyph_atlas: bool,
+    // Maya numerals font to use unless MAYAN_CALENDAR_FONT names one
+    pub mayan_font: Option<String>,
+    // Try the Maya numerals font first for all UI text, not just the Long Count
+    pub mayan_font_everywhere: bool,
 }
 
 impl Default for Config {
@@ -58,6 +64,9 @@ impl Default for Config {
         Self {
             tzolkin_glyphs,
             haab_glyphs,
+            glyph_atlas: true,
+            mayan_font: None,
+            mayan_font_everywhere: false,
         }
     }
 }
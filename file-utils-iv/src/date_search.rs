@@ -0,0 +1,441 @@
+use chrono::{Datelike, NaiveDate};
+use crate::LongCount;
+use crate::astronomical::moon_phase;
+use crate::date_utils::{
+    days_since_creation, gregorian_to_jdn, haab_date, jdn_to_gregorian, tzolkin_date,
+    HaabDate, HaabNumbering, TzolkinDate, GMT_CORRELATION, HAAB_MONTHS, HAAB_SEATING, TZOLKIN_NAMES,
+};
+
+// Phase names as reported by `moon_phase`, without the emoji
+pub const MOON_PHASES: [&str; 8] = [
+    "New Moon", "Waxing Crescent", "First Quarter", "Waxing Gibbous",
+    "Full Moon", "Waning Gibbous", "Last Quarter", "Waning Crescent",
+];
+
+// Long Count places from largest to smallest, with the highest value each
+// can take (a uinal is 18 of them, the rest 20)
+pub const LONG_COUNT_PLACES: [(&str, i32); 5] = [
+    ("Bak'tun", 19), ("K'atun", 19), ("Tun", 19), ("Winal", 17), ("K'in", 19),
+];
+
+// A little over two Calendar Rounds (2 x 18,980 days), so every Calendar
+// Round position turns up at least twice within the default range
+pub const DEFAULT_SEARCH_DAYS: i32 = 40_000;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum QueryError {
+    #[error("Nothing to search for")]
+    Empty,
+    #[error("Tzolk'in number {0} is outside 1-13")]
+    TzolkinNumberOutOfRange(i32),
+    #[error("Haab' day {day} is outside 0-{max} for {month}")]
+    HaabDayOutOfRange { day: i32, month: String, max: i32 },
+    #[error("Unknown Tzolk'in day name: {0}")]
+    UnknownDayName(String),
+    #[error("Unknown Haab' month: {0}")]
+    UnknownMonth(String),
+    #[error("Unknown moon phase: {0}")]
+    UnknownMoonPhase(String),
+    #[error("{place} {value} is outside 0-{max}")]
+    LongCountOutOfRange { place: &'static str, value: i32, max: i32 },
+    #[error("Can't read '{0}' as part of a date")]
+    Unrecognized(String),
+    #[error("Checking a date needs its full Long Count, Tzolk'in and Haab'")]
+    IncompleteDate,
+}
+
+// Names match regardless of case and apostrophes, so "kumku" finds Kumk'u
+fn same_name(a: &str, b: &str) -> bool {
+    let fold = |s: &str| s.chars().filter(|&c| c != '\'').flat_map(char::to_lowercase).collect::<String>();
+    fold(a) == fold(b)
+}
+
+fn find_name(names: &[&'static str], name: &str) -> Option<&'static str> {
+    names.iter().copied().find(|candidate| same_name(candidate, name))
+}
+
+// Read a partial Long Count such as "9.12.11.5.18", "9.12.*.*.*" or "9.12":
+// places written as "*" or left off the end match any value
+pub fn parse_long_count(text: &str) -> Result<[Option<i32>; 5], QueryError> {
+    let unrecognized = || QueryError::Unrecognized(text.to_string());
+    let parts: Vec<&str> = text.trim().split('.').collect();
+    if parts.len() > LONG_COUNT_PLACES.len() {
+        return Err(unrecognized());
+    }
+
+    let mut places = [None; 5];
+    for (place, part) in places.iter_mut().zip(&parts) {
+        *place = match part.trim() {
+            "*" => None,
+            part => Some(part.parse().map_err(|_| unrecognized())?),
+        };
+    }
+    Ok(places)
+}
+
+// The parts of a date to look for; parts left as `None` match any day.
+// Names are Yucatec, as in `TzolkinDate` and `HaabDate`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DateQuery {
+    pub tzolkin_number: Option<i32>,
+    pub tzolkin_name: Option<String>,
+    pub haab_day: Option<i32>,
+    pub haab_month: Option<String>,
+    pub moon_phase: Option<String>,
+    // Bak'tun, k'atun, tun, winal and k'in, as in `LONG_COUNT_PLACES`
+    pub long_count: [Option<i32>; 5],
+}
+
+impl DateQuery {
+    // Read a query such as "4 Ajaw", "4 Ajaw 8 Kumk'u", "Ajaw",
+    // "13 Ajaw full moon" or "9.12.*.*.* 8 Ajaw": a number followed by a day
+    // name or month name, either name on its own, one of the `MOON_PHASES`
+    // and/or a partial Long Count (see `parse_long_count`). Haab' days count
+    // from 0, and "Chum Pop" is read as 0 Pop.
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let mut query = DateQuery::default();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut i = 0;
+
+        while i < words.len() {
+            if let Some(next) = words.get(i + 1) {
+                if let Some(phase) = find_name(&MOON_PHASES, &format!("{} {}", words[i], next)) {
+                    query.moon_phase = Some(phase.to_string());
+                    i += 2;
+                    continue;
+                }
+            }
+
+            if words[i].contains('.') {
+                query.long_count = parse_long_count(words[i])?;
+                i += 1;
+                continue;
+            }
+
+            let seating = same_name(words[i], HAAB_SEATING);
+            let (number, name) = match (words[i].parse::<i32>(), words.get(i + 1)) {
+                (Ok(number), Some(name)) => (Some(number), *name),
+                (Err(_), Some(name)) if seating && find_name(&HAAB_MONTHS, name).is_some() => (Some(0), *name),
+                (Ok(_), None) => return Err(QueryError::Unrecognized(words[i].to_string())),
+                (Err(_), _) => (None, words[i]),
+            };
+            i += if number.is_some() { 2 } else { 1 };
+
+            if let Some(day_name) = find_name(&TZOLKIN_NAMES, name) {
+                query.tzolkin_number = number.or(query.tzolkin_number);
+                query.tzolkin_name = Some(day_name.to_string());
+            } else if let Some(month) = find_name(&HAAB_MONTHS, name) {
+                query.haab_day = number.or(query.haab_day);
+                query.haab_month = Some(month.to_string());
+            } else {
+                return Err(QueryError::Unrecognized(name.to_string()));
+            }
+        }
+
+        query.validate()?;
+        Ok(query)
+    }
+
+    // Check that the query asks for something and that it can ever match
+    pub fn validate(&self) -> Result<(), QueryError> {
+        if *self == DateQuery::default() {
+            return Err(QueryError::Empty);
+        }
+        if let Some(number) = self.tzolkin_number {
+            if !(1..=13).contains(&number) {
+                return Err(QueryError::TzolkinNumberOutOfRange(number));
+            }
+        }
+        if let Some(name) = &self.tzolkin_name {
+            find_name(&TZOLKIN_NAMES, name).ok_or_else(|| QueryError::UnknownDayName(name.clone()))?;
+        }
+        if let Some(month) = &self.haab_month {
+            find_name(&HAAB_MONTHS, month).ok_or_else(|| QueryError::UnknownMonth(month.clone()))?;
+        }
+        if let Some(day) = self.haab_day {
+            // Wayeb' is the five-day closing month; the rest have 20 days
+            let is_wayeb = self.haab_month.as_deref().is_some_and(|m| same_name(m, "Wayeb'"));
+            let max = if is_wayeb { 4 } else { 19 };
+            if !(0..=max).contains(&day) {
+                let month = self.haab_month.clone().unwrap_or_else(|| "any month".to_string());
+                return Err(QueryError::HaabDayOutOfRange { day, month, max });
+            }
+        }
+        if let Some(phase) = &self.moon_phase {
+            find_name(&MOON_PHASES, phase).ok_or_else(|| QueryError::UnknownMoonPhase(phase.clone()))?;
+        }
+        for (&value, &(place, max)) in self.long_count.iter().zip(&LONG_COUNT_PLACES) {
+            if let Some(value) = value {
+                if !(0..=max).contains(&value) {
+                    return Err(QueryError::LongCountOutOfRange { place, value, max });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The Long Count and Calendar Round of a fully written date, such as
+    // "9.12.11.5.18 6 Etz'nab' 11 Yax", to check one against the other with
+    // `validate_date`
+    pub fn full_date(&self) -> Result<(LongCount, TzolkinDate, HaabDate), QueryError> {
+        let incomplete = || QueryError::IncompleteDate;
+        let [baktun, katun, tun, uinal, kin] = self.long_count;
+        let long_count = LongCount {
+            baktun: baktun.ok_or_else(incomplete)?,
+            katun: katun.ok_or_else(incomplete)?,
+            tun: tun.ok_or_else(incomplete)?,
+            uinal: uinal.ok_or_else(incomplete)?,
+            kin: kin.ok_or_else(incomplete)?,
+        };
+        let tzolkin = TzolkinDate::new(self.tzolkin_number.ok_or_else(incomplete)?, self.tzolkin_name.as_deref().ok_or_else(incomplete)?);
+        let haab = HaabDate::new(self.haab_day.ok_or_else(incomplete)?, self.haab_month.as_deref().ok_or_else(incomplete)?);
+        Ok((long_count, tzolkin, haab))
+    }
+
+    // Whether the day `days` after creation, which is Julian Day `jdn`, matches
+    fn matches(&self, days: i32, jdn: i32) -> bool {
+        let tzolkin = tzolkin_date(days);
+        let haab = haab_date(days);
+        let long_count = LongCount::from_days(days);
+        let places = [long_count.baktun, long_count.katun, long_count.tun, long_count.uinal, long_count.kin];
+        let phase = || {
+            let phase = moon_phase(jdn);
+            phase.split_once(' ').map(|(_, name)| name.to_string()).unwrap_or(phase)
+        };
+
+        self.tzolkin_number.is_none_or(|number| number == tzolkin.number)
+            && self.tzolkin_name.as_deref().is_none_or(|name| same_name(name, &tzolkin.yucatec_name))
+            && self.haab_day.is_none_or(|day| day == haab.day)
+            && self.haab_month.as_deref().is_none_or(|month| same_name(month, &haab.yucatec_month))
+            && self.moon_phase.as_deref().is_none_or(|wanted| same_name(wanted, &phase()))
+            && self.long_count.iter().zip(places).all(|(wanted, place)| wanted.is_none_or(|wanted| wanted == place))
+    }
+}
+
+// The search panel's fields as typed: numbers and the Long Count stay text so
+// half-finished input can be reported, and blank fields match any day. Names
+// come from `TZOLKIN_NAMES` and `HAAB_MONTHS`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DateSearchForm {
+    pub tzolkin_number: String,
+    pub tzolkin_name: Option<&'static str>,
+    pub haab_day: String,
+    pub haab_month: Option<&'static str>,
+    pub long_count: String,
+}
+
+impl DateSearchForm {
+    // The query the fields describe. The Haab' day is read the way the app
+    // displays it under `numbering`, so "20 Pop" counted from 1 is 19 Pop,
+    // and "Chum" is the seating day either way.
+    pub fn to_query(&self, numbering: HaabNumbering) -> Result<DateQuery, QueryError> {
+        let number = |text: &str| -> Result<Option<i32>, QueryError> {
+            match text.trim() {
+                "" => Ok(None),
+                text => text.parse().map(Some).map_err(|_| QueryError::Unrecognized(text.to_string())),
+            }
+        };
+
+        let haab_day = if same_name(self.haab_day.trim(), HAAB_SEATING) {
+            Some(0)
+        } else {
+            number(&self.haab_day)?.map(|day| match numbering {
+                HaabNumbering::Seating => day,
+                HaabNumbering::OneBased => day - 1,
+            })
+        };
+        let long_count = if self.long_count.trim().is_empty() {
+            [None; 5]
+        } else {
+            parse_long_count(&self.long_count)?
+        };
+
+        let query = DateQuery {
+            tzolkin_number: number(&self.tzolkin_number)?,
+            tzolkin_name: self.tzolkin_name.map(str::to_string),
+            haab_day,
+            haab_month: self.haab_month.map(str::to_string),
+            moon_phase: None,
+            long_count,
+        };
+        query.validate()?;
+        Ok(query)
+    }
+}
+
+// How far `date` is from `today`, e.g. "today", "in 3 days" or
+// "18980 days ago (52.0 years)"
+pub fn describe_distance(today: NaiveDate, date: NaiveDate) -> String {
+    let days = (date - today).num_days();
+    let amount = match days.abs() {
+        0 => return "today".to_string(),
+        1 => "1 day".to_string(),
+        n if n < 365 => format!("{} days", n),
+        n => format!("{} days ({:.1} years)", n, n as f64 / 365.2425),
+    };
+    if days > 0 {
+        format!("in {}", amount)
+    } else {
+        format!("{} ago", amount)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    pub direction: Direction,
+    // Stop after this many matches
+    pub count: usize,
+    // Give up after scanning this many days from the reference date
+    pub max_days: i32,
+    // JDN of the creation date, e.g. GMT_CORRELATION
+    pub correlation: i32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            direction: Direction::Forward,
+            count: 5,
+            max_days: DEFAULT_SEARCH_DAYS,
+            correlation: GMT_CORRELATION,
+        }
+    }
+}
+
+// The Gregorian dates after (or before) `from` that match `query`, nearest
+// first; `from` itself is not included. Fewer than `options.count` dates come
+// back if `options.max_days` runs out first.
+pub fn find_dates(query: &DateQuery, from: NaiveDate, options: &SearchOptions) -> Result<Vec<NaiveDate>, QueryError> {
+    query.validate()?;
+
+    let from_jdn = gregorian_to_jdn(from.year(), from.month() as i32, from.day() as i32);
+    let step = match options.direction {
+        Direction::Forward => 1,
+        Direction::Backward => -1,
+    };
+
+    let mut dates = Vec::new();
+    for offset in 1..=options.max_days {
+        if dates.len() >= options.count {
+            break;
+        }
+        let jdn = from_jdn + step * offset;
+        if query.matches(days_since_creation(jdn, options.correlation), jdn) {
+            if let Some(date) = jdn_to_gregorian(jdn) {
+                dates.push(date);
+            }
+        }
+    }
+    Ok(dates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_utils::{validate_date, CalendarField};
+
+    #[test]
+    fn fully_written_dates_are_checked_against_their_long_count() {
+        let (long_count, tzolkin, haab) = DateQuery::parse("9.12.11.5.18 6 Etz'nab' 11 Yax").unwrap().full_date().unwrap();
+        assert!(validate_date(&long_count, &tzolkin, &haab).is_ok());
+
+        let (long_count, tzolkin, haab) = DateQuery::parse("9.12.11.5.18 6 Etznab 12 Yax").unwrap().full_date().unwrap();
+        assert_eq!(validate_date(&long_count, &tzolkin, &haab).unwrap_err().fields, vec![CalendarField::HaabDay]);
+    }
+
+    #[test]
+    fn partial_dates_cannot_be_checked() {
+        for text in ["9.12.*.5.18 6 Etz'nab' 11 Yax", "9.12.11.5.18 6 Etz'nab'", "9.12.11.5.18 Etz'nab' 11 Yax"] {
+            assert_eq!(DateQuery::parse(text).unwrap().full_date().unwrap_err(), QueryError::IncompleteDate, "{text}");
+        }
+    }
+
+    // The form as filled in for 4 Ajaw 3 Kankin, the end of the 13th b'ak'tun
+    fn calendar_round_form(haab_day: &str) -> DateSearchForm {
+        DateSearchForm {
+            tzolkin_number: "4".to_string(),
+            tzolkin_name: Some("Ajaw"),
+            haab_day: haab_day.to_string(),
+            haab_month: Some("Kankin"),
+            long_count: String::new(),
+        }
+    }
+
+    #[test]
+    fn the_form_finds_a_calendar_round_once_every_52_years() {
+        let end_of_baktun = NaiveDate::from_ymd_opt(2012, 12, 21).unwrap();
+        let query = calendar_round_form("3").to_query(HaabNumbering::Seating).unwrap();
+        // The same day counted from 1
+        assert_eq!(calendar_round_form("4").to_query(HaabNumbering::OneBased).unwrap(), query);
+
+        let forward = SearchOptions { count: 2, max_days: 40_000, ..SearchOptions::default() };
+        let dates = find_dates(&query, end_of_baktun.pred_opt().unwrap(), &forward).unwrap();
+        let next_round = end_of_baktun + chrono::Duration::days(18_980);
+        assert_eq!(dates, vec![end_of_baktun, next_round]);
+        assert_eq!(describe_distance(end_of_baktun, next_round), "in 18980 days (52.0 years)");
+
+        let backward = SearchOptions { direction: Direction::Backward, count: 1, ..SearchOptions::default() };
+        assert_eq!(find_dates(&query, end_of_baktun.succ_opt().unwrap(), &backward).unwrap(), vec![end_of_baktun]);
+
+        // Adding the Long Count pins it to one day
+        let form = DateSearchForm { long_count: "13.0.0.0.0".to_string(), ..calendar_round_form("3") };
+        let query = form.to_query(HaabNumbering::Seating).unwrap();
+        let dates = find_dates(&query, end_of_baktun.pred_opt().unwrap(), &forward).unwrap();
+        assert_eq!(dates, vec![end_of_baktun]);
+    }
+
+    #[test]
+    fn unfinished_form_fields_are_reported() {
+        let form = DateSearchForm { tzolkin_number: "four".to_string(), ..calendar_round_form("3") };
+        assert_eq!(form.to_query(HaabNumbering::Seating), Err(QueryError::Unrecognized("four".to_string())));
+        assert!(calendar_round_form("25").to_query(HaabNumbering::Seating).is_err());
+        assert_eq!(DateSearchForm::default().to_query(HaabNumbering::Seating), Err(QueryError::Empty));
+    }
+
+    // Days between consecutive matches of `text` after the end of the 13th b'ak'tun
+    fn gaps(text: &str, count: usize) -> Vec<i64> {
+        let from = NaiveDate::from_ymd_opt(2012, 12, 21).unwrap();
+        let dates = find_dates(&DateQuery::parse(text).unwrap(), from, &SearchOptions { count, ..SearchOptions::default() }).unwrap();
+        assert_eq!(dates.len(), count, "{text}");
+        dates.windows(2).map(|pair| (pair[1] - pair[0]).num_days()).collect()
+    }
+
+    #[test]
+    fn partial_matches_recur_on_their_own_cycles() {
+        // A Tzolk'in date comes round every 260 days, a Haab' date every 365
+        assert!(gaps("4 Ajaw", 6).iter().all(|&gap| gap == 260));
+        assert!(gaps("Ajaw", 6).iter().all(|&gap| gap == 20));
+        assert!(gaps("3 Kankin", 6).iter().all(|&gap| gap == 365));
+        assert!(gaps("Kankin", 30).iter().all(|&gap| gap == 1 || gap == 365 - 19));
+
+        // A phase lasts three or four days, and comes back a synodic month later
+        let full_moon = gaps("full moon", 40);
+        let mut run = 1;
+        for &gap in &full_moon {
+            if gap == 1 {
+                run += 1;
+            } else {
+                assert!((3..=4).contains(&run), "{full_moon:?}");
+                assert!((29..=30).contains(&(gap + run - 1)), "{full_moon:?}");
+                run = 1;
+            }
+        }
+
+        // Both at once keeps to the Tzolk'in cycle, on full moons only. The
+        // search starts on 4 Ajaw, and 13 Ajaw is five Ajaw days (100 days) on
+        let from = NaiveDate::from_ymd_opt(2012, 12, 21).unwrap();
+        let dates = find_dates(&DateQuery::parse("13 Ajaw full moon").unwrap(), from, &SearchOptions { count: 4, ..SearchOptions::default() }).unwrap();
+        assert_eq!(dates.len(), 4);
+        for date in &dates {
+            assert_eq!((*date - from).num_days() % 260, 100, "{date}");
+            let jdn = gregorian_to_jdn(date.year(), date.month() as i32, date.day() as i32);
+            assert!(moon_phase(jdn).ends_with("Full Moon"), "{date}");
+        }
+    }
+}
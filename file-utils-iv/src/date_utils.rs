@@ -1,3 +1,37 @@
+use chrono::{Datelike, NaiveDate};
+use crate::LongCount;
+
+// Julian Day Number of the Maya creation date, 13.0.0.0.0 4 Ajaw 8 Kumk'u
+// (August 11, 3114 BCE), under the Goodman-Martinez-Thompson correlation
+pub const GMT_CORRELATION: i32 = 584283;
+// The "GMT+2" variant (Lounsbury) preferred by some epigraphers
+pub const LOUNSBURY_CORRELATION: i32 = 584285;
+
+// Calendar round position on the creation date. The Tzolk'in and Haab' are
+// counted in days since creation, the same count as the Long Count, so they
+// follow whichever correlation was used to find the creation date.
+pub const TZOLKIN_NUMBER_AT_CREATION: i32 = 4; // 4 ...
+pub const TZOLKIN_NAME_AT_CREATION: i32 = 19; // ... Ajaw (index into the 20 day names)
+pub const HAAB_POSITION_AT_CREATION: i32 = 17 * 20 + 8; // 8 Kumk'u (day of the 365-day year)
+
+pub const TZOLKIN_NAMES: [&str; 20] = [
+    "Imix", "Ik'", "Ak'b'al", "K'an", "Chikchan",
+    "Kimi", "Manik'", "Lamat", "Muluk", "Ok",
+    "Chuwen", "Eb'", "B'en", "Ix", "Men",
+    "Kib'", "Kab'an", "Etz'nab'", "Kawak", "Ajaw"
+];
+
+pub const HAAB_MONTHS: [&str; 19] = [
+    "Pop", "Wo'", "Sip", "Sotz'", "Sek", "Xul", "Yaxkin", "Mol",
+    "Ch'en", "Yax", "Zac", "Ceh", "Mac", "Kankin", "Muan", "Pax",
+    "Kayab", "Kumk'u", "Wayeb'"
+];
+
+// Days elapsed since the creation date under the given correlation
+pub fn days_since_creation(jdn: i32, correlation: i32) -> i32 {
+    jdn - correlation
+}
+
 pub fn gregorian_to_jdn(year: i32, month: i32, day: i32) -> i32 {
 // Convert a Gregorian date to Julian Day Number (JDN)
   let a = (14 - month) / 12;
@@ -6,7 +40,101 @@ pub fn gregorian_to_jdn(year: i32, month: i32, day: i32) -> i32 {
   day + ((153 * m + 2) / 5) + 365 * y + y / 4 - y / 100 + y / 400 - 32045
 }
 
-#[derive(Clone)]
+// Convert a Julian Day Number back to a (proleptic) Gregorian date;
+// `None` outside the range chrono can represent
+pub fn jdn_to_gregorian(jdn: i32) -> Option<NaiveDate> {
+    // JDN 1721426 is January 1 of year 1, day 1 in chrono's count
+    NaiveDate::from_num_days_from_ce_opt(jdn - 1_721_425)
+}
+
+// Convert a Julian Day Number to a (proleptic) Julian calendar date as
+// (year, month, day), for JDN 0 onward
+pub fn jdn_to_julian(jdn: i32) -> (i32, i32, i32) {
+    let c = jdn + 32082;
+    let d = (4 * c + 3) / 1461;
+    let e = c - 1461 * d / 4;
+    let m = (5 * e + 2) / 153;
+    let day = e - (153 * m + 2) / 5 + 1;
+    let month = m + 3 - 12 * (m / 10);
+    let year = d - 4800 + m / 10;
+    (year, month, day)
+}
+
+// The calendar the Western date is written in alongside the Maya dates.
+// Gregorian is the modern civil calendar; Julian is what historical and
+// colonial sources before 1582 use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CalendarSystem {
+    #[default]
+    Gregorian,
+    Julian,
+}
+
+impl CalendarSystem {
+    // The date for Julian Day `jdn`, as "YYYY-MM-DD" in this calendar
+    pub fn format_jdn(self, jdn: i32) -> String {
+        let (year, month, day) = match self {
+            CalendarSystem::Gregorian => match jdn_to_gregorian(jdn) {
+                Some(date) => (date.year(), date.month() as i32, date.day() as i32),
+                None => return format!("JDN {}", jdn),
+            },
+            CalendarSystem::Julian => jdn_to_julian(jdn),
+        };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+// Which four Tzolk'in day names carry the years. The bearer is the day name
+// of the day the year is reckoned from, and regions and periods reckoned
+// from different days, each set one day sign on from the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum YearBearerSystem {
+    // Classic period: the day of the seating of Pop (Ik', Manik', Eb', Kab'an)
+    #[default]
+    Classic,
+    // Mayapan and Campeche: the day after (Ak'b'al, Lamat, B'en, Etz'nab')
+    Mayapan,
+    // Colonial Yucatan, as recorded by Landa: two days after (K'an, Muluk, Ix, Kawak)
+    Colonial,
+}
+
+impl YearBearerSystem {
+    // Days after the seating of Pop that the year is reckoned from
+    pub fn new_year_offset(self) -> i32 {
+        match self {
+            YearBearerSystem::Classic => 0,
+            YearBearerSystem::Mayapan => 1,
+            YearBearerSystem::Colonial => 2,
+        }
+    }
+
+    // The four bearers in the order they follow one another
+    pub fn bearers(self) -> [&'static str; 4] {
+        let first = 1 + self.new_year_offset() as usize; // Ik' for the Classic set
+        [0, 5, 10, 15].map(|step| TZOLKIN_NAMES[first + step])
+    }
+}
+
+// Everything besides the date that `CalendarData` depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CalendarSettings {
+    // JDN of the creation date, e.g. GMT_CORRELATION
+    pub correlation: i32,
+    pub system: CalendarSystem,
+    pub year_bearers: YearBearerSystem,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        Self {
+            correlation: GMT_CORRELATION,
+            system: CalendarSystem::default(),
+            year_bearers: YearBearerSystem::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TzolkinDate {
     pub number: i32,
     pub yucatec_name: String,
@@ -22,21 +150,29 @@ impl TzolkinDate {
 }
 
 pub fn tzolkin_date(days: i32) -> TzolkinDate {
-    let number = (((days + 3) % 13 + 13) % 13) + 1;
-    let yucatec_names = [
-        "Imix", "Ik'", "Ak'b'al", "K'an", "Chikchan",
-        "Kimi", "Manik'", "Lamat", "Muluk", "Ok",
-        "Chuwen", "Eb'", "B'en", "Ix", "Men",
-        "Kib'", "Kab'an", "Etz'nab'", "Kawak", "Ajaw"
-    ];
-    let index = (((days + 19) % 20 + 20) % 20) as usize;
+    let number = (days + TZOLKIN_NUMBER_AT_CREATION - 1).rem_euclid(13) + 1;
+    let index = (days + TZOLKIN_NAME_AT_CREATION).rem_euclid(20) as usize;
     TzolkinDate {
         number,
-        yucatec_name: yucatec_names[index].to_string(),
+        yucatec_name: TZOLKIN_NAMES[index].to_string(),
     }
 }
 
-#[derive(Clone)]
+// The word written in place of the day number on the first day of a Haab'
+// month, its "seating": Chum Pop is the day before 1 Pop
+pub const HAAB_SEATING: &str = "Chum";
+
+// How Haab' day numbers are written. Inscriptions count 0-19, with day 0
+// written as the seating of the month; many modern sources count 1-20
+// instead, so their 1 Pop is the seating and 20 Pop the last day of Pop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HaabNumbering {
+    #[default]
+    Seating,
+    OneBased,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct HaabDate {
     pub day: i32,
     pub yucatec_month: String,
@@ -49,21 +185,144 @@ impl HaabDate {
             yucatec_month: month.to_string(),
         }
     }
+
+    // The first day of the month, day 0 in the internal count
+    pub fn is_seating(&self) -> bool {
+        self.day == 0
+    }
+
+    // The day number as written under `numbering`
+    pub fn display_day(&self, numbering: HaabNumbering) -> i32 {
+        match numbering {
+            HaabNumbering::Seating => self.day,
+            HaabNumbering::OneBased => self.day + 1,
+        }
+    }
+
+    // The date as written under `numbering`, e.g. "Chum Wo'" or "1 Wo'"
+    // for the day after 19 Pop (20 Pop when counting from 1)
+    pub fn label(&self, numbering: HaabNumbering) -> String {
+        if numbering == HaabNumbering::Seating && self.is_seating() {
+            format!("{} {}", HAAB_SEATING, self.yucatec_month)
+        } else {
+            format!("{} {}", self.display_day(numbering), self.yucatec_month)
+        }
+    }
 }
 
 pub fn haab_date(days: i32) -> HaabDate {
-    let haab_day = ((days + 348) % 365 + 365) % 365;
+    let haab_day = (days + HAAB_POSITION_AT_CREATION).rem_euclid(365);
     let month_index = haab_day / 20;
     let day = haab_day % 20;
     
-    let yucatec_months = [
-        "Pop", "Wo'", "Sip", "Sotz'", "Sek", "Xul", "Yaxkin", "Mol",
-        "Ch'en", "Yax", "Zac", "Ceh", "Mac", "Kankin", "Muan", "Pax",
-        "Kayab", "Kumk'u", "Wayeb'"
-    ];
-    
     HaabDate {
         day,
-        yucatec_month: yucatec_months[month_index as usize].to_string(),
+        yucatec_month: HAAB_MONTHS[month_index as usize].to_string(),
+    }
+}
+
+// Days since creation of the start of the Haab' year containing `days`,
+// the year beginning `offset` days after the seating of Pop
+pub fn haab_new_year(days: i32, offset: i32) -> i32 {
+    let into_year = (days + HAAB_POSITION_AT_CREATION - offset).rem_euclid(365);
+    days - into_year
+}
+
+// Parts of a Calendar Round that can disagree with a Long Count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarField {
+    TzolkinNumber,
+    TzolkinName,
+    HaabDay,
+    HaabMonth,
+}
+
+// A Calendar Round that is not the one the Long Count falls on
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Calendar Round mismatch in {fields:?}: Long Count falls on {} {} {} {}",
+    expected_tzolkin.number, expected_tzolkin.yucatec_name, expected_haab.day, expected_haab.yucatec_month)]
+pub struct Mismatch {
+    pub fields: Vec<CalendarField>,
+    pub expected_tzolkin: TzolkinDate,
+    pub expected_haab: HaabDate,
+}
+
+// Check a transcribed Long Count against its Calendar Round. Each Long Count
+// determines exactly one Calendar Round, so the Tzolk'in and Haab' are
+// recomputed from it and compared field by field (names ignore case).
+pub fn validate_date(long_count: &LongCount, tzolkin: &TzolkinDate, haab: &HaabDate) -> Result<(), Mismatch> {
+    let days = long_count.to_days();
+    let expected_tzolkin = tzolkin_date(days);
+    let expected_haab = haab_date(days);
+
+    let mut fields = Vec::new();
+    if tzolkin.number != expected_tzolkin.number {
+        fields.push(CalendarField::TzolkinNumber);
+    }
+    if !tzolkin.yucatec_name.eq_ignore_ascii_case(&expected_tzolkin.yucatec_name) {
+        fields.push(CalendarField::TzolkinName);
     }
-}
\ No newline at end of file
+    if haab.day != expected_haab.day {
+        fields.push(CalendarField::HaabDay);
+    }
+    if !haab.yucatec_month.eq_ignore_ascii_case(&expected_haab.yucatec_month) {
+        fields.push(CalendarField::HaabMonth);
+    }
+
+    if fields.is_empty() {
+        Ok(())
+    } else {
+        Err(Mismatch { fields, expected_tzolkin, expected_haab })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pakal's death on the Palenque sarcophagus lid
+    fn pakal_death() -> LongCount {
+        LongCount::from_days(9 * 144_000 + 12 * 7_200 + 11 * 360 + 5 * 20 + 18)
+    }
+
+    #[test]
+    fn consistent_dates_validate() {
+        assert!(validate_date(&pakal_death(), &TzolkinDate::new(6, "Etz'nab'"), &HaabDate::new(11, "Yax")).is_ok());
+        // Names ignore case
+        assert!(validate_date(&LongCount::from_days(0), &TzolkinDate::new(4, "ajaw"), &HaabDate::new(8, "KUMK'U")).is_ok());
+    }
+
+    #[test]
+    fn mismatches_name_the_wrong_fields_and_the_right_date() {
+        let mismatch = validate_date(&pakal_death(), &TzolkinDate::new(6, "Etz'nab'"), &HaabDate::new(16, "Yax")).unwrap_err();
+        assert_eq!(mismatch.fields, vec![CalendarField::HaabDay]);
+        assert_eq!(mismatch.expected_haab.day, 11);
+
+        let mismatch = validate_date(&pakal_death(), &TzolkinDate::new(7, "Kawak"), &HaabDate::new(11, "Zac")).unwrap_err();
+        assert_eq!(mismatch.fields, vec![CalendarField::TzolkinNumber, CalendarField::TzolkinName, CalendarField::HaabMonth]);
+        assert_eq!(mismatch.expected_tzolkin.number, 6);
+        assert_eq!(mismatch.expected_tzolkin.yucatec_name, "Etz'nab'");
+        assert_eq!(mismatch.expected_haab.yucatec_month, "Yax");
+    }
+
+    #[test]
+    fn haab_days_are_written_under_either_numbering_across_a_month_change() {
+        // The creation date is 8 Kumk'u, so day 17 seats Pop and day 37 seats Wo'
+        let [last_of_pop, seating_of_wo, next] = [36, 37, 38].map(haab_date);
+        assert_eq!(last_of_pop, HaabDate::new(19, "Pop"));
+        assert_eq!(seating_of_wo, HaabDate::new(0, "Wo'"));
+        assert!(seating_of_wo.is_seating() && !last_of_pop.is_seating());
+
+        let written = |numbering| [&last_of_pop, &seating_of_wo, &next].map(|date| (date.display_day(numbering), date.label(numbering)));
+        assert_eq!(written(HaabNumbering::Seating), [
+            (19, "19 Pop".to_string()),
+            (0, "Chum Wo'".to_string()),
+            (1, "1 Wo'".to_string()),
+        ]);
+        assert_eq!(written(HaabNumbering::OneBased), [
+            (20, "20 Pop".to_string()),
+            (1, "1 Wo'".to_string()),
+            (2, "2 Wo'".to_string()),
+        ]);
+    }
+}
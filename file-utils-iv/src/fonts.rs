@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+// Where the Maya numerals font is looked for, under the working directory
+// and next to the executable
+pub const MAYAN_FONT_PATH: &str = "assets/fonts/NotoSansMayanNumerals-Regular.ttf";
+
+// Environment variable naming a font file to use before any other
+pub const MAYAN_FONT_ENV: &str = "MAYAN_CALENDAR_FONT";
+
+// The font built into the binary by the `vendored-fonts` feature (on by
+// default), used when no font file is found
+#[cfg(feature = "vendored-fonts")]
+pub const EMBEDDED_FONT: Option<&[u8]> = Some(include_bytes!("../assets/fonts/NotoSansMayanNumerals-Regular.ttf"));
+#[cfg(not(feature = "vendored-fonts"))]
+pub const EMBEDDED_FONT: Option<&[u8]> = None;
+
+// Where the font in use came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontSource {
+    File(PathBuf),
+    Embedded,
+}
+
+impl std::fmt::Display for FontSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontSource::File(path) => write!(f, "{}", path.display()),
+            FontSource::Embedded => write!(f, "the built-in copy"),
+        }
+    }
+}
+
+// The font files to try, in order: the one named by MAYAN_FONT_ENV, the
+// configured one, then MAYAN_FONT_PATH under the working directory and next
+// to the executable
+pub fn font_candidates(configured: Option<&Path>) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = std::env::var_os(MAYAN_FONT_ENV).map(PathBuf::from).into_iter().collect();
+    candidates.extend(configured.map(Path::to_path_buf));
+    candidates.push(PathBuf::from(MAYAN_FONT_PATH));
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        candidates.push(exe_dir.join(MAYAN_FONT_PATH));
+    }
+    candidates
+}
+
+// The first candidate that reads as a non-empty file, else the embedded
+// font if there is one
+pub fn resolve_font(candidates: &[PathBuf], embedded: Option<&'static [u8]>) -> Option<(Vec<u8>, FontSource)> {
+    for path in candidates {
+        match std::fs::read(path) {
+            Ok(data) if !data.is_empty() => return Some((data, FontSource::File(path.clone()))),
+            Ok(_) => debug!("Skipping empty font file {}", path.display()),
+            Err(e) => debug!("No font at {}: {}", path.display(), e),
+        }
+    }
+    embedded.map(|data| (data.to_vec(), FontSource::Embedded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_are_tried_in_order_then_the_embedded_font() {
+        let dir = std::env::temp_dir().join(format!("mayan-fonts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (empty, first, second) = (dir.join("empty.ttf"), dir.join("first.ttf"), dir.join("second.ttf"));
+        std::fs::write(&empty, b"").unwrap();
+        std::fs::write(&first, b"first").unwrap();
+        std::fs::write(&second, b"second").unwrap();
+        let missing = dir.join("missing.ttf");
+
+        // Missing and empty files are passed over
+        let found = resolve_font(&[missing.clone(), empty.clone(), first.clone(), second.clone()], None);
+        assert_eq!(found, Some((b"first".to_vec(), FontSource::File(first.clone()))));
+        let found = resolve_font(&[second.clone(), first], None);
+        assert_eq!(found, Some((b"second".to_vec(), FontSource::File(second))));
+
+        // With no file to read, the embedded copy if there is one
+        assert_eq!(resolve_font(&[missing.clone(), empty.clone()], Some(b"embedded")), Some((b"embedded".to_vec(), FontSource::Embedded)));
+        assert_eq!(resolve_font(&[missing, empty], None), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_configured_font_comes_before_the_default_locations() {
+        let configured = Path::new("/opt/fonts/mayan.ttf");
+        let candidates = font_candidates(Some(configured));
+        let position = |path: &Path| candidates.iter().position(|candidate| candidate == path).unwrap();
+        // MAYAN_FONT_ENV, if set, would come first
+        let offset = usize::from(std::env::var_os(MAYAN_FONT_ENV).is_some());
+        assert_eq!(position(configured), offset);
+        assert_eq!(position(Path::new(MAYAN_FONT_PATH)), offset + 1);
+        assert!(candidates[offset + 2..].iter().all(|path| path.ends_with(MAYAN_FONT_PATH)));
+
+        assert_eq!(font_candidates(None)[offset], PathBuf::from(MAYAN_FONT_PATH));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use egui::{Color32, ColorImage, Pos2, Rect};
+
+// Transparent pixels around each glyph, so filtering at a glyph's edge
+// doesn't pick up its neighbours
+pub const GUTTER: usize = 2;
+
+// Every glyph of one type packed into a single image, so the calendar draws
+// from one texture per glyph type instead of one per glyph. Glyphs sit on a
+// square-ish grid of cells the size of the largest glyph plus a `GUTTER` on
+// each side, in name order.
+pub struct GlyphAtlas {
+    image: ColorImage,
+    // Pixel position and size of each glyph in `image`
+    placements: HashMap<String, ([usize; 2], [usize; 2])>,
+}
+
+impl GlyphAtlas {
+    pub fn pack(mut glyphs: Vec<(String, ColorImage)>) -> Self {
+        glyphs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let cell_width = glyphs.iter().map(|(_, glyph)| glyph.size[0]).max().unwrap_or(0) + 2 * GUTTER;
+        let cell_height = glyphs.iter().map(|(_, glyph)| glyph.size[1]).max().unwrap_or(0) + 2 * GUTTER;
+        let columns = (glyphs.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let rows = glyphs.len().div_ceil(columns);
+
+        let mut image = ColorImage::new([columns * cell_width, rows * cell_height], Color32::TRANSPARENT);
+        let mut placements = HashMap::new();
+        for (i, (name, glyph)) in glyphs.into_iter().enumerate() {
+            let origin = [(i % columns) * cell_width + GUTTER, (i / columns) * cell_height + GUTTER];
+            for y in 0..glyph.size[1] {
+                let source = &glyph.pixels[y * glyph.size[0]..(y + 1) * glyph.size[0]];
+                let start = (origin[1] + y) * image.size[0] + origin[0];
+                image.pixels[start..start + glyph.size[0]].copy_from_slice(source);
+            }
+            placements.insert(name, (origin, glyph.size));
+        }
+
+        Self { image, placements }
+    }
+
+    // Give up the packed pixels once they have been uploaded
+    pub fn take_image(&mut self) -> ColorImage {
+        let size = self.image.size;
+        std::mem::replace(&mut self.image, ColorImage { size, pixels: Vec::new() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.placements.len()
+    }
+
+    // Where the glyph sits in the atlas, in pixels
+    pub fn pixel_rect(&self, name: &str) -> Option<Rect> {
+        let &(origin, size) = self.placements.get(name)?;
+        Some(Rect::from_min_size(
+            Pos2::new(origin[0] as f32, origin[1] as f32),
+            egui::vec2(size[0] as f32, size[1] as f32),
+        ))
+    }
+
+    // Where the glyph sits in the atlas in texture coordinates (0-1), for
+    // `egui::Image::uv`. Inset by half a texel, so sampling stays on the
+    // glyph's own pixels.
+    pub fn uv_rect(&self, name: &str) -> Option<Rect> {
+        let rect = self.pixel_rect(name)?.shrink(0.5);
+        let [width, height] = self.image.size.map(|n| n as f32);
+        Some(Rect::from_min_max(
+            Pos2::new(rect.min.x / width, rect.min.y / height),
+            Pos2::new(rect.max.x / width, rect.max.y / height),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(name: &str, size: [usize; 2], color: Color32) -> (String, ColorImage) {
+        (name.to_string(), ColorImage::new(size, color))
+    }
+
+    #[test]
+    fn glyphs_sit_in_their_cells_with_a_gutter() {
+        let mut atlas = GlyphAtlas::pack(vec![
+            glyph("kan", [8, 6], Color32::RED),
+            glyph("ajaw", [8, 8], Color32::GREEN),
+            glyph("imix", [4, 8], Color32::BLUE),
+        ]);
+        assert_eq!(atlas.len(), 3);
+
+        // Name order on a 2x2 grid of 12x12 cells
+        let cell = 8 + 2 * GUTTER;
+        let expected = [("ajaw", [0, 0], [8, 8]), ("imix", [1, 0], [4, 8]), ("kan", [0, 1], [8, 6])];
+        for (name, [column, row], [width, height]) in expected {
+            let min = Pos2::new((column * cell + GUTTER) as f32, (row * cell + GUTTER) as f32);
+            assert_eq!(atlas.pixel_rect(name), Some(Rect::from_min_size(min, egui::vec2(width as f32, height as f32))), "{name}");
+        }
+        assert_eq!(atlas.pixel_rect("wayeb"), None);
+
+        let image = atlas.take_image();
+        assert_eq!(image.size, [2 * cell, 2 * cell]);
+        let pixel = |x: usize, y: usize| image.pixels[y * image.size[0] + x];
+        assert_eq!(pixel(GUTTER, GUTTER), Color32::GREEN);
+        // Gutters stay clear between neighbours
+        for x in [GUTTER + 8, cell, cell + GUTTER - 1] {
+            assert_eq!(pixel(x, GUTTER), Color32::TRANSPARENT, "x = {x}");
+        }
+        assert_eq!(pixel(cell + GUTTER, GUTTER), Color32::BLUE);
+    }
+
+    #[test]
+    fn uv_rects_are_inset_by_half_a_texel() {
+        let atlas = GlyphAtlas::pack(vec![glyph("ajaw", [8, 8], Color32::GREEN)]);
+        let size = (8 + 2 * GUTTER) as f32;
+        let uv = atlas.uv_rect("ajaw").unwrap();
+        assert_eq!(uv.min, Pos2::new((GUTTER as f32 + 0.5) / size, (GUTTER as f32 + 0.5) / size));
+        assert_eq!(uv.max, Pos2::new((GUTTER as f32 + 7.5) / size, (GUTTER as f32 + 7.5) / size));
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use crate::LongCount;
+use crate::astronomical::{next_eclipse, next_solstice_or_equinox, venus_phase, year_bearer};
+use crate::date_utils::{days_since_creation, gregorian_to_jdn, haab_date, tzolkin_date, CalendarSettings, HaabNumbering};
+
+// Longest range one export may cover. Every day gets its own event, and
+// calendar apps slow down importing much more than a few years of them.
+pub const MAX_EXPORT_DAYS: i64 = 3 * 366;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("The range ends ({end}) before it starts ({start})")]
+    EndBeforeStart { start: NaiveDate, end: NaiveDate },
+    #[error("The range covers {days} days; at most {max} can be exported at once")]
+    RangeTooLong { days: i64, max: i64 },
+    #[error("Can't read '{0}' as a date (expected YYYY-MM-DD)")]
+    BadDate(String),
+    #[error("Couldn't write the calendar: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// Which events go into an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportOptions {
+    // An all-day event per day with its Tzolk'in and Haab' date
+    pub calendar_round: bool,
+    pub seasons: bool,
+    // Eclipse windows from the Saros cycle, one event per window
+    pub eclipses: bool,
+    // The day Venus enters each phase of `venus_phase`. These stand in for
+    // its stations, which would need its apparent motion, not just the
+    // position in its synodic period that the app computes.
+    pub venus: bool,
+    pub haab_numbering: HaabNumbering,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            calendar_round: true,
+            seasons: true,
+            eclipses: true,
+            venus: true,
+            haab_numbering: HaabNumbering::default(),
+        }
+    }
+}
+
+// The export panel's fields, as typed
+#[derive(Debug, Clone, Default)]
+pub struct ExportForm {
+    pub start: String,
+    pub end: String,
+    pub options: ExportOptions,
+}
+
+impl ExportForm {
+    // The range the fields describe, checked against MAX_EXPORT_DAYS
+    pub fn to_range(&self) -> Result<(NaiveDate, NaiveDate), ExportError> {
+        let date = |text: &str| {
+            NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").map_err(|_| ExportError::BadDate(text.to_string()))
+        };
+        let (start, end) = (date(&self.start)?, date(&self.end)?);
+        check_range(start, end)?;
+        Ok((start, end))
+    }
+}
+
+fn check_range(start: NaiveDate, end: NaiveDate) -> Result<(), ExportError> {
+    if end < start {
+        return Err(ExportError::EndBeforeStart { start, end });
+    }
+    let days = (end - start).num_days() + 1;
+    if days > MAX_EXPORT_DAYS {
+        return Err(ExportError::RangeTooLong { days, max: MAX_EXPORT_DAYS });
+    }
+    Ok(())
+}
+
+// One all-day event, from `start` up to but not including `end`
+struct Event {
+    start: NaiveDate,
+    end: NaiveDate,
+    kind: &'static str,
+    summary: String,
+    description: Option<String>,
+}
+
+// An iCalendar (RFC 5545) file with the Maya dates and astronomical events
+// from `start` to `end`, both included, computed under `settings`.
+//
+// Everything here happens to a whole day rather than at a time of day, so
+// events are all-day dates with no timezone: they land on the same date in
+// whatever timezone the calendar app shows them, just as the app computes one
+// Maya date per local calendar day. Only the DTSTAMP (`created`) is in UTC.
+pub fn calendar_ics(
+    start: NaiveDate,
+    end: NaiveDate,
+    settings: CalendarSettings,
+    options: ExportOptions,
+    created: DateTime<Utc>,
+) -> Result<String, ExportError> {
+    check_range(start, end)?;
+
+    let jdn = |date: NaiveDate| gregorian_to_jdn(date.year(), date.month() as i32, date.day() as i32);
+    let mut events = Vec::new();
+    // Open eclipse window: its first day and status
+    let mut eclipse: Option<(NaiveDate, String)> = None;
+
+    for date in start.iter_days().take_while(|&date| date <= end) {
+        let day_jdn = jdn(date);
+        let days = days_since_creation(day_jdn, settings.correlation);
+
+        if options.calendar_round {
+            let tzolkin = tzolkin_date(days);
+            let long_count = LongCount::from_days(days);
+            events.push(Event {
+                start: date,
+                end: date + Duration::days(1),
+                kind: "calendar-round",
+                summary: format!("{} {} {}", tzolkin.number, tzolkin.yucatec_name, haab_date(days).label(options.haab_numbering)),
+                description: Some(format!(
+                    "Long Count {}.{}.{}.{}.{}\nYear Bearer: {}",
+                    long_count.baktun, long_count.katun, long_count.tun, long_count.uinal, long_count.kin,
+                    year_bearer(days, settings.year_bearers),
+                )),
+            });
+        }
+
+        if options.seasons {
+            // The next event as seen from yesterday is today's
+            let yesterday = date - Duration::days(1);
+            let (name, days_until) = next_solstice_or_equinox(yesterday.year(), yesterday.month() as i32, yesterday.day() as i32);
+            if days_until == 1 {
+                events.push(Event { start: date, end: date + Duration::days(1), kind: "season", summary: name, description: None });
+            }
+        }
+
+        if options.eclipses {
+            let status = next_eclipse(day_jdn);
+            let possible = status.contains("Possible").then_some(status);
+            if eclipse.as_ref().map(|(_, status)| status) != possible.as_ref() {
+                if let Some((first, status)) = eclipse.take() {
+                    events.push(eclipse_event(first, date, status));
+                }
+                eclipse = possible.map(|status| (date, status));
+            }
+        }
+
+        if options.venus {
+            let phase = venus_phase(day_jdn);
+            if phase != venus_phase(day_jdn - 1) {
+                events.push(Event {
+                    start: date,
+                    end: date + Duration::days(1),
+                    kind: "venus",
+                    summary: format!("Venus phase: {}", phase),
+                    description: None,
+                });
+            }
+        }
+    }
+    if let Some((first, status)) = eclipse {
+        events.push(eclipse_event(first, end + Duration::days(1), status));
+    }
+
+    let stamp = created.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//mayan_calendar//Maya Calendar Export//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:Maya Calendar".to_string(),
+    ];
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        // Stable across exports, so importing an overlapping range again
+        // updates events instead of duplicating them
+        lines.push(format!("UID:{}-{}-{}@mayan-calendar", event.start.format("%Y%m%d"), event.kind, settings.correlation));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", event.start.format("%Y%m%d")));
+        lines.push(format!("DTEND;VALUE=DATE:{}", event.end.format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        // Show as free time
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold_line(&line));
+    }
+    Ok(ics)
+}
+
+fn eclipse_event(start: NaiveDate, end: NaiveDate, status: String) -> Event {
+    Event {
+        start,
+        end,
+        kind: "eclipse",
+        summary: status,
+        description: Some("Estimated from the Saros cycle; check an ephemeris for exact dates".to_string()),
+    }
+}
+
+// Escape TEXT values: backslashes, separators and line breaks
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// End a content line with CRLF, folding it so no line is longer than 75
+// bytes; continuation lines start with a space. Folds fall between characters.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(start: NaiveDate, end: NaiveDate, options: ExportOptions) -> String {
+        let created = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        calendar_ics(start, end, CalendarSettings::default(), options, created).unwrap()
+    }
+
+    // The unfolded content lines of every VEVENT
+    fn events(ics: &str) -> Vec<Vec<String>> {
+        let unfolded = ics.replace("\r\n ", "");
+        let mut events = Vec::new();
+        let mut current: Option<Vec<String>> = None;
+        for line in unfolded.split("\r\n") {
+            match line {
+                "BEGIN:VEVENT" => current = Some(Vec::new()),
+                "END:VEVENT" => events.extend(current.take()),
+                line => if let Some(event) = current.as_mut() { event.push(line.to_string()) },
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn days_become_well_formed_all_day_events_with_their_maya_date() {
+        let date = |day| NaiveDate::from_ymd_opt(2012, 12, day).unwrap();
+        let options = ExportOptions { eclipses: false, venus: false, ..ExportOptions::default() };
+        let ics = export(date(20), date(22), options);
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), ics.matches("END:VEVENT").count());
+
+        let events = events(&ics);
+        let maya: Vec<&Vec<String>> = events.iter().filter(|event| event[0].contains("-calendar-round-")).collect();
+        assert_eq!(maya.len(), 3);
+        for event in &maya {
+            for field in ["UID:", "DTSTAMP:20240501T120000Z", "DTSTART;VALUE=DATE:", "DTEND;VALUE=DATE:", "SUMMARY:"] {
+                assert!(event.iter().any(|line| line.starts_with(field)), "{field} missing from {event:?}");
+            }
+        }
+        // The end of the 13th bak'tun
+        let solstice = maya.iter().find(|event| event.contains(&"DTSTART;VALUE=DATE:20121221".to_string())).unwrap();
+        assert!(solstice.contains(&"DTEND;VALUE=DATE:20121222".to_string()));
+        assert!(solstice.contains(&"SUMMARY:4 Ajaw 3 Kankin".to_string()), "{solstice:?}");
+        assert!(solstice.iter().any(|line| line.starts_with("DESCRIPTION:Long Count 13.0.0.0.0\\n")));
+
+        let seasons: Vec<&Vec<String>> = events.iter().filter(|event| event[0].contains("-season-")).collect();
+        assert_eq!(seasons.len(), 1);
+        assert!(seasons[0].contains(&"DTSTART;VALUE=DATE:20121221".to_string()));
+        assert!(seasons[0].contains(&"SUMMARY:Winter Solstice".to_string()));
+    }
+
+    #[test]
+    fn ranges_are_bounded() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let created = Utc::now();
+        let settings = CalendarSettings::default();
+        assert!(matches!(
+            calendar_ics(start, start - Duration::days(1), settings, ExportOptions::default(), created),
+            Err(ExportError::EndBeforeStart { .. })
+        ));
+        assert!(matches!(
+            calendar_ics(start, start + Duration::days(MAX_EXPORT_DAYS), settings, ExportOptions::default(), created),
+            Err(ExportError::RangeTooLong { .. })
+        ));
+    }
+}
@@ -4,10 +4,10 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use lru::LruCache;
-use chrono::{NaiveDate, NaiveDateTime, Datelike};
+use chrono::{NaiveDate, NaiveDateTime, Datelike, Timelike};
 
 use eframe::{App, NativeOptions};
-use egui::{self, Context, TextureHandle, ColorImage, TextureOptions, Vec2, ViewportBuilder};
+use egui::{self, Context, TextureHandle, ColorImage, TextureOptions, Vec2, ViewportBuilder, Rect, Pos2};
 use tracing::{error, info, Level};
 use tracing_subscriber::EnvFilter;
 
@@ -15,9 +15,17 @@ use tracing_subscriber::EnvFilter;
 mod config;
 mod date_utils;
 mod astronomical;
+mod date_search;
+mod fonts;
+mod glyph_atlas;
+mod ical_export;
 
 use config::Config;
-use date_utils::{gregorian_to_jdn, tzolkin_date, haab_date, TzolkinDate, HaabDate};
+use fonts::{font_candidates, resolve_font, FontSource, EMBEDDED_FONT, MAYAN_FONT_ENV};
+use glyph_atlas::GlyphAtlas;
+use ical_export::{calendar_ics, ExportError, ExportForm};
+use date_search::{describe_distance, find_dates, DateQuery, DateSearchForm, Direction, QueryError, SearchOptions};
+use date_utils::{gregorian_to_jdn, days_since_creation, tzolkin_date, haab_date, validate_date, Mismatch, TzolkinDate, HaabDate, HaabNumbering, CalendarSettings, CalendarSystem, YearBearerSystem, GMT_CORRELATION, LOUNSBURY_CORRELATION, HAAB_MONTHS, TZOLKIN_NAMES};
 use astronomical::{
     moon_phase,
     venus_phase,
@@ -28,7 +36,7 @@ use astronomical::{
 };
 
 // Enum for Glyph Types
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum GlyphType {
     Tzolkin,
     Haab,
@@ -91,11 +99,15 @@ impl Metrics {
 pub struct TextureCache {
     tzolkin_textures: HashMap<String, TextureHandle>,
     haab_textures: HashMap<String, TextureHandle>,
+    // One packed texture per glyph type when `Config::glyph_atlas` is set;
+    // None if none of the type's glyphs could be loaded
+    atlases: HashMap<GlyphType, Option<(TextureHandle, GlyphAtlas)>>,
 }
 
-// Calendar Cache
+// Calendar Cache, keyed by the date and the settings it was computed under,
+// so changing the correlation or calendar system never returns stale data
 pub struct CalendarCache {
-    cache: LruCache<i32, CalendarData>,
+    cache: LruCache<(NaiveDate, CalendarSettings), CalendarData>,
 }
 
 impl CalendarCache {
@@ -105,12 +117,12 @@ impl CalendarCache {
         }
     }
     
-    pub fn get_calendar_data(&mut self, days: i32) -> Option<CalendarData> {
-        self.cache.get(&days).cloned()
+    pub fn get_calendar_data(&mut self, date: NaiveDate, settings: CalendarSettings) -> Option<CalendarData> {
+        self.cache.get(&(date, settings)).cloned()
     }
     
-    pub fn put_calendar_data(&mut self, days: i32, data: CalendarData) {
-        self.cache.put(days, data);
+    pub fn put_calendar_data(&mut self, date: NaiveDate, settings: CalendarSettings, data: CalendarData) {
+        self.cache.put((date, settings), data);
     }
 }
 
@@ -170,12 +182,19 @@ pub struct CalendarData {
     eclipse_status: String,
     historical_event: Option<String>,
     gregorian_date: NaiveDate,
+    // The date as written in the configured calendar system
+    western_date: String,
+    settings: CalendarSettings,
     julian_day_number: i32,
     days_since_creation: i32,
 }
 
 impl CalendarData {
     pub fn new(date: NaiveDateTime) -> Self {
+        Self::with_settings(date, CalendarSettings::default())
+    }
+
+    pub fn with_settings(date: NaiveDateTime, settings: CalendarSettings) -> Self {
         // Get the current date components
         let year = date.year();
         let month = date.month() as i32;
@@ -184,9 +203,8 @@ impl CalendarData {
         // Calculate Julian Day Number using the function from date_utils
         let jdn = gregorian_to_jdn(year, month, day);
         
-        // Mayan epoch: August 11, 3114 BCE = JDN 584283
-        let mayan_epoch_jdn = 584283;
-        let days_since_creation = jdn - mayan_epoch_jdn;
+        // Long Count, Tzolk'in and Haab' all count from the creation date
+        let days_since_creation = days_since_creation(jdn, settings.correlation);
         
         info!("Date: {}-{}-{}, JDN: {}, Days since creation: {}", 
               year, month, day, jdn, days_since_creation);
@@ -198,7 +216,7 @@ impl CalendarData {
         // Calculate astronomical data
         let moon = moon_phase(jdn);
         let venus = venus_phase(jdn);
-        let bearer = year_bearer(jdn);
+        let bearer = year_bearer(days_since_creation, settings.year_bearers);
         let eclipse = next_eclipse(jdn);
         let (solstice_name, days_to_solstice) = next_solstice_or_equinox(year, month, day);
         let historical = historical_event(jdn);
@@ -214,6 +232,8 @@ impl CalendarData {
             eclipse_status: eclipse,
             historical_event: historical.map(|s| s.to_string()),
             gregorian_date: date.date(),
+            western_date: settings.system.format_jdn(jdn),
+            settings,
             julian_day_number: jdn,
             days_since_creation,
         }
@@ -259,6 +279,7 @@ impl GlyphRenderer {
             cache: Arc::new(RwLock::new(TextureCache {
                 tzolkin_textures: HashMap::new(),
                 haab_textures: HashMap::new(),
+                atlases: HashMap::new(),
             })),
             config,
             metrics: Arc::new(Metrics::new()),
@@ -266,19 +287,93 @@ impl GlyphRenderer {
         }
     }
 
-    pub fn get_texture(&self, glyph_type: GlyphType, name: &str) -> Option<TextureHandle> {
+    fn glyph_paths(&self, glyph_type: GlyphType) -> &HashMap<String, String> {
+        match glyph_type {
+            GlyphType::Tzolkin => &self.config.tzolkin_glyphs,
+            GlyphType::Haab => &self.config.haab_glyphs,
+        }
+    }
+
+    // The texture holding a glyph and the part of it the glyph covers, for
+    // `egui::Image::uv`: a rect of the glyph type's atlas, or the whole of a
+    // texture of its own when the atlas is switched off
+    pub fn get_glyph(&self, glyph_type: GlyphType, name: &str) -> Option<(TextureHandle, Rect)> {
         // Normalize the name to match config keys
         let normalized_name = name.to_lowercase();
         
         info!("Looking for glyph: {} (normalized: {})", name, normalized_name);
         
-        // Get the path from the configuration
-        let path = match glyph_type {
-            GlyphType::Tzolkin => self.config.tzolkin_glyphs.get(&normalized_name),
-            GlyphType::Haab => self.config.haab_glyphs.get(&normalized_name),
+        if self.config.glyph_atlas {
+            return self.get_atlas_glyph(glyph_type, &normalized_name);
+        }
+        
+        let full = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+        self.get_texture(glyph_type, &normalized_name).map(|texture| (texture, full))
+    }
+
+    fn get_atlas_glyph(&self, glyph_type: GlyphType, normalized_name: &str) -> Option<(TextureHandle, Rect)> {
+        let mut cache = self.cache.write().unwrap();
+        let (texture, atlas) = match cache.atlases.entry(glyph_type) {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                self.metrics.record_cache_hit();
+                entry.into_mut()
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                self.metrics.record_cache_miss();
+                entry.insert(self.build_atlas(glyph_type))
+            }
+        }.as_ref()?;
+        match atlas.uv_rect(normalized_name) {
+            Some(uv) => Some((texture.clone(), uv)),
+            None => {
+                error!("No glyph found for: {} (type: {:?})", normalized_name, glyph_type);
+                None
+            }
+        }
+    }
+
+    // Load every configured glyph of a type and upload them as one texture
+    fn build_atlas(&self, glyph_type: GlyphType) -> Option<(TextureHandle, GlyphAtlas)> {
+        let start_time = std::time::Instant::now();
+        let glyphs: Vec<(String, ColorImage)> = self.glyph_paths(glyph_type).iter()
+            .filter_map(|(name, path)| Some((name.clone(), Self::load_image(path)?)))
+            .collect();
+        if glyphs.is_empty() {
+            error!("No glyphs could be loaded for the {:?} atlas", glyph_type);
+            return None;
+        }
+        
+        let mut atlas = GlyphAtlas::pack(glyphs);
+        let texture = self.ctx.load_texture(
+            format!("{:?}_atlas", glyph_type),
+            atlas.take_image(),
+            TextureOptions::default()
+        );
+        info!("Packed {} {:?} glyphs into one texture in {:?}", atlas.len(), glyph_type, start_time.elapsed());
+        Some((texture, atlas))
+    }
+
+    fn load_image(path: &str) -> Option<ColorImage> {
+        let image = match image::open(path) {
+            Ok(img) => {
+                info!("Successfully loaded glyph image: {}", path);
+                img
+            }
+            Err(e) => {
+                error!("Failed to load image at {}: {}", path, e);
+                return None;
+            }
         };
 
-        let path = match path {
+        let size = [image.width() as usize, image.height() as usize];
+        let image_buffer = image.to_rgba8();
+        let pixels = image_buffer.as_flat_samples();
+        Some(ColorImage::from_rgba_unmultiplied(size, pixels.as_slice()))
+    }
+
+    fn get_texture(&self, glyph_type: GlyphType, normalized_name: &str) -> Option<TextureHandle> {
+        // Get the path from the configuration
+        let path = match self.glyph_paths(glyph_type).get(normalized_name) {
             Some(p) => p,
             None => {
                 error!("No path found for glyph: {} (type: {:?})", normalized_name, glyph_type);
@@ -302,25 +397,11 @@ impl GlyphRenderer {
 
         // Load image
         let start_time = std::time::Instant::now();
-        let image = match image::open(path) {
-            Ok(img) => {
-                info!("Successfully loaded glyph image: {}", path);
-                img
-            }
-            Err(e) => {
-                error!("Failed to load image at {}: {}", path, e);
-                return None;
-            }
-        };
-
-        let size = [image.width() as usize, image.height() as usize];
-        let image_buffer = image.to_rgba8();
-        let pixels = image_buffer.as_flat_samples();
-        let image_data = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+        let image_data = Self::load_image(path)?;
 
         // Load texture into egui
         let texture = self.ctx.load_texture(
-            &format!("{}_{}", glyph_type as u8, normalized_name), 
+            format!("{}_{}", glyph_type as u8, normalized_name), 
             image_data, 
             TextureOptions::default()
         );
@@ -349,13 +430,30 @@ pub struct MayanCalendar {
     cache: Arc<RwLock<CalendarCache>>,
     glyph_renderer: GlyphRenderer,
     metrics: Arc<Metrics>,
+    // How Haab' days are numbered on screen: 0-19 with seating, or 1-20
+    haab_numbering: HaabNumbering,
+    // Correlation and Western calendar the dates are computed under
+    calendar_settings: CalendarSettings,
+    // Date search panel: the query text, the structured fields and the last
+    // search's outcome (from either)
+    date_query: String,
+    date_form: DateSearchForm,
+    date_search_results: Option<Result<Vec<NaiveDate>, QueryError>>,
+    // Whether the date in the query text checked out, see `check_date`
+    date_check: Option<Result<Result<(), Mismatch>, QueryError>>,
+    // iCalendar export panel: the range and events to export, and where the
+    // last export went
+    export_form: ExportForm,
+    export_status: Option<Result<std::path::PathBuf, ExportError>>,
+    // Where the Maya numerals font was loaded from; None if it wasn't
+    font_source: Option<FontSource>,
 }
 
 impl MayanCalendar {
-    pub fn new(ctx: &Context) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(ctx: &Context, config: Config, font_source: Option<FontSource>) -> Result<Self, Box<dyn std::error::Error>> {
         let metrics = Arc::new(Metrics::new());
         let cache = Arc::new(RwLock::new(CalendarCache::new(NonZeroUsize::new(100).unwrap())));
-        let glyph_renderer = GlyphRenderer::new(ctx, Config::default());
+        let glyph_renderer = GlyphRenderer::new(ctx, config);
         let now = chrono::Local::now().naive_local();
 
         Ok(Self {
@@ -365,30 +463,127 @@ impl MayanCalendar {
             cache: Arc::clone(&cache),
             glyph_renderer,
             metrics,
+            haab_numbering: HaabNumbering::default(),
+            calendar_settings: CalendarSettings::default(),
+            date_query: String::new(),
+            date_form: DateSearchForm::default(),
+            date_search_results: None,
+            date_check: None,
+            export_form: ExportForm {
+                start: now.date().format("%Y-%m-%d").to_string(),
+                end: (now.date() + chrono::Duration::days(29)).format("%Y-%m-%d").to_string(),
+                ..Default::default()
+            },
+            export_status: None,
+            font_source,
         })
     }
 
+    fn export_calendar(&mut self) {
+        self.export_status = Some(self.write_calendar_export());
+    }
+
+    // Write the range in the export form to an .ics file in the working
+    // directory, with Haab' days numbered as on screen
+    fn write_calendar_export(&self) -> Result<std::path::PathBuf, ExportError> {
+        let (start, end) = self.export_form.to_range()?;
+        let mut options = self.export_form.options;
+        options.haab_numbering = self.haab_numbering;
+        let ics = calendar_ics(start, end, self.calendar_settings, options, chrono::Utc::now())?;
+        let path = std::path::PathBuf::from(format!("maya-calendar-{}-to-{}.ics", start, end));
+        std::fs::write(&path, ics)?;
+        info!("Exported {} to {} as {}", start, end, path.display());
+        Ok(path)
+    }
+
+    fn run_date_search(&mut self, direction: Direction) {
+        let query = DateQuery::parse(&self.date_query);
+        self.search_dates(query, direction);
+    }
+
+    fn run_form_search(&mut self, direction: Direction) {
+        let query = self.date_form.to_query(self.haab_numbering);
+        self.search_dates(query, direction);
+    }
+
+    // Check the Long Count in the query text against its Calendar Round, as
+    // when proofreading the transcription of an inscription
+    fn check_date(&mut self) {
+        let date = DateQuery::parse(&self.date_query).and_then(|query| query.full_date());
+        self.date_check = Some(date.map(|(long_count, tzolkin, haab)| validate_date(&long_count, &tzolkin, &haab)));
+    }
+
+    fn search_dates(&mut self, query: Result<DateQuery, QueryError>, direction: Direction) {
+        let options = SearchOptions {
+            direction,
+            correlation: self.calendar_settings.correlation,
+            ..SearchOptions::default()
+        };
+        let from = self.current_time.date_naive();
+        self.date_search_results = Some(query.and_then(|query| find_dates(&query, from, &options)));
+    }
+
     pub fn update_calendar_data(&mut self) {
-        let now = chrono::Local::now();
-        if now != self.current_time {
-            let start = std::time::Instant::now();
-            self.current_time = now;
-            self.calendar_data = CalendarData::new(self.current_time.naive_local());
-            self.metrics.record_calculation(start.elapsed());
-            
-            info!(
-                "Updated calendar: Long Count {}.{}.{}.{}.{}, Tzolkin {} {}, Haab {} {}",
-                self.calendar_data.long_count.baktun,
-                self.calendar_data.long_count.katun,
-                self.calendar_data.long_count.tun,
-                self.calendar_data.long_count.uinal,
-                self.calendar_data.long_count.kin,
-                self.calendar_data.tzolkin.number,
-                self.calendar_data.tzolkin.yucatec_name,
-                self.calendar_data.haab.day,
-                self.calendar_data.haab.yucatec_month
-            );
+        self.tick(chrono::Local::now());
+    }
+
+    // Switch to new calendar settings, recomputing the current date under them
+    pub fn set_calendar_settings(&mut self, settings: CalendarSettings) {
+        if settings != self.calendar_settings {
+            self.calendar_settings = settings;
+            self.refresh_calendar_data();
+        }
+    }
+
+    // Advance the clock to `now`. The clock only moves when the displayed
+    // second changes, and `CalendarData` (which depends on the date and the
+    // calendar settings alone) is only recomputed when the day changes.
+    // Returns whether it was recomputed.
+    fn tick(&mut self, now: chrono::DateTime<chrono::Local>) -> bool {
+        if displayed_second(&now) == displayed_second(&self.current_time) {
+            return false;
+        }
+        self.current_time = now;
+        if now.date_naive() == self.calendar_data.gregorian_date {
+            return false;
         }
+        self.refresh_calendar_data();
+        true
+    }
+
+    // Bring `calendar_data` in line with the current date and settings, from
+    // the cache if this date was already computed under these settings
+    fn refresh_calendar_data(&mut self) {
+        let now = self.current_time.naive_local();
+        let settings = self.calendar_settings;
+        let cached = self.cache.write().unwrap().get_calendar_data(now.date(), settings);
+        self.calendar_data = match cached {
+            Some(data) => {
+                self.metrics.record_cache_hit();
+                data
+            }
+            None => {
+                self.metrics.record_cache_miss();
+                let start = std::time::Instant::now();
+                let data = CalendarData::with_settings(now, settings);
+                self.metrics.record_calculation(start.elapsed());
+                self.cache.write().unwrap().put_calendar_data(now.date(), settings, data.clone());
+                data
+            }
+        };
+        self.last_calendar_update = now;
+        
+        info!(
+            "Updated calendar: Long Count {}.{}.{}.{}.{}, Tzolkin {} {}, Haab {}",
+            self.calendar_data.long_count.baktun,
+            self.calendar_data.long_count.katun,
+            self.calendar_data.long_count.tun,
+            self.calendar_data.long_count.uinal,
+            self.calendar_data.long_count.kin,
+            self.calendar_data.tzolkin.number,
+            self.calendar_data.tzolkin.yucatec_name,
+            self.calendar_data.haab.label(self.haab_numbering)
+        );
     }
 
     pub fn render(&mut self, ctx: &Context) {
@@ -421,13 +616,21 @@ impl MayanCalendar {
                     self.calendar_data.long_count.kin
                 ));
                 
-                // Mayan numerals
-                let mayan_text = to_mayan_numeral_string(&self.calendar_data.long_count);
-                ui.label(
-                    egui::RichText::new(format!("Mayan: {}", mayan_text))
-                        .family(egui::FontFamily::Name("mayan".into()))
-                        .size(32.0)
-                );
+                // Mayan numerals, which need their font: the "mayan" family
+                // only exists once it is loaded
+                if self.font_source.is_some() {
+                    let mayan_text = to_mayan_numeral_string(&self.calendar_data.long_count);
+                    ui.label(
+                        egui::RichText::new(format!("Mayan: {}", mayan_text))
+                            .family(egui::FontFamily::Name("mayan".into()))
+                            .size(32.0)
+                    );
+                } else {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("⚠ Maya numerals font not found, so numerals aren't shown. Set {} to the font file.", MAYAN_FONT_ENV),
+                    );
+                }
             });
             
             ui.separator();
@@ -444,11 +647,11 @@ impl MayanCalendar {
                             self.calendar_data.tzolkin.yucatec_name
                         ));
                         
-                        if let Some(tzolkin_glyph) = self.glyph_renderer.get_texture(
+                        if let Some((tzolkin_glyph, uv)) = self.glyph_renderer.get_glyph(
                             GlyphType::Tzolkin,
                             &self.calendar_data.tzolkin.yucatec_name,
                         ) {
-                            ui.add(egui::Image::new(&tzolkin_glyph).fit_to_exact_size(desired_size));
+                            ui.add(egui::Image::new(&tzolkin_glyph).uv(uv).fit_to_exact_size(desired_size));
                         } else {
                             ui.colored_label(
                                 egui::Color32::RED, 
@@ -462,17 +665,17 @@ impl MayanCalendar {
                 ui.group(|ui| {
                     ui.vertical(|ui| {
                         ui.label(egui::RichText::new("Haab'").size(16.0).strong());
-                        ui.label(format!(
-                            "{} {}",
-                            self.calendar_data.haab.day,
-                            self.calendar_data.haab.yucatec_month
-                        ));
+                        ui.label(self.calendar_data.haab.label(self.haab_numbering));
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.haab_numbering, HaabNumbering::Seating, "0-19 (seating)");
+                            ui.radio_value(&mut self.haab_numbering, HaabNumbering::OneBased, "1-20");
+                        });
                         
-                        if let Some(haab_glyph) = self.glyph_renderer.get_texture(
+                        if let Some((haab_glyph, uv)) = self.glyph_renderer.get_glyph(
                             GlyphType::Haab,
                             &self.calendar_data.haab.yucatec_month,
                         ) {
-                            ui.add(egui::Image::new(&haab_glyph).fit_to_exact_size(desired_size));
+                            ui.add(egui::Image::new(&haab_glyph).uv(uv).fit_to_exact_size(desired_size));
                         } else {
                             ui.colored_label(
                                 egui::Color32::RED, 
@@ -508,9 +711,154 @@ impl MayanCalendar {
                 });
             }
             
+            // Date Search, e.g. "4 Ajaw" or "13 Ajaw full moon"
+            ui.separator();
+            ui.collapsing("Date Search", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Find:");
+                    let response = ui.text_edit_singleline(&mut self.date_query);
+                    let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Next").clicked() || submitted {
+                        self.run_date_search(Direction::Forward);
+                    }
+                    if ui.button("Previous").clicked() {
+                        self.run_date_search(Direction::Backward);
+                    }
+                    if ui.button("Check").on_hover_text("Check a Long Count against its Calendar Round").clicked() {
+                        self.check_date();
+                    }
+                });
+                match &self.date_check {
+                    Some(Ok(Ok(()))) => {
+                        ui.colored_label(egui::Color32::GREEN, "Long Count and Calendar Round agree");
+                    },
+                    Some(Ok(Err(mismatch))) => {
+                        ui.colored_label(egui::Color32::RED, mismatch.to_string());
+                    },
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    },
+                    None => {},
+                }
+                
+                // The same search field by field; blank fields match any day
+                egui::Grid::new("date_search_form").num_columns(3).show(ui, |ui| {
+                    let form = &mut self.date_form;
+                    ui.label("Tzolk'in:");
+                    ui.add(egui::TextEdit::singleline(&mut form.tzolkin_number).desired_width(40.0).hint_text("1-13"));
+                    egui::ComboBox::from_id_source("tzolkin_name")
+                        .selected_text(form.tzolkin_name.unwrap_or("any day"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut form.tzolkin_name, None, "any day");
+                            for name in TZOLKIN_NAMES {
+                                ui.selectable_value(&mut form.tzolkin_name, Some(name), name);
+                            }
+                        });
+                    ui.end_row();
+                    
+                    ui.label("Haab':");
+                    let day_hint = match self.haab_numbering {
+                        HaabNumbering::Seating => "0-19",
+                        HaabNumbering::OneBased => "1-20",
+                    };
+                    ui.add(egui::TextEdit::singleline(&mut form.haab_day).desired_width(40.0).hint_text(day_hint));
+                    egui::ComboBox::from_id_source("haab_month")
+                        .selected_text(form.haab_month.unwrap_or("any month"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut form.haab_month, None, "any month");
+                            for month in HAAB_MONTHS {
+                                ui.selectable_value(&mut form.haab_month, Some(month), month);
+                            }
+                        });
+                    ui.end_row();
+                    
+                    ui.label("Long Count:");
+                    ui.add(egui::TextEdit::singleline(&mut form.long_count).desired_width(120.0).hint_text("9.12.*.*.*"));
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Find next").clicked() {
+                        self.run_form_search(Direction::Forward);
+                    }
+                    if ui.button("Find previous").clicked() {
+                        self.run_form_search(Direction::Backward);
+                    }
+                });
+                
+                let today = self.current_time.date_naive();
+                match &self.date_search_results {
+                    Some(Ok(dates)) if dates.is_empty() => {
+                        ui.label("No matching dates within the search range");
+                    },
+                    Some(Ok(dates)) => {
+                        for date in dates {
+                            ui.label(format!("{} ({})", date.format("%Y-%m-%d"), describe_distance(today, *date)));
+                        }
+                    },
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    },
+                    None => {},
+                }
+            });
+            
+            // Export to other calendar apps
+            ui.separator();
+            ui.collapsing("Export to Calendar (.ics)", |ui| {
+                let form = &mut self.export_form;
+                ui.horizontal(|ui| {
+                    ui.label("From:");
+                    ui.add(egui::TextEdit::singleline(&mut form.start).desired_width(90.0).hint_text("YYYY-MM-DD"));
+                    ui.label("to:");
+                    ui.add(egui::TextEdit::singleline(&mut form.end).desired_width(90.0).hint_text("YYYY-MM-DD"));
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut form.options.calendar_round, "Maya dates");
+                    ui.checkbox(&mut form.options.seasons, "Solstices and equinoxes");
+                    ui.checkbox(&mut form.options.eclipses, "Eclipses");
+                    ui.checkbox(&mut form.options.venus, "Venus phases");
+                });
+                if ui.button("Export").clicked() {
+                    self.export_calendar();
+                }
+                match &self.export_status {
+                    Some(Ok(path)) => {
+                        ui.label(format!("Saved {}", path.display()));
+                    },
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    },
+                    None => {},
+                }
+            });
+            
+            // Calendar Settings
+            ui.separator();
+            ui.collapsing("Calendar Settings", |ui| {
+                let mut settings = self.calendar_settings;
+                ui.horizontal(|ui| {
+                    ui.label("Correlation:");
+                    ui.radio_value(&mut settings.correlation, GMT_CORRELATION, format!("GMT ({})", GMT_CORRELATION));
+                    ui.radio_value(&mut settings.correlation, LOUNSBURY_CORRELATION, format!("Lounsbury ({})", LOUNSBURY_CORRELATION));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Western calendar:");
+                    ui.radio_value(&mut settings.system, CalendarSystem::Gregorian, "Gregorian");
+                    ui.radio_value(&mut settings.system, CalendarSystem::Julian, "Julian");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Year bearers:");
+                    for system in [YearBearerSystem::Classic, YearBearerSystem::Mayapan, YearBearerSystem::Colonial] {
+                        ui.radio_value(&mut settings.year_bearers, system, format!("{:?} ({})", system, system.bearers().join(", ")));
+                    }
+                });
+                self.set_calendar_settings(settings);
+            });
+            
             // Debug Information
             ui.separator();
             ui.collapsing("Debug Information", |ui| {
+                ui.label(format!("{:?} date: {}", self.calendar_data.settings.system, self.calendar_data.western_date));
                 ui.label(format!("JDN: {}", self.calendar_data.julian_day_number));
                 ui.label(format!("Days since creation: {}", self.calendar_data.days_since_creation));
                 ui.label(self.metrics.report());
@@ -519,54 +867,69 @@ impl MayanCalendar {
     }
 }
 
+// The second shown by the clock. chrono reports a leap second as the
+// previous second with nanoseconds past 1e9; it is shown as :60, so it
+// counts as a second of its own.
+fn displayed_second(time: &chrono::DateTime<chrono::Local>) -> (i64, bool) {
+    (time.timestamp(), time.nanosecond() >= 1_000_000_000)
+}
+
 impl App for MayanCalendar {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        if (chrono::Local::now() - self.current_time).num_seconds() >= 1 {
-            self.update_calendar_data();
-        }
+        self.update_calendar_data();
         self.render(ctx);
-        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        // Wake at the next second boundary rather than a second from now, so
+        // the clock doesn't drift up to a second behind
+        let into_second = self.current_time.nanosecond() % 1_000_000_000;
+        ctx.request_repaint_after(std::time::Duration::from_nanos(u64::from(1_000_000_000 - into_second)));
     }
 }
 
-fn configure_fonts(ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+// The default fonts plus the Maya numerals font in its own "mayan" family,
+// which falls back to the regular fonts for the Latin text around the
+// numerals. Only with `everywhere` does it go ahead of the regular fonts too:
+// it shadows their glyphs and metrics for ordinary text.
+fn mayan_font_definitions(font_data: Vec<u8>, everywhere: bool) -> egui::FontDefinitions {
     let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert(
+        "mayan_numerals".to_owned(),
+        egui::FontData::from_owned(font_data)
+    );
     
-    // Try to load the Mayan numerals font
-    match std::fs::read("assets/fonts/NotoSansMayanNumerals-Regular.ttf") {
-        Ok(font_data) => {
-            info!("Font file loaded successfully, size: {} bytes", font_data.len());
-            
-            fonts.font_data.insert(
-                "mayan_numerals".to_owned(),
-                egui::FontData::from_owned(font_data)
-            );
-            
-            // Register for all font families
-            fonts.families.get_mut(&egui::FontFamily::Proportional)
-                .unwrap()
-                .insert(0, "mayan_numerals".to_owned());
-            
-            fonts.families.get_mut(&egui::FontFamily::Monospace)
+    let mut mayan_family = vec!["mayan_numerals".to_owned()];
+    mayan_family.extend(fonts.families[&egui::FontFamily::Proportional].iter().cloned());
+    fonts.families.insert(egui::FontFamily::Name("mayan".into()), mayan_family);
+    
+    if everywhere {
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts.families.get_mut(&family)
                 .unwrap()
                 .insert(0, "mayan_numerals".to_owned());
-            
-            // Create dedicated Mayan family
-            fonts.families.insert(
-                egui::FontFamily::Name("mayan".into()),
-                vec!["mayan_numerals".to_owned()]
-            );
-            
-            ctx.set_fonts(fonts);
+        }
+    }
+    fonts
+}
+
+// Load the Maya numerals font from the first place it is found (see
+// `font_candidates`), falling back to the built-in copy. Returns where it
+// came from, or None if there was none, in which case the app runs without
+// it and says so.
+fn configure_fonts(ctx: &Context, config: &Config) -> Option<FontSource> {
+    // Try to load the Mayan numerals font
+    let candidates = font_candidates(config.mayan_font.as_deref().map(std::path::Path::new));
+    match resolve_font(&candidates, EMBEDDED_FONT) {
+        Some((font_data, source)) => {
+            info!("Font loaded from {}, size: {} bytes", source, font_data.len());
+            ctx.set_fonts(mayan_font_definitions(font_data, config.mayan_font_everywhere));
             info!("Font configuration completed successfully");
+            Some(source)
         }
-        Err(e) => {
-            error!("Failed to load Mayan numerals font: {}. Continuing without it.", e);
-            // Continue without the font - numbers will display as regular digits
+        None => {
+            let tried: Vec<String> = candidates.iter().map(|path| path.display().to_string()).collect();
+            error!("Mayan numerals font not found (tried {}). Continuing without it.", tried.join(", "));
+            None
         }
     }
-    
-    Ok(())
 }
 
 fn main() -> Result<(), eframe::Error> {
@@ -602,11 +965,10 @@ fn main() -> Result<(), eframe::Error> {
         options,
         Box::new(|cc| {
             // Configure fonts before creating the app
-            if let Err(e) = configure_fonts(&cc.egui_ctx) {
-                error!("Font configuration error: {}", e);
-            }
+            let config = Config::default();
+            let font_source = configure_fonts(&cc.egui_ctx, &config);
             
-            match MayanCalendar::new(&cc.egui_ctx) {
+            match MayanCalendar::new(&cc.egui_ctx, config, font_source) {
                 Ok(app) => Box::new(app),
                 Err(e) => {
                     error!("Failed to create app: {}", e);
@@ -615,4 +977,112 @@ fn main() -> Result<(), eframe::Error> {
             }
         }),
     )
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn calendar() -> MayanCalendar {
+        MayanCalendar::new(&Context::default(), Config::default(), None).unwrap()
+    }
+
+    // Times CalendarData was computed or taken from the cache
+    fn refreshes(calendar: &MayanCalendar) -> u64 {
+        calendar.metrics.cache_hits.load(Ordering::Relaxed) + calendar.metrics.cache_misses.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn calendar_data_is_recomputed_on_day_changes_only() {
+        let mut calendar = calendar();
+        let before_midnight = chrono::Local.with_ymd_and_hms(2024, 6, 15, 23, 59, 58).unwrap();
+        assert!(calendar.tick(before_midnight));
+        assert_eq!(calendar.calendar_data.gregorian_date, NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let after = refreshes(&calendar);
+
+        // Within the displayed second nothing moves
+        assert!(!calendar.tick(before_midnight + chrono::Duration::milliseconds(400)));
+        assert_eq!(calendar.current_time, before_midnight);
+        // The next second moves the clock but not the date
+        let next_second = before_midnight + chrono::Duration::seconds(1);
+        assert!(!calendar.tick(next_second));
+        assert_eq!(calendar.current_time, next_second);
+        assert_eq!(refreshes(&calendar), after);
+
+        assert!(calendar.tick(next_second + chrono::Duration::seconds(1)));
+        assert_eq!(calendar.calendar_data.gregorian_date, NaiveDate::from_ymd_opt(2024, 6, 16).unwrap());
+        assert_eq!(refreshes(&calendar), after + 1);
+    }
+
+    #[test]
+    fn changing_the_correlation_misses_the_cache_and_recomputes() {
+        let mut calendar = calendar();
+        calendar.tick(chrono::Local.with_ymd_and_hms(2012, 12, 21, 12, 0, 0).unwrap());
+        let gmt = calendar.calendar_data.clone();
+        assert_eq!(gmt.long_count, LongCount::from_days(13 * 144_000));
+        let before = calendar.metrics.cache_misses.load(Ordering::Relaxed);
+
+        let lounsbury = CalendarSettings { correlation: LOUNSBURY_CORRELATION, ..CalendarSettings::default() };
+        calendar.set_calendar_settings(lounsbury);
+        assert_eq!(calendar.metrics.cache_misses.load(Ordering::Relaxed), before + 1);
+        let fresh = CalendarData::with_settings(calendar.current_time.naive_local(), lounsbury);
+        assert_eq!(calendar.calendar_data.days_since_creation, gmt.days_since_creation - 2);
+        assert_eq!(calendar.calendar_data.long_count, fresh.long_count);
+        assert_eq!(calendar.calendar_data.tzolkin, fresh.tzolkin);
+
+        // Going back finds the GMT data still cached
+        let hits = calendar.metrics.cache_hits.load(Ordering::Relaxed);
+        calendar.set_calendar_settings(CalendarSettings::default());
+        assert_eq!(calendar.metrics.cache_hits.load(Ordering::Relaxed), hits + 1);
+        assert_eq!(calendar.calendar_data.long_count, gmt.long_count);
+
+        let date = NaiveDate::from_ymd_opt(2012, 12, 21).unwrap();
+        let mut cache = CalendarCache::new(NonZeroUsize::new(4).unwrap());
+        cache.put_calendar_data(date, CalendarSettings::default(), gmt);
+        assert!(cache.get_calendar_data(date, lounsbury).is_none());
+        assert!(cache.get_calendar_data(date, CalendarSettings::default()).is_some());
+    }
+
+    #[test]
+    fn the_mayan_font_only_joins_its_own_family() {
+        let font = std::fs::read(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(fonts::MAYAN_FONT_PATH)).unwrap();
+        let defaults = egui::FontDefinitions::default();
+        let mayan = egui::FontFamily::Name("mayan".into());
+
+        let definitions = mayan_font_definitions(font.clone(), false);
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            assert_eq!(definitions.families[&family], defaults.families[&family], "{family:?}");
+        }
+        assert_eq!(definitions.families[&mayan][0], "mayan_numerals");
+        assert_eq!(definitions.families[&mayan][1..], defaults.families[&egui::FontFamily::Proportional][..]);
+
+        // Headless, the numerals resolve through "mayan" and nowhere else
+        let ctx = Context::default();
+        ctx.set_fonts(definitions);
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+        let zero = '\u{1D2E0}';
+        assert!(ctx.fonts(|fonts| fonts.has_glyph(&egui::FontId::new(14.0, mayan.clone()), zero)));
+        assert!(!ctx.fonts(|fonts| fonts.has_glyph(&egui::FontId::proportional(14.0), zero)));
+
+        // Unless asked to override the defaults
+        let everywhere = mayan_font_definitions(font, true);
+        assert_eq!(everywhere.families[&egui::FontFamily::Proportional][0], "mayan_numerals");
+        assert_eq!(everywhere.families[&egui::FontFamily::Monospace][0], "mayan_numerals");
+    }
+
+    #[test]
+    fn the_creation_date_is_4_ajaw_8_kumku_under_its_correlation() {
+        let at_jdn = |jdn: i32, correlation: i32| {
+            let date = date_utils::jdn_to_gregorian(jdn).unwrap().and_hms_opt(12, 0, 0).unwrap();
+            let data = CalendarData::with_settings(date, CalendarSettings { correlation, ..CalendarSettings::default() });
+            assert_eq!(data.julian_day_number, jdn);
+            (data.days_since_creation, data.tzolkin, data.haab)
+        };
+        let creation = (0, TzolkinDate::new(4, "Ajaw"), HaabDate::new(8, "Kumk'u"));
+        assert_eq!(at_jdn(GMT_CORRELATION, GMT_CORRELATION), creation);
+
+        // Lounsbury puts the creation two days later, so the GMT date is two days short of it
+        assert_eq!(at_jdn(GMT_CORRELATION, LOUNSBURY_CORRELATION), (-2, TzolkinDate::new(2, "Etz'nab'"), HaabDate::new(6, "Kumk'u")));
+        assert_eq!(at_jdn(LOUNSBURY_CORRELATION, LOUNSBURY_CORRELATION), creation);
+    }
+}